@@ -0,0 +1,242 @@
+//! Test de charge d'un serveur mini-irc : des clients simulés se connectent, rejoignent des
+//! canaux et y envoient des messages à un débit donné. Chaque message contient son heure
+//! d'envoi, ce qui permet de mesurer sa latence à la réception par les membres du canal.
+
+use anyhow::{bail, Context, Result};
+use mini_irc_protocol::{ChanOp, MessageReceiver, Request, Response, Transport};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Adresse du serveur par défaut
+const DEFAULT_ADDRESS: &str = "127.0.0.1:6379";
+/// Délai accordé aux derniers messages pour arriver, une fois les envois terminés
+const GRACE: Duration = Duration::from_secs(2);
+
+const USAGE: &str = "Utilisation: ./mini-irc-bench [adresse-serveur:port | unix:///chemin/socket]
+                      [--clients N] [--channels M] [--rate MESSAGES_PAR_SECONDE]
+                      [--duration SECONDES]";
+
+#[derive(Debug)]
+struct Config {
+    address: String,
+    /// Nombre de clients simulés
+    clients: usize,
+    /// Nombre de canaux, rejoints par tous les clients
+    channels: usize,
+    /// Messages envoyés par seconde, par client
+    rate: f64,
+    /// Durée des envois
+    duration: Duration,
+}
+
+impl Config {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut config = Self {
+            address: DEFAULT_ADDRESS.to_string(),
+            clients: 10,
+            channels: 1,
+            rate: 1.0,
+            duration: Duration::from_secs(10),
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("missing value for {arg}"))
+            };
+            match arg.as_str() {
+                "--clients" => config.clients = value()?.parse()?,
+                "--channels" => config.channels = value()?.parse()?,
+                "--rate" => config.rate = value()?.parse()?,
+                "--duration" => config.duration = Duration::from_secs_f64(value()?.parse()?),
+                _ if arg.starts_with("--") => bail!("unknown option: {}", arg),
+                _ => config.address = arg,
+            }
+        }
+        if config.clients == 0 || config.channels == 0 {
+            bail!("--clients and --channels must be positive");
+        }
+        if !config.rate.is_finite() || config.rate <= 0.0 {
+            bail!("--rate must be positive");
+        }
+        Ok(config)
+    }
+
+    fn channel(&self, index: usize) -> String {
+        format!("bench{}", index % self.channels)
+    }
+}
+
+/// Bilan d'un client simulé
+#[derive(Debug, Default)]
+struct Report {
+    sent: u64,
+    received: u64,
+    /// Messages perdus par le serveur, faute d'avoir été lus à temps
+    missed: u64,
+    errors: u64,
+    latencies: Vec<Duration>,
+}
+
+impl Report {
+    fn merge(&mut self, other: Report) {
+        self.sent += other.sent;
+        self.received += other.received;
+        self.missed += other.missed;
+        self.errors += other.errors;
+        self.latencies.extend(other.latencies);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let config = match Config::parse(std::env::args().skip(1)) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            println!("{e}");
+            println!("{USAGE}");
+            return Ok(());
+        }
+    };
+
+    // Référence commune des heures d'envoi
+    let epoch = Instant::now();
+    let mut tasks = Vec::new();
+    for id in 0..config.clients {
+        let config = config.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut report = Report::default();
+            if let Err(e) = connect_and_run(id, &config, epoch, &mut report).await {
+                println!("client {id}: {e}");
+                report.errors += 1;
+            }
+            report
+        }));
+    }
+
+    let mut report = Report::default();
+    for task in tasks {
+        report.merge(task.await?);
+    }
+    print_report(&config, report);
+    Ok(())
+}
+
+async fn connect_and_run(
+    id: usize,
+    config: &Config,
+    epoch: Instant,
+    report: &mut Report,
+) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(path) = config.address.strip_prefix("unix://") {
+        let stream = tokio::net::UnixStream::connect(path).await?;
+        return run(stream, id, config, epoch, report).await;
+    }
+    let stream = tokio::net::TcpStream::connect(&config.address).await?;
+    run(stream, id, config, epoch, report).await
+}
+
+async fn run<S>(
+    stream: S,
+    id: usize,
+    config: &Config,
+    epoch: Instant,
+    report: &mut Report,
+) -> Result<()>
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    let mut channel = mini_irc_testkit::handshake(stream).await?;
+    channel
+        .send(&Request::Connect(format!("bench{id}")))
+        .await?;
+    match channel.recv().await? {
+        Some(Response::AckConnect(_)) => {}
+        other => bail!("connection refused: {:?}", other),
+    }
+    for index in 0..config.channels {
+        channel
+            .send(&Request::JoinChan(config.channel(index)))
+            .await?;
+    }
+    let (mut reader, mut writer) = channel.into_split();
+
+    let start = Instant::now();
+    let deadline = start + config.duration;
+    let sending = async {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / config.rate));
+        let mut sent = 0;
+        while interval.tick().await < deadline {
+            let request = Request::Message {
+                to: MessageReceiver::Channel(config.channel(sent as usize)),
+                content: epoch.elapsed().as_nanos().to_string(),
+            };
+            writer.send(&request).await?;
+            sent += 1;
+        }
+        anyhow::Ok(sent)
+    };
+    let receiving = async {
+        let mut received = Report::default();
+        while let Ok(response) = tokio::time::timeout_at(deadline + GRACE, reader.recv()).await {
+            match response? {
+                Some(Response::Channel {
+                    op: ChanOp::Message { content, .. },
+                    ..
+                }) => {
+                    if let Ok(sent_at) = content.parse::<u64>() {
+                        received.received += 1;
+                        received
+                            .latencies
+                            .push(epoch.elapsed() - Duration::from_nanos(sent_at));
+                    }
+                }
+                Some(Response::Channel {
+                    op: ChanOp::Missed(missed),
+                    ..
+                }) => received.missed += missed,
+                Some(Response::Error(_)) => received.errors += 1,
+                Some(_) => {}
+                None => bail!("connection closed by the server"),
+            }
+        }
+        Ok(received)
+    };
+
+    let (sent, received) = tokio::join!(sending, receiving);
+    report.sent += sent?;
+    report.merge(received?);
+    Ok(())
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
+
+fn print_report(config: &Config, mut report: Report) {
+    println!(
+        "{} client(s), {} canal(aux), {} message(s)/s par client pendant {:?}",
+        config.clients, config.channels, config.rate, config.duration
+    );
+    println!(
+        "messages envoyés: {}, reçus: {}, manqués: {}, erreurs: {}",
+        report.sent, report.received, report.missed, report.errors
+    );
+    if report.latencies.is_empty() {
+        println!("aucun message reçu");
+        return;
+    }
+    report.latencies.sort();
+    println!(
+        "latence p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+        percentile(&report.latencies, 0.50),
+        percentile(&report.latencies, 0.90),
+        percentile(&report.latencies, 0.99),
+        report.latencies.last().unwrap()
+    );
+}
@@ -0,0 +1,94 @@
+//! Découpage des commandes saisies (`/to "bob smith" salut`) : un nom, puis des arguments
+//! séparés par des espaces. Un argument entre guillemets peut contenir des espaces, et `\"` ou
+//! `\\` y désignent un guillemet ou une barre oblique inverse.
+
+use crate::error::ClientError;
+
+/// Commande saisie : son nom, sans `/`, et le reste de la ligne à découper selon la commande.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command<'a> {
+    pub name: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Command<'a> {
+    /// Commande saisie, ou `None` si la saisie ne commence pas par `/`.
+    pub fn parse(input: &'a str) -> Option<Self> {
+        let input = input.strip_prefix('/')?;
+        let (name, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        Some(Self {
+            name,
+            rest: rest.trim_start(),
+        })
+    }
+
+    /// Reste de la ligne après le nom, tel quel.
+    pub fn rest(&self) -> &'a str {
+        self.rest
+    }
+
+    /// Tous les arguments.
+    pub fn args(&self) -> Result<Vec<String>, ClientError> {
+        tokenize(self.rest)
+    }
+
+    /// Exactement `N` arguments, sinon une erreur rappelant `usage`.
+    pub fn exactly<const N: usize>(&self, usage: &'static str) -> Result<[String; N], ClientError> {
+        self.args()?
+            .try_into()
+            .map_err(|_| ClientError::Usage(usage))
+    }
+
+    /// Au plus un argument, sinon une erreur rappelant `usage`.
+    pub fn optional(&self, usage: &'static str) -> Result<Option<String>, ClientError> {
+        let mut args = self.args()?;
+        if args.len() > 1 {
+            return Err(ClientError::Usage(usage));
+        }
+        Ok(args.pop())
+    }
+
+    /// Premier argument, et le reste de la ligne tel quel, non vide : par exemple le
+    /// destinataire et le contenu d'un message.
+    pub fn first_and_rest(&self, usage: &'static str) -> Result<(String, &'a str), ClientError> {
+        match next_token(self.rest)? {
+            Some((first, rest)) if !rest.is_empty() => Ok((first, rest)),
+            _ => Err(ClientError::Usage(usage)),
+        }
+    }
+}
+
+/// Découpe `s` en arguments.
+pub fn tokenize(mut s: &str) -> Result<Vec<String>, ClientError> {
+    let mut tokens = Vec::new();
+    while let Some((token, rest)) = next_token(s)? {
+        tokens.push(token);
+        s = rest;
+    }
+    Ok(tokens)
+}
+
+// Premier argument de `s`, et ce qui le suit sans les espaces qui les séparent
+fn next_token(s: &str) -> Result<Option<(String, &str)>, ClientError> {
+    let s = s.trim_start();
+    let Some(quoted) = s.strip_prefix('"') else {
+        if s.is_empty() {
+            return Ok(None);
+        }
+        let (token, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        return Ok(Some((token.to_string(), rest.trim_start())));
+    };
+    let mut token = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok(Some((token, quoted[i + 1..].trim_start()))),
+            '\\' => match chars.next() {
+                Some((_, c)) => token.push(c),
+                None => break,
+            },
+            c => token.push(c),
+        }
+    }
+    Err(ClientError::UnterminatedQuote)
+}
@@ -0,0 +1,192 @@
+//! Diagnostic des connexions qui n'aboutissent pas, avant le démarrage de l'interface : chaque
+//! cause a son message et son code de sortie, pour les scripts.
+//!
+//! | Code | Cause                                                      |
+//! |------|------------------------------------------------------------|
+//! | 0    | Connexion réussie, puis fermée par l'utilisateur           |
+//! | 1    | Erreur inattendue                                          |
+//! | 2    | Arguments invalides                                        |
+//! | 3    | Adresse introuvable                                        |
+//! | 4    | Serveur injoignable : connexion refusée, pas de réponse... |
+//! | 5    | Réservé (anciennement : TLS demandé)                       |
+//! | 6    | Échange impossible : ce n'est pas un serveur mini-irc ?    |
+//! | 7    | Nom déjà pris                                              |
+//! | 8    | Nom refusé, ou reprise de session refusée                  |
+//! | 9    | Serveur complet : trop de connexions simultanées           |
+//! | 10   | Adresse du client listée par une liste de blocage (DNSBL)  |
+
+use mini_irc_protocol::SocketOptions;
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Délai accordé à chaque adresse pour accepter la connexion
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Délai avant d'essayer l'adresse suivante sans attendre l'échec de la précédente
+pub const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Silence du serveur au-delà duquel la connexion est considérée perdue : le serveur envoie
+/// un ping toutes les 30 secondes.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Délai accordé au serveur pour lire une requête
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Inactivité après laquelle le système sonde la connexion (TCP keepalive)
+pub const KEEPALIVE_IDLE: Duration = Duration::from_secs(60);
+
+/// Code de sortie pour des arguments invalides
+pub const EXIT_USAGE: i32 = 2;
+
+/// Raison pour laquelle la connexion n'a pas abouti, à afficher à l'utilisateur.
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    #[error("{address} : adresse introuvable ({source})")]
+    Resolve { address: String, source: io::Error },
+    #[error("{address} : connexion refusée, le serveur est-il démarré ?")]
+    Refused { address: String },
+    #[error("{address} : le serveur ne répond pas")]
+    TimedOut { address: String },
+    #[error("{address} : {source}")]
+    Unreachable { address: String, source: io::Error },
+    /// Réponse incompréhensible pendant l'échange de clés ou l'identification
+    #[error("Échange impossible avec le serveur, est-ce bien un serveur mini-irc ? ({0})")]
+    Protocol(String),
+    #[error("Le nom {nickname} est déjà pris : essayez {suggestion}, ou --ghost s'il s'agit de votre session restée ouverte")]
+    NicknameInUse {
+        nickname: String,
+        suggestion: String,
+    },
+    #[error("Nom refusé par le serveur : {0}")]
+    NicknameRefused(String),
+    #[error("Reprise de session refusée : {0}")]
+    Takeover(String),
+    #[error("Serveur complet : réessayez dans {} s, ou --wait-full pour attendre une place", .retry_after.as_secs())]
+    ServerFull { retry_after: Duration },
+    /// Refus du serveur, l'adresse du client étant listée par cette zone DNSBL
+    #[error("Connexion refusée : votre adresse est listée par {0}")]
+    Blocklisted(String),
+}
+
+impl ConnectError {
+    /// Erreur de connexion à `address`, selon sa nature.
+    pub fn from_io(address: &str, source: io::Error) -> Self {
+        let address = address.to_string();
+        match source.kind() {
+            io::ErrorKind::ConnectionRefused | io::ErrorKind::NotFound => Self::Refused { address },
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Self::TimedOut { address },
+            _ => Self::Unreachable { address, source },
+        }
+    }
+
+    /// Nom pris, avec une variante à proposer.
+    pub fn nickname_in_use(nickname: &str) -> Self {
+        Self::NicknameInUse {
+            nickname: nickname.to_string(),
+            suggestion: format!("{nickname}_"),
+        }
+    }
+
+    /// Code de sortie du client, voir le tableau du module.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Resolve { .. } => 3,
+            Self::Refused { .. } | Self::TimedOut { .. } | Self::Unreachable { .. } => 4,
+            Self::Protocol(_) => 6,
+            Self::NicknameInUse { .. } => 7,
+            Self::NicknameRefused(_) | Self::Takeover(_) => 8,
+            Self::ServerFull { .. } => 9,
+            Self::Blocklisted(_) => 10,
+        }
+    }
+}
+
+/// Réglages de la socquette une fois connectée, pour qu'un serveur disparu sans fermer la
+/// connexion ne bloque pas le client indéfiniment.
+pub fn socket_options() -> SocketOptions {
+    SocketOptions::new()
+        .read_timeout(READ_TIMEOUT)
+        .write_timeout(WRITE_TIMEOUT)
+        .keepalive(KEEPALIVE_IDLE)
+}
+
+/// Adresses de `address` (`hôte:port`), la résolution de nom distinguant une adresse
+/// introuvable d'un serveur injoignable.
+pub fn resolve(address: &str) -> Result<Vec<SocketAddr>, ConnectError> {
+    let resolve_error = |source| ConnectError::Resolve {
+        address: address.to_string(),
+        source,
+    };
+    let addrs: Vec<SocketAddr> = address.to_socket_addrs().map_err(resolve_error)?.collect();
+    if addrs.is_empty() {
+        return Err(resolve_error(io::Error::new(
+            io::ErrorKind::NotFound,
+            "aucune adresse",
+        )));
+    }
+    Ok(addrs)
+}
+
+/// Ordre des tentatives : les adresses alternent entre IPv6 et IPv4, en commençant par la
+/// famille de la première adresse, celle que préfère le résolveur.
+pub fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().is_some_and(SocketAddr::is_ipv6);
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_v6);
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return ordered,
+            (first, second) => ordered.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+/// Se connecte à `address` à la façon de « happy eyeballs » : les adresses sont essayées dans
+/// l'ordre de [`interleave`], une nouvelle tentative démarrant dès l'échec de la précédente ou
+/// au bout de [`ATTEMPT_DELAY`], sans abandonner celles en cours. La première connexion établie
+/// l'emporte, [`TcpStream::peer_addr`] indique laquelle. Chaque adresse dispose de
+/// [`CONNECT_TIMEOUT`] ; si aucune n'accepte, l'erreur est celle de la dernière à échouer.
+pub fn connect_tcp(address: &str) -> Result<TcpStream, ConnectError> {
+    let mut pending = interleave(resolve(address)?).into_iter();
+    let (tx, rx) = mpsc::channel();
+    let mut running = 0;
+    let mut last = None;
+    loop {
+        if let Some(addr) = pending.next() {
+            let tx = tx.clone();
+            // Une connexion établie après la gagnante est simplement fermée
+            thread::spawn(move || {
+                let _ = tx.send(TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT));
+            });
+            running += 1;
+        }
+        if running == 0 {
+            break;
+        }
+        let result = if pending.len() == 0 {
+            rx.recv().expect("attempts always report their result")
+        } else {
+            match rx.recv_timeout(ATTEMPT_DELAY) {
+                Ok(result) => result,
+                // Pas de réponse à temps : on essaie l'adresse suivante en parallèle
+                Err(_) => continue,
+            }
+        };
+        running -= 1;
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(ConnectError::from_io(
+        address,
+        last.expect("resolve returns at least one address"),
+    ))
+}
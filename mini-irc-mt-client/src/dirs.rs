@@ -0,0 +1,22 @@
+//! Emplacement des fichiers du client, selon les conventions de chaque système :
+//! `~/.config/mini-irc` sous Linux, `%APPDATA%\mini-irc\config` sous Windows,
+//! `~/Library/Application Support/mini-irc` sous macOS...
+//!
+//! Les chemins ne doivent pas être construits à la main (`$HOME` n'existe pas sous Windows).
+
+use directories::ProjectDirs;
+use std::path::PathBuf;
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "mini-irc")
+}
+
+/// Répertoire des fichiers de configuration, `None` si le système n'en définit pas
+pub fn config_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+/// Répertoire des données conservées entre deux lancements (historique, journaux...)
+pub fn data_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.data_dir().to_path_buf())
+}
@@ -0,0 +1,53 @@
+//! Erreurs de saisie, renvoyées par [`crate::handle_user_input`] : le client choisit selon
+//! leur nature comment les présenter.
+
+use mini_irc_protocol::ParseReceiverError;
+use std::io;
+use std::path::PathBuf;
+
+/// Commande ou message refusé avant tout envoi au serveur.
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// Commande mal formée, avec la syntaxe attendue
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    /// Argument entre guillemets jamais refermés
+    #[error("Missing closing quote")]
+    UnterminatedQuote,
+    /// Commande qui ne s'applique qu'à un canal, utilisée dans un autre tab
+    #[error("The command '{0}' has to be used in a channel.")]
+    ChannelOnly(&'static str),
+    /// Aucun tab ouvert auquel appliquer la commande
+    #[error("No tab opened.")]
+    NoTab,
+    /// Aucune invitation en attente, ou pas vers ce canal
+    #[error("No pending invites")]
+    NoInvites,
+    #[error("No pending invite to #{0}")]
+    NoInviteTo(String),
+    /// Argument de la bonne forme, mais de valeur inconnue
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("Not a command: {0}")]
+    UnknownCommand(String),
+    /// Le tab courant n'accepte pas de message
+    #[error(transparent)]
+    Receiver(#[from] ParseReceiverError),
+    #[error("Cannot export to {}: {source}", path.display())]
+    Export { path: PathBuf, source: io::Error },
+    #[error("Cannot save muted channels: {0}")]
+    SaveMutes(io::Error),
+}
+
+impl ClientError {
+    /// Indique si l'erreur vient de la syntaxe d'une commande, que rappeler suffit à corriger.
+    pub fn is_usage(&self) -> bool {
+        matches!(
+            self,
+            Self::Usage(_)
+                | Self::UnterminatedQuote
+                | Self::ChannelOnly(_)
+                | Self::InvalidArgument(_)
+        )
+    }
+}
@@ -0,0 +1,93 @@
+//! Export de l'historique d'un tab par `/export [chemin]`, par exemple pour garder le compte
+//! rendu d'une réunion. Un chemin en `.json` donne un tableau JSON, tout autre chemin du texte,
+//! une ligne par entrée :
+//!
+//! ```text
+//! [2024-05-01 12:34:56 UTC] alice: bonjour
+//! ```
+
+use mini_irc_ui::{HistoryEntry, TimedEntry};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Écrit `entries` dans `path`, au format JSON si son extension est `.json`, en texte sinon.
+pub fn export(path: &Path, entries: &[TimedEntry]) -> std::io::Result<()> {
+    let json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let content = if json {
+        to_json(entries)
+    } else {
+        to_text(entries)
+    };
+    fs::write(path, content)
+}
+
+/// Une ligne par entrée, précédée de sa date.
+pub fn to_text(entries: &[TimedEntry]) -> String {
+    entries
+        .iter()
+        .map(|timed| format!("[{}] {}\n", format_time(timed.at), timed.entry.text()))
+        .collect()
+}
+
+/// Tableau d'objets `{ "time", "kind", "from", "content" }`. `from` n'existe que pour les
+/// messages et actions.
+pub fn to_json(entries: &[TimedEntry]) -> String {
+    let entries: Vec<_> = entries
+        .iter()
+        .map(|timed| {
+            let (kind, from, content) = match &timed.entry {
+                HistoryEntry::UserMessage { from, content } => ("message", Some(from), content),
+                HistoryEntry::Action { from, content } => ("action", Some(from), content),
+                HistoryEntry::Join(user) => ("join", None, user),
+                HistoryEntry::Leave(user) => ("leave", None, user),
+                HistoryEntry::TopicChange { by, topic } => ("topic", Some(by), topic),
+                HistoryEntry::Notice(notice) => ("notice", None, notice),
+                HistoryEntry::Error(error) => ("error", None, error),
+            };
+            let mut entry = json!({
+                "time": format_time(timed.at),
+                "kind": kind,
+                "content": content,
+            });
+            if let Some(from) = from {
+                entry["from"] = json!(from);
+            }
+            entry
+        })
+        .collect();
+    serde_json::to_string_pretty(&entries).expect("history entries are valid JSON")
+}
+
+/// Date UTC au format `2024-05-01 12:34:56 UTC`.
+pub fn format_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+// Date du calendrier grégorien à `days` jours de l'époque Unix (algorithme de H. Hinnant)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
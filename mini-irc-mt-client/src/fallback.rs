@@ -0,0 +1,86 @@
+//! Noms de repli : quand le nom demandé est déjà pris, le client se connecte sous un nom
+//! voisin plutôt que de s'arrêter.
+//!
+//! Les noms essayés suivent des motifs séparés par des virgules, où `{nick}` est remplacé par
+//! le nom demandé. Le dernier motif peut contenir `{n}`, remplacé par 1, 2... jusqu'à épuiser
+//! les essais : par défaut `alice_`, `alice1`, `alice2`...
+
+use thiserror::Error;
+
+/// Motifs des noms essayés par défaut
+pub const DEFAULT_FALLBACK_PATTERN: &str = "{nick}_,{nick}{n}";
+
+/// Nombre de noms de repli essayés par défaut
+pub const DEFAULT_FALLBACK_ATTEMPTS: usize = 5;
+
+/// Motif refusé, avec la raison du refus
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Motif de noms de repli invalide : {0}")]
+pub struct InvalidPattern(pub String);
+
+/// Noms essayés quand le nom demandé est pris.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NickFallback {
+    patterns: Vec<String>,
+    attempts: usize,
+}
+
+impl Default for NickFallback {
+    fn default() -> Self {
+        Self::new(DEFAULT_FALLBACK_PATTERN).unwrap()
+    }
+}
+
+impl NickFallback {
+    /// Noms de repli suivant `pattern`. Chaque motif doit contenir `{nick}`, et seul le dernier
+    /// peut contenir `{n}`.
+    pub fn new(pattern: &str) -> Result<Self, InvalidPattern> {
+        let patterns: Vec<String> = pattern.split(',').map(str::to_string).collect();
+        if let Some(pattern) = patterns.iter().find(|pattern| !pattern.contains("{nick}")) {
+            return Err(InvalidPattern(format!(
+                "{pattern:?} ne contient pas {{nick}}"
+            )));
+        }
+        let numbered = patterns.iter().position(|pattern| pattern.contains("{n}"));
+        if numbered.is_some_and(|index| index + 1 < patterns.len()) {
+            return Err(InvalidPattern(
+                "seul le dernier motif peut contenir {n}".to_string(),
+            ));
+        }
+        Ok(Self {
+            patterns,
+            attempts: DEFAULT_FALLBACK_ATTEMPTS,
+        })
+    }
+
+    /// Essaie au plus `attempts` noms de repli, aucun avec 0.
+    pub fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Noms à essayer dans l'ordre quand `nickname` est pris, sans doublon.
+    pub fn candidates(&self, nickname: &str) -> Vec<String> {
+        let mut candidates = Vec::new();
+        let mut n = 1;
+        for pattern in &self.patterns {
+            loop {
+                if candidates.len() == self.attempts {
+                    return candidates;
+                }
+                let candidate = pattern
+                    .replace("{nick}", nickname)
+                    .replace("{n}", &n.to_string());
+                if candidate != nickname && !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+                // Seul un motif numéroté produit plusieurs noms
+                if !pattern.contains("{n}") {
+                    break;
+                }
+                n += 1;
+            }
+        }
+        candidates
+    }
+}
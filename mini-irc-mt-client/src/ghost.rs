@@ -0,0 +1,57 @@
+//! Clé de reprise de session : enregistrée auprès du serveur à la connexion
+//! ([`Request::GhostKey`]), elle permet à un nouveau lancement du client de fermer une session
+//! restée ouverte ([`Request::Ghost`]), par exemple après un plantage.
+//!
+//! La clé est conservée dans [`dirs::data_dir`], et propre à chaque installation du client.
+
+use crate::dirs;
+use mini_irc_protocol::Request;
+use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("ghost.key"))
+}
+
+fn generate() -> String {
+    SharedKey::generate()
+        .as_slice()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Lit la clé conservée, ou en crée une. Sans répertoire de données, la clé ne vaut que pour
+// ce lancement.
+fn load() -> String {
+    let Some(path) = path() else {
+        return generate();
+    };
+    match fs::read_to_string(&path) {
+        Ok(key) if !key.trim().is_empty() => key.trim().to_string(),
+        _ => {
+            let key = generate();
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let _ = fs::write(&path, &key);
+            key
+        }
+    }
+}
+
+/// Clé de reprise de ce client, lue une seule fois par lancement.
+pub fn key() -> &'static str {
+    static KEY: OnceLock<String> = OnceLock::new();
+    KEY.get_or_init(load)
+}
+
+/// Requête fermant la session `nickname` ouverte par ce client.
+pub fn ghost(nickname: &str) -> Request {
+    Request::Ghost {
+        nickname: nickname.to_string(),
+        key: key().to_string(),
+    }
+}
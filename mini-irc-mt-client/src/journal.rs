@@ -0,0 +1,35 @@
+//! Journal du client, `client.log` dans [`dirs::data_dir`] : l'interface occupe le terminal,
+//! les évènements à examiner après coup y sont ajoutés, une ligne chacun.
+
+use crate::dirs;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Fichier du journal, `None` si le système ne définit pas de répertoire de données
+pub fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("client.log"))
+}
+
+/// Ajoute `line` au journal. Un journal impossible à écrire est ignoré : il ne doit pas
+/// interrompre le client.
+pub fn log(line: &str) {
+    if let Some(path) = path() {
+        let _ = log_to(&path, line);
+    }
+}
+
+/// Comme [`log`], mais dans le fichier `path`. Chaque ligne est précédée de sa date, en
+/// secondes depuis l'époque Unix.
+pub fn log_to(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{now} {}", line.replace('\n', " "))
+}
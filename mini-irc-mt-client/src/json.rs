@@ -0,0 +1,98 @@
+//! Mode `--json` : sans interface, chaque réponse du serveur est écrite sur la sortie standard
+//! et chaque ligne de l'entrée standard est une requête, en JSON. Un script peut ainsi piloter
+//! une connexion sans implémenter le protocole ni le chiffrement, par exemple :
+//!
+//! ```text
+//! $ echo '{"JoinChan":"general"}' | ./client --json 127.0.0.1:6379 bot
+//! {"AckJoin":{"chan":"general","users":["bot"]}}
+//! {"Channel":{"op":{"UserAdd":"bot"},"chan":"general"}}
+//! ```
+
+use mini_irc_protocol::{
+    Encrypted, ProtocolError, Request, Response, SyncTransport, TypedReader, TypedWriter,
+};
+use std::error::Error;
+use std::fmt::Debug;
+use std::io::{BufRead, Write};
+use std::net::Shutdown;
+use std::sync::mpsc;
+use std::thread::spawn;
+
+enum Event {
+    ServerResponse(Response),
+    /// Connexion fermée par le serveur, ou perdue
+    Closed,
+    Line(String),
+    /// Fin de l'entrée standard
+    EndOfInput,
+}
+
+/// Relaie les réponses et les requêtes jusqu'à la fin de l'entrée standard ou de la connexion.
+/// Les pings du serveur reçoivent une réponse sans être écrits.
+pub fn run<S>(
+    stream: S,
+    mut reader: TypedReader<S, Response, Encrypted>,
+    mut writer: TypedWriter<S, Request, Encrypted>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: SyncTransport + Debug + Send + 'static,
+{
+    let (events_tx, events_rx) = mpsc::channel();
+
+    let server_events = events_tx.clone();
+    spawn(move || {
+        loop {
+            match reader.recv() {
+                Ok(Some(response)) => {
+                    if server_events.send(Event::ServerResponse(response)).is_err() {
+                        return;
+                    }
+                }
+                Ok(None) | Err(ProtocolError::Closed) => break,
+                Err(e) => {
+                    eprintln!("Connexion au serveur perdue : {e}");
+                    break;
+                }
+            }
+        }
+        let _ = server_events.send(Event::Closed);
+    });
+    // Le thread reste bloqué sur l'entrée standard si la connexion se ferme avant : il est
+    // interrompu avec le processus.
+    spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if events_tx.send(Event::Line(line)).is_err() {
+                return;
+            }
+        }
+        let _ = events_tx.send(Event::EndOfInput);
+    });
+
+    let mut stdout = std::io::stdout().lock();
+    while let Ok(event) = events_rx.recv() {
+        match event {
+            Event::ServerResponse(Response::Ping(id)) => writer.send(&Request::Pong(id))?,
+            Event::ServerResponse(response) => {
+                serde_json::to_writer(&mut stdout, &response)?;
+                writeln!(stdout)?;
+                stdout.flush()?;
+            }
+            Event::Line(line) if line.trim().is_empty() => {}
+            Event::Line(line) => match serde_json::from_str::<Request>(&line) {
+                Ok(request) => writer.send(&request)?,
+                Err(e) => eprintln!("Requête invalide ({e}) : {line}"),
+            },
+            Event::Closed => break,
+            Event::EndOfInput => {
+                // Fermeture voulue, annoncée au serveur
+                let _ = writer.close();
+                break;
+            }
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Mesure continue de la latence : un [`Request::Ping`] est envoyé à intervalle régulier, et le
+//! temps jusqu'au [`mini_irc_protocol::Response::Pong`] correspondant est affiché par
+//! [`mini_irc_ui::App::set_lag`].
+
+use mini_irc_protocol::Request;
+use std::time::{Duration, Instant};
+
+/// Intervalle par défaut entre deux sondes
+pub const LAG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sondes de latence d'une connexion. Une seule sonde est en attente à la fois.
+#[derive(Debug)]
+pub struct LagMeter {
+    interval: Duration,
+    next_id: u64,
+    /// Sonde en attente de réponse, et sa date d'envoi
+    pending: Option<(u64, Instant)>,
+    last_sent: Option<Instant>,
+    /// Dernière latence affichée
+    last: Option<Duration>,
+}
+
+impl Default for LagMeter {
+    fn default() -> Self {
+        Self::new(LAG_INTERVAL)
+    }
+}
+
+impl LagMeter {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_id: 0,
+            pending: None,
+            last_sent: None,
+            last: None,
+        }
+    }
+
+    /// Sonde à envoyer à la date `now`, si la précédente a reçu sa réponse depuis assez
+    /// longtemps.
+    pub fn probe(&mut self, now: Instant) -> Option<Request> {
+        let due = self
+            .last_sent
+            .is_none_or(|sent| now.duration_since(sent) >= self.interval);
+        if self.pending.is_some() || !due {
+            return None;
+        }
+        self.next_id += 1;
+        self.pending = Some((self.next_id, now));
+        self.last_sent = Some(now);
+        Some(Request::Ping(self.next_id))
+    }
+
+    /// Latence à afficher à la réception du pong `id` à la date `now`. Un pong inattendu
+    /// n'est pas mesuré.
+    pub fn pong(&mut self, id: u64, now: Instant) -> Option<Duration> {
+        match self.pending {
+            Some((pending, sent)) if pending == id => {
+                self.pending = None;
+                let lag = now.duration_since(sent);
+                self.last = Some(lag);
+                Some(lag)
+            }
+            _ => None,
+        }
+    }
+
+    /// Latence à afficher à la date `now` alors que la sonde en attente dépasse déjà la
+    /// dernière mesure : un serveur qui ne répond plus voit sa latence grandir.
+    pub fn overdue(&mut self, now: Instant) -> Option<Duration> {
+        let (_, sent) = self.pending?;
+        let waiting = now.duration_since(sent);
+        if self.last.is_some_and(|last| waiting <= last) {
+            return None;
+        }
+        self.last = Some(waiting);
+        Some(waiting)
+    }
+}
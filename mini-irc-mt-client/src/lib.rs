@@ -1,37 +1,263 @@
-use mini_irc_protocol::{MessageReceiver, Request};
-use mini_irc_ui::App;
-
-pub fn handle_user_input(input: String, app: &mut App) -> Result<Option<Request>, String> {
-    if input.starts_with('/') {
-        // On a reçu une commande.
-        if input.starts_with("/join") {
-            match input.strip_prefix("/join ") {
-                Some(chan) => Ok(Some(Request::JoinChan(chan.to_string()))),
-                None => Err(
-                    "The command 'join' has to be used with the name of a channel to join."
-                        .to_string(),
-                ),
-            }
-        } else if input.starts_with("/quit") {
+use command::Command;
+use error::ClientError;
+use mini_irc_protocol::{ErrorCode, MessageReceiver, MessageTargets, Request};
+use mini_irc_ui::{App, Severity};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub mod command;
+pub mod diagnostic;
+pub mod dirs;
+pub mod error;
+pub mod export;
+pub mod fallback;
+pub mod ghost;
+pub mod journal;
+pub mod lag;
+pub mod locale;
+pub mod mutes;
+pub mod recall;
+pub mod retry;
+pub mod sequence;
+pub mod session;
+pub mod trace;
+
+use mutes::Mutes;
+
+pub fn handle_user_input(
+    input: String,
+    app: &mut App,
+    mutes: &mut Mutes,
+) -> Result<Option<Request>, ClientError> {
+    let Some(command) = Command::parse(&input) else {
+        // On a reçu un message pour le tab courant.
+        return Ok(Some(Request::Message {
+            to: app.get_current_tab().parse()?,
+            content: input,
+        }));
+    };
+    // On a reçu une commande.
+    match command.name {
+        "join" => {
+            let [chan] = command.exactly("/join <channel>")?;
+            // Le tab s'ouvre tout de suite, et disparaît si le serveur refuse
+            app.add_pending_tab(chan_tab(&chan));
+            Ok(Some(Request::JoinChan(chan)))
+        }
+        "quit" => {
             let s = app.get_current_tab();
             if s.is_empty() {
-                Err("Can't quit. No channel joined.".to_string())
+                return Err(ClientError::NoTab);
+            }
+            match s.parse()? {
+                MessageReceiver::Channel(chan) => Ok(Some(Request::LeaveChan(chan))),
+                // Rien à quitter côté serveur : le tab se ferme
+                MessageReceiver::User(_) => {
+                    app.remove_tab(s);
+                    Ok(None)
+                }
+                // Aucun tab n'est ouvert pour les annonces
+                MessageReceiver::All | MessageReceiver::Channels(_) => Err(ClientError::NoTab),
+            }
+        }
+        // Resynchronise la liste des membres du canal courant
+        "names" => Ok(Some(Request::Names(current_chan(app, "names")?))),
+        "export" => {
+            // `/export notes.json` : historique complet du tab courant, daté
+            let path = command.optional("/export [path]")?;
+            let tab = app.get_current_tab();
+            let Some(entries) = app.history(&tab) else {
+                return Err(ClientError::NoTab);
+            };
+            let path = match path {
+                Some(path) => PathBuf::from(path),
+                None => PathBuf::from(format!("{}.txt", tab.trim_start_matches(['#', '@']))),
+            };
+            if let Err(source) = export::export(&path, entries) {
+                return Err(ClientError::Export { path, source });
+            }
+            let notif = format!("{} entries exported to {}", entries.len(), path.display());
+            app.notify(Severity::Info, notif);
+            Ok(None)
+        }
+        "op" => {
+            // `/op bob` : transmet la propriété du canal courant à bob
+            let chan = current_chan(app, "op")?;
+            let [to] = command.exactly("/op <nickname>")?;
+            Ok(Some(Request::TransferOp { chan, to }))
+        }
+        "welcome" => {
+            // `/welcome Bienvenue !` : message d'accueil du canal courant, supprimé sans texte
+            let chan = current_chan(app, "welcome")?;
+            Ok(Some(Request::SetWelcome {
+                chan,
+                text: command.rest().trim_end().to_string(),
+            }))
+        }
+        "invites" => {
+            let invites: Vec<String> = app
+                .invites()
+                .iter()
+                .map(|invite| format!("{} (by {})", chan_tab(&invite.chan), invite.by))
+                .collect();
+            let notif = if invites.is_empty() {
+                "No pending invites".to_string()
             } else {
-                match s.parse() {
-                    Ok(MessageReceiver::Channel(chan)) => Ok(Some(Request::LeaveChan(chan))),
-                    Ok(MessageReceiver::User(_)) => {
-                        todo!("What does it mean to leave DM from one user?")
-                    }
-                    Err(e) => Err(e),
+                format!("Pending invites: {}", invites.join(", "))
+            };
+            app.notify(Severity::Info, notif);
+            Ok(None)
+        }
+        "invite" => {
+            // `/invite bob` : invite bob dans le canal courant
+            let chan = current_chan(app, "invite")?;
+            let [user] = command.exactly("/invite <nickname>")?;
+            Ok(Some(Request::Invite { chan, user }))
+        }
+        "accept-invite" => {
+            // Sans canal, la dernière invitation reçue
+            let chan = command.optional("/accept-invite [channel]")?;
+            let chan = chan.as_deref().map(|chan| chan.trim_start_matches('#'));
+            match app.accept_invite(chan) {
+                Some(invite) => {
+                    app.add_pending_tab(chan_tab(&invite.chan));
+                    Ok(Some(Request::JoinChan(invite.chan)))
                 }
+                None => Err(match chan {
+                    Some(chan) => ClientError::NoInviteTo(chan.to_string()),
+                    None => ClientError::NoInvites,
+                }),
+            }
+        }
+        "unblock" => {
+            let [user] = command.exactly("/unblock <nickname>")?;
+            Ok(Some(Request::Unblock(user)))
+        }
+        "block" => {
+            // Contrairement à l'ignorance, le serveur refuse les messages directs à la source
+            let [user] = command.exactly("/block <nickname>")?;
+            Ok(Some(Request::Block(user)))
+        }
+        "claim" => Ok(Some(Request::ClaimOp(current_chan(app, "claim")?))),
+        "list" => Ok(Some(Request::ListChans)),
+        "whois" => {
+            let [user] = command.exactly("/whois <nickname>")?;
+            Ok(Some(Request::WhoIs(user)))
+        }
+        name @ ("mute" | "unmute") => {
+            // Les messages du tab courant s'accumulent sans le signaler comme non lu
+            let tab = app.get_current_tab();
+            let Ok(MessageReceiver::Channel(_)) = tab.parse() else {
+                return Err(ClientError::ChannelOnly("mute"));
+            };
+            let muted = name == "mute";
+            app.set_muted(&tab, muted);
+            mutes.set(&tab, muted).map_err(ClientError::SaveMutes)?;
+            Ok(None)
+        }
+        name @ ("watch" | "unwatch") => {
+            // `/watch spam`: signale les messages du canal courant contenant « spam »
+            let [keyword] = command.exactly("/watch <keyword>, /unwatch <keyword>")?;
+            let chan = current_chan(app, "watch")?;
+            if name == "watch" {
+                Ok(Some(Request::WatchKeyword { chan, keyword }))
+            } else {
+                Ok(Some(Request::UnwatchKeyword { chan, keyword }))
+            }
+        }
+        "ghost" => {
+            // `/ghost alice`: ferme la session alice restée ouverte par ce client
+            let [nickname] = command.exactly("/ghost <nickname>")?;
+            Ok(Some(ghost::ghost(&nickname)))
+        }
+        "stats" => match command.optional("/stats [nickname]")? {
+            Some(user) => Ok(Some(Request::StatsOf(user))),
+            None => Ok(Some(Request::Stats)),
+        },
+        "filter" => {
+            // `/filter joins off`: masque les arrivées dans le tab courant
+            let usage = "/filter <messages|actions|joins|leaves|topics|notices|errors> <on|off>";
+            let [kind, state] = command.exactly(usage)?;
+            let visible = on_off(&state, usage)?;
+            let kind = kind.parse().map_err(ClientError::InvalidArgument)?;
+            app.set_visible(kind, visible);
+            Ok(None)
+        }
+        "completion" => {
+            // `/completion ,` : Tab complète « bob, » en début de ligne ; `/completion off` :
+            // le nom seul
+            let [suffix] = command.exactly("/completion <suffix|off>")?;
+            match suffix.as_str() {
+                "off" => app.set_completion_suffix(String::new()),
+                suffix => app.set_completion_suffix(format!("{suffix} ")),
+            }
+            Ok(None)
+        }
+        "collapse" => {
+            // `/collapse off`: affiche chaque arrivée et départ au lieu d'un résumé
+            let usage = "/collapse <on|off>";
+            let [state] = command.exactly(usage)?;
+            app.set_collapse_presence(on_off(&state, usage)?);
+            Ok(None)
+        }
+        "reveal" => {
+            // `/reveal 2` : affiche, ou masque à nouveau, les ||spoilers|| de l'avant-dernier
+            // message qui en contient
+            let usage = "/reveal [n]";
+            let n = match command.optional(usage)? {
+                Some(n) => n.parse().map_err(|_| ClientError::Usage(usage))?,
+                None => 1,
+            };
+            if !app.toggle_spoilers(n) {
+                return Err(ClientError::InvalidArgument(format!(
+                    "No message #{n} with a spoiler in this tab"
+                )));
+            }
+            Ok(None)
+        }
+        "markdown" => {
+            // `/markdown off` : affiche `*gras*` tel quel
+            let usage = "/markdown <on|off>";
+            let [state] = command.exactly(usage)?;
+            app.set_markdown(on_off(&state, usage)?);
+            Ok(None)
+        }
+        "direction" => {
+            // `/direction rtl`: saisie de droite à gauche dans le tab courant
+            let [direction] = command.exactly("/direction <auto|ltr|rtl>")?;
+            let direction = direction.parse().map_err(ClientError::InvalidArgument)?;
+            app.set_input_direction(direction);
+            Ok(None)
+        }
+        "clear" => {
+            let [what] = command.exactly("/clear notif")?;
+            if what != "notif" {
+                return Err(ClientError::Usage("/clear notif"));
             }
-        } else if input.starts_with("/clear notif") {
             app.clear_notif();
             Ok(None)
-        } else if input.starts_with("/to") {
-            let res = input.splitn(3, ' ').collect::<Vec<_>>();
-            let username = res[1].to_string();
-            let msg = res[2].to_string();
+        }
+        "notif" => {
+            // `/notif 30`: les notifications disparaissent après 30 secondes
+            let [secs] = command.exactly("/notif <seconds|off>")?;
+            let duration = match secs.as_str() {
+                "off" => None,
+                secs => match secs.parse() {
+                    Ok(secs) => Some(Duration::from_secs(secs)),
+                    Err(_) => {
+                        return Err(ClientError::InvalidArgument(format!(
+                            "Not a number of seconds: {secs}"
+                        )))
+                    }
+                },
+            };
+            app.set_notification_duration(duration);
+            Ok(None)
+        }
+        "to" => {
+            // `/to "bob smith" salut` : le message est le reste de la ligne, tel quel
+            let (username, msg) = command.first_and_rest("/to <nickname> <message>")?;
+            let msg = msg.to_string();
             let tab_name = format!("@{username}");
             app.add_tab(tab_name.clone());
             app.push_message("myself".into(), msg.clone(), tab_name);
@@ -39,16 +265,84 @@ pub fn handle_user_input(input: String, app: &mut App) -> Result<Option<Request>
                 to: MessageReceiver::User(username),
                 content: msg,
             }))
-        } else {
-            Err(format!("Not a command: {input}"))
         }
-    } else {
-        // On a reçu un message pour le tab courant.
-        // Pour le moment, on ne gère que le cas des channels.
+        "msg" => {
+            // `/msg @bob on mange ?` : sans changer de tab, ni en ouvrir un par défaut.
+            // `/msg #a,#b,@carol salut` : le même message à chacun
+            let (to, msg) = command.first_and_rest("/msg <#channel|@user>[,...] <message>")?;
+            let MessageTargets(mut to) = to.parse()?;
+            let msg = msg.to_string();
+            for target in &to {
+                if let MessageReceiver::User(user) = target {
+                    // Le serveur ne renvoie pas les messages directs à leur auteur
+                    let tab_name = format!("@{user}");
+                    if app.msg_opens_tab() {
+                        app.add_tab(tab_name.clone());
+                    }
+                    if app.history(&tab_name).is_some() {
+                        app.push_message("myself".into(), msg.clone(), tab_name);
+                    } else if to.len() == 1 {
+                        app.notify(Severity::Info, format!("Message sent to {tab_name}"));
+                    }
+                }
+            }
+            if to.len() == 1 {
+                Ok(Some(Request::Message {
+                    to: to.remove(0),
+                    content: msg,
+                }))
+            } else {
+                // Le serveur rapporte le résultat de chaque envoi
+                Ok(Some(Request::MessageMany { to, content: msg }))
+            }
+        }
+        "msgtab" => {
+            // `/msgtab on` : `/msg` ouvre le tab de la conversation
+            let usage = "/msgtab <on|off>";
+            let [state] = command.exactly(usage)?;
+            app.set_msg_opens_tab(on_off(&state, usage)?);
+            Ok(None)
+        }
+        _ => Err(ClientError::UnknownCommand(input)),
+    }
+}
 
-        Ok(Some(Request::Message {
-            to: app.get_current_tab().parse()?,
-            content: input,
-        }))
+/// Nom du tab d'un canal : `#general`, ou `&annonces` pour un canal système.
+pub fn chan_tab(chan: &str) -> String {
+    MessageReceiver::Channel(chan.to_string()).to_string()
+}
+
+/// Retire le tab ouvert d'avance par le `/join` que `code` refuse, et renvoie son nom. Le
+/// refus nomme son canal : l'erreur d'une autre requête en attente, un message direct par
+/// exemple, ne ferme aucun tab.
+pub fn cancel_refused_join(app: &mut App, code: &ErrorCode) -> Option<String> {
+    let (ErrorCode::AlreadyInChannel(chan)
+    | ErrorCode::UnknownChannel(chan)
+    | ErrorCode::SeparatorInChannelName(chan)) = code
+    else {
+        return None;
+    };
+    let tab = chan_tab(chan);
+    app.cancel_pending_tab(&tab).then_some(tab)
+}
+
+/// Canal d'un tab nommé par [`chan_tab`], `None` pour un tab de messages directs.
+pub fn tab_chan(tab: &str) -> Option<String> {
+    match tab.parse() {
+        Ok(MessageReceiver::Channel(chan)) => Some(chan),
+        _ => None,
+    }
+}
+
+// Canal du tab courant, pour une commande qui ne s'applique qu'à un canal
+fn current_chan(app: &App, command: &'static str) -> Result<String, ClientError> {
+    tab_chan(&app.get_current_tab()).ok_or(ClientError::ChannelOnly(command))
+}
+
+fn on_off(state: &str, usage: &'static str) -> Result<bool, ClientError> {
+    match state {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(ClientError::Usage(usage)),
     }
 }
@@ -0,0 +1,172 @@
+//! Traduction des erreurs et des avis du serveur, qui n'envoie que des codes
+//! ([`ErrorCode`], [`Notice`]). La langue est lue dans `MINI_IRC_LANG`, puis dans les
+//! variables de locale habituelles (`LC_ALL`, `LC_MESSAGES`, `LANG`) : `fr_FR.UTF-8`
+//! affiche les messages en français, toute autre langue en anglais.
+
+use mini_irc_protocol::{ErrorCode, Notice};
+
+/// Langue des messages du serveur.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+}
+
+impl Locale {
+    /// Langue de l'environnement, anglais par défaut.
+    pub fn from_env() -> Self {
+        ["MINI_IRC_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map_or_else(Self::default, |value| Self::parse(&value))
+    }
+
+    /// Langue d'une locale POSIX (`fr`, `fr_FR.UTF-8`...), anglais si elle n'est pas traduite.
+    pub fn parse(locale: &str) -> Self {
+        let language = locale.split(['_', '-', '.', '@']).next().unwrap_or("");
+        match language.to_ascii_lowercase().as_str() {
+            "fr" => Self::Fr,
+            _ => Self::En,
+        }
+    }
+
+    /// Texte d'une erreur du serveur.
+    pub fn error(self, code: &ErrorCode) -> String {
+        match self {
+            Self::En => error_en(code),
+            Self::Fr => error_fr(code),
+        }
+    }
+
+    /// Texte d'un avis du serveur.
+    pub fn notice(self, notice: &Notice) -> String {
+        match self {
+            Self::En => notice_en(notice),
+            Self::Fr => notice_fr(notice),
+        }
+    }
+}
+
+fn error_en(code: &ErrorCode) -> String {
+    match code {
+        ErrorCode::UnexpectedHandshake => "Unexpected handshake request".to_string(),
+        ErrorCode::HandshakeInProgress => "Handshake in progress".to_string(),
+        ErrorCode::NotConnected => "Please connect first".to_string(),
+        ErrorCode::AlreadyConnected => "Already connected".to_string(),
+        ErrorCode::EmptyNickname => "Empty username".to_string(),
+        ErrorCode::ChannelPrefixInNickname => {
+            "Invalid username: # and & start channel names".to_string()
+        }
+        ErrorCode::WhitespaceInNickname => "Invalid username: no spaces allowed".to_string(),
+        ErrorCode::ReservedNickname(nickname) => format!("Reserved username: {nickname}"),
+        ErrorCode::NickInUse => "Username already taken".to_string(),
+        ErrorCode::UnknownUser(user) => format!("Unknown user: {user}"),
+        ErrorCode::UnknownChannel(chan) => format!("Unknown channel: {chan}"),
+        ErrorCode::PermissionDenied => "Permission denied".to_string(),
+        ErrorCode::NotInChannel => "Not in channel".to_string(),
+        ErrorCode::AlreadyInChannel(chan) => format!("Channel already joined: {chan}"),
+        ErrorCode::UserInChannel(user) => format!("Already in channel: {user}"),
+        ErrorCode::NotAMember(user) => format!("Not a member: {user}"),
+        ErrorCode::ChannelHasOwner => "Channel has an owner".to_string(),
+        ErrorCode::MessageTooLong => "Message too long".to_string(),
+        ErrorCode::Blocked => "Blocked".to_string(),
+        ErrorCode::CannotBlockSelf => "Cannot block yourself".to_string(),
+        ErrorCode::CannotGhostSelf => "Cannot ghost your own session".to_string(),
+        ErrorCode::Unreachable(user) => format!("User {user} cannot receive messages"),
+        ErrorCode::InvalidKeyword(keyword) => format!("Invalid keyword: {keyword}"),
+        ErrorCode::SessionTakenOver => "Session taken over by another connection".to_string(),
+        ErrorCode::ServerFull { retry_after } => {
+            format!("Server full, retry in {} s", retry_after.as_secs())
+        }
+        ErrorCode::Blocklisted(zone) => format!("Address listed by {zone}"),
+        ErrorCode::TooManyTargets(max) => format!("Too many recipients, at most {max}"),
+        ErrorCode::TooManyBlocked(max) => format!("Too many blocked users, at most {max}"),
+        ErrorCode::RateLimited { retry_after } => {
+            format!("Rate limited, retry in {} ms", retry_after.as_millis())
+        }
+        ErrorCode::SeparatorInNickname => "Invalid username: no commas allowed".to_string(),
+        ErrorCode::SeparatorInChannelName(chan) => {
+            format!("Invalid channel name, no commas allowed: {chan}")
+        }
+        unknown => format!("Server error: {unknown:?}"),
+    }
+}
+
+fn error_fr(code: &ErrorCode) -> String {
+    match code {
+        ErrorCode::UnexpectedHandshake => "Échange de clés inattendu".to_string(),
+        ErrorCode::HandshakeInProgress => "Échange de clés en cours".to_string(),
+        ErrorCode::NotConnected => "Connectez-vous d'abord".to_string(),
+        ErrorCode::AlreadyConnected => "Déjà connecté".to_string(),
+        ErrorCode::EmptyNickname => "Nom d'utilisateur vide".to_string(),
+        ErrorCode::ChannelPrefixInNickname => {
+            "Nom d'utilisateur invalide : # et & commencent les noms de canaux".to_string()
+        }
+        ErrorCode::WhitespaceInNickname => {
+            "Nom d'utilisateur invalide : espaces interdits".to_string()
+        }
+        ErrorCode::ReservedNickname(nickname) => format!("Nom d'utilisateur réservé : {nickname}"),
+        ErrorCode::NickInUse => "Nom d'utilisateur déjà pris".to_string(),
+        ErrorCode::UnknownUser(user) => format!("Utilisateur inconnu : {user}"),
+        ErrorCode::UnknownChannel(chan) => format!("Canal inconnu : {chan}"),
+        ErrorCode::PermissionDenied => "Permission refusée".to_string(),
+        ErrorCode::NotInChannel => "Pas dans le canal".to_string(),
+        ErrorCode::AlreadyInChannel(chan) => format!("Canal déjà rejoint : {chan}"),
+        ErrorCode::UserInChannel(user) => format!("Déjà dans le canal : {user}"),
+        ErrorCode::NotAMember(user) => format!("Pas membre du canal : {user}"),
+        ErrorCode::ChannelHasOwner => "Le canal a un propriétaire".to_string(),
+        ErrorCode::MessageTooLong => "Message trop long".to_string(),
+        ErrorCode::Blocked => "Bloqué".to_string(),
+        ErrorCode::CannotBlockSelf => "Impossible de se bloquer soi-même".to_string(),
+        ErrorCode::CannotGhostSelf => "Impossible de fermer sa propre session".to_string(),
+        ErrorCode::Unreachable(user) => format!("{user} ne peut pas recevoir de messages"),
+        ErrorCode::InvalidKeyword(keyword) => format!("Mot-clé invalide : {keyword}"),
+        ErrorCode::SessionTakenOver => "Session reprise par une autre connexion".to_string(),
+        ErrorCode::ServerFull { retry_after } => {
+            format!(
+                "Serveur complet, réessayez dans {} s",
+                retry_after.as_secs()
+            )
+        }
+        ErrorCode::Blocklisted(zone) => format!("Adresse listée par {zone}"),
+        ErrorCode::TooManyTargets(max) => format!("Trop de destinataires, {max} au plus"),
+        ErrorCode::TooManyBlocked(max) => format!("Trop d'utilisateurs bloqués, {max} au plus"),
+        ErrorCode::RateLimited { retry_after } => {
+            format!(
+                "Débit limité, réessayez dans {} ms",
+                retry_after.as_millis()
+            )
+        }
+        ErrorCode::SeparatorInNickname => {
+            "Nom d'utilisateur invalide : virgules interdites".to_string()
+        }
+        ErrorCode::SeparatorInChannelName(chan) => {
+            format!("Nom de canal invalide, virgules interdites : {chan}")
+        }
+        unknown => format!("Erreur du serveur : {unknown:?}"),
+    }
+}
+
+fn notice_en(notice: &Notice) -> String {
+    match notice {
+        Notice::Welcome(text) => text.clone(),
+        Notice::WelcomeSet(text) => format!("Welcome message set: {text}"),
+        Notice::WelcomeCleared => "Welcome message cleared".to_string(),
+        Notice::InviteSent(user) => format!("Invitation sent to {user}"),
+        Notice::Announcement { from, content } => format!("Announcement from {from}: {content}"),
+        unknown => format!("Server notice: {unknown:?}"),
+    }
+}
+
+fn notice_fr(notice: &Notice) -> String {
+    match notice {
+        Notice::Welcome(text) => text.clone(),
+        Notice::WelcomeSet(text) => format!("Message d'accueil : {text}"),
+        Notice::WelcomeCleared => "Message d'accueil supprimé".to_string(),
+        Notice::InviteSent(user) => format!("Invitation envoyée à {user}"),
+        Notice::Announcement { from, content } => format!("Annonce de {from} : {content}"),
+        unknown => format!("Avis du serveur : {unknown:?}"),
+    }
+}
@@ -1,12 +1,37 @@
 use crossterm::event;
-use mini_irc_mt::handle_user_input;
-use mini_irc_protocol::{ChanOp, Request, Response, TypedReader, TypedWriter};
-use mini_irc_ui::{App, KeyReaction};
+use mini_irc_mt::{
+    cancel_refused_join, chan_tab,
+    diagnostic::{self, ConnectError, EXIT_USAGE},
+    error::ClientError,
+    fallback::NickFallback,
+    ghost, handle_user_input, journal,
+    lag::LagMeter,
+    locale::Locale,
+    mutes::Mutes,
+    recall::Recall,
+    retry::RetryQueue,
+    sequence::Sequences,
+    session::Session,
+    tab_chan,
+    trace::{self, DEBUG_DUMP_FRAMES},
+};
+use mini_irc_protocol::{
+    ChanOp, Encrypted, EncryptionStatus, ErrorCode, HandshakeRequest, HandshakeResponse,
+    NoticeScope, Plain, ProtocolError, Request, RequestResponder, Response, SyncTransport,
+    SyncTypedChannel, TypedReader, TypedWriter, WireTap, MAX_HISTORY_FETCH,
+};
+use mini_irc_ui::{
+    mask_spoilers, App, ChannelEntry, ConnectInfo, HistoryEntry, KeyReaction, Presence, Security,
+    ServerStats, Severity, TabLayout, TimedEntry, UserDetails, NETWORK_TAB,
+};
+use std::collections::HashMap;
 use std::env;
 use std::error::Error;
+use std::fmt::Debug;
 use std::net::Shutdown;
-use std::thread::spawn;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
 
 use crypto_box::PublicKey;
 use serde_encrypt::{
@@ -15,74 +40,536 @@ use serde_encrypt::{
 };
 use serde_encrypt_core::key::key_pair::public_key::ReceiverPublicKey;
 
+mod json;
+
+#[allow(clippy::enum_variant_names)]
 enum Event {
     TerminalEvent(event::Event),
     ServerResponse(Response),
+    // Connexion fermée proprement par le serveur
+    Closed,
+    // Connexion perdue
+    Disconnected,
+    // Plus rien reçu du serveur, pas même un ping, depuis `diagnostic::READ_TIMEOUT`
+    Unresponsive,
+    // Envoyé toutes les `TICK_RATE` : animations, expiration des notifications...
+    Tick,
+}
+
+// Période des `Event::Tick`
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+// Délai pour annoncer la fermeture au serveur en quittant, s'il ne lit plus
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Interface du client une fois connecté
+enum Frontend<'a> {
+    Tui {
+        app: &'a mut App,
+        start_time: Instant,
+        // Marge ajoutée au délai des messages refusés par limite de débit, voir `retry`
+        retry_margin: Duration,
+        // Saisies conservées entre deux lancements, voir `recall`
+        history: bool,
+    },
+    // Requêtes et réponses en JSON sur l'entrée et la sortie standard, voir `json`
+    Json,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     // Initialisation pour les logs d'erreurs.
     let start_time = Instant::now();
 
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // `--ghost`: ferme la session restée ouverte sous le même nom, voir `ghost`
+    let takeover = match args.iter().position(|arg| arg == "--ghost") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    // `--wait-full`: réessaie tant que le serveur est complet, au rythme qu'il indique
+    let wait_full = match args.iter().position(|arg| arg == "--wait-full") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    // `--no-history`: les saisies ne sont ni relues ni enregistrées, voir `recall`
+    let history = match args.iter().position(|arg| arg == "--no-history") {
+        Some(index) => {
+            args.remove(index);
+            false
+        }
+        None => true,
+    };
+    // `--retry-margin MS`: marge avant de renvoyer un message refusé par limite de débit
+    let retry_margin = match args.iter().position(|arg| arg == "--retry-margin") {
+        Some(index) if index + 1 < args.len() => {
+            let Ok(ms) = args[index + 1].parse() else {
+                eprintln!("--retry-margin attend une durée en millisecondes");
+                std::process::exit(EXIT_USAGE);
+            };
+            args.drain(index..=index + 1);
+            Duration::from_millis(ms)
+        }
+        _ => mini_irc_mt::retry::RETRY_MARGIN,
+    };
+    // `--nick-fallback MOTIFS`: noms essayés quand le nom demandé est pris, voir `fallback`
+    let fallback = match args.iter().position(|arg| arg == "--nick-fallback") {
+        Some(index) if index + 1 < args.len() => {
+            let fallback = match NickFallback::new(&args[index + 1]) {
+                Ok(fallback) => fallback,
+                Err(e) => {
+                    eprintln!("{e}");
+                    std::process::exit(EXIT_USAGE);
+                }
+            };
+            args.drain(index..=index + 1);
+            fallback
+        }
+        Some(_) => {
+            eprintln!("--nick-fallback attend des motifs, par exemple {{nick}}_,{{nick}}{{n}}");
+            std::process::exit(EXIT_USAGE);
+        }
+        None => NickFallback::default(),
+    };
+    // `--nick-attempts N`: nombre de noms de repli essayés, aucun avec 0
+    let fallback = match args.iter().position(|arg| arg == "--nick-attempts") {
+        Some(index) if index + 1 < args.len() => {
+            let Ok(attempts) = args[index + 1].parse() else {
+                eprintln!("--nick-attempts attend un nombre d'essais");
+                std::process::exit(EXIT_USAGE);
+            };
+            args.drain(index..=index + 1);
+            fallback.with_attempts(attempts)
+        }
+        Some(_) => {
+            eprintln!("--nick-attempts attend un nombre d'essais");
+            std::process::exit(EXIT_USAGE);
+        }
+        None => fallback,
+    };
+    // `--debug-dump FICHIER`: trace des trames échangées, écrite en quittant, voir `trace`
+    let debug_dump = match args.iter().position(|arg| arg == "--debug-dump") {
+        Some(index) if index + 1 < args.len() => {
+            let path = PathBuf::from(&args[index + 1]);
+            args.drain(index..=index + 1);
+            Some(path)
+        }
+        Some(_) => {
+            eprintln!("--debug-dump attend un chemin de fichier");
+            std::process::exit(EXIT_USAGE);
+        }
+        None => None,
+    };
+    let tap = debug_dump.as_ref().map(|_| WireTap::new(DEBUG_DUMP_FRAMES));
+    // `--json`: sans interface, voir `json`
+    let json = args.len() > 1 && args[1] == "--json";
+    if json {
+        args.remove(1);
+    }
     // Premier argument: l'addresse du serveur
     // Deuxième argument: nickname
-    if args.len() != 3 {
-        println!("Utilisation: ./client adresse-serveur:port nom_utilisateur");
-        return Ok(());
+    // Sans argument, les paramètres sont demandés par un formulaire
+    // Etape 1: créer la structure
+    let mut app = App::default();
+    match args.len() {
+        3 => {
+            let info = ConnectInfo {
+                address: args[1].clone(),
+                nickname: args[2].clone(),
+                takeover,
+                ..Default::default()
+            };
+            let connected = loop {
+                let frontend = if json {
+                    Frontend::Json
+                } else {
+                    Frontend::Tui {
+                        app: &mut app,
+                        start_time,
+                        retry_margin,
+                        history,
+                    }
+                };
+                match connect(&info, frontend, &fallback, tap.as_ref()) {
+                    Ok(Err(ConnectError::ServerFull { retry_after })) if wait_full => {
+                        eprintln!(
+                            "Serveur complet, nouvel essai dans {} s",
+                            retry_after.as_secs()
+                        );
+                        sleep(retry_after);
+                    }
+                    connected => break connected,
+                }
+            };
+            write_debug_dump(debug_dump.as_deref(), tap.as_ref());
+            if let Err(refused) = connected? {
+                eprintln!("{refused}");
+                std::process::exit(refused.exit_code());
+            }
+            Ok(())
+        }
+        1 if !json => {
+            let mut info = ConnectInfo {
+                takeover,
+                ..Default::default()
+            };
+            let mut error = None;
+            while let Some(submitted) = app.connect_screen(&info, error.take())? {
+                info = submitted;
+                let frontend = Frontend::Tui {
+                    app: &mut app,
+                    start_time,
+                    retry_margin,
+                    history,
+                };
+                let connected = connect(&info, frontend, &fallback, tap.as_ref());
+                write_debug_dump(debug_dump.as_deref(), tap.as_ref());
+                match connected? {
+                    Ok(()) => break,
+                    Err(refused) => error = Some(refused.to_string()),
+                }
+            }
+            Ok(())
+        }
+        _ => {
+            println!("Utilisation: ./client adresse-serveur:port nom_utilisateur");
+            println!("             ./client unix:///chemin/socket nom_utilisateur");
+            println!("             ./client");
+            println!("             ./client --json adresse-serveur:port nom_utilisateur");
+            println!("             ./client --ghost adresse-serveur:port nom_utilisateur");
+            println!("             ./client --wait-full adresse-serveur:port nom_utilisateur");
+            println!("             ./client --no-history adresse-serveur:port nom_utilisateur");
+            println!(
+                "             ./client --retry-margin MS adresse-serveur:port nom_utilisateur"
+            );
+            println!(
+                "             ./client --debug-dump FICHIER adresse-serveur:port nom_utilisateur"
+            );
+            println!(
+                "             ./client --nick-fallback MOTIFS adresse-serveur:port nom_utilisateur"
+            );
+            println!(
+                "             ./client --nick-attempts N adresse-serveur:port nom_utilisateur"
+            );
+            std::process::exit(EXIT_USAGE);
+        }
     }
+}
 
-    let nickname = &args[2];
-    // On se connecte au serveur
-    let tcp_stream = std::net::TcpStream::connect(&args[1])?;
-
-    // On envoie le nom d'utilisateur, pour vérifier qu'il n'est pas déjà pris.
-    let mut typed_tcp_tx = TypedWriter::new(tcp_stream.try_clone()?);
-    let mut typed_tcp_rx = TypedReader::new(tcp_stream.try_clone()?);
+// Écrit la trace des trames échangées, si elle a été demandée
+fn write_debug_dump(path: Option<&Path>, tap: Option<&WireTap>) {
+    if let (Some(path), Some(tap)) = (path, tap) {
+        if let Err(e) = trace::write_dump(path, tap) {
+            eprintln!("Trace non écrite dans {} : {e}", path.display());
+        }
+    }
+}
 
-    let key_pair = SenderKeyPair::generate();
-    typed_tcp_tx.send(&Request::Secure(
-        key_pair.public_key().as_ref().as_bytes().to_vec(),
-    ))?;
-    let public_key;
-    if let Response::Secure(key) = typed_tcp_rx.recv()?.unwrap() {
-        let key_bytes: [u8; 32] = key.try_into().unwrap();
-        public_key = Some(ReceiverPublicKey::from(PublicKey::from(key_bytes)))
-    } else {
-        public_key = None;
+// Se connecte au serveur, puis exécute le client jusqu'à ce que l'utilisateur quitte. Un nom
+// déjà pris est remplacé par un nom de `fallback`. Avec `tap`, les trames échangées sont
+// capturées dès l'échange de clés.
+fn connect(
+    info: &ConnectInfo,
+    mut frontend: Frontend,
+    fallback: &NickFallback,
+    tap: Option<&WireTap>,
+) -> Result<Result<(), ConnectError>, Box<dyn Error>> {
+    #[cfg(unix)]
+    if let Some(path) = info.address.strip_prefix("unix://") {
+        return match std::os::unix::net::UnixStream::connect(path) {
+            Ok(stream) => {
+                diagnostic::socket_options().apply(&stream)?;
+                run(stream, info, frontend, fallback, tap)
+            }
+            Err(e) => Ok(Err(ConnectError::from_io(&info.address, e))),
+        };
+    }
+    match diagnostic::connect_tcp(&info.address) {
+        Ok(stream) => {
+            diagnostic::socket_options().apply(&stream)?;
+            // Adresse retenue parmi celles du serveur, IPv6 ou IPv4
+            if let (Frontend::Tui { app, .. }, Ok(peer)) = (&mut frontend, stream.peer_addr()) {
+                app.set_peer_address(peer);
+            }
+            run(stream, info, frontend, fallback, tap)
+        }
+        Err(refused) => Ok(Err(refused)),
     }
+}
 
-    if let Some(key) = public_key {
-        let combined = SenderCombinedKey::new(key_pair.private_key(), &key);
-        let shared = SharedKey::generate();
-        let encrypted_shared_key = shared.clone().encrypt(&combined)?;
-        let shared_key_serialize: Vec<u8> = encrypted_shared_key.serialize();
-        typed_tcp_rx.set_shared_key(shared.clone());
-        typed_tcp_tx.send(&Request::Shared(shared_key_serialize))?;
-        let _ = typed_tcp_rx.recv()?;
-        typed_tcp_tx.set_shared_key(shared);
+fn run<S>(
+    stream: S,
+    info: &ConnectInfo,
+    frontend: Frontend,
+    fallback: &NickFallback,
+    tap: Option<&WireTap>,
+) -> Result<Result<(), ConnectError>, Box<dyn Error>>
+where
+    S: SyncTransport + Debug + Send + 'static,
+{
+    let nickname = &info.nickname;
+    let locale = Locale::from_env();
+    let logged_in = login(&stream, nickname, info.takeover, fallback, locale, tap);
+    let ((reader, writer), granted) = match logged_in {
+        Ok(logged_in) => logged_in,
+        Err(refused) => return Ok(Err(refused)),
+    };
+    // Connecté sous un nom de repli
+    let fallback_notice = (granted != *nickname)
+        .then(|| format!("Le nom {nickname} est déjà pris : connecté en tant que {granted}"));
+    let info = &ConnectInfo {
+        nickname: granted,
+        ..info.clone()
+    };
+    match frontend {
+        Frontend::Tui {
+            app,
+            start_time,
+            retry_margin,
+            history,
+        } => {
+            // Saisies des lancements précédents, rappelées avec les flèches
+            let mut recall = if history {
+                Recall::load(&info.address)
+            } else {
+                Recall::load_from(None, &info.address)
+            };
+            if let Some(notice) = fallback_notice {
+                app.notify(Severity::Warning, notice);
+            }
+            app.restore_input_history(recall.tabs());
+            run_tui(stream, info, reader, writer, app, start_time, retry_margin)?;
+            if let Err(e) = recall.save(app.input_history()) {
+                journal::log(&format!("Historique des saisies non enregistré : {e}"));
+            }
+        }
+        Frontend::Json => {
+            if let Some(notice) = fallback_notice {
+                eprintln!("{notice}");
+            }
+            json::run(stream, reader, writer)?
+        }
     }
+    Ok(Ok(()))
+}
 
-    typed_tcp_tx.send(&Request::Connect(nickname.clone()))?;
+// Canal chiffré établi avec le serveur
+type Channel<S> = (
+    TypedReader<S, Response, Encrypted>,
+    TypedWriter<S, Request, Encrypted>,
+);
 
-    // On vérifie la réponse
-    let nickname_response = typed_tcp_rx.recv()?;
+// Établit la communication chiffrée, puis se connecte sous le nom `nickname`, ou sous un nom
+// de `fallback` s'il est pris. Renvoie le nom accordé par le serveur. Avec `takeover`, une
+// session restée ouverte sous ce nom par ce client est d'abord fermée. Les refus du serveur
+// sont traduits dans `locale`.
+fn login<S>(
+    stream: &S,
+    nickname: &str,
+    takeover: bool,
+    fallback: &NickFallback,
+    locale: Locale,
+    tap: Option<&WireTap>,
+) -> Result<(Channel<S>, String), ConnectError>
+where
+    S: SyncTransport + Debug,
+{
+    // Un échec à ce stade vient d'un serveur qui ne parle pas le protocole
+    let protocol = |e: Box<dyn Error>| ConnectError::Protocol(e.to_string());
+    let ((mut typed_tcp_rx, mut typed_tcp_tx), ack) = handshake(stream, tap).map_err(protocol)?;
+    // Un serveur qui refuse la connexion répond par la raison du refus, puis la ferme
+    match ack {
+        Some(Response::Error(ErrorCode::ServerFull { retry_after })) => {
+            return Err(ConnectError::ServerFull { retry_after })
+        }
+        Some(Response::Error(ErrorCode::Blocklisted(zone))) => {
+            return Err(ConnectError::Blocklisted(zone))
+        }
+        _ => {}
+    }
+    let responder = RequestResponder::new();
+    if takeover {
+        let response = exchange(
+            &mut typed_tcp_rx,
+            &mut typed_tcp_tx,
+            &responder,
+            &ghost::ghost(nickname),
+        )
+        .map_err(|e| protocol(e.into()))?;
+        match response {
+            Some(Response::AckGhost(_)) => {}
+            // Aucune session à reprendre : le nom est libre
+            Some(Response::Error(ErrorCode::UnknownUser(_))) => {}
+            Some(Response::Error(code)) => return Err(ConnectError::Takeover(locale.error(&code))),
+            response => {
+                return Err(ConnectError::Protocol(format!(
+                    "réponse inattendue : {response:?}"
+                )))
+            }
+        }
+    }
+
+    // On envoie le nom d'utilisateur, pour vérifier qu'il n'est pas déjà pris, et on vérifie
+    // la réponse. Un nom pris est remplacé par le nom de repli suivant.
+    let mut candidates = fallback.candidates(nickname).into_iter();
+    let mut attempt = nickname.to_string();
+    let granted = loop {
+        let nickname_response = exchange(
+            &mut typed_tcp_rx,
+            &mut typed_tcp_tx,
+            &responder,
+            &Request::Connect(attempt.clone()),
+        )
+        .map_err(|e| protocol(e.into()))?;
 
-    match nickname_response {
-        Some(Response::AckConnect(_)) => { /* Tout s'est bien passé */ }
-        Some(Response::Error(msg)) => {
-            println!("Message du serveur : {msg}");
-            return Ok(());
+        match nickname_response {
+            Some(Response::AckConnect(granted)) => {
+                // Pour pouvoir reprendre la session si le client plante
+                typed_tcp_tx
+                    .send(&Request::GhostKey(ghost::key().to_string()))
+                    .map_err(|e| protocol(e.into()))?;
+                break granted;
+            }
+            // Le nom est pris, peut-être par une session restée ouverte
+            Some(Response::Error(ErrorCode::NickInUse)) => match candidates.next() {
+                Some(next) => attempt = next,
+                // La suggestion n'est pas un nom déjà essayé
+                None => {
+                    return Err(ConnectError::NicknameInUse {
+                        nickname: nickname.to_string(),
+                        suggestion: format!("{attempt}_"),
+                    })
+                }
+            },
+            Some(Response::Error(code)) => {
+                return Err(ConnectError::NicknameRefused(locale.error(&code)))
+            }
+            _ => {
+                return Err(ConnectError::Protocol(format!(
+                    "réponse inattendue : {nickname_response:?}"
+                )));
+            }
         }
-        _ => {
-            println!("Réponse inattendue du serveur : {nickname_response:?}");
-            return Ok(());
+    };
+    Ok(((typed_tcp_rx, typed_tcp_tx), granted))
+}
+
+// Envoie `request` et attend sa réponse, ou le refus du serveur : les réponses qui ne la
+// concernent pas (ping du serveur, avis...) peuvent s'intercaler, et sont ignorées avant la
+// connexion. `None` si une réponse n'a pas pu être décodée.
+fn exchange<S>(
+    rx: &mut TypedReader<S, Response, Encrypted>,
+    tx: &mut TypedWriter<S, Request, Encrypted>,
+    responder: &RequestResponder<Response>,
+    request: &Request,
+) -> Result<Option<Response>, ProtocolError>
+where
+    S: SyncTransport + Debug,
+{
+    let mut pending = responder
+        .expect(request)
+        .expect("les requêtes de connexion attendent une réponse");
+    tx.send(request)?;
+    loop {
+        let Some(response) = rx.recv()? else {
+            return Ok(None);
+        };
+        match responder.dispatch(response) {
+            None => return Ok(pending.try_recv().ok()),
+            // Les refus ne sont rattachés à aucune requête : c'est celle en cours
+            Some(refused @ Response::Error(_)) => {
+                responder.cancel();
+                return Ok(Some(refused));
+            }
+            Some(Response::Ping(value)) => tx.send(&Request::Pong(value))?,
+            Some(_) => {}
         }
     }
+}
+
+// Échange de clés, jusqu'à l'accusé de réception de la clé partagée, renvoyé avec le canal
+fn handshake<S>(
+    stream: &S,
+    tap: Option<&WireTap>,
+) -> Result<(Channel<S>, Option<Response>), Box<dyn Error>>
+where
+    S: SyncTransport + Debug,
+{
+    // On établit d'abord une communication chiffrée.
+    let mut channel = SyncTypedChannel::<HandshakeRequest, HandshakeResponse, Plain, S>::new(
+        stream.try_clone()?,
+    )?;
+    if let Some(tap) = tap {
+        channel.set_tap(tap.clone());
+    }
+
+    let key_pair = SenderKeyPair::generate();
+    channel.send(&HandshakeRequest::Secure(
+        key_pair.public_key().as_ref().as_bytes().to_vec(),
+    ))?;
+    let key = match channel.recv()? {
+        Some(HandshakeResponse::Secure(key)) => key,
+        None => return Err("connexion fermée pendant l'échange de clés".into()),
+    };
+    let key_bytes: [u8; 32] = key.as_slice().try_into()?;
+    let public_key = ReceiverPublicKey::from(PublicKey::from(key_bytes));
+
+    let combined = SenderCombinedKey::new(key_pair.private_key(), &public_key);
+    let shared = SharedKey::generate();
+    let encrypted_shared_key = shared.clone().encrypt(&combined)?;
+    channel.send(&HandshakeRequest::Shared(encrypted_shared_key.serialize()))?;
+    // Toutes les trames suivantes sont chiffrées, à commencer par l'accusé de réception
+    let mut channel = channel.upgrade::<Request, Response>(shared);
+    let ack = channel.recv()?;
+    Ok((channel.into_split(), ack))
+}
+
+fn run_tui<S>(
+    stream: S,
+    info: &ConnectInfo,
+    mut typed_tcp_rx: TypedReader<S, Response, Encrypted>,
+    mut typed_tcp_tx: TypedWriter<S, Request, Encrypted>,
+    app: &mut App,
+    start_time: Instant,
+    retry_margin: Duration,
+) -> Result<(), Box<dyn Error>>
+where
+    S: SyncTransport + Debug + Send + 'static,
+{
     // Et puis, on join le chan general
     typed_tcp_tx.send(&Request::JoinChan("general".into()))?;
+    // Ainsi que les limites du serveur, comme la taille maximale des messages
+    typed_tcp_tx.send(&Request::Capabilities)?;
+    // Et l'état du serveur, affiché dans la barre d'état
+    typed_tcp_tx.send(&Request::SubscribeStats)?;
 
+    // Langue des erreurs et des avis du serveur
+    let locale = Locale::from_env();
     // Ok, tout s'est bien passé !
+    // Les deux sens sont chiffrés de la même façon : l'indicateur suit la réception
+    let security = match typed_tcp_rx.encryption_status() {
+        EncryptionStatus::Plain => Security::Plain,
+        EncryptionStatus::Encrypted(mechanism) => Security::Encrypted(mechanism.to_string()),
+    };
+    // Les évènements du serveur qui ne concernent aucun autre tab, à commencer par la connexion
+    app.add_tab(NETWORK_TAB.to_string());
+    let connected = match &security {
+        Security::Plain => "non chiffrée".to_string(),
+        Security::Encrypted(mechanism) => format!("chiffrée par {mechanism}"),
+    };
+    network(
+        app,
+        HistoryEntry::Notice(format!(
+            "Connecté à {} en tant que {} (connexion {connected})",
+            info.address, info.nickname
+        )),
+    );
+    app.set_security(security);
 
     // On crée deux channels pour que les threads puissent communiquer entre eux
     let (ui_output_tx, ui_output_rx) = std::sync::mpsc::channel();
@@ -94,12 +581,20 @@ fn main() -> Result<(), Box<dyn Error>> {
     let tcp_reader = {
         let ui_input_tx = ui_input_tx.clone();
         spawn(move || {
-            while let Ok(Some(response)) = typed_tcp_rx.recv() {
-                if ui_input_tx.send(Event::ServerResponse(response)).is_err() {
-                    // Il y a eu une erreur, on arrête tout
-                    break;
+            let event = loop {
+                match typed_tcp_rx.recv() {
+                    Ok(Some(response)) => {
+                        if ui_input_tx.send(Event::ServerResponse(response)).is_err() {
+                            // Il y a eu une erreur, on arrête tout
+                            return;
+                        }
+                    }
+                    Err(ProtocolError::Closed) => break Event::Closed,
+                    Err(ProtocolError::TimedOut) => break Event::Unresponsive,
+                    _ => break Event::Disconnected,
                 }
-            }
+            };
+            let _ = ui_input_tx.send(event);
         })
     };
     // L'inverse pour la partie émission : on lit sur le channel, et on envoie sur la socket
@@ -107,15 +602,60 @@ fn main() -> Result<(), Box<dyn Error>> {
         while let Ok(request) = ui_output_rx.recv() {
             if typed_tcp_tx.send(&request).is_err() {
                 // Il y a eu une erreur, on arrête tout
-                break;
+                return;
             }
         }
+        // L'utilisateur quitte : le serveur sait que ce n'est pas une panne
+        let _ = typed_tcp_tx.close();
     });
-    // Etape 1: créer la structure
-    let mut app = App::default();
-    // Etape 2: on démarre la TUI
+    // Les messages qui le mentionnent sont mis en évidence
+    app.set_nickname(info.nickname.clone());
+    // Canaux muets de ce serveur, rétablis à leur arrivée
+    let mut mutes = Mutes::load(&info.address);
+    // Pour détecter les réponses perdues de chaque canal
+    let mut sequences = Sequences::default();
+    // Plus petit numéro de séquence connu de chaque canal : l'historique plus ancien est
+    // demandé à partir de celui-ci
+    let mut oldest: HashMap<String, u64> = HashMap::new();
+    // Tabs du dernier lancement : les canaux sont rejoints, et leur défilement et leur
+    // dernier message lu rétablis une fois leur historique chargé
+    let mut session = Session::load(&info.address);
+    let mut restoring: HashMap<String, TabLayout> = HashMap::new();
+    for layout in session.tabs() {
+        match tab_chan(&layout.name) {
+            Some(chan) => {
+                // Déjà demandé pour tout le monde
+                if chan != "general" {
+                    let _ = ui_output_tx.send(Request::JoinChan(chan.clone()));
+                }
+                app.add_pending_tab(layout.name.clone());
+                restoring.insert(chan, layout);
+            }
+            None => app.add_tab(layout.name),
+        }
+    }
+    if let Some(current) = session.current() {
+        app.select_tab(current);
+    }
+    // Latence affichée dans la barre d'état
+    let mut lag = LagMeter::default();
+    // Nombre d'utilisateurs et de canaux du dernier `ServerStats`
+    let mut last_counts: Option<(usize, usize)> = None;
+    // Messages refusés par limite de débit, renvoyés après le délai du serveur
+    let mut retries = RetryQueue::new(retry_margin);
+    // Etape 2: on démarre la TUI, si le formulaire de connexion ne l'a pas déjà fait
     app.start().unwrap();
     app.draw().unwrap();
+    // Un thread pour le temps qui passe, même sans évènement
+    let _ticker = {
+        let ui_input_tx = ui_input_tx.clone();
+        spawn(move || loop {
+            sleep(TICK_RATE);
+            if ui_input_tx.send(Event::Tick).is_err() {
+                break;
+            }
+        })
+    };
     // Ein, un dernier thread pour les évènements du terminal
     let _terminal_event_handler = spawn(move || {
         while let Ok(e) = event::read() {
@@ -133,58 +673,412 @@ fn main() -> Result<(), Box<dyn Error>> {
         app.draw()?;
         let msg = ui_input_rx.recv()?;
         match msg {
+            Event::Tick => {
+                let now = Instant::now();
+                app.tick(now);
+                if let Some(ping) = lag.probe(now) {
+                    let _ = ui_output_tx.send(ping);
+                }
+                if let Some(lag) = lag.overdue(now) {
+                    app.set_lag(lag);
+                }
+                let waiting = retries.remaining(now).is_some();
+                if let Some(message) = retries.due(now) {
+                    let _ = ui_output_tx.send(message);
+                }
+                if retries.remaining(now).is_some() {
+                    show_retries(app, &retries, now);
+                } else if waiting {
+                    app.clear_notif();
+                }
+            }
+            Event::Closed => {
+                network(
+                    app,
+                    HistoryEntry::Error("Connexion fermée par le serveur".to_string()),
+                );
+                app.notify(
+                    Severity::Error,
+                    "Connexion fermée par le serveur".to_string(),
+                );
+            }
+            Event::Unresponsive => {
+                network(
+                    app,
+                    HistoryEntry::Error("Le serveur ne répond plus".to_string()),
+                );
+                app.notify(Severity::Error, "Le serveur ne répond plus".to_string());
+            }
+            Event::Disconnected => {
+                network(
+                    app,
+                    HistoryEntry::Error("Connexion au serveur perdue".to_string()),
+                );
+                app.notify(Severity::Error, "Connexion au serveur perdue".to_string());
+            }
             Event::TerminalEvent(e) => {
                 match app.react_to_event(e) {
                     Some(KeyReaction::Quit) => {
+                        let current = Some(app.get_current_tab()).filter(|tab| !tab.is_empty());
+                        if let Err(e) = session.save(app.layout(), current) {
+                            journal::log(&format!("Disposition des tabs non enregistrée : {e}"));
+                        }
                         break;
                     }
                     Some(KeyReaction::UserInput(input)) => {
-                        // On gère l'input de l'utilisateur.
-                        match handle_user_input(input, &mut app) {
-                            // Requête à envoyer au serveur.
-                            Ok(Some(req)) => {
-                                let _ = ui_output_tx.send(req);
-                            }
-                            // Aucune action à réaliser.
-                            Ok(None) => {}
-                            // On affiche l'erreur.
-                            Err(e) => {
-                                let time = start_time.elapsed();
-                                let notif =
-                                    format!("{},{}s: {}", time.as_secs(), time.subsec_millis(), e);
-                                app.set_notification(notif);
-                            }
-                        };
+                        submit_input(
+                            input,
+                            app,
+                            &mut mutes,
+                            &mut retries,
+                            &ui_output_tx,
+                            start_time,
+                        );
+                    }
+                    // Message trop long, découpé par l'interface
+                    Some(KeyReaction::UserInputs(inputs)) => {
+                        for input in inputs {
+                            submit_input(
+                                input,
+                                app,
+                                &mut mutes,
+                                &mut retries,
+                                &ui_output_tx,
+                                start_time,
+                            );
+                        }
+                    }
+                    Some(KeyReaction::UserDetails(name)) => {
+                        let _ = ui_output_tx.send(Request::WhoIs(name));
                     }
+                    Some(KeyReaction::JoinChannel(name)) => {
+                        app.add_pending_tab(chan_tab(&name));
+                        let _ = ui_output_tx.send(Request::JoinChan(name));
+                    }
+                    Some(KeyReaction::LoadOlder(tab)) => match tab_chan(&tab) {
+                        Some(chan) => {
+                            let _ = ui_output_tx.send(Request::FetchHistory {
+                                before_id: oldest.get(&chan).copied(),
+                                chan,
+                                limit: MAX_HISTORY_FETCH,
+                            });
+                        }
+                        // Les messages directs ne sont pas archivés
+                        None => app.prepend_history(&tab, Vec::new(), false),
+                    },
                     None => {} // Géré en interne
                 }
             }
+            // Chaque réponse nomme son canal ou son utilisateur : elle est traitée d'après son
+            // contenu, quelle que soit la requête envoyée en dernier
             Event::ServerResponse(response) => {
                 match response {
                     Response::DirectMessage { from, content } => {
                         let user_tab = format!("@{from}");
                         app.push_message(from, content, user_tab.clone());
                     }
-                    Response::AckJoin { chan, users } => {
-                        let tab = format!("#{chan}");
+                    Response::AckJoin { chan, users, owner } => {
+                        let tab = chan_tab(&chan);
                         app.add_tab_with_users(tab.clone(), users);
+                        if let Some(owner) = owner {
+                            app.set_owner(&owner, &tab);
+                        }
+                        if mutes.contains(&tab) {
+                            app.set_muted(&tab, true);
+                        }
+                        // Pour montrer ce qui a été manqué depuis le dernier lancement
+                        if restoring.contains_key(&chan) {
+                            let _ = ui_output_tx.send(Request::FetchHistory {
+                                chan,
+                                before_id: None,
+                                limit: MAX_HISTORY_FETCH,
+                            });
+                        }
+                    }
+                    Response::Names { chan, users } => {
+                        app.set_users(&chan_tab(&chan), users);
                     }
                     Response::AckLeave(chan) => {
-                        app.remove_tab(format!("#{chan}"));
+                        sequences.forget(&chan);
+                        oldest.remove(&chan);
+                        app.remove_tab(chan_tab(&chan));
                     }
-                    Response::Channel { op, chan } => {
-                        let chan = format!("#{chan}");
+                    Response::Channel { op, chan, seq } => {
+                        if seq != 0 {
+                            oldest.entry(chan.clone()).or_insert(seq);
+                        }
+                        let missed = sequences.check(&chan, seq);
+                        if missed > 0 {
+                            // Des arrivées et des départs ont pu être perdus
+                            let _ = ui_output_tx.send(Request::Names(chan.clone()));
+                        }
+                        let chan = chan_tab(&chan);
+                        if missed > 0 {
+                            app.push_entry(
+                                HistoryEntry::Notice(format!(
+                                    "{missed} message(s) ont pu être manqués"
+                                )),
+                                chan.clone(),
+                            );
+                        }
                         match op {
                             ChanOp::Message { from, content } => {
                                 app.push_message(from, content, chan)
                             }
-                            ChanOp::UserAdd(nickname) => app.add_user(nickname, chan),
-                            ChanOp::UserDel(nickname) => app.remove_user(&nickname, chan),
+                            ChanOp::UserAdd(nickname) => {
+                                app.add_user(nickname.clone(), chan.clone());
+                                app.push_entry(HistoryEntry::Join(nickname), chan);
+                            }
+                            ChanOp::UserDel(nickname) => {
+                                app.remove_user(&nickname, chan.clone());
+                                app.push_entry(HistoryEntry::Leave(nickname), chan);
+                            }
+                            ChanOp::Missed(missed) => {
+                                // Des arrivées et des départs ont pu être perdus. L'écart des
+                                // numéros de séquence est déjà signalé ici.
+                                if let Some(name) = tab_chan(&chan) {
+                                    sequences.forget(&name);
+                                    let _ = ui_output_tx.send(Request::Names(name));
+                                }
+                                app.push_entry(
+                                    HistoryEntry::Notice(format!("{missed} message(s) manqué(s)")),
+                                    chan,
+                                )
+                            }
+                            ChanOp::UserAway(nickname) => {
+                                app.set_user_presence(&nickname, Presence::Away);
+                                app.push_entry(
+                                    HistoryEntry::Notice(format!("{nickname} est absent")),
+                                    chan,
+                                )
+                            }
+                            ChanOp::Owner(nickname) => {
+                                app.set_owner(&nickname, &chan);
+                                app.push_entry(
+                                    HistoryEntry::Notice(format!(
+                                        "{nickname} est propriétaire du canal"
+                                    )),
+                                    chan,
+                                )
+                            }
+                            ChanOp::UserBack(nickname) => {
+                                app.set_user_presence(&nickname, Presence::Active);
+                                app.push_entry(
+                                    HistoryEntry::Notice(format!("{nickname} est de retour")),
+                                    chan,
+                                )
+                            }
                         }
                     }
-                    _ => {
-                        // on, ignore pour l'instant
-                        todo!()
+                    Response::WhoIs {
+                        user,
+                        channels,
+                        idle,
+                        away,
+                    } => {
+                        let notif = format!(
+                            "{user}: {}, inactif depuis {}s, canaux: {}",
+                            if away { "absent" } else { "présent" },
+                            idle.as_secs(),
+                            channels.join(" ")
+                        );
+                        // Affichée dans la fenêtre de l'utilisateur si elle est ouverte
+                        let details = UserDetails {
+                            name: user,
+                            channels,
+                            idle,
+                            away,
+                        };
+                        if !app.show_user_details(details) {
+                            app.set_notification(notif);
+                        }
+                    }
+                    Response::Stats(stats) => {
+                        let rtt = stats
+                            .ping_rtt
+                            .map_or("?".to_string(), |rtt| format!("{rtt:?}"));
+                        app.set_notification(format!(
+                            "{}: connecté depuis {}s, {} message(s), {} octet(s) envoyé(s), {} reçu(s), canaux: {}, ping: {}, {}",
+                            stats.user,
+                            stats.connected_for.as_secs(),
+                            stats.messages_sent,
+                            stats.bytes_sent,
+                            stats.bytes_received,
+                            stats.channels.join(" "),
+                            rtt,
+                            stats.encryption
+                        ));
+                    }
+                    Response::ChanList(channels) => {
+                        app.show_channel_list(
+                            channels
+                                .into_iter()
+                                .map(|chan| ChannelEntry {
+                                    name: chan.name,
+                                    users: chan.users,
+                                    topic: chan.topic,
+                                })
+                                .collect(),
+                        );
+                    }
+                    Response::Capabilities(capabilities) => {
+                        app.set_max_message_len(capabilities.max_message_len);
+                    }
+                    Response::Ping(id) => {
+                        let _ = ui_output_tx.send(Request::Pong(id));
+                    }
+                    Response::RateLimited { retry_after_ms } => {
+                        let now = Instant::now();
+                        retries.rate_limited(Duration::from_millis(retry_after_ms), now);
+                        show_retries(app, &retries, now);
+                    }
+                    Response::Pong(id) => {
+                        if let Some(lag) = lag.pong(id, Instant::now()) {
+                            app.set_lag(lag);
+                        }
+                    }
+                    Response::Keywords { chan, keywords } => {
+                        let notif = if keywords.is_empty() {
+                            format!("{} : aucun mot-clé surveillé", chan_tab(&chan))
+                        } else {
+                            format!(
+                                "{} : mots-clés surveillés : {}",
+                                chan_tab(&chan),
+                                keywords.join(", ")
+                            )
+                        };
+                        app.notify(Severity::Info, notif);
+                    }
+                    Response::Invited { chan, by } => app.add_invite(chan, by),
+                    Response::BlockList(blocked) => {
+                        let notif = if blocked.is_empty() {
+                            "Aucun utilisateur bloqué".to_string()
+                        } else {
+                            format!("Utilisateurs bloqués : {}", blocked.join(", "))
+                        };
+                        app.notify(Severity::Info, notif);
+                    }
+                    // Signalée même si le canal n'est pas affiché
+                    Response::Notice { scope, notice } => {
+                        let text = locale.notice(&notice);
+                        let tab = match scope {
+                            NoticeScope::Server => None,
+                            NoticeScope::Channel(chan) => Some(chan_tab(&chan)),
+                            NoticeScope::User(user) => Some(format!("@{user}")),
+                        };
+                        // Sans tab ouvert pour l'afficher, l'avis rejoint le tab du serveur
+                        match tab {
+                            Some(tab) if app.history(&tab).is_some() => {
+                                app.push_entry(HistoryEntry::Notice(text), tab);
+                            }
+                            Some(tab) => {
+                                network(app, HistoryEntry::Notice(format!("{tab}: {text}")))
+                            }
+                            None => network(app, HistoryEntry::Notice(text)),
+                        }
+                    }
+                    Response::ServerStats {
+                        users,
+                        channels,
+                        msgs_per_min,
+                        ..
+                    } => {
+                        app.set_server_stats(ServerStats {
+                            users,
+                            channels,
+                            msgs_per_min,
+                        });
+                        // Le tab du serveur n'en garde que les changements
+                        if last_counts.replace((users, channels)) != Some((users, channels)) {
+                            let stats = format!(
+                                "{users} utilisateur(s) connecté(s), {channels} canal(aux)"
+                            );
+                            network(app, HistoryEntry::Notice(stats));
+                        }
+                    }
+                    Response::KeywordAlert {
+                        chan,
+                        keyword,
+                        from,
+                        content,
+                    } => {
+                        // Les notifications ne se dévoilent pas : les spoilers y restent masqués
+                        let content = mask_spoilers(&content);
+                        app.notify(
+                            Severity::Warning,
+                            format!("{} [{keyword}] {from} : {content}", chan_tab(&chan)),
+                        );
+                    }
+                    Response::History {
+                        chan,
+                        messages,
+                        more,
+                    } => {
+                        if let Some(first) = messages.first() {
+                            let known = oldest.entry(chan.clone()).or_insert(first.id);
+                            *known = first.id.min(*known);
+                        }
+                        let entries = messages
+                            .into_iter()
+                            .map(|message| TimedEntry {
+                                at: message.at,
+                                entry: HistoryEntry::UserMessage {
+                                    from: message.from,
+                                    content: message.content,
+                                },
+                            })
+                            .collect();
+                        app.prepend_history(&chan_tab(&chan), entries, more);
+                        if let Some(layout) = restoring.remove(&chan) {
+                            app.restore_layout(&layout);
+                        }
+                    }
+                    Response::AckGhost(nickname) => {
+                        app.notify(Severity::Info, format!("Session {nickname} fermée"));
+                    }
+                    // Erreur suite à une requête : affichée dans le tab courant
+                    Response::Error(code) => {
+                        let error = locale.error(&code);
+                        let tab = app.get_current_tab();
+                        if let Some(chan) = cancel_refused_join(app, &code) {
+                            app.notify(Severity::Error, format!("{chan}: {error}"));
+                        } else if tab.is_empty() {
+                            network(app, HistoryEntry::Error(error));
+                        } else {
+                            app.push_entry(HistoryEntry::Error(error), tab);
+                        }
+                    }
+                    Response::Delivery(results) => {
+                        let refused: Vec<String> = results
+                            .iter()
+                            .filter_map(|(to, result)| {
+                                let code = result.as_ref().err()?;
+                                Some(format!("{to}: {}", locale.error(code)))
+                            })
+                            .collect();
+                        if refused.is_empty() {
+                            let sent: Vec<String> =
+                                results.iter().map(|(to, _)| to.to_string()).collect();
+                            app.notify(
+                                Severity::Info,
+                                format!("Message envoyé à {}", sent.join(", ")),
+                            );
+                        } else {
+                            app.notify(Severity::Error, refused.join(" ; "));
+                        }
+                    }
+                    // Accusés attendus seulement pendant la connexion : un accusé isolé ne
+                    // concerne aucune requête en cours
+                    Response::Ack | Response::AckConnect(_) => {}
+                    // Réponse ajoutée au protocole après ce client
+                    unknown => {
+                        journal::log(&format!("Réponse inconnue : {unknown:?}"));
+                        network(
+                            app,
+                            HistoryEntry::Error(format!(
+                                "Réponse inconnue du serveur : {unknown:?}"
+                            )),
+                        );
                     }
                 }
             }
@@ -193,7 +1087,12 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Extinction: les canaux internes doivent retourner une variante d'erreur
     drop(ui_output_tx);
-    tcp_stream.shutdown(Shutdown::Both)?;
+    // Le temps d'annoncer la fermeture, sans attendre indéfiniment un serveur bloqué
+    let deadline = Instant::now() + CLOSE_TIMEOUT;
+    while !tcp_writer.is_finished() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    close(&stream)?;
     let _ = tcp_reader.join();
     let _ = tcp_writer.join();
 
@@ -204,3 +1103,73 @@ fn main() -> Result<(), Box<dyn Error>> {
     // let _ = _terminal_event_handler.join();
     Ok(())
 }
+
+// Ferme la connexion. Si le serveur l'a déjà fermée, Windows refuse le `shutdown` (`NotConnected`)
+// là où Linux l'accepte : ce n'est pas une erreur.
+fn close<S: SyncTransport>(stream: &S) -> std::io::Result<()> {
+    match stream.shutdown(Shutdown::Both) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotConnected => Ok(()),
+        result => result,
+    }
+}
+
+// On gère l'input de l'utilisateur.
+fn submit_input(
+    input: String,
+    app: &mut App,
+    mutes: &mut Mutes,
+    retries: &mut RetryQueue,
+    ui_output_tx: &std::sync::mpsc::Sender<Request>,
+    start_time: Instant,
+) {
+    match handle_user_input(input, app, mutes) {
+        // Message, qui attend son tour si d'autres ont été refusés par limite de débit
+        Ok(Some(req @ Request::Message { .. })) => {
+            if let Some(req) = retries.send(req) {
+                let _ = ui_output_tx.send(req);
+            }
+        }
+        // Requête à envoyer au serveur.
+        Ok(Some(req)) => {
+            let _ = ui_output_tx.send(req);
+        }
+        // Aucune action à réaliser.
+        Ok(None) => {}
+        // Message saisi hors d'un canal ou d'une conversation, comme dans le tab du serveur
+        Err(ClientError::Receiver(_)) => {
+            app.notify(
+                Severity::Warning,
+                "Ce tab n'accepte pas de messages : rejoignez un canal (/join) ou écrivez à quelqu'un (/to)"
+                    .to_string(),
+            );
+        }
+        // Il suffit de rappeler la syntaxe
+        Err(e) if e.is_usage() => app.notify(Severity::Warning, e.to_string()),
+        // On affiche l'erreur.
+        Err(e) => {
+            let time = start_time.elapsed();
+            let notif = format!("{},{}s: {}", time.as_secs(), time.subsec_millis(), e);
+            app.notify(Severity::Error, notif);
+        }
+    };
+}
+
+// Compte à rebours des messages en attente de renvoi, dans la zone de notification
+fn show_retries(app: &mut App, retries: &RetryQueue, now: Instant) {
+    let Some(remaining) = retries.remaining(now) else {
+        return;
+    };
+    let secs = remaining.as_millis().div_ceil(1000);
+    app.notify(
+        Severity::Warning,
+        format!(
+            "Trop de messages : {} en attente, renvoi dans {secs} s",
+            retries.len()
+        ),
+    );
+}
+
+// Ajoute un évènement au tab du serveur
+fn network(app: &mut App, entry: HistoryEntry) {
+    app.push_entry(entry, NETWORK_TAB.to_string());
+}
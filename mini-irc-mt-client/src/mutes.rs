@@ -0,0 +1,75 @@
+//! Canaux rendus muets par `/mute`, conservés entre deux lancements dans
+//! [`dirs::config_dir`] : `muted.json` associe à chaque adresse de serveur ses tabs muets.
+//!
+//! ```json
+//! { "127.0.0.1:6667": ["#general"] }
+//! ```
+
+use crate::dirs;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+type Muted = BTreeMap<String, BTreeSet<String>>;
+
+/// Tabs muets d'un serveur.
+#[derive(Debug, Default)]
+pub struct Mutes {
+    /// Fichier de configuration, `None` si le système n'en définit pas
+    path: Option<PathBuf>,
+    server: String,
+    muted: Muted,
+}
+
+impl Mutes {
+    /// Lit les tabs muets de `server`. Un fichier absent ou illisible n'en contient aucun.
+    pub fn load(server: &str) -> Self {
+        let path = dirs::config_dir().map(|dir| dir.join("muted.json"));
+        Self::load_from(path, server)
+    }
+
+    /// Comme [`Mutes::load`], mais depuis le fichier `path`.
+    pub fn load_from(path: Option<PathBuf>, server: &str) -> Self {
+        let muted = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            server: server.to_string(),
+            muted,
+        }
+    }
+
+    pub fn contains(&self, tab: &str) -> bool {
+        self.muted
+            .get(&self.server)
+            .is_some_and(|tabs| tabs.contains(tab))
+    }
+
+    /// Rend `tab` muet ou non, et l'enregistre aussitôt.
+    pub fn set(&mut self, tab: &str, muted: bool) -> std::io::Result<()> {
+        let tabs = self.muted.entry(self.server.clone()).or_default();
+        if muted {
+            tabs.insert(tab.to_string());
+        } else {
+            tabs.remove(tab);
+            if tabs.is_empty() {
+                self.muted.remove(&self.server);
+            }
+        }
+        self.save()
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.muted).map_err(std::io::Error::other)?;
+        fs::write(path, json)
+    }
+}
@@ -0,0 +1,80 @@
+//! Saisies envoyées dans chaque tab, conservées entre deux lancements dans
+//! [`dirs::data_dir`] pour être rappelées avec les flèches, comme l'historique d'un shell :
+//! `input_history.json` associe à chaque adresse de serveur les saisies de chacun de ses tabs,
+//! de la plus ancienne à la plus récente.
+//!
+//! ```json
+//! { "127.0.0.1:6667": { "#general": ["bonjour", "/join #rust"] } }
+//! ```
+//!
+//! Seules les [`INPUT_HISTORY_LEN`] dernières saisies de chaque tab sont gardées. L'option
+//! `--no-history` du client n'en lit ni n'en enregistre aucune.
+
+use crate::dirs;
+use mini_irc_ui::INPUT_HISTORY_LEN;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+type SavedInputs = BTreeMap<String, Vec<String>>;
+
+/// Saisies des tabs d'un serveur, aux lancements précédents.
+#[derive(Debug, Default)]
+pub struct Recall {
+    /// Fichier de données, `None` si le système n'en définit pas ou si l'historique est
+    /// désactivé
+    path: Option<PathBuf>,
+    server: String,
+    servers: BTreeMap<String, SavedInputs>,
+}
+
+impl Recall {
+    /// Lit les saisies enregistrées pour `server`. Un fichier absent ou illisible n'en contient
+    /// aucune.
+    pub fn load(server: &str) -> Self {
+        let path = dirs::data_dir().map(|dir| dir.join("input_history.json"));
+        Self::load_from(path, server)
+    }
+
+    /// Comme [`Recall::load`], mais depuis le fichier `path`. Sans fichier, rien n'est lu ni
+    /// enregistré.
+    pub fn load_from(path: Option<PathBuf>, server: &str) -> Self {
+        let servers = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            server: server.to_string(),
+            servers,
+        }
+    }
+
+    /// Saisies de chaque tab, de la plus ancienne à la plus récente.
+    pub fn tabs(&self) -> SavedInputs {
+        self.servers.get(&self.server).cloned().unwrap_or_default()
+    }
+
+    /// Enregistre les saisies `tabs`, en ne gardant que les dernières de chaque tab.
+    pub fn save(&mut self, tabs: SavedInputs) -> std::io::Result<()> {
+        let tabs = tabs
+            .into_iter()
+            .filter(|(_, inputs)| !inputs.is_empty())
+            .map(|(tab, mut inputs)| {
+                let excess = inputs.len().saturating_sub(INPUT_HISTORY_LEN);
+                inputs.drain(..excess);
+                (tab, inputs)
+            })
+            .collect();
+        self.servers.insert(self.server.clone(), tabs);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.servers).map_err(std::io::Error::other)?;
+        fs::write(path, json)
+    }
+}
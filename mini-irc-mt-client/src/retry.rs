@@ -0,0 +1,89 @@
+//! Renvoi automatique des messages refusés par un serveur qui limite le débit : à la réception
+//! d'un [`mini_irc_protocol::Response::RateLimited`], le dernier message envoyé est remis en
+//! file, et les suivants l'y rejoignent jusqu'à la fin du délai indiqué. Les messages en
+//! attente partent ensuite un par un, dans l'ordre de saisie.
+
+use mini_irc_protocol::Request;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Marge ajoutée par défaut au délai indiqué par le serveur, pour que le renvoi ne le
+/// devance pas
+pub const RETRY_MARGIN: Duration = Duration::from_millis(100);
+
+/// Messages en attente de renvoi d'une connexion.
+#[derive(Debug)]
+pub struct RetryQueue {
+    margin: Duration,
+    /// Dernier message envoyé, celui que refuse un `RateLimited`
+    last_sent: Option<Request>,
+    /// Date à partir de laquelle renvoyer, tant que des messages attendent
+    until: Option<Instant>,
+    queued: VecDeque<Request>,
+}
+
+impl Default for RetryQueue {
+    fn default() -> Self {
+        Self::new(RETRY_MARGIN)
+    }
+}
+
+impl RetryQueue {
+    pub fn new(margin: Duration) -> Self {
+        Self {
+            margin,
+            last_sent: None,
+            until: None,
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Message à envoyer tout de suite, ou `None` s'il rejoint la file pendant le délai.
+    pub fn send(&mut self, message: Request) -> Option<Request> {
+        if self.until.is_some() {
+            self.queued.push_back(message);
+            None
+        } else {
+            self.last_sent = Some(message.clone());
+            Some(message)
+        }
+    }
+
+    /// Refus du dernier message envoyé, à renvoyer `retry_after` après `now`.
+    pub fn rate_limited(&mut self, retry_after: Duration, now: Instant) {
+        if let Some(message) = self.last_sent.take() {
+            self.queued.push_front(message);
+        }
+        if !self.queued.is_empty() {
+            self.until = Some(now + retry_after + self.margin);
+        }
+    }
+
+    /// Message à renvoyer à la date `now`, un seul à la fois : les suivants attendent l'appel
+    /// d'après.
+    pub fn due(&mut self, now: Instant) -> Option<Request> {
+        if self.until.is_none_or(|until| now < until) {
+            return None;
+        }
+        let message = self.queued.pop_front();
+        if self.queued.is_empty() {
+            self.until = None;
+        }
+        self.last_sent = message.clone();
+        message
+    }
+
+    /// Temps restant avant le prochain renvoi à la date `now`, tant que des messages attendent.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.until.map(|until| until.saturating_duration_since(now))
+    }
+
+    /// Nombre de messages en attente.
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+}
@@ -0,0 +1,30 @@
+//! Détection des réponses perdues d'un canal, grâce aux numéros de séquence des
+//! [`mini_irc_protocol::Response::Channel`].
+
+use std::collections::HashMap;
+
+/// Dernier numéro de séquence reçu, par canal.
+#[derive(Debug, Default)]
+pub struct Sequences {
+    last: HashMap<String, u64>,
+}
+
+impl Sequences {
+    /// Enregistre le numéro `seq` reçu sur `chan`, et renvoie le nombre de réponses manquées
+    /// depuis la précédente. La première réponse d'un canal ne manque rien ; un numéro nul
+    /// est hors séquence.
+    pub fn check(&mut self, chan: &str, seq: u64) -> u64 {
+        if seq == 0 {
+            return 0;
+        }
+        match self.last.insert(chan.to_string(), seq) {
+            Some(last) if seq > last + 1 => seq - last - 1,
+            _ => 0,
+        }
+    }
+
+    /// Oublie le canal, quitté : la numérotation reprendra à la prochaine réponse.
+    pub fn forget(&mut self, chan: &str) {
+        self.last.remove(chan);
+    }
+}
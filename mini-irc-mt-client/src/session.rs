@@ -0,0 +1,115 @@
+//! Disposition des tabs, conservée entre deux lancements dans [`dirs::data_dir`] :
+//! `session.json` associe à chaque adresse de serveur ses tabs ouverts dans l'ordre, avec leur
+//! défilement et la date de leur dernier message lu, et le tab courant.
+//!
+//! ```json
+//! { "127.0.0.1:6667": {
+//!     "tabs": [{ "name": "#general", "scroll": 0, "last_read": 1760000000000 }],
+//!     "current": "#general" } }
+//! ```
+//!
+//! L'état muet des canaux est conservé à part, voir [`crate::mutes`].
+
+use crate::dirs;
+use mini_irc_ui::TabLayout;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedSession {
+    tabs: Vec<SavedTab>,
+    current: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedTab {
+    name: String,
+    scroll: usize,
+    /// En millisecondes depuis l'epoch Unix
+    last_read: Option<u64>,
+}
+
+/// Disposition des tabs d'un serveur, au dernier lancement.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Fichier de données, `None` si le système n'en définit pas
+    path: Option<PathBuf>,
+    server: String,
+    sessions: BTreeMap<String, SavedSession>,
+}
+
+impl Session {
+    /// Lit la disposition enregistrée pour `server`. Un fichier absent ou illisible n'en
+    /// contient aucune.
+    pub fn load(server: &str) -> Self {
+        let path = dirs::data_dir().map(|dir| dir.join("session.json"));
+        Self::load_from(path, server)
+    }
+
+    /// Comme [`Session::load`], mais depuis le fichier `path`.
+    pub fn load_from(path: Option<PathBuf>, server: &str) -> Self {
+        let sessions = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            server: server.to_string(),
+            sessions,
+        }
+    }
+
+    /// Tabs ouverts au dernier lancement, dans l'ordre.
+    pub fn tabs(&self) -> Vec<TabLayout> {
+        let Some(session) = self.sessions.get(&self.server) else {
+            return Vec::new();
+        };
+        session
+            .tabs
+            .iter()
+            .map(|tab| TabLayout {
+                name: tab.name.clone(),
+                scroll: tab.scroll,
+                last_read: tab
+                    .last_read
+                    .map(|ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms)),
+            })
+            .collect()
+    }
+
+    /// Tab courant au dernier lancement.
+    pub fn current(&self) -> Option<&str> {
+        self.sessions.get(&self.server)?.current.as_deref()
+    }
+
+    /// Enregistre la disposition `tabs`, avec le tab `current`.
+    pub fn save(&mut self, tabs: Vec<TabLayout>, current: Option<String>) -> std::io::Result<()> {
+        let tabs = tabs
+            .into_iter()
+            .map(|tab| SavedTab {
+                name: tab.name,
+                scroll: tab.scroll,
+                last_read: tab.last_read.map(|at| {
+                    let since_epoch = at
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default();
+                    since_epoch.as_millis() as u64
+                }),
+            })
+            .collect();
+        self.sessions
+            .insert(self.server.clone(), SavedSession { tabs, current });
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.sessions).map_err(std::io::Error::other)?;
+        fs::write(path, json)
+    }
+}
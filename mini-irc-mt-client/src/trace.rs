@@ -0,0 +1,63 @@
+//! Trace des trames échangées avec le serveur, écrite par `--debug-dump <fichier>` pour
+//! accompagner un rapport de bug. Le fichier JSON liste les dernières trames (voir
+//! [`DEBUG_DUMP_FRAMES`]), dans l'ordre où elles ont transité :
+//!
+//! ```text
+//! [
+//!   {"at_ms":1700000000123,"direction":"sent","len":36,"value":"Secure([...])","raw":"00000020..."},
+//!   ...
+//! ]
+//! ```
+//!
+//! `raw` contient la trame en hexadécimal, en-tête compris et chiffrée le cas échéant.
+
+use mini_irc_protocol::{Direction, TapRecord, WireTap};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Nombre de trames conservées pour la trace
+pub const DEBUG_DUMP_FRAMES: usize = 1024;
+
+#[derive(Serialize)]
+struct DumpedFrame<'a> {
+    at_ms: u128,
+    direction: &'static str,
+    len: usize,
+    value: Option<&'a str>,
+    raw: String,
+}
+
+impl<'a> From<&'a TapRecord> for DumpedFrame<'a> {
+    fn from(record: &'a TapRecord) -> Self {
+        Self {
+            at_ms: record
+                .at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |at| at.as_millis()),
+            direction: match record.direction {
+                Direction::Sent => "sent",
+                Direction::Received => "received",
+            },
+            len: record.len,
+            value: record.value.as_deref(),
+            raw: record
+                .raw
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        }
+    }
+}
+
+/// Écrit les trames capturées par `tap` dans `path`, en remplaçant le fichier existant.
+pub fn write_dump(path: &Path, tap: &WireTap) -> io::Result<()> {
+    let records = tap.records();
+    let frames: Vec<DumpedFrame> = records.iter().map(DumpedFrame::from).collect();
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, &frames)?;
+    writeln!(file)?;
+    file.flush()
+}
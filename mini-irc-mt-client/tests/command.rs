@@ -0,0 +1,57 @@
+use mini_irc_mt::command::{tokenize, Command};
+use mini_irc_mt::error::ClientError;
+
+#[test]
+fn arguments_are_split_on_spaces_and_quotes() {
+    assert_eq!(tokenize("  a  b ").unwrap(), ["a", "b"]);
+    assert_eq!(
+        tokenize(r#""bob smith" "dit \"salut\"" c:\\"#).unwrap(),
+        ["bob smith", r#"dit "salut""#, r"c:\\"]
+    );
+    assert_eq!(tokenize(r#""" x"#).unwrap(), ["", "x"]);
+    assert!(matches!(
+        tokenize(r#"a "b c"#),
+        Err(ClientError::UnterminatedQuote)
+    ));
+    assert!(matches!(
+        tokenize(r#""b\"#),
+        Err(ClientError::UnterminatedQuote)
+    ));
+}
+
+#[test]
+fn commands_check_their_arity() {
+    assert_eq!(Command::parse("bonjour"), None);
+    let command = Command::parse("/op bob").unwrap();
+    assert_eq!(command.name, "op");
+    let [to] = command.exactly("/op <nickname>").unwrap();
+    assert_eq!(to, "bob");
+    assert!(matches!(
+        command.exactly::<2>("/op <nickname>"),
+        Err(ClientError::Usage("/op <nickname>"))
+    ));
+
+    let command = Command::parse("/stats").unwrap();
+    assert_eq!(command.optional("/stats [nickname]").unwrap(), None);
+    let command = Command::parse("/stats a b").unwrap();
+    assert!(command.optional("/stats [nickname]").is_err());
+}
+
+#[test]
+fn message_keeps_its_spacing() {
+    let command = Command::parse(r#"/to "bob smith"  salut   toi"#).unwrap();
+    let (to, message) = command.first_and_rest("/to <nickname> <message>").unwrap();
+    assert_eq!(to, "bob smith");
+    assert_eq!(message, "salut   toi");
+
+    for input in ["/to", "/to bob", "/to bob   "] {
+        let command = Command::parse(input).unwrap();
+        assert!(
+            matches!(
+                command.first_and_rest("/to <nickname> <message>"),
+                Err(ClientError::Usage(_))
+            ),
+            "{input}"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use mini_irc_mt::diagnostic::{self, ConnectError, EXIT_USAGE};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::time::Duration;
+
+#[test]
+fn address_without_port_is_not_found() {
+    let err = diagnostic::connect_tcp("localhost").unwrap_err();
+    assert!(matches!(err, ConnectError::Resolve { .. }), "{err:?}");
+    assert_eq!(err.exit_code(), 3);
+}
+
+#[test]
+fn closed_port_is_refused() {
+    // Port libéré juste après avoir été attribué : personne n'écoute
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let err = diagnostic::connect_tcp(&format!("127.0.0.1:{port}")).unwrap_err();
+    assert!(matches!(err, ConnectError::Refused { .. }), "{err:?}");
+    assert_eq!(err.exit_code(), 4);
+}
+
+#[test]
+fn listening_port_connects() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    assert!(diagnostic::connect_tcp(&address).is_ok());
+}
+
+#[test]
+fn io_errors_are_classified() {
+    let classify = |kind| ConnectError::from_io("example:6667", io::Error::from(kind));
+    assert!(matches!(
+        classify(io::ErrorKind::ConnectionRefused),
+        ConnectError::Refused { .. }
+    ));
+    assert!(matches!(
+        classify(io::ErrorKind::TimedOut),
+        ConnectError::TimedOut { .. }
+    ));
+    assert!(matches!(
+        classify(io::ErrorKind::PermissionDenied),
+        ConnectError::Unreachable { .. }
+    ));
+}
+
+#[test]
+fn nickname_in_use_suggests_another() {
+    let err = ConnectError::nickname_in_use("alice");
+    let message = err.to_string();
+    assert!(message.contains("alice_"), "{message}");
+    assert!(message.contains("--ghost"), "{message}");
+    assert_eq!(err.exit_code(), 7);
+}
+
+#[test]
+fn full_server_tells_when_to_retry() {
+    let err = ConnectError::ServerFull {
+        retry_after: Duration::from_secs(10),
+    };
+    let message = err.to_string();
+    assert!(message.contains("10 s"), "{message}");
+    assert!(message.contains("--wait-full"), "{message}");
+}
+
+#[test]
+fn exit_codes_tell_causes_apart() {
+    let codes = [
+        EXIT_USAGE,
+        ConnectError::from_io("a:1", io::Error::from(io::ErrorKind::ConnectionRefused)).exit_code(),
+        ConnectError::Protocol("?".into()).exit_code(),
+        ConnectError::nickname_in_use("alice").exit_code(),
+        ConnectError::NicknameRefused("Reserved username".into()).exit_code(),
+        ConnectError::ServerFull {
+            retry_after: Duration::from_secs(10),
+        }
+        .exit_code(),
+        ConnectError::Blocklisted("dnsbl.example".into()).exit_code(),
+    ];
+    let mut sorted = codes.to_vec();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted.len(), codes.len());
+    // 0 et 1 restent ceux d'une fin normale et d'une erreur inattendue
+    assert!(codes.iter().all(|&code| code > 1));
+}
+
+#[test]
+fn address_families_alternate() {
+    let v6 = |port| SocketAddr::from((Ipv6Addr::LOCALHOST, port));
+    let v4 = |port| SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    assert_eq!(
+        diagnostic::interleave(vec![v6(1), v6(2), v6(3), v4(4), v4(5)]),
+        vec![v6(1), v4(4), v6(2), v4(5), v6(3)]
+    );
+    // La famille préférée par le résolveur passe en premier
+    assert_eq!(
+        diagnostic::interleave(vec![v4(1), v4(2), v6(3)]),
+        vec![v4(1), v6(3), v4(2)]
+    );
+    assert_eq!(diagnostic::interleave(vec![]), vec![]);
+}
+
+#[test]
+fn falls_back_to_the_address_that_listens() {
+    // Seule l'adresse IPv4 de localhost écoute : l'IPv6, si elle existe, refuse
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let stream = diagnostic::connect_tcp(&format!("localhost:{port}")).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+}
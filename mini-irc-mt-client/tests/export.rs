@@ -0,0 +1,61 @@
+use mini_irc_mt::export::{export, format_time, to_json, to_text};
+use mini_irc_ui::{HistoryEntry, TimedEntry};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn entries() -> Vec<TimedEntry> {
+    vec![
+        TimedEntry {
+            at: UNIX_EPOCH + Duration::from_secs(1_714_566_896),
+            entry: HistoryEntry::Join("alice".to_string()),
+        },
+        TimedEntry {
+            at: UNIX_EPOCH + Duration::from_secs(1_714_566_900),
+            entry: HistoryEntry::UserMessage {
+                from: "alice".to_string(),
+                content: "bonjour".to_string(),
+            },
+        },
+    ]
+}
+
+#[test]
+fn dates_are_utc() {
+    assert_eq!(format_time(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+    assert_eq!(
+        format_time(UNIX_EPOCH + Duration::from_secs(1_714_566_896)),
+        "2024-05-01 12:34:56 UTC"
+    );
+    // 29 février d'une année bissextile
+    assert_eq!(
+        format_time(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+        "2000-02-29 00:00:00 UTC"
+    );
+}
+
+#[test]
+fn history_is_exported_as_text_or_json() {
+    assert_eq!(
+        to_text(&entries()),
+        "[2024-05-01 12:34:56 UTC] alice joined\n[2024-05-01 12:35:00 UTC] alice: bonjour\n"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&to_json(&entries())).unwrap();
+    assert_eq!(json[0]["kind"], "join");
+    assert!(json[0].get("from").is_none());
+    assert_eq!(json[1]["from"], "alice");
+    assert_eq!(json[1]["content"], "bonjour");
+    assert_eq!(json[1]["time"], "2024-05-01 12:35:00 UTC");
+
+    // Le format suit l'extension du fichier
+    let dir = std::env::temp_dir().join(format!("mini-irc-{}-export", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    export(&dir.join("notes.JSON"), &entries()).unwrap();
+    export(&dir.join("notes.log"), &entries()).unwrap();
+    assert!(std::fs::read_to_string(dir.join("notes.JSON"))
+        .unwrap()
+        .starts_with('['));
+    assert!(std::fs::read_to_string(dir.join("notes.log"))
+        .unwrap()
+        .starts_with("[2024"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,31 @@
+use mini_irc_mt::fallback::{NickFallback, DEFAULT_FALLBACK_ATTEMPTS};
+
+#[test]
+fn default_fallbacks_append_an_underscore_then_numbers() {
+    let fallback = NickFallback::default();
+    let candidates = fallback.candidates("alice");
+    assert_eq!(candidates.len(), DEFAULT_FALLBACK_ATTEMPTS);
+    assert_eq!(candidates[..3], ["alice_", "alice1", "alice2"]);
+    assert!(fallback.with_attempts(0).candidates("alice").is_empty());
+}
+
+#[test]
+fn patterns_are_configurable() {
+    let fallback = NickFallback::new("{nick}_,_{nick}_,{nick}-{n}")
+        .unwrap()
+        .with_attempts(4);
+    assert_eq!(
+        fallback.candidates("bob"),
+        ["bob_", "_bob_", "bob-1", "bob-2"]
+    );
+    // Sans `{n}`, chaque motif ne donne qu'un nom, et les doublons sont ignorés
+    let fallback = NickFallback::new("{nick},{nick}_,{nick}_").unwrap();
+    assert_eq!(fallback.candidates("bob"), ["bob_"]);
+}
+
+#[test]
+fn invalid_patterns_are_refused() {
+    assert!(NickFallback::new("guest{n}").is_err());
+    assert!(NickFallback::new("{nick}{n},{nick}_").is_err());
+    assert!(NickFallback::new("{nick},").is_err());
+}
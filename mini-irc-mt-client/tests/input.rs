@@ -0,0 +1,250 @@
+use mini_irc_mt::error::ClientError;
+use mini_irc_mt::mutes::Mutes;
+use mini_irc_mt::{cancel_refused_join, handle_user_input};
+use mini_irc_protocol::{ErrorCode, MessageReceiver, MessageTargets, ParseReceiverError, Request};
+use mini_irc_ui::App;
+
+// Saisie dans le tab `tab`, sans fichier de configuration
+fn submit(tab: &str, input: &str) -> Result<Option<Request>, ClientError> {
+    let mut app = App::default();
+    app.add_tab(tab.to_string());
+    let mut mutes = Mutes::load_from(None, "localhost:6667");
+    handle_user_input(input.to_string(), &mut app, &mut mutes)
+}
+
+#[test]
+fn messages_go_to_the_current_tab() {
+    assert_eq!(
+        submit("#general", "bonjour").unwrap(),
+        Some(Request::Message {
+            to: MessageReceiver::Channel("general".to_string()),
+            content: "bonjour".to_string(),
+        })
+    );
+    assert!(matches!(
+        submit("network", "bonjour"),
+        Err(ClientError::Receiver(ParseReceiverError::Unrecognized(tab))) if tab == "network"
+    ));
+}
+
+#[test]
+fn malformed_commands_are_usage_errors() {
+    for (tab, input) in [
+        ("#general", "/op"),
+        ("#general", "/to bob"),
+        ("@bob", "/claim"),
+        ("#general", "/filter joins maybe"),
+        ("#general", "/direction sideways"),
+    ] {
+        let error = submit(tab, input).unwrap_err();
+        assert!(error.is_usage(), "{input}: {error}");
+    }
+    assert!(matches!(
+        submit("@bob", "/names"),
+        Err(ClientError::ChannelOnly("names"))
+    ));
+}
+
+#[test]
+fn other_errors_keep_their_kind() {
+    assert!(matches!(
+        submit("#general", "/dance"),
+        Err(ClientError::UnknownCommand(input)) if input == "/dance"
+    ));
+    assert!(matches!(
+        submit("#general", "/accept-invite rust"),
+        Err(ClientError::NoInviteTo(chan)) if chan == "rust"
+    ));
+    assert!(!ClientError::NoInvites.is_usage());
+}
+
+#[test]
+fn receivers_are_parsed() {
+    assert_eq!("@bob".parse(), Ok(MessageReceiver::User("bob".to_string())));
+    assert_eq!(
+        "#".parse::<MessageReceiver>(),
+        Err(ParseReceiverError::TooShort("#".to_string()))
+    );
+    // Le préfixe d'un canal système fait partie de son nom
+    assert_eq!(
+        "&annonces".parse(),
+        Ok(MessageReceiver::Channel("&annonces".to_string()))
+    );
+    for tab in ["#general", "&annonces", "@bob"] {
+        assert_eq!(tab.parse::<MessageReceiver>().unwrap().to_string(), tab);
+    }
+    assert_eq!("$all".parse(), Ok(MessageReceiver::All));
+    assert_eq!(
+        "$#dev-*".parse(),
+        Ok(MessageReceiver::Channels("dev-*".to_string()))
+    );
+    assert_eq!(
+        "$#".parse::<MessageReceiver>(),
+        Err(ParseReceiverError::TooShort("$#".to_string()))
+    );
+    assert_eq!(
+        "$everyone".parse::<MessageReceiver>(),
+        Err(ParseReceiverError::Unrecognized("$everyone".to_string()))
+    );
+    let targets: MessageTargets = "#a,@bob,&annonces,$all,$#dev-*".parse().unwrap();
+    assert_eq!(targets.to_string(), "#a,@bob,&annonces,$all,$#dev-*");
+    assert_eq!(
+        ParseReceiverError::Unrecognized("bob".to_string()).to_string(),
+        "Unrecognized receiver: bob"
+    );
+}
+
+#[test]
+fn to_accepts_quoted_nicknames() {
+    assert_eq!(
+        submit("#general", r#"/to "bob smith" salut"#).unwrap(),
+        Some(Request::Message {
+            to: MessageReceiver::User("bob smith".to_string()),
+            content: "salut".to_string(),
+        })
+    );
+    for input in ["/to", "/to bob"] {
+        assert!(matches!(
+            submit("#general", input),
+            Err(ClientError::Usage("/to <nickname> <message>"))
+        ));
+    }
+    // Le nom de la commande est exact
+    assert!(matches!(
+        submit("#general", "/tomato bob salut"),
+        Err(ClientError::UnknownCommand(_))
+    ));
+}
+
+#[test]
+fn msg_sends_without_switching_tabs() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    let mut mutes = Mutes::load_from(None, "localhost:6667");
+    let mut input =
+        |app: &mut App, input: &str| handle_user_input(input.to_string(), app, &mut mutes);
+
+    assert_eq!(
+        input(&mut app, "/msg #rust  on se voit ?").unwrap(),
+        Some(Request::Message {
+            to: MessageReceiver::Channel("rust".to_string()),
+            content: "on se voit ?".to_string(),
+        })
+    );
+    assert_eq!(
+        input(&mut app, "/msg @bob salut").unwrap(),
+        Some(Request::Message {
+            to: MessageReceiver::User("bob".to_string()),
+            content: "salut".to_string(),
+        })
+    );
+    assert!(app.history("@bob").is_none());
+
+    input(&mut app, "/msgtab on").unwrap();
+    input(&mut app, "/msg @bob re").unwrap();
+    assert_eq!(app.history("@bob").map(<[_]>::len), Some(1));
+    assert_eq!(app.get_current_tab(), "#general");
+
+    assert!(matches!(
+        input(&mut app, "/msg bob salut"),
+        Err(ClientError::Receiver(_))
+    ));
+    assert!(matches!(
+        input(&mut app, "/msg @bob"),
+        Err(ClientError::Usage(_))
+    ));
+}
+
+#[test]
+fn msg_fans_out_to_several_targets() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    app.add_tab("@carol".to_string());
+    let mut mutes = Mutes::load_from(None, "localhost:6667");
+    let mut input =
+        |app: &mut App, input: &str| handle_user_input(input.to_string(), app, &mut mutes);
+
+    assert_eq!(
+        input(&mut app, "/msg #a,&annonces,@carol,#a salut").unwrap(),
+        Some(Request::MessageMany {
+            to: vec![
+                MessageReceiver::Channel("a".to_string()),
+                MessageReceiver::Channel("&annonces".to_string()),
+                MessageReceiver::User("carol".to_string()),
+            ],
+            content: "salut".to_string(),
+        })
+    );
+    // Le message direct s'affiche dans la conversation ouverte
+    assert_eq!(app.history("@carol").map(<[_]>::len), Some(1));
+    // Un seul destinataire, une fois les doublons retirés
+    assert!(matches!(
+        input(&mut app, "/msg @bob,@bob salut").unwrap(),
+        Some(Request::Message { .. })
+    ));
+    assert!(matches!(
+        input(&mut app, "/msg #a,,@bob salut"),
+        Err(ClientError::Receiver(_))
+    ));
+}
+
+#[test]
+fn reveal_counts_messages_with_spoilers() {
+    let mut app = App::default();
+    app.add_tab("#quiz".to_string());
+    let mut mutes = Mutes::load_from(None, "localhost:6667");
+    let mut input =
+        |app: &mut App, input: &str| handle_user_input(input.to_string(), app, &mut mutes);
+    app.push_message("bob".into(), "réponse : ||42||".into(), "#quiz".into());
+    app.push_message("bob".into(), "sans spoiler".into(), "#quiz".into());
+
+    input(&mut app, "/reveal").unwrap();
+    input(&mut app, "/reveal 1").unwrap();
+    assert!(matches!(
+        input(&mut app, "/reveal 2"),
+        Err(ClientError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        input(&mut app, "/reveal deux"),
+        Err(ClientError::Usage(_))
+    ));
+    // L'historique garde le message tel quel
+    let history = app.history("#quiz").unwrap();
+    assert_eq!(history[0].entry.text(), "bob: réponse : ||42||");
+}
+
+#[test]
+fn join_refusal_waits_for_its_own_channel() {
+    let mut app = App::default();
+    app.add_tab("@bob".to_string());
+    let mut mutes = Mutes::load_from(None, "localhost:6667");
+    handle_user_input("salut".to_string(), &mut app, &mut mutes).unwrap();
+    handle_user_input("/join rust".to_string(), &mut app, &mut mutes).unwrap();
+    assert_eq!(app.get_current_tab(), "#rust");
+
+    // Le refus du message direct arrive pendant que le `/join` attend sa réponse
+    let dm = ErrorCode::UnknownUser("bob".to_string());
+    assert_eq!(cancel_refused_join(&mut app, &dm), None);
+    assert_eq!(app.get_current_tab(), "#rust");
+
+    let join = ErrorCode::AlreadyInChannel("rust".to_string());
+    assert_eq!(
+        cancel_refused_join(&mut app, &join),
+        Some("#rust".to_string())
+    );
+    assert_eq!(app.get_current_tab(), "@bob");
+}
+
+#[test]
+fn quit_closes_a_direct_message_tab() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    app.add_tab("@bob".to_string());
+    let mut mutes = Mutes::load_from(None, "localhost:6667");
+    assert!(app.select_tab("@bob"));
+    assert_eq!(
+        handle_user_input("/quit".to_string(), &mut app, &mut mutes).unwrap(),
+        None
+    );
+    assert_eq!(app.get_current_tab(), "#general");
+}
@@ -0,0 +1,19 @@
+use mini_irc_mt::journal;
+
+#[test]
+fn lines_are_appended() {
+    let dir = std::env::temp_dir().join(format!("mini-irc-{}-journal", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("client.log");
+
+    journal::log_to(&path, "première").unwrap();
+    journal::log_to(&path, "deux\nlignes").unwrap();
+    let log = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(" première"));
+    // Une ligne par évènement
+    assert!(lines[1].ends_with(" deux lignes"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,38 @@
+use mini_irc_mt::lag::LagMeter;
+use mini_irc_protocol::Request;
+use std::time::{Duration, Instant};
+
+#[test]
+fn probes_are_spaced_and_measured() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let mut lag = LagMeter::new(Duration::from_secs(5));
+
+    assert_eq!(lag.probe(at(0)), Some(Request::Ping(1)));
+    // Une seule sonde en attente
+    assert_eq!(lag.probe(at(6000)), None);
+    assert_eq!(lag.pong(2, at(40)), None);
+    assert_eq!(lag.pong(1, at(40)), Some(Duration::from_millis(40)));
+    assert_eq!(lag.pong(1, at(50)), None);
+
+    // La suivante attend l'intervalle
+    assert_eq!(lag.probe(at(4000)), None);
+    assert_eq!(lag.probe(at(5000)), Some(Request::Ping(2)));
+}
+
+#[test]
+fn unanswered_probes_show_a_growing_lag() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let mut lag = LagMeter::new(Duration::from_secs(5));
+    lag.probe(at(0));
+    lag.pong(1, at(100));
+
+    lag.probe(at(5000));
+    // Rien de plus que la dernière mesure
+    assert_eq!(lag.overdue(at(5050)), None);
+    assert_eq!(lag.overdue(at(5300)), Some(Duration::from_millis(300)));
+    assert_eq!(lag.overdue(at(7000)), Some(Duration::from_millis(2000)));
+    assert_eq!(lag.pong(2, at(7500)), Some(Duration::from_millis(2500)));
+    assert_eq!(lag.overdue(at(8000)), None);
+}
@@ -0,0 +1,35 @@
+use mini_irc_mt::locale::Locale;
+use mini_irc_protocol::{ErrorCode, Notice};
+
+#[test]
+fn posix_locales_select_the_language() {
+    assert_eq!(Locale::parse("fr"), Locale::Fr);
+    assert_eq!(Locale::parse("fr_CA.UTF-8"), Locale::Fr);
+    assert_eq!(Locale::parse("FR-fr"), Locale::Fr);
+    assert_eq!(Locale::parse("en_US.UTF-8"), Locale::En);
+    // Langue non traduite
+    assert_eq!(Locale::parse("de_DE"), Locale::En);
+    assert_eq!(Locale::parse("C"), Locale::En);
+}
+
+#[test]
+fn codes_are_translated_with_their_arguments() {
+    let code = ErrorCode::UnknownUser("bob".to_string());
+    assert_eq!(Locale::En.error(&code), "Unknown user: bob");
+    assert_eq!(Locale::Fr.error(&code), "Utilisateur inconnu : bob");
+    assert_eq!(
+        Locale::Fr.error(&ErrorCode::NotInChannel),
+        "Pas dans le canal"
+    );
+
+    let notice = Notice::InviteSent("bob".to_string());
+    assert_eq!(Locale::En.notice(&notice), "Invitation sent to bob");
+    assert_eq!(Locale::Fr.notice(&notice), "Invitation envoyée à bob");
+}
+
+#[test]
+fn welcome_messages_are_not_translated() {
+    let notice = Notice::Welcome("Bienvenue !".to_string());
+    assert_eq!(Locale::En.notice(&notice), "Bienvenue !");
+    assert_eq!(Locale::Fr.notice(&notice), "Bienvenue !");
+}
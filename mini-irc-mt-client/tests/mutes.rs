@@ -0,0 +1,37 @@
+use mini_irc_mt::mutes::Mutes;
+use std::path::PathBuf;
+
+fn config_file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mini-irc-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("muted.json")
+}
+
+#[test]
+fn mutes_are_kept_per_server() {
+    let path = config_file("mutes");
+    let mut mutes = Mutes::load_from(Some(path.clone()), "localhost:6667");
+    mutes.set("#general", true).unwrap();
+    mutes.set("#rust", true).unwrap();
+    mutes.set("#rust", false).unwrap();
+
+    let mutes = Mutes::load_from(Some(path.clone()), "localhost:6667");
+    assert!(mutes.contains("#general"));
+    assert!(!mutes.contains("#rust"));
+    // Le même canal d'un autre serveur n'est pas muet
+    let other = Mutes::load_from(Some(path.clone()), "example.org:6667");
+    assert!(!other.contains("#general"));
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn unreadable_config_mutes_nothing() {
+    let path = config_file("garbage");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "not json").unwrap();
+    assert!(!Mutes::load_from(Some(path.clone()), "localhost:6667").contains("#general"));
+    assert!(!Mutes::load_from(None, "localhost:6667").contains("#general"));
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
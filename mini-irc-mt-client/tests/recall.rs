@@ -0,0 +1,59 @@
+use mini_irc_mt::recall::Recall;
+use mini_irc_ui::INPUT_HISTORY_LEN;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn data_file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mini-irc-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("input_history.json")
+}
+
+fn inputs(tab: &str, inputs: &[&str]) -> BTreeMap<String, Vec<String>> {
+    let inputs = inputs.iter().map(|input| input.to_string()).collect();
+    BTreeMap::from([(tab.to_string(), inputs)])
+}
+
+#[test]
+fn inputs_are_kept_per_server() {
+    let path = data_file("recall");
+    let mut recall = Recall::load_from(Some(path.clone()), "localhost:6667");
+    recall
+        .save(inputs("#rust", &["bonjour", "/join #general"]))
+        .unwrap();
+    let mut other = Recall::load_from(Some(path.clone()), "example.org:6667");
+    assert!(other.tabs().is_empty());
+    other.save(inputs("@bob", &["salut"])).unwrap();
+
+    let recall = Recall::load_from(Some(path.clone()), "localhost:6667");
+    assert_eq!(
+        recall.tabs(),
+        inputs("#rust", &["bonjour", "/join #general"])
+    );
+    let other = Recall::load_from(Some(path), "example.org:6667");
+    assert_eq!(other.tabs(), inputs("@bob", &["salut"]));
+}
+
+#[test]
+fn only_the_last_inputs_are_saved() {
+    let path = data_file("recall-cap");
+    let mut recall = Recall::load_from(Some(path.clone()), "localhost:6667");
+    let many: Vec<String> = (0..INPUT_HISTORY_LEN + 5).map(|i| i.to_string()).collect();
+    let mut tabs = BTreeMap::from([("#rust".to_string(), many)]);
+    // Un tab sans saisie n'est pas enregistré
+    tabs.insert("#vide".to_string(), Vec::new());
+    recall.save(tabs).unwrap();
+
+    let saved = Recall::load_from(Some(path), "localhost:6667").tabs();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved["#rust"].len(), INPUT_HISTORY_LEN);
+    assert_eq!(saved["#rust"][0], "5");
+}
+
+#[test]
+fn nothing_is_written_without_a_file() {
+    let mut recall = Recall::load_from(None, "localhost:6667");
+    recall.save(inputs("#rust", &["bonjour"])).unwrap();
+    // Conservé seulement le temps du lancement
+    assert_eq!(recall.tabs(), inputs("#rust", &["bonjour"]));
+}
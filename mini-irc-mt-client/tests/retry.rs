@@ -0,0 +1,61 @@
+use mini_irc_mt::retry::RetryQueue;
+use mini_irc_protocol::{MessageReceiver, Request};
+use std::time::{Duration, Instant};
+
+fn message(content: &str) -> Request {
+    Request::Message {
+        to: MessageReceiver::Channel("general".to_string()),
+        content: content.to_string(),
+    }
+}
+
+#[test]
+fn refused_message_is_resent_after_the_delay() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let mut retries = RetryQueue::new(Duration::from_millis(100));
+
+    assert_eq!(retries.send(message("un")), Some(message("un")));
+    assert_eq!(retries.send(message("deux")), Some(message("deux")));
+    retries.rate_limited(Duration::from_secs(2), at(0));
+    // Les suivants attendent derrière le message refusé
+    assert_eq!(retries.send(message("trois")), None);
+    assert_eq!(retries.len(), 2);
+    assert_eq!(
+        retries.remaining(at(500)),
+        Some(Duration::from_millis(1600))
+    );
+
+    assert_eq!(retries.due(at(2000)), None);
+    assert_eq!(retries.due(at(2100)), Some(message("deux")));
+    // Un par un, dans l'ordre de saisie
+    assert_eq!(retries.due(at(2100)), Some(message("trois")));
+    assert_eq!(retries.due(at(2200)), None);
+    assert!(retries.is_empty());
+    assert_eq!(retries.remaining(at(2200)), None);
+    assert_eq!(retries.send(message("quatre")), Some(message("quatre")));
+}
+
+#[test]
+fn resent_message_can_be_refused_again() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let mut retries = RetryQueue::new(Duration::ZERO);
+
+    retries.send(message("un"));
+    retries.rate_limited(Duration::from_secs(1), at(0));
+    retries.send(message("deux"));
+    assert_eq!(retries.due(at(1000)), Some(message("un")));
+    retries.rate_limited(Duration::from_secs(1), at(1000));
+    assert_eq!(retries.len(), 2);
+    assert_eq!(retries.due(at(2000)), Some(message("un")));
+    assert_eq!(retries.due(at(2000)), Some(message("deux")));
+}
+
+#[test]
+fn stray_refusal_is_ignored() {
+    let mut retries = RetryQueue::default();
+    retries.rate_limited(Duration::from_secs(1), Instant::now());
+    assert_eq!(retries.remaining(Instant::now()), None);
+    assert_eq!(retries.send(message("un")), Some(message("un")));
+}
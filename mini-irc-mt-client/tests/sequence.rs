@@ -0,0 +1,23 @@
+use mini_irc_mt::sequence::Sequences;
+
+#[test]
+fn gaps_are_counted_per_channel() {
+    let mut sequences = Sequences::default();
+    // La première réponse d'un canal ne manque rien, quel que soit son numéro
+    assert_eq!(sequences.check("general", 7), 0);
+    assert_eq!(sequences.check("general", 8), 0);
+    assert_eq!(sequences.check("rust", 1), 0);
+    assert_eq!(sequences.check("general", 11), 2);
+    assert_eq!(sequences.check("rust", 2), 0);
+    // Hors séquence
+    assert_eq!(sequences.check("general", 0), 0);
+    assert_eq!(sequences.check("general", 12), 0);
+}
+
+#[test]
+fn left_channels_start_over() {
+    let mut sequences = Sequences::default();
+    sequences.check("general", 3);
+    sequences.forget("general");
+    assert_eq!(sequences.check("general", 10), 0);
+}
@@ -0,0 +1,60 @@
+use mini_irc_mt::session::Session;
+use mini_irc_ui::TabLayout;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+fn data_file(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mini-irc-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("session.json")
+}
+
+#[test]
+fn layout_is_kept_per_server() {
+    let path = data_file("session");
+    let read_at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_760_000_000_123);
+    let tabs = vec![
+        TabLayout {
+            name: "#rust".to_string(),
+            scroll: 3,
+            last_read: Some(read_at),
+        },
+        TabLayout {
+            name: "@bob".to_string(),
+            scroll: 0,
+            last_read: None,
+        },
+    ];
+    let mut session = Session::load_from(Some(path.clone()), "localhost:6667");
+    session
+        .save(tabs.clone(), Some("@bob".to_string()))
+        .unwrap();
+
+    let session = Session::load_from(Some(path.clone()), "localhost:6667");
+    assert_eq!(session.tabs(), tabs);
+    assert_eq!(session.current(), Some("@bob"));
+    // Un autre serveur n'a aucun tab enregistré
+    let mut other = Session::load_from(Some(path.clone()), "example.org:6667");
+    assert!(other.tabs().is_empty());
+    assert_eq!(other.current(), None);
+
+    // Enregistrer celui-ci ne touche pas au premier
+    other.save(Vec::new(), None).unwrap();
+    let session = Session::load_from(Some(path.clone()), "localhost:6667");
+    assert_eq!(session.tabs(), tabs);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn unreadable_file_restores_nothing() {
+    let path = data_file("garbage-session");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "not json").unwrap();
+    assert!(Session::load_from(Some(path.clone()), "localhost:6667")
+        .tabs()
+        .is_empty());
+    assert!(Session::load_from(None, "localhost:6667").tabs().is_empty());
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
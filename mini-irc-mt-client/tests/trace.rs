@@ -0,0 +1,58 @@
+use mini_irc_mt::trace::write_dump;
+use mini_irc_protocol::{HandshakeRequest, Request, TypedWriter, WireTap, WireVersion};
+use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
+
+#[test]
+fn dump_lists_frames_as_json() {
+    let tap = WireTap::new(8);
+    let mut writer = TypedWriter::<_, HandshakeRequest>::new(Vec::new());
+    writer.set_tap(tap.clone());
+    writer.send(&HandshakeRequest::Shared(vec![255])).unwrap();
+
+    let path = std::env::temp_dir().join(format!("mini-irc-trace-{}.json", std::process::id()));
+    write_dump(&path, &tap).unwrap();
+    let dump: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let frames = dump.as_array().unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0]["direction"], "sent");
+    assert_eq!(frames[0]["value"], "Shared([255])");
+    let raw = frames[0]["raw"].as_str().unwrap();
+    assert_eq!(raw.len(), 2 * frames[0]["len"].as_u64().unwrap() as usize);
+    // Suivi de l'annonce de version des trames en clair
+    assert!(raw.ends_with(&format!("ff{:02x}", WireVersion::LATEST as u8)));
+}
+
+#[test]
+fn dump_hides_session_keys() {
+    let key = "0123456789abcdef";
+    let tap = WireTap::new(8);
+    let writer = TypedWriter::<_, HandshakeRequest>::new(Vec::new());
+    let mut writer = writer.upgrade::<Request>(SharedKey::generate());
+    writer.set_tap(tap.clone());
+    // Identification du client : reprise de session, nom, puis clé de reprise
+    let login = [
+        Request::Ghost {
+            nickname: "alice".to_string(),
+            key: key.to_string(),
+        },
+        Request::Connect("alice".to_string()),
+        Request::GhostKey(key.to_string()),
+    ];
+    for request in &login {
+        writer.send(request).unwrap();
+    }
+
+    let path =
+        std::env::temp_dir().join(format!("mini-irc-trace-keys-{}.json", std::process::id()));
+    write_dump(&path, &tap).unwrap();
+    let dump = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(!dump.contains(key), "{dump}");
+    let hex: String = key.bytes().map(|byte| format!("{byte:02x}")).collect();
+    assert!(!dump.contains(&hex), "{dump}");
+    assert!(dump.contains(r#"GhostKey(\"<redacted>\")"#), "{dump}");
+    assert!(dump.contains(r#"Connect(\"alice\")"#), "{dump}");
+}
@@ -0,0 +1,79 @@
+//! Latence d'encodage et de décodage d'une trame, en clair et chiffrée.
+
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, Criterion};
+use mini_irc_protocol::{MessageReceiver, MiniIrcCodec, Plain, Request, Transmissible};
+use serde::{Deserialize, Serialize};
+use serde_encrypt::{
+    serialize::impls::BincodeSerializer, shared_key::SharedKey, traits::SerdeEncryptSharedKey,
+    AsSharedKey,
+};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// [`Request`] autorisée à circuler en clair, de même encodage bincode.
+#[derive(Serialize, Deserialize, Debug)]
+struct Clear(Request);
+
+impl SerdeEncryptSharedKey for Clear {
+    type S = BincodeSerializer<Self>;
+}
+
+impl Transmissible<Plain> for Clear {}
+
+fn request() -> Request {
+    Request::Message {
+        to: MessageReceiver::Channel("general".to_string()),
+        content: "Lorem ipsum dolor sit amet, consectetur adipiscing elit".to_string(),
+    }
+}
+
+fn plain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("plain");
+    let request = Clear(request());
+    let mut codec = MiniIrcCodec::<Clear>::new();
+
+    group.bench_function("encode", |b| {
+        let mut buf = BytesMut::new();
+        b.iter(|| {
+            buf.clear();
+            codec.encode(&request, &mut buf).unwrap();
+        })
+    });
+
+    let mut frame = BytesMut::new();
+    codec.encode(&request, &mut frame).unwrap();
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            let mut buf = frame.clone();
+            codec.decode(&mut buf).unwrap().unwrap();
+        })
+    });
+    group.finish();
+}
+
+fn encrypted(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encrypted");
+    let request = request();
+    let mut codec = MiniIrcCodec::<Clear>::new().upgrade::<Request>(SharedKey::generate());
+
+    group.bench_function("encode", |b| {
+        let mut buf = BytesMut::new();
+        b.iter(|| {
+            buf.clear();
+            codec.encode(&request, &mut buf).unwrap();
+        })
+    });
+
+    let mut frame = BytesMut::new();
+    codec.encode(&request, &mut frame).unwrap();
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            let mut buf = frame.clone();
+            codec.decode(&mut buf).unwrap().unwrap();
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, plain, encrypted);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! Débit de diffusion d'un [`BroadcastSenderWithList`] : temps pour qu'un message soit reçu par
+//! tous les abonnés d'un canal.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mini_irc_protocol::{BroadcastReceiverWithList, BroadcastSenderWithList};
+
+const SUBSCRIBERS: [usize; 3] = [10, 100, 1000];
+
+fn fanout(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("fanout");
+
+    for subscribers in SUBSCRIBERS {
+        group.throughput(Throughput::Elements(subscribers as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(subscribers),
+            &subscribers,
+            |b, &subscribers| {
+                let sender = BroadcastSenderWithList::<String, usize>::new(16);
+                let mut receivers: Vec<BroadcastReceiverWithList<String, usize>> = (0..subscribers)
+                    .map(|i| sender.subscribe(i).unwrap())
+                    .collect();
+                b.iter(|| {
+                    sender.send("Lorem ipsum".to_string()).unwrap();
+                    runtime.block_on(async {
+                        for receiver in receivers.iter_mut() {
+                            receiver.recv().await.unwrap();
+                        }
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fanout);
+criterion_main!(benches);
@@ -0,0 +1,139 @@
+//! Compare l'ancien chemin (une allocation par trame) aux tampons réutilisés du codec
+//! et à la réception empruntée, sur un flux de messages de canal.
+//!
+//! Les trames sont mesurées en clair, le chiffrement masquant le coût du découpage.
+
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use mini_irc_protocol::{
+    MessageReceiver, MiniIrcCodec, Plain, Request, Transmissible, TypedReader,
+};
+use serde::{Deserialize, Serialize};
+use serde_encrypt::{serialize::impls::BincodeSerializer, traits::SerdeEncryptSharedKey};
+use std::io::{Read, Write};
+use tokio_util::codec::Encoder;
+
+const FRAMES: usize = 1_000;
+
+/// [`Request`] autorisée à circuler en clair, de même encodage bincode.
+#[derive(Serialize, Deserialize, Debug)]
+struct Clear(Request);
+
+impl SerdeEncryptSharedKey for Clear {
+    type S = BincodeSerializer<Self>;
+}
+
+impl Transmissible<Plain> for Clear {}
+
+/// Vue empruntée de [`Request`], de même encodage bincode.
+#[allow(dead_code)]
+#[derive(Deserialize)]
+enum RequestRef<'a> {
+    Connect(&'a str),
+    JoinChan(&'a str),
+    LeaveChan(&'a str),
+    Message {
+        to: MessageReceiverRef<'a>,
+        content: &'a str,
+    },
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+enum MessageReceiverRef<'a> {
+    User(&'a str),
+    Channel(&'a str),
+}
+
+fn message() -> Clear {
+    Clear(Request::Message {
+        to: MessageReceiver::Channel("general".to_string()),
+        content: "Lorem ipsum dolor sit amet, consectetur adipiscing elit".to_string(),
+    })
+}
+
+fn frames() -> Vec<u8> {
+    let mut codec = MiniIrcCodec::<Clear>::new();
+    let mut buf = BytesMut::new();
+    for _ in 0..FRAMES {
+        codec.encode(&message(), &mut buf).unwrap();
+    }
+    buf.to_vec()
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Elements(FRAMES as u64));
+    let request = message();
+
+    group.bench_function("per_frame_alloc", |b| {
+        let mut out = Vec::new();
+        b.iter(|| {
+            out.clear();
+            for _ in 0..FRAMES {
+                let data = bincode::serialize(&request).unwrap();
+                out.write_all(&(data.len() as u32).to_be_bytes()).unwrap();
+                out.write_all(&data).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("codec_reused_buffer", |b| {
+        let mut codec = MiniIrcCodec::<Clear>::new();
+        let mut buf = BytesMut::new();
+        b.iter(|| {
+            buf.clear();
+            for _ in 0..FRAMES {
+                codec.encode(&request, &mut buf).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(FRAMES as u64));
+    let data = frames();
+
+    group.bench_function("per_frame_alloc", |b| {
+        b.iter(|| {
+            let mut stream = &data[..];
+            for _ in 0..FRAMES {
+                let mut size = [0; 4];
+                stream.read_exact(&mut size).unwrap();
+                let mut buf = vec![0; u32::from_be_bytes(size) as usize];
+                stream.read_exact(&mut buf).unwrap();
+                let _: Clear = bincode::deserialize(&buf).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("recv", |b| {
+        b.iter_batched(
+            || TypedReader::<_, Clear>::new(&data[..]),
+            |mut reader| {
+                for _ in 0..FRAMES {
+                    reader.recv().unwrap().unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("recv_borrowed", |b| {
+        b.iter_batched(
+            || TypedReader::<_, Clear>::new(&data[..]),
+            |mut reader| {
+                for _ in 0..FRAMES {
+                    let _: RequestRef = reader.recv_borrowed().unwrap().unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -0,0 +1,453 @@
+use arc_swap::ArcSwap;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+/// Évènement reçu par un [`BroadcastReceiverWithList`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastEvent<T> {
+    /// Message diffusé sur le canal.
+    Message(T),
+    /// Le récepteur est en retard : ce nombre de messages, les plus anciens, a été perdu
+    /// faute de place dans le canal (voir [`BroadcastSenderWithList::new`]).
+    Lagged(u64),
+}
+
+/// Un abonné d'un canal de diffusion, tel que renvoyé par
+/// [`BroadcastSenderWithList::snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscriber<U, M = ()> {
+    pub identity: U,
+    /// Date d'abonnement au canal
+    pub joined_at: SystemTime,
+    /// Données propres à l'abonné (rôle dans le canal...), voir
+    /// [`BroadcastSenderWithList::update_meta`]
+    pub meta: M,
+}
+
+/// État d'un canal de diffusion, tel que renvoyé par [`BroadcastSenderWithList::metrics`].
+/// Les compteurs ne font que croître depuis la création du canal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BroadcastMetrics<U> {
+    /// Messages diffusés
+    pub sent: u64,
+    /// Messages conservés dans le canal, que tous les récepteurs n'ont pas encore lus
+    pub queued: usize,
+    /// Messages manqués par des récepteurs en retard, y compris ceux désabonnés depuis
+    pub dropped: u64,
+    /// Abonnés actuels, avec le nombre de messages que chacun a manqués, dans l'ordre
+    /// d'abonnement
+    pub subscribers: Vec<(U, u64)>,
+}
+
+/// Message diffusé, accompagné de son rang.
+#[derive(Clone)]
+struct Envelope<T> {
+    /// Rang du message parmi ceux diffusés sur le canal
+    rank: u64,
+    data: T,
+}
+
+/// Abonné tel que conservé dans la liste partagée du canal, sous son identité.
+#[derive(Clone)]
+struct Entry<M> {
+    /// Distingue deux abonnements successifs d'une même identité, et les ordonne
+    id: u64,
+    joined_at: SystemTime,
+    meta: M,
+    /// Annulé lorsque l'abonné est désabonné par [`BroadcastSenderWithList::unsubscribe`]
+    unsubscribed: CancellationToken,
+    /// Rang du premier message diffusé après le désabonnement, que l'abonné ne reçoit plus
+    until: Arc<AtomicU64>,
+    /// Messages manqués par l'abonné
+    dropped: Arc<AtomicU64>,
+}
+
+/// Abonnés, par identité, partagés entre l'émetteur et les récepteurs. La table n'est jamais
+/// modifiée en place : chaque abonnement, désabonnement ou mise à jour la remplace par une copie,
+/// ce qui permet de la lire sans verrou, y compris depuis un contexte asynchrone ou un `Drop`.
+type Entries<U, M> = Arc<ArcSwap<HashMap<U, Entry<M>>>>;
+
+/// Remplace la table des abonnés par `update(table)`, si cette fonction renvoie une nouvelle
+/// table. Elle peut être appelée plusieurs fois en cas de modifications concurrentes.
+fn update<U: Eq + Hash, M, R>(
+    entries: &Entries<U, M>,
+    mut update: impl FnMut(&HashMap<U, Entry<M>>) -> (Option<HashMap<U, Entry<M>>>, R),
+) -> R {
+    let mut result = None;
+    entries.rcu(|current| {
+        let (new, res) = update(current);
+        result = Some(res);
+        match new {
+            Some(new) => Arc::new(new),
+            None => current.clone(),
+        }
+    });
+    result.expect("rcu always calls its closure")
+}
+
+/// Abonnés de la table, dans l'ordre d'abonnement.
+fn ordered<U, M>(entries: &HashMap<U, Entry<M>>) -> Vec<(&U, &Entry<M>)> {
+    let mut ordered: Vec<_> = entries.iter().collect();
+    ordered.sort_by_key(|(_, entry)| entry.id);
+    ordered
+}
+
+/// Canal de diffusion qui tient la liste de ses abonnés, chacun désigné par une identité `U`
+/// unique dans le canal et accompagné de données `M` (aucune par défaut).
+pub struct BroadcastSenderWithList<T, U, M = ()>
+where
+    T: Clone,
+    U: 'static + Eq + Hash + Clone,
+    M: 'static + Clone,
+{
+    sender: broadcast::Sender<Envelope<T>>,
+    subscribers: Entries<U, M>,
+    next_id: AtomicU64,
+    /// Nombre de messages diffusés
+    sent: Arc<AtomicU64>,
+    /// Nombre de messages manqués, par tous les récepteurs
+    dropped: Arc<AtomicU64>,
+}
+
+pub struct BroadcastReceiverWithList<T, U, M = ()>
+where
+    T: Clone,
+    U: 'static + Eq + Hash + Clone,
+    M: 'static + Clone,
+{
+    receiver: broadcast::Receiver<Envelope<T>>,
+    subscribers: Entries<U, M>,
+    identifier: U,
+    id: u64,
+    unsubscribed: CancellationToken,
+    until: Arc<AtomicU64>,
+    dropped: Arc<AtomicU64>,
+    /// Compteur de l'émetteur, pour tous les récepteurs
+    dropped_total: Arc<AtomicU64>,
+    /// Nombre de messages diffusés, compté par l'émetteur
+    sent: Arc<AtomicU64>,
+}
+
+impl<T, U, M> Debug for BroadcastSenderWithList<T, U, M>
+where
+    T: Clone,
+    U: 'static + Eq + Hash + Clone,
+    M: 'static + Clone,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BroadcastSenderWithList")
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+impl<T, U, M> BroadcastSenderWithList<T, U, M>
+where
+    T: Clone,
+    U: 'static + Eq + Hash + Clone,
+    M: 'static + Clone,
+{
+    /// Créé un nouveau canal de diffusion, pouvant conserver `capacity` messages non lus par
+    /// un récepteur avant que celui-ci ne les manque.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            sender,
+            subscribers: Arc::new(ArcSwap::from_pointee(HashMap::new())),
+            next_id: AtomicU64::new(0),
+            sent: Arc::new(AtomicU64::new(0)),
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Abonne `identity` au canal. Renvoie `None` si elle y est déjà abonnée.
+    pub fn subscribe(&self, identity: U) -> Option<BroadcastReceiverWithList<T, U, M>>
+    where
+        M: Default,
+    {
+        self.subscribe_with(identity, M::default())
+    }
+
+    /// Comme [`BroadcastSenderWithList::subscribe`], avec les données `meta` pour l'abonné.
+    pub fn subscribe_with(
+        &self,
+        identity: U,
+        meta: M,
+    ) -> Option<BroadcastReceiverWithList<T, U, M>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let unsubscribed = CancellationToken::new();
+        let until = Arc::new(AtomicU64::new(u64::MAX));
+        let dropped = Arc::new(AtomicU64::new(0));
+        // Le récepteur est créé avant l'ajout à la liste : tout abonné listé reçoit les messages
+        let receiver = self.sender.subscribe();
+        let entry = Entry {
+            id,
+            joined_at: SystemTime::now(),
+            meta,
+            unsubscribed: unsubscribed.clone(),
+            until: until.clone(),
+            dropped: dropped.clone(),
+        };
+        let subscribed = update(&self.subscribers, |subscribers| {
+            if subscribers.contains_key(&identity) {
+                return (None, false);
+            }
+            let mut subscribers = subscribers.clone();
+            subscribers.insert(identity.clone(), entry.clone());
+            (Some(subscribers), true)
+        });
+        if !subscribed {
+            return None;
+        }
+
+        Some(BroadcastReceiverWithList {
+            receiver,
+            subscribers: self.subscribers.clone(),
+            identifier: identity,
+            id,
+            unsubscribed,
+            until,
+            dropped,
+            dropped_total: self.dropped.clone(),
+            sent: self.sent.clone(),
+        })
+    }
+
+    /// Désabonne `identity` du canal, par exemple pour l'en exclure : son récepteur reçoit
+    /// encore les messages diffusés avant le désabonnement, puis renvoie
+    /// [`broadcast::error::RecvError::Closed`] sans attendre. Renvoie `false` si elle n'était
+    /// pas abonnée.
+    pub fn unsubscribe(&self, identity: &U) -> bool {
+        remove(&self.subscribers, &self.sent, identity, None)
+    }
+
+    /// Diffuse `data` à tous les abonnés, sans exception : l'auteur d'un message le reçoit
+    /// comme les autres, dans le même ordre que les autres diffusions du canal.
+    pub fn send(&self, data: T) -> Result<usize, tokio::sync::broadcast::error::SendError<T>> {
+        let rank = self.sent.fetch_add(1, Ordering::SeqCst);
+        self.sender
+            .send(Envelope { rank, data })
+            .map_err(|e| broadcast::error::SendError(e.0.data))
+    }
+
+    /// Nombre d'abonnés au canal.
+    pub fn len(&self) -> usize {
+        self.subscribers.load().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.load().is_empty()
+    }
+
+    /// Indique si `identity` est abonnée au canal.
+    pub fn contains(&self, identity: &U) -> bool {
+        self.subscribers.load().contains_key(identity)
+    }
+
+    /// L'abonné `identity`, s'il est abonné au canal.
+    pub fn subscriber(&self, identity: &U) -> Option<Subscriber<U, M>> {
+        self.subscribers
+            .load()
+            .get(identity)
+            .map(|entry| subscriber(identity, entry))
+    }
+
+    /// Modifie par `update` les données de l'abonné `identity`, par exemple pour lui donner un
+    /// rôle dans le canal. `update` peut être appelée plusieurs fois en cas de modifications
+    /// concurrentes. Renvoie `false` si `identity` n'est pas abonnée.
+    pub fn update_meta(&self, identity: &U, mut update_meta: impl FnMut(&mut M)) -> bool {
+        update(&self.subscribers, |subscribers| {
+            if !subscribers.contains_key(identity) {
+                return (None, false);
+            }
+            let mut subscribers = subscribers.clone();
+            if let Some(entry) = subscribers.get_mut(identity) {
+                update_meta(&mut entry.meta);
+            }
+            (Some(subscribers), true)
+        })
+    }
+
+    /// Identités des abonnés, dans l'ordre d'abonnement.
+    pub fn subscribers(&self) -> Vec<U> {
+        subscribers(&self.subscribers)
+    }
+
+    /// Abonnés, avec leur date d'abonnement et leurs données, dans l'ordre d'abonnement.
+    pub fn snapshot(&self) -> Vec<Subscriber<U, M>> {
+        ordered(&self.subscribers.load())
+            .into_iter()
+            .map(|(identity, entry)| subscriber(identity, entry))
+            .collect()
+    }
+
+    /// État du canal, à relever par exemple par un système de supervision.
+    pub fn metrics(&self) -> BroadcastMetrics<U> {
+        BroadcastMetrics {
+            sent: self.sent.load(Ordering::SeqCst),
+            queued: self.sender.len(),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            subscribers: ordered(&self.subscribers.load())
+                .into_iter()
+                .map(|(identity, entry)| (identity.clone(), entry.dropped.load(Ordering::Relaxed)))
+                .collect(),
+        }
+    }
+
+    #[deprecated(note = "use `subscribers` instead")]
+    pub fn into_subscribers(&self) -> Vec<U> {
+        self.subscribers()
+    }
+}
+
+/// Retire de la table l'abonné `identity`, s'il s'agit de l'abonnement `id` lorsqu'il est
+/// donné : son récepteur reçoit encore les messages déjà diffusés, soit `sent`, puis se ferme.
+/// Renvoie `false` si rien n'a été retiré.
+fn remove<U: Eq + Hash + Clone, M: Clone>(
+    entries: &Entries<U, M>,
+    sent: &AtomicU64,
+    identity: &U,
+    id: Option<u64>,
+) -> bool {
+    let removed = update(entries, |subscribers| match subscribers.get(identity) {
+        Some(entry) if id.is_none_or(|id| id == entry.id) => {
+            let mut subscribers = subscribers.clone();
+            let removed = subscribers.remove(identity);
+            (Some(subscribers), removed)
+        }
+        _ => (None, None),
+    });
+    match removed {
+        Some(entry) => {
+            entry
+                .until
+                .store(sent.load(Ordering::SeqCst), Ordering::SeqCst);
+            entry.unsubscribed.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+fn subscriber<U: Clone, M: Clone>(identity: &U, entry: &Entry<M>) -> Subscriber<U, M> {
+    Subscriber {
+        identity: identity.clone(),
+        joined_at: entry.joined_at,
+        meta: entry.meta.clone(),
+    }
+}
+
+fn subscribers<U: Clone, M>(entries: &Entries<U, M>) -> Vec<U> {
+    ordered(&entries.load())
+        .into_iter()
+        .map(|(identity, _)| identity.clone())
+        .collect()
+}
+
+impl<T, U, M> BroadcastReceiverWithList<T, U, M>
+where
+    T: Clone,
+    U: 'static + Eq + Hash + Clone,
+    M: 'static + Clone,
+{
+    /// Reçoit le prochain message diffusé, ou le nombre de messages manqués si le récepteur
+    /// est en retard. Renvoie une erreur [`broadcast::error::RecvError::Closed`] une fois le
+    /// canal fermé, ou l'abonné désabonné.
+    pub async fn recv(
+        &mut self,
+    ) -> Result<BroadcastEvent<T>, tokio::sync::broadcast::error::RecvError> {
+        loop {
+            let res = if self.unsubscribed.is_cancelled() {
+                // Désabonné : seuls restent les messages déjà dans le canal, et les messages
+                // perdus ne concernent plus l'abonné
+                match self.receiver.try_recv() {
+                    Ok(envelope) => Ok(envelope),
+                    Err(_) => return Err(broadcast::error::RecvError::Closed),
+                }
+            } else {
+                let recv = pin!(self.receiver.recv());
+                let unsubscribed = pin!(self.unsubscribed.cancelled());
+                match futures::future::select(unsubscribed, recv).await {
+                    futures::future::Either::Left(_) => continue,
+                    futures::future::Either::Right((res, _)) => res,
+                }
+            };
+            match res {
+                Ok(envelope) if envelope.rank >= self.until.load(Ordering::SeqCst) => {
+                    return Err(broadcast::error::RecvError::Closed)
+                }
+                Ok(envelope) => return Ok(BroadcastEvent::Message(envelope.data)),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    self.dropped.fetch_add(missed, Ordering::Relaxed);
+                    self.dropped_total.fetch_add(missed, Ordering::Relaxed);
+                    return Ok(BroadcastEvent::Lagged(missed));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Identités des abonnés, dans l'ordre d'abonnement.
+    pub fn subscribers(&self) -> Vec<U> {
+        subscribers(&self.subscribers)
+    }
+
+    /// Désabonne ce récepteur, sans attendre qu'il soit abandonné, comme le ferait
+    /// [`BroadcastSenderWithList::unsubscribe`] : un abonnement plus récent de la même identité
+    /// n'est pas touché. Renvoie `false` s'il était déjà désabonné.
+    pub fn unsubscribe(&self) -> bool {
+        remove(
+            &self.subscribers,
+            &self.sent,
+            &self.identifier,
+            Some(self.id),
+        )
+    }
+
+    #[deprecated(note = "use `subscribers` instead")]
+    pub fn into_subscribers(&self) -> Vec<U> {
+        self.subscribers()
+    }
+}
+
+impl<T, U, M> Debug for BroadcastReceiverWithList<T, U, M>
+where
+    T: Clone,
+    U: 'static + Eq + Hash + Clone,
+    M: 'static + Clone,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BroadcastReceiverWithList")
+            .field("receiver", &self.receiver)
+            .finish()
+    }
+}
+
+impl<T, U, M> Drop for BroadcastReceiverWithList<T, U, M>
+where
+    T: Clone,
+    U: 'static + Eq + Hash + Clone,
+    M: 'static + Clone,
+{
+    // We must remove the relevant receiver from list, unless it was already unsubscribed
+    // and its identity subscribed again
+    fn drop(&mut self) {
+        update(&self.subscribers, |subscribers| {
+            match subscribers.get(&self.identifier) {
+                Some(entry) if entry.id == self.id => {
+                    let mut subscribers = subscribers.clone();
+                    subscribers.remove(&self.identifier);
+                    (Some(subscribers), ())
+                }
+                _ => (None, ()),
+            }
+        });
+    }
+}
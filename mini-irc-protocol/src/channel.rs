@@ -0,0 +1,257 @@
+use crate::{
+    AsyncTypedReader, AsyncTypedWriter, Encrypted, Encryption, EncryptionStatus, Heartbeat, Plain,
+    ProtocolError, SyncTransport, Transmissible, Transport, TypedReader, TypedWriter, WireTap,
+    WireVersion,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_encrypt::shared_key::SharedKey;
+use serde_encrypt::traits::SerdeEncryptSharedKey;
+use std::fmt::Debug;
+use tokio::io::DuplexStream;
+
+/// Partie réception d'un [`TypedChannel`] séparé.
+pub type ChannelReader<Stream, In, E = Plain> =
+    AsyncTypedReader<<Stream as Transport>::ReadHalf, In, E>;
+
+/// Partie émission d'un [`TypedChannel`] séparé.
+pub type ChannelWriter<Stream, Out, E = Plain> =
+    AsyncTypedWriter<<Stream as Transport>::WriteHalf, Out, E>;
+
+/// Canal de communication bidirectionnel, typé et **asynchrone**, qui envoie des `Out` et
+/// reçoit des `In` sur un même [`Transport`]. Regroupe un [`AsyncTypedReader`] et un
+/// [`AsyncTypedWriter`].
+///
+/// # Exemple
+///
+/// [`TypedChannel::duplex`] relie deux canaux en mémoire, sans socquette :
+///
+/// ```
+/// use mini_irc_protocol::{Request, Response, TypedChannel};
+/// use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let (client, server) = TypedChannel::<_, Request, Response>::duplex(1024);
+/// let shared_key = SharedKey::generate();
+/// let mut client = client.upgrade::<Request, Response>(shared_key.clone());
+/// let mut server = server.upgrade::<Response, Request>(shared_key);
+/// client.send(&Request::Connect("toto".to_string())).await.unwrap();
+/// assert_eq!(
+///     server.recv().await.unwrap(),
+///     Some(Request::Connect("toto".to_string()))
+/// );
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TypedChannel<Stream, Out, In, E = Plain>
+where
+    Stream: Transport,
+{
+    pub reader: AsyncTypedReader<Stream::ReadHalf, In, E>,
+    pub writer: AsyncTypedWriter<Stream::WriteHalf, Out, E>,
+}
+
+impl<Stream, Out, In> TypedChannel<Stream, Out, In>
+where
+    Stream: Transport,
+{
+    /// Créé un nouveau TypedChannel
+    pub fn new(stream: Stream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self {
+            reader: AsyncTypedReader::new(reader),
+            writer: AsyncTypedWriter::new(writer),
+        }
+    }
+
+    /// Chiffre les trames suivantes, reçues comme émises, avec la clé partagée fournie. Le
+    /// format des trames devient le plus récent commun aux deux pairs, voir [`WireVersion`].
+    pub fn upgrade<Out2, In2>(
+        self,
+        shared_key: SharedKey,
+    ) -> TypedChannel<Stream, Out2, In2, Encrypted> {
+        let mut writer = self.writer;
+        // Seule la réception a vu la version annoncée par l'autre pair
+        writer
+            .codec
+            .set_peer_version(self.reader.codec.peer_version());
+        TypedChannel {
+            reader: self.reader.upgrade(shared_key.clone()),
+            writer: writer.upgrade(shared_key),
+        }
+    }
+
+    /// N'annonce pas de format plus récent que `max` à l'autre pair, voir [`WireVersion`].
+    pub fn set_max_wire_version(&mut self, max: WireVersion) {
+        self.reader.set_max_wire_version(max);
+        self.writer.set_max_wire_version(max);
+    }
+}
+
+impl<Stream, Out, In, E> TypedChannel<Stream, Out, In, E>
+where
+    Stream: Transport,
+    E: Encryption,
+{
+    /// Indique si les trames sont chiffrées, et par quel mécanisme.
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        self.reader.encryption_status()
+    }
+
+    /// Envoie des trames de maintien et considère le pair perdu s'il n'en envoie pas, voir
+    /// [`Heartbeat`].
+    pub fn set_heartbeat(&mut self, heartbeat: Heartbeat) {
+        self.reader.set_heartbeat(heartbeat);
+        self.writer.set_heartbeat(heartbeat);
+    }
+
+    /// Sépare le canal, par exemple pour recevoir et émettre depuis deux tâches différentes.
+    pub fn into_split(self) -> (ChannelReader<Stream, In, E>, ChannelWriter<Stream, Out, E>) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<Out, In> TypedChannel<DuplexStream, Out, In> {
+    /// Créé deux canaux reliés en mémoire : ce qui est envoyé par l'un est reçu par l'autre.
+    /// `max_buf_size` est la taille du tampon de chaque direction (voir [`tokio::io::duplex`]).
+    pub fn duplex(max_buf_size: usize) -> (Self, TypedChannel<DuplexStream, In, Out>) {
+        let (a, b) = tokio::io::duplex(max_buf_size);
+        (Self::new(a), TypedChannel::new(b))
+    }
+}
+
+impl<Stream, Out, In, E> TypedChannel<Stream, Out, In, E>
+where
+    Stream: Transport,
+    Stream::ReadHalf: Debug,
+    Stream::WriteHalf: Debug,
+    Out: Serialize + Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    In: DeserializeOwned + Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    /// Voir [`AsyncTypedWriter::send`].
+    pub async fn send(&mut self, value: &Out) -> Result<(), ProtocolError> {
+        self.writer.send(value).await
+    }
+
+    /// Voir [`AsyncTypedReader::recv`].
+    pub async fn recv(&mut self) -> Result<Option<In>, ProtocolError> {
+        self.reader.recv().await
+    }
+
+    /// Voir [`AsyncTypedWriter::close`].
+    pub async fn close(&mut self) -> Result<(), ProtocolError> {
+        self.writer.close().await
+    }
+
+    /// Voir [`AsyncTypedWriter::heartbeat`].
+    pub async fn heartbeat(&mut self) -> Result<(), ProtocolError> {
+        self.writer.heartbeat().await
+    }
+}
+
+/// Canal de communication bidirectionnel, typé et **synchrone**, qui envoie des `Out` et
+/// reçoit des `In` sur une même socquette TCP ou Unix (voir [`SyncTransport`]). Regroupe un
+/// [`TypedReader`] et un [`TypedWriter`].
+///
+/// # Exemple
+///
+/// ```no_run
+/// use std::net::TcpStream;
+/// use mini_irc_protocol::{Request, Response, SyncTypedChannel};
+/// use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
+///
+/// # let shared_key = SharedKey::generate();
+/// let stream = TcpStream::connect("serveur:port").unwrap();
+/// let channel = SyncTypedChannel::<Request, Response>::new(stream).unwrap();
+/// let mut channel = channel.upgrade::<Request, Response>(shared_key);
+/// channel.send(&Request::Connect("toto".to_string())).unwrap();
+/// let response: Response = channel.recv().unwrap().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SyncTypedChannel<Out, In, E = Plain, Stream = std::net::TcpStream>
+where
+    Stream: SyncTransport,
+{
+    pub reader: TypedReader<Stream, In, E>,
+    pub writer: TypedWriter<Stream, Out, E>,
+}
+
+impl<Out, In, Stream: SyncTransport> SyncTypedChannel<Out, In, Plain, Stream> {
+    /// Créé un nouveau SyncTypedChannel. La socquette est dupliquée pour la réception.
+    pub fn new(stream: Stream) -> std::io::Result<Self> {
+        Ok(Self {
+            reader: TypedReader::new(stream.try_clone()?),
+            writer: TypedWriter::new(stream),
+        })
+    }
+
+    /// Chiffre les trames suivantes, reçues comme émises, avec la clé partagée fournie. Le
+    /// format des trames devient le plus récent commun aux deux pairs, voir [`WireVersion`].
+    pub fn upgrade<Out2, In2>(
+        self,
+        shared_key: SharedKey,
+    ) -> SyncTypedChannel<Out2, In2, Encrypted, Stream> {
+        let mut writer = self.writer;
+        // Seule la réception a vu la version annoncée par l'autre pair
+        writer
+            .codec
+            .set_peer_version(self.reader.codec.peer_version());
+        SyncTypedChannel {
+            reader: self.reader.upgrade(shared_key.clone()),
+            writer: writer.upgrade(shared_key),
+        }
+    }
+
+    /// N'annonce pas de format plus récent que `max` à l'autre pair, voir [`WireVersion`].
+    pub fn set_max_wire_version(&mut self, max: WireVersion) {
+        self.reader.set_max_wire_version(max);
+        self.writer.set_max_wire_version(max);
+    }
+}
+
+impl<Out, In, E, Stream> SyncTypedChannel<Out, In, E, Stream>
+where
+    E: Encryption,
+    Stream: SyncTransport,
+{
+    /// Indique si les trames sont chiffrées, et par quel mécanisme.
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        self.reader.encryption_status()
+    }
+
+    /// Capture les trames reçues et envoyées suivantes dans `tap`, voir [`WireTap`].
+    pub fn set_tap(&mut self, tap: WireTap) {
+        self.reader.set_tap(tap.clone());
+        self.writer.set_tap(tap);
+    }
+
+    /// Sépare le canal, par exemple pour recevoir et émettre depuis deux threads différents.
+    pub fn into_split(self) -> (TypedReader<Stream, In, E>, TypedWriter<Stream, Out, E>) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<Out, In, E, Stream> SyncTypedChannel<Out, In, E, Stream>
+where
+    Stream: SyncTransport + Debug,
+    Out: Serialize + Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    In: DeserializeOwned + Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    /// Voir [`TypedWriter::send`].
+    pub fn send(&mut self, value: &Out) -> Result<(), ProtocolError> {
+        self.writer.send(value)
+    }
+
+    /// Voir [`TypedReader::recv`].
+    pub fn recv(&mut self) -> Result<Option<In>, ProtocolError> {
+        self.reader.recv()
+    }
+
+    /// Voir [`TypedWriter::close`].
+    pub fn close(&mut self) -> Result<(), ProtocolError> {
+        self.writer.close()
+    }
+}
@@ -0,0 +1,329 @@
+use crate::{
+    Encrypted, Encryption, EncryptionStatus, FrameTag, Plain, ProtocolError, Transmissible,
+    WireVersion,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_encrypt::shared_key::SharedKey;
+use serde_encrypt::traits::SerdeEncryptSharedKey;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::Range;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Taille de l'en-tête d'une trame: la taille des données, en u32 big-endian.
+const HEADER_LEN: usize = 4;
+
+/// Taille maximale par défaut des données d'une trame reçue, voir
+/// [`MiniIrcCodec::set_max_frame_length`].
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Codec du protocole mini-irc, utilisable avec [`tokio_util::codec::Framed`],
+/// [`tokio_util::codec::FramedRead`] ou [`tokio_util::codec::FramedWrite`].
+///
+/// Chaque trame est composée de la taille des données (u32 big-endian), suivie de la
+/// valeur sérialisée avec [`bincode`], ou chiffrée avec la clé partagée une fois le codec passé
+/// à l'état [`Encrypted`] par [`MiniIrcCodec::upgrade`]. Une fois chiffrées, les trames
+/// portent aussi leur type si les deux pairs le permettent, voir [`WireVersion`].
+///
+/// # Exemple
+///
+/// ```no_run
+/// use futures::{SinkExt, StreamExt};
+/// use tokio::net::TcpStream;
+/// use tokio_util::codec::Framed;
+/// use mini_irc_protocol::{HandshakeRequest, MiniIrcCodec, Request};
+/// use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// # let shared_key = SharedKey::generate();
+/// let stream = TcpStream::connect("serveur:port").await.unwrap();
+/// let codec = MiniIrcCodec::<HandshakeRequest>::new().upgrade::<Request>(shared_key);
+/// let (mut sink, _) = Framed::new(stream, codec).split();
+/// sink.send(Request::Connect("toto".to_string())).await.unwrap();
+/// # }
+/// ```
+pub struct MiniIrcCodec<T, E = Plain> {
+    encryption: E,
+    /// Valeur sérialisée en cours d'encodage, conservée pour éviter une allocation par trame
+    scratch: Vec<u8>,
+    /// Format des trames
+    version: WireVersion,
+    /// Version la plus récente annoncée à l'autre pair
+    max_version: WireVersion,
+    /// Version annoncée par l'autre pair pendant l'échange de clés, s'il en a annoncé une
+    peer_version: Option<u8>,
+    /// Taille maximale des données d'une trame reçue
+    max_frame_length: usize,
+    _t: PhantomData<fn() -> T>,
+}
+
+impl<T> MiniIrcCodec<T> {
+    /// Créé un nouveau codec, sans chiffrement.
+    pub fn new() -> Self {
+        Self {
+            encryption: Plain,
+            scratch: Vec::new(),
+            version: WireVersion::V1,
+            max_version: WireVersion::LATEST,
+            peer_version: None,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            _t: PhantomData,
+        }
+    }
+
+    /// Chiffre les trames suivantes avec la clé partagée fournie. Le type des trames peut
+    /// changer à cette occasion, par exemple de [`crate::HandshakeRequest`] à [`crate::Request`].
+    ///
+    /// Le format des trames devient le plus récent commun aux deux pairs, si l'autre pair en
+    /// a annoncé un dans une trame reçue par ce codec.
+    pub fn upgrade<U>(self, shared_key: SharedKey) -> MiniIrcCodec<U, Encrypted> {
+        MiniIrcCodec {
+            encryption: Encrypted::new(shared_key),
+            scratch: self.scratch,
+            version: WireVersion::negotiate(self.max_version, self.peer_version),
+            max_version: self.max_version,
+            peer_version: self.peer_version,
+            max_frame_length: self.max_frame_length,
+            _t: PhantomData,
+        }
+    }
+
+    /// N'annonce pas de version plus récente que `max`, par exemple pour se comporter comme
+    /// un pair antérieur.
+    pub fn set_max_wire_version(&mut self, max: WireVersion) {
+        self.max_version = max;
+    }
+
+    /// Version annoncée par l'autre pair, à reporter sur le codec d'émission lorsque la
+    /// réception est faite par un autre codec.
+    pub(crate) fn peer_version(&self) -> Option<u8> {
+        self.peer_version
+    }
+
+    pub(crate) fn set_peer_version(&mut self, peer_version: Option<u8>) {
+        self.peer_version = peer_version;
+    }
+}
+
+impl<T, E: Encryption> MiniIrcCodec<T, E> {
+    /// Indique si les trames sont chiffrées.
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption_status() != EncryptionStatus::Plain
+    }
+
+    /// Indique si les trames sont chiffrées, et par quel mécanisme.
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        E::STATUS
+    }
+
+    /// Format des trames.
+    pub fn wire_version(&self) -> WireVersion {
+        self.version
+    }
+
+    /// Taille maximale des données d'une trame reçue.
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    /// Refuse les trames reçues dont les données dépassent `max` octets, par
+    /// [`ProtocolError::FrameTooLarge`], plutôt que de réserver la place annoncée par
+    /// l'en-tête. Par défaut, [`DEFAULT_MAX_FRAME_LENGTH`].
+    pub fn set_max_frame_length(&mut self, max: usize) {
+        self.max_frame_length = max;
+    }
+
+    /// Ajoute à `dst` une trame sans données de type `tag`. Renvoie `false` si le format des
+    /// trames ne le permet pas : en [`WireVersion::V1`], seul le maintien de connexion existe.
+    pub(crate) fn encode_control(&self, tag: FrameTag, dst: &mut BytesMut) -> bool {
+        match (self.version, tag) {
+            (WireVersion::V1, FrameTag::Keepalive) => dst.put_u32(0),
+            (WireVersion::V1, _) => return false,
+            (WireVersion::V2, tag) => {
+                dst.put_u32(1);
+                dst.put_u8(tag as u8);
+            }
+        }
+        true
+    }
+
+    /// Position des données de la prochaine trame de données dans `src`, si elle a été
+    /// entièrement reçue. La trame se termine à la fin de l'intervalle renvoyé. Les trames de
+    /// maintien de connexion sont retirées au passage. Une trame de fermeture produit
+    /// [`ProtocolError::Closed`] et reste dans le tampon : les réceptions suivantes échouent de
+    /// la même façon. Les autres trames de contrôle, qui ne sont pas encore supportées, sont
+    /// retirées et produisent une erreur [`ProtocolError::UnsupportedFrame`]. Une trame plus
+    /// grande que [`MiniIrcCodec::max_frame_length`] produit [`ProtocolError::FrameTooLarge`]
+    /// sans que rien ne soit réservé pour elle.
+    pub(crate) fn next_frame(
+        &self,
+        src: &mut BytesMut,
+    ) -> Result<Option<Range<usize>>, ProtocolError> {
+        loop {
+            if src.len() < HEADER_LEN {
+                return Ok(None);
+            }
+            let mut size = [0; HEADER_LEN];
+            size.copy_from_slice(&src[..HEADER_LEN]);
+            let size = u32::from_be_bytes(size) as usize;
+            // Taille annoncée par le pair, avant même qu'il soit authentifié
+            if size > self.max_frame_length {
+                return Err(ProtocolError::FrameTooLarge {
+                    len: size,
+                    max: self.max_frame_length,
+                });
+            }
+            if src.len() < HEADER_LEN + size {
+                // La trame n'est pas encore complète
+                src.reserve(HEADER_LEN + size - src.len());
+                return Ok(None);
+            }
+            let end = HEADER_LEN + size;
+            // Aucune valeur sérialisée ou chiffrée n'est vide : c'est une trame de maintien
+            if size == 0 {
+                src.advance(end);
+                continue;
+            }
+            let tag = match self.version {
+                WireVersion::V1 => return Ok(Some(HEADER_LEN..end)),
+                WireVersion::V2 => src[HEADER_LEN],
+            };
+            match FrameTag::try_from(tag) {
+                Ok(FrameTag::Data) => return Ok(Some(HEADER_LEN + 1..end)),
+                Ok(FrameTag::Keepalive) => src.advance(end),
+                Ok(FrameTag::Close) => return Err(ProtocolError::Closed),
+                _ => {
+                    src.advance(end);
+                    return Err(ProtocolError::UnsupportedFrame(tag));
+                }
+            }
+        }
+    }
+}
+
+impl<T, E> MiniIrcCodec<T, E>
+where
+    T: DeserializeOwned + SerdeEncryptSharedKey,
+    E: Encryption,
+{
+    /// Déchiffre (le cas échéant) puis désérialise les données d'une trame, sans l'en-tête.
+    /// Les données déchiffrées sont conservées dans `plain` afin que `B` puisse leur emprunter
+    /// ses champs ; sans chiffrement, `B` emprunte directement à `payload`.
+    pub(crate) fn deserialize_payload<'a, B>(
+        &mut self,
+        payload: &'a [u8],
+        plain: &'a mut Vec<u8>,
+    ) -> Result<B, ProtocolError>
+    where
+        B: Deserialize<'a>,
+    {
+        if !self.is_encrypted() {
+            if let Some(version) = announced_version::<T>(payload) {
+                self.peer_version = Some(version);
+            }
+        }
+        self.encryption.open::<T, B>(payload, plain)
+    }
+}
+
+/// Version annoncée à la fin d'une trame en clair, après la valeur sérialisée.
+fn announced_version<T: DeserializeOwned>(payload: &[u8]) -> Option<u8> {
+    let mut rest = payload;
+    bincode::deserialize_from::<_, T>(&mut rest).ok()?;
+    match rest {
+        [version] => Some(*version),
+        _ => None,
+    }
+}
+
+impl<T> Default for MiniIrcCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E: Encryption> Clone for MiniIrcCodec<T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            encryption: self.encryption.clone(),
+            scratch: Vec::new(),
+            version: self.version,
+            max_version: self.max_version,
+            peer_version: self.peer_version,
+            max_frame_length: self.max_frame_length,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T, E: Debug> Debug for MiniIrcCodec<T, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MiniIrcCodec")
+            .field("encryption", &self.encryption)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl<T, E> Decoder for MiniIrcCodec<T, E>
+where
+    T: DeserializeOwned + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    type Item = T;
+    type Error = ProtocolError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, ProtocolError> {
+        match self.next_frame(src)? {
+            Some(frame) => {
+                let res = self.deserialize_payload(&src[frame.clone()], &mut Vec::new());
+                src.advance(frame.end);
+                res.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'a, T, E> Encoder<&'a T> for MiniIrcCodec<T, E>
+where
+    T: Serialize + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    type Error = ProtocolError;
+
+    fn encode(&mut self, item: &'a T, dst: &mut BytesMut) -> Result<(), ProtocolError> {
+        self.encryption.seal(item, &mut self.scratch)?;
+        if !self.is_encrypted() {
+            // Annonce de version, ignorée par les pairs antérieurs
+            self.scratch.push(self.max_version as u8);
+        }
+        let tagged = self.version >= WireVersion::V2;
+        let data = &self.scratch;
+        let size = u32::try_from(data.len() + usize::from(tagged)).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Frame too large")
+        })?;
+        dst.reserve(HEADER_LEN + size as usize);
+        dst.put_u32(size);
+        if tagged {
+            dst.put_u8(FrameTag::Data as u8);
+        }
+        dst.put_slice(data);
+        Ok(())
+    }
+}
+
+impl<T, E> Encoder<T> for MiniIrcCodec<T, E>
+where
+    T: Serialize + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    type Error = ProtocolError;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), ProtocolError> {
+        self.encode(&item, dst)
+    }
+}
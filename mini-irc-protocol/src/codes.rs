@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use serde_encrypt::{serialize::impls::BincodeSerializer, traits::SerdeEncryptSharedKey};
+use std::time::Duration;
+
+/// Raison du refus d'une requête, dans une [`crate::Response::Error`]. Le serveur n'envoie
+/// aucun texte : le client affiche l'erreur dans la langue de son utilisateur.
+///
+/// De nouvelles variantes peuvent être ajoutées : hors de ce crate, un `match` doit prévoir
+/// un cas par défaut pour les erreurs qu'il ne connaît pas.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// Requête d'échange de clés dans le désordre, ou répétée
+    UnexpectedHandshake,
+    /// Requête avant la fin de l'échange de clés
+    HandshakeInProgress,
+    /// Requête réservée aux utilisateurs identifiés par [`crate::Request::Connect`]
+    NotConnected,
+    /// Second [`crate::Request::Connect`]
+    AlreadyConnected,
+    /// Nom d'utilisateur vide
+    EmptyNickname,
+    /// Nom d'utilisateur commençant comme un nom de canal, par `#` ou `&`
+    ChannelPrefixInNickname,
+    /// Nom d'utilisateur contenant des espaces
+    WhitespaceInNickname,
+    /// Nom d'utilisateur réservé par le serveur
+    ReservedNickname(String),
+    /// Nom d'utilisateur déjà pris par une autre session
+    NickInUse,
+    UnknownUser(String),
+    UnknownChannel(String),
+    /// Requête réservée au propriétaire du canal ou aux administrateurs
+    PermissionDenied,
+    /// Requête réservée aux membres du canal
+    NotInChannel,
+    /// Canal déjà rejoint, nommé pour que le client reconnaisse le `/join` refusé
+    AlreadyInChannel(String),
+    /// L'utilisateur invité est déjà membre du canal
+    UserInChannel(String),
+    /// L'utilisateur désigné n'est pas membre du canal
+    NotAMember(String),
+    /// Le canal a déjà un propriétaire présent
+    ChannelHasOwner,
+    /// Contenu plus long que [`crate::Capabilities::max_message_len`]
+    MessageTooLong,
+    /// Le destinataire a bloqué l'expéditeur
+    Blocked,
+    CannotBlockSelf,
+    CannotGhostSelf,
+    /// La file d'envoi du destinataire est pleine
+    Unreachable(String),
+    /// Mot-clé vide ou non alphanumérique
+    InvalidKeyword(String),
+    /// Session fermée par [`crate::Request::Ghost`] depuis une autre connexion
+    SessionTakenOver,
+    /// Nombre maximal de connexions simultanées atteint : envoyé à la place de
+    /// [`crate::Response::Ack`], à la fin de l'échange de clés, avant que le serveur ne ferme
+    /// la connexion. Le client peut réessayer après `retry_after`.
+    ServerFull {
+        retry_after: Duration,
+    },
+    /// Adresse du client listée par cette zone DNSBL : envoyé à la place de
+    /// [`crate::Response::Ack`], avant que le serveur ne ferme la connexion.
+    Blocklisted(String),
+    /// Plus de destinataires que [`crate::MAX_MESSAGE_TARGETS`], la limite indiquée
+    TooManyTargets(usize),
+    /// Message direct refusé par la limite de débit du serveur, à renvoyer après
+    /// `retry_after`. Seulement dans une [`crate::Response::Delivery`] : un
+    /// [`crate::Request::Message`] est refusé par un [`crate::Response::RateLimited`].
+    RateLimited {
+        retry_after: Duration,
+    },
+    /// Nom d'utilisateur contenant [`crate::TARGET_SEPARATOR`]
+    SeparatorInNickname,
+    /// Nom de canal contenant [`crate::TARGET_SEPARATOR`]
+    SeparatorInChannelName(String),
+    /// Plus d'utilisateurs bloqués que [`crate::MAX_BLOCKED`], la limite indiquée
+    TooManyBlocked(usize),
+}
+
+impl SerdeEncryptSharedKey for ErrorCode {
+    type S = BincodeSerializer<Self>;
+}
+
+/// Contenu d'une [`crate::Response::Notice`], affiché par le client dans la langue de son
+/// utilisateur. Seuls les textes saisis par les utilisateurs sont transmis tels quels.
+///
+/// De nouvelles variantes peuvent être ajoutées : hors de ce crate, un `match` doit prévoir
+/// un cas par défaut pour les avis qu'il ne connaît pas.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum Notice {
+    /// Message d'accueil du canal, fixé par [`crate::Request::SetWelcome`]
+    Welcome(String),
+    /// Message d'accueil du canal remplacé par ce texte
+    WelcomeSet(String),
+    /// Message d'accueil du canal supprimé
+    WelcomeCleared,
+    /// Invitation transmise à cet utilisateur
+    InviteSent(String),
+    /// Annonce d'un administrateur à tous les utilisateurs connectés, voir
+    /// [`crate::MessageReceiver::All`]
+    Announcement { from: String, content: String },
+}
+
+impl SerdeEncryptSharedKey for Notice {
+    type S = BincodeSerializer<Self>;
+}
@@ -0,0 +1,121 @@
+use crate::Transport;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Nombre d'octets lus et écrits sur un [`ByteCounter`], partagé avec ses deux moitiés une fois
+/// séparé, et lisible depuis une autre tâche.
+#[derive(Debug, Clone, Default)]
+pub struct ByteCounts {
+    read: Arc<AtomicU64>,
+    written: Arc<AtomicU64>,
+}
+
+impl ByteCounts {
+    pub fn read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    pub fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+}
+
+/// Flux asynchrone qui compte les octets lus et écrits, par exemple pour les statistiques d'une
+/// connexion. Un `ByteCounter` autour d'un [`Transport`] est lui-même un transport.
+///
+/// # Exemple
+///
+/// ```
+/// use mini_irc_protocol::{ByteCounter, HandshakeRequest, HandshakeResponse, TypedChannel};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let (client, server) = tokio::io::duplex(1024);
+/// let client = ByteCounter::new(client);
+/// let counts = client.counts();
+/// let mut client = TypedChannel::<_, HandshakeRequest, HandshakeResponse>::new(client);
+/// let mut server = TypedChannel::<_, HandshakeResponse, HandshakeRequest>::new(server);
+/// client.send(&HandshakeRequest::Secure(vec![0; 32])).await.unwrap();
+/// server.recv().await.unwrap();
+/// assert!(counts.written() > 32);
+/// assert_eq!(counts.read(), 0);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ByteCounter<S> {
+    inner: S,
+    counts: ByteCounts,
+}
+
+impl<S> ByteCounter<S> {
+    pub fn new(inner: S) -> Self {
+        Self::with_counts(inner, ByteCounts::default())
+    }
+
+    /// Comme [`ByteCounter::new`], en ajoutant les octets à des compteurs existants.
+    pub fn with_counts(inner: S, counts: ByteCounts) -> Self {
+        Self { inner, counts }
+    }
+
+    pub fn counts(&self) -> ByteCounts {
+        self.counts.clone()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ByteCounter<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - before) as u64;
+        self.counts.read.fetch_add(read, Ordering::Relaxed);
+        res
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ByteCounter<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let res = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = res {
+            self.counts
+                .written
+                .fetch_add(written as u64, Ordering::Relaxed);
+        }
+        res
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl<S: Transport> Transport for ByteCounter<S> {
+    type ReadHalf = ByteCounter<S::ReadHalf>;
+    type WriteHalf = ByteCounter<S::WriteHalf>;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        let (read, write) = self.inner.into_split();
+        (
+            ByteCounter::with_counts(read, self.counts.clone()),
+            ByteCounter::with_counts(write, self.counts),
+        )
+    }
+}
@@ -0,0 +1,155 @@
+use crate::ProtocolError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_encrypt::serialize::TypedSerialized;
+use serde_encrypt::shared_key::SharedKey;
+use serde_encrypt::{traits::SerdeEncryptSharedKey, EncryptedMessage};
+use std::fmt::{Debug, Display};
+
+/// État de chiffrement d'un canal typé : [`Plain`] ou [`Encrypted`].
+///
+/// Un canal est créé en clair, puis passe à l'état chiffré via `upgrade`. Seuls les types
+/// [`Transmissible`] dans l'état courant peuvent être envoyés ou reçus.
+pub trait Encryption: sealed::Sealed {}
+
+/// Canal en clair, utilisé uniquement pour établir le chiffrement.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Plain;
+
+/// Canal chiffré avec une clé partagée.
+#[derive(Clone)]
+pub struct Encrypted {
+    shared_key: SharedKey,
+}
+
+impl Encrypted {
+    pub(crate) fn new(shared_key: SharedKey) -> Self {
+        Self { shared_key }
+    }
+}
+
+impl Debug for Encrypted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // La clé ne doit pas apparaître dans les traces
+        f.write_str("Encrypted")
+    }
+}
+
+/// Mécanisme de chiffrement d'une connexion.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mechanism {
+    /// Clé partagée XChaCha20-Poly1305 (`serde-encrypt`), transmise chiffrée par une clé
+    /// X25519 éphémère.
+    XChaCha20Poly1305,
+}
+
+impl Display for Mechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mechanism::XChaCha20Poly1305 => f.write_str("XChaCha20-Poly1305"),
+        }
+    }
+}
+
+/// État de chiffrement d'une connexion, par exemple pour l'afficher à l'utilisateur.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionStatus {
+    /// Trames en clair, le temps d'établir le chiffrement.
+    Plain,
+    /// Trames chiffrées.
+    Encrypted(Mechanism),
+}
+
+impl Display for EncryptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncryptionStatus::Plain => f.write_str("non chiffré"),
+            EncryptionStatus::Encrypted(mechanism) => write!(f, "chiffré ({mechanism})"),
+        }
+    }
+}
+
+impl Encryption for Plain {}
+impl Encryption for Encrypted {}
+
+/// Type pouvant être envoyé et reçu sur un canal dans l'état de chiffrement `E`.
+///
+/// Tous les types peuvent transiter sur un canal [`Encrypted`] ; seuls ceux qui ne contiennent
+/// pas de données sensibles, comme [`crate::HandshakeRequest`], implémentent
+/// `Transmissible<Plain>`. Envoyer une [`crate::Request`] avant d'avoir chiffré le canal est donc
+/// une erreur de compilation.
+pub trait Transmissible<E: Encryption> {}
+
+impl<T: SerdeEncryptSharedKey> Transmissible<Encrypted> for T {}
+
+pub(crate) mod sealed {
+    use super::*;
+
+    pub trait Sealed: Clone + Debug + Unpin {
+        const STATUS: EncryptionStatus;
+
+        /// Sérialise `item` dans `out`, en le chiffrant le cas échéant.
+        fn seal<T>(&self, item: &T, out: &mut Vec<u8>) -> Result<(), ProtocolError>
+        where
+            T: Serialize + SerdeEncryptSharedKey;
+
+        /// Déchiffre (le cas échéant) puis désérialise les données d'une trame, sans l'en-tête.
+        /// Les données déchiffrées sont conservées dans `plain` afin que `B` puisse leur
+        /// emprunter ses champs ; sans chiffrement, `B` emprunte directement à `payload`.
+        fn open<'a, T, B>(
+            &self,
+            payload: &'a [u8],
+            plain: &'a mut Vec<u8>,
+        ) -> Result<B, ProtocolError>
+        where
+            T: DeserializeOwned + SerdeEncryptSharedKey,
+            B: Deserialize<'a>;
+    }
+
+    impl Sealed for Plain {
+        const STATUS: EncryptionStatus = EncryptionStatus::Plain;
+
+        fn seal<T>(&self, item: &T, out: &mut Vec<u8>) -> Result<(), ProtocolError>
+        where
+            T: Serialize + SerdeEncryptSharedKey,
+        {
+            // Le tampon intermédiaire est réutilisé d'une trame à l'autre
+            out.clear();
+            bincode::serialize_into(out, item).map_err(ProtocolError::Serialize)
+        }
+
+        fn open<'a, T, B>(&self, payload: &'a [u8], _: &'a mut Vec<u8>) -> Result<B, ProtocolError>
+        where
+            T: DeserializeOwned + SerdeEncryptSharedKey,
+            B: Deserialize<'a>,
+        {
+            bincode::deserialize(payload).map_err(ProtocolError::Deserialize)
+        }
+    }
+
+    impl Sealed for Encrypted {
+        const STATUS: EncryptionStatus = EncryptionStatus::Encrypted(Mechanism::XChaCha20Poly1305);
+
+        fn seal<T>(&self, item: &T, out: &mut Vec<u8>) -> Result<(), ProtocolError>
+        where
+            T: Serialize + SerdeEncryptSharedKey,
+        {
+            *out = item.encrypt(&self.shared_key)?.serialize();
+            Ok(())
+        }
+
+        fn open<'a, T, B>(
+            &self,
+            payload: &'a [u8],
+            plain: &'a mut Vec<u8>,
+        ) -> Result<B, ProtocolError>
+        where
+            T: DeserializeOwned + SerdeEncryptSharedKey,
+            B: Deserialize<'a>,
+        {
+            let encrypted_message = EncryptedMessage::deserialize(payload.to_vec())?;
+            *plain = T::decrypt_ref(&encrypted_message, &self.shared_key)?.into_vec();
+            bincode::deserialize(plain).map_err(ProtocolError::Deserialize)
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt::Display;
+
+/// Erreur pouvant survenir lors de l'envoi ou de la réception d'une trame mini-irc.
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// Erreur du canal sous-jacent.
+    Io(std::io::Error),
+    /// La valeur n'a pas pu être sérialisée.
+    Serialize(bincode::Error),
+    /// La trame reçue ne correspond pas au type attendu.
+    Deserialize(bincode::Error),
+    /// Erreur lors du chiffrement ou du déchiffrement d'une trame.
+    Encryption(serde_encrypt::Error),
+    /// Le délai d'attente a expiré avant la fin de l'opération.
+    TimedOut,
+    /// Rien n'a été reçu pendant la tolérance du [`crate::Heartbeat`] : le pair est perdu.
+    PeerLost,
+    /// Le pair a fermé la connexion proprement, par `close`. Contrairement à une erreur
+    /// [`ProtocolError::Io`], ce n'est pas une panne du réseau.
+    Closed,
+    /// Trame de contrôle de type inconnu ou pas encore supporté, voir [`crate::FrameTag`].
+    UnsupportedFrame(u8),
+    /// La trame annoncée par l'en-tête dépasse la taille maximale acceptée.
+    FrameTooLarge { len: usize, max: usize },
+}
+
+impl ProtocolError {
+    /// Convertit les erreurs d'expiration des socquettes synchrones en [`ProtocolError::TimedOut`].
+    pub(crate) fn into_timed_out(self) -> Self {
+        match self {
+            Self::Io(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                Self::TimedOut
+            }
+            e => e,
+        }
+    }
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::Serialize(e) => write!(f, "Serialization error: {e}"),
+            Self::Deserialize(e) => write!(f, "Deserialization error: {e}"),
+            Self::Encryption(e) => write!(f, "Encryption error: {e}"),
+            Self::TimedOut => write!(f, "Timed out"),
+            Self::PeerLost => write!(f, "Peer lost: no heartbeat received"),
+            Self::Closed => write!(f, "Connection closed by peer"),
+            Self::UnsupportedFrame(tag) => write!(f, "Unsupported frame type: {tag}"),
+            Self::FrameTooLarge { len, max } => {
+                write!(f, "Frame too large: {len} bytes, at most {max}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Serialize(e) | Self::Deserialize(e) => Some(e),
+            Self::Encryption(e) => Some(e),
+            Self::TimedOut
+            | Self::PeerLost
+            | Self::Closed
+            | Self::UnsupportedFrame(_)
+            | Self::FrameTooLarge { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ProtocolError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_encrypt::Error> for ProtocolError {
+    fn from(e: serde_encrypt::Error) -> Self {
+        Self::Encryption(e)
+    }
+}
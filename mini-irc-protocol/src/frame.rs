@@ -0,0 +1,67 @@
+/// Version du format des trames. Chaque pair annonce la version la plus récente qu'il connaît
+/// pendant l'échange de clés, et les deux passent à la plus récente qu'ils ont en commun en
+/// chiffrant le canal (`upgrade`) :
+///
+/// | Version | Trame                                                             |
+/// |---------|-------------------------------------------------------------------|
+/// | `V1`    | taille (u32 big-endian), données                                  |
+/// | `V2`    | taille (u32 big-endian), type de trame ([`FrameTag`], u8), données |
+///
+/// Les trames de l'échange de clés restent au format `V1`, et l'annonce est un octet ajouté
+/// après la valeur sérialisée : un pair antérieur l'ignore, comme tout octet en trop, et
+/// continue en `V1`. En `V1`, seules les trames de données et de maintien (sans données)
+/// existent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum WireVersion {
+    V1 = 1,
+    V2 = 2,
+}
+
+impl WireVersion {
+    /// Version la plus récente connue de ce crate
+    pub const LATEST: WireVersion = WireVersion::V2;
+
+    /// Version la plus récente commune à ce pair, qui connaît jusqu'à `max`, et à celui d'en
+    /// face, qui a annoncé `announced` (ou rien, s'il est antérieur aux versions).
+    pub(crate) fn negotiate(max: WireVersion, announced: Option<u8>) -> WireVersion {
+        match announced {
+            Some(version) if version >= WireVersion::V2 as u8 && max >= WireVersion::V2 => {
+                WireVersion::V2
+            }
+            _ => WireVersion::V1,
+        }
+    }
+}
+
+/// Type d'une trame à partir de [`WireVersion::V2`], placé juste après sa taille. Seules les
+/// trames de données sont remontées aux lecteurs ; les autres servent au transport lui-même.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum FrameTag {
+    /// Valeur sérialisée, chiffrée le cas échéant
+    Data = 0,
+    /// Maintien de connexion, sans données, voir [`crate::Heartbeat`]
+    Keepalive = 1,
+    /// Changement de clé partagée (réservé)
+    Rekey = 2,
+    /// Données compressées (réservé)
+    Compressed = 3,
+    /// Fermeture de la connexion par le pair, voir [`crate::AsyncTypedWriter::close`]
+    Close = 4,
+}
+
+impl TryFrom<u8> for FrameTag {
+    type Error = u8;
+
+    fn try_from(tag: u8) -> Result<Self, u8> {
+        Ok(match tag {
+            0 => FrameTag::Data,
+            1 => FrameTag::Keepalive,
+            2 => FrameTag::Rekey,
+            3 => FrameTag::Compressed,
+            4 => FrameTag::Close,
+            tag => return Err(tag),
+        })
+    }
+}
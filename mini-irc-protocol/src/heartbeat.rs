@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+/// Nombre d'intervalles sans rien recevoir tolérés par défaut
+pub const DEFAULT_TOLERANCE: u32 = 3;
+
+/// Maintien de connexion au niveau des trames, indépendant du `Ping` applicatif : tout
+/// protocole bâti sur [`crate::AsyncTypedReader`] et [`crate::AsyncTypedWriter`] peut ainsi
+/// détecter un pair disparu sans déconnexion propre.
+///
+/// - Côté émission, [`crate::AsyncTypedWriter::heartbeat`] envoie une trame de maintien
+///   lorsque rien n'a été écrit depuis `interval`.
+/// - Côté réception, [`crate::AsyncTypedReader::recv`] renvoie [`crate::ProtocolError::PeerLost`]
+///   lorsque rien, trame de maintien comprise, n'a été reçu depuis `tolerance` intervalles.
+///
+/// Les trames de maintien sont ignorées avant la désérialisation, par tous les lecteurs : un
+/// pair qui ne les active pas les reçoit sans s'en apercevoir.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    pub interval: Duration,
+    pub tolerance: u32,
+}
+
+impl Heartbeat {
+    /// Maintien toutes les `interval`, avec la tolérance par défaut ([`DEFAULT_TOLERANCE`]).
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: u32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Silence au-delà duquel le pair est considéré perdu.
+    pub fn deadline(&self) -> Duration {
+        self.interval * self.tolerance
+    }
+}
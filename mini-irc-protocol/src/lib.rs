@@ -1,29 +1,125 @@
 //! Ce crate contient plusieurs énumérations et structures utiles pour la communication entre
 //! les clients mini-irc et le serveur mini-irc. Des communications via sockets "standards"
-//! ou asynchrones (uniquement via [tokio]) sont supportés.
+//! ou asynchrones (uniquement via [tokio]) sont supportés. Les canaux asynchrones peuvent
+//! utiliser tout [`Transport`], dont QUIC avec la fonctionnalité `quinn`.
 
+mod broadcast;
+mod channel;
+mod codec;
+mod codes;
+mod counter;
+mod encryption;
+mod error;
+mod frame;
+mod heartbeat;
+mod responder;
+mod tap;
+mod transport;
+
+pub use broadcast::{
+    BroadcastEvent, BroadcastMetrics, BroadcastReceiverWithList, BroadcastSenderWithList,
+    Subscriber,
+};
+pub use channel::{ChannelReader, ChannelWriter, SyncTypedChannel, TypedChannel};
+pub use codec::{MiniIrcCodec, DEFAULT_MAX_FRAME_LENGTH};
+pub use codes::{ErrorCode, Notice};
+pub use counter::{ByteCounter, ByteCounts};
+pub use encryption::{Encrypted, Encryption, EncryptionStatus, Mechanism, Plain, Transmissible};
+
+pub use error::ProtocolError;
+pub use frame::{FrameTag, WireVersion};
+pub use heartbeat::{Heartbeat, DEFAULT_TOLERANCE};
+pub use responder::{Correlate, Correlation, RequestResponder};
+pub use tap::{Direction, TapRecord, WireTap};
+#[cfg(feature = "quinn")]
+pub use transport::QuicStream;
+pub use transport::{SyncTransport, Transport};
+
+use bytes::{Buf, BytesMut};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_encrypt::shared_key::SharedKey;
-use serde_encrypt::{
-    serialize::impls::BincodeSerializer, traits::SerdeEncryptSharedKey, EncryptedMessage,
-};
-use std::fmt::Debug;
+use serde_encrypt::{serialize::impls::BincodeSerializer, traits::SerdeEncryptSharedKey};
+use std::fmt::{self, Debug};
+use std::future::poll_fn;
 use std::io::{Read, Write};
-use std::ops::Deref;
+use std::ops::Range;
+use std::pin::Pin;
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::sync::broadcast;
+use tokio_util::codec::Encoder;
+use tokio_util::io::poll_read_buf;
 use tracing::info;
 
-///  Une requête mini-irc, c'est-à-dire un message envoyé par le client au serveur.
+/// Nombre maximal de messages renvoyés par un [`Request::FetchHistory`].
+pub const MAX_HISTORY_FETCH: u32 = 100;
+
+/// Nombre maximal de destinataires d'un [`Request::MessageMany`].
+pub const MAX_MESSAGE_TARGETS: usize = 8;
+
+/// Nombre maximal d'utilisateurs bloqués par une session, voir [`Request::Block`].
+pub const MAX_BLOCKED: usize = 256;
+
+/// Sépare les destinataires de [`MessageTargets`] : il est interdit dans les noms
+/// d'utilisateurs et de canaux.
+pub const TARGET_SEPARATOR: char = ',';
+
+/// Taille des trames en attente à partir de laquelle [`TypedWriter`] et [`AsyncTypedWriter`]
+/// les écrivent, même lorsqu'elles sont accumulées ([`FlushPolicy::Manual`] ou [`futures::Sink`]).
+const BACKPRESSURE_BOUNDARY: usize = 8 * 1024;
+
+/// Taille minimale des lectures effectuées par [`TypedReader`].
+const READ_CHUNK_SIZE: usize = 4 * 1024;
+
+// Les canaux typés peuvent être partagés entre threads et conservés au travers d'un `.await`
+// dès lors que la socquette le permet.
+const _: () = {
+    const fn assert_send_sync<X: Send + Sync>() {}
+    assert_send_sync::<TypedReader<std::net::TcpStream, Response>>();
+    assert_send_sync::<TypedWriter<std::net::TcpStream, Request>>();
+    assert_send_sync::<AsyncTypedReader<tokio::io::ReadHalf<tokio::io::DuplexStream>, Request>>();
+    assert_send_sync::<AsyncTypedWriter<tokio::io::WriteHalf<tokio::io::DuplexStream>, Response>>();
+    assert_send_sync::<MiniIrcCodec<Request>>();
+    assert_send_sync::<MiniIrcCodec<Request, Encrypted>>();
+};
+
+/// Une requête d'établissement du chiffrement, envoyée en clair par le client au serveur
+/// avant toute [`Request`].
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
-pub enum Request {
-    /// Partage shared key pour chiffrement
+pub enum HandshakeRequest {
+    /// Demande de communication sécurisé, avec la clé publique du client
+    Secure(Vec<u8>),
+    /// Partage shared key pour chiffrement, chiffrée avec la clé publique du serveur
     Shared(Vec<u8>),
-    /// Demande de communication sécurisé
+}
+
+impl SerdeEncryptSharedKey for HandshakeRequest {
+    type S = BincodeSerializer<Self>;
+}
+
+impl Transmissible<Plain> for HandshakeRequest {}
+
+/// Une réponse d'établissement du chiffrement, envoyée en clair par le serveur au client.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum HandshakeResponse {
+    /// Repondre de communication sécurisé, avec la clé publique du serveur
     Secure(Vec<u8>),
+}
+
+impl SerdeEncryptSharedKey for HandshakeResponse {
+    type S = BincodeSerializer<Self>;
+}
+
+impl Transmissible<Plain> for HandshakeResponse {}
+
+///  Une requête mini-irc, c'est-à-dire un message envoyé par le client au serveur.
+///
+/// Les requêtes ne peuvent transiter que sur un canal chiffré. Leur affichage par [`Debug`],
+/// repris par les traces et par [`WireTap`], masque les clés de reprise de session.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub enum Request {
     /// Demande de connexion avec le nom d'utilisateur fourni.
     Connect(String),
     /// Demande de rejoindre un canal mini-irc donné. S'il n'existe pas encore, le canal est créé.
@@ -35,47 +131,278 @@ pub enum Request {
         to: MessageReceiver,
         content: String,
     },
+    /// Même message envoyé à plusieurs canaux ou utilisateurs (au plus
+    /// [`MAX_MESSAGE_TARGETS`]), chacun comme par un [`Request::Message`]. Répondue par un
+    /// [`Response::Delivery`], même si tous les envois réussissent.
+    MessageMany {
+        to: Vec<MessageReceiver>,
+        content: String,
+    },
+    /// Demande les statistiques de sa propre connexion.
+    Stats,
+    /// Demande les statistiques de la connexion d'un autre utilisateur (administrateurs
+    /// uniquement).
+    StatsOf(String),
+    /// Réponse à un [`Response::Ping`], avec la même valeur.
+    Pong(u64),
+    /// Sonde envoyée par le client pour mesurer sa latence : le serveur répond aussitôt par un
+    /// [`Response::Pong`] de même valeur, dans l'ordre de ses autres réponses.
+    Ping(u64),
+    /// Demande des informations sur un utilisateur connecté.
+    WhoIs(String),
+    /// Demande la liste des canaux non vides.
+    ListChans,
+    /// Demande les limites du serveur, voir [`Capabilities`].
+    Capabilities,
+    /// Enregistre la clé qui permettra de reprendre la session, par exemple après un plantage
+    /// du client que le serveur n'a pas encore remarqué. Pas de réponse.
+    GhostKey(String),
+    /// Ferme la session de `nickname`, si elle a enregistré la même clé avec
+    /// [`Request::GhostKey`]. Acceptée avant [`Request::Connect`], pour libérer le nom.
+    Ghost { nickname: String, key: String },
+    /// Surveille un mot-clé sur un canal (opérateurs uniquement) : chaque message qui le
+    /// contient est signalé par un [`Response::KeywordAlert`].
+    WatchKeyword { chan: String, keyword: String },
+    /// Cesse de surveiller un mot-clé sur un canal.
+    UnwatchKeyword { chan: String, keyword: String },
+    /// Demande la liste complète des membres d'un canal, pour resynchroniser celle construite
+    /// à partir des [`ChanOp::UserAdd`] et [`ChanOp::UserDel`]. Réservée aux membres du canal.
+    Names(String),
+    /// Transmet la propriété du canal à l'un de ses membres (propriétaire du canal ou
+    /// administrateurs uniquement). Annoncée aux membres par un [`ChanOp::Owner`].
+    TransferOp { chan: String, to: String },
+    /// Revendique la propriété d'un canal dont on est membre, s'il n'a pas de propriétaire ou
+    /// que le sien est absent depuis trop longtemps (selon la configuration du serveur).
+    ClaimOp(String),
+    /// Fixe le message d'accueil du canal (propriétaire du canal ou administrateurs uniquement),
+    /// envoyé à chaque arrivant par un [`Response::Notice`] juste après son
+    /// [`Response::AckJoin`]. Un texte vide le supprime.
+    SetWelcome { chan: String, text: String },
+    /// Bloque un utilisateur jusqu'à la fin de la session : ses messages directs sont refusés
+    /// par le serveur, qui lui répond `Blocked`. Répondue par la liste à jour,
+    /// [`Response::BlockList`], d'au plus [`MAX_BLOCKED`] utilisateurs.
+    Block(String),
+    /// Débloque un utilisateur, répondue par la liste à jour.
+    Unblock(String),
+    /// Invite un utilisateur connecté à rejoindre un canal dont on est membre : il reçoit un
+    /// [`Response::Invited`].
+    Invite { chan: String, user: String },
+    /// Demande au plus `limit` messages archivés du canal (voir [`MAX_HISTORY_FETCH`]),
+    /// antérieurs à celui de numéro de séquence `before_id`, ou les plus récents si `None`.
+    /// Réservée aux membres du canal.
+    FetchHistory {
+        chan: String,
+        before_id: Option<u64>,
+        limit: u32,
+    },
+    /// Abonne la connexion aux statistiques du serveur : un [`Response::ServerStats`] aussitôt,
+    /// puis à chaque période si le serveur est configuré pour, jusqu'à
+    /// [`Request::UnsubscribeStats`] ou la déconnexion.
+    SubscribeStats,
+    /// Met fin à l'abonnement aux statistiques, répondue par un [`Response::Ack`].
+    UnsubscribeStats,
 }
 
 impl SerdeEncryptSharedKey for Request {
     type S = BincodeSerializer<Self>;
 }
 
+/// Remplace une clé dans l'affichage d'une requête
+const REDACTED: &str = "<redacted>";
+
+impl Debug for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(nickname) => f.debug_tuple("Connect").field(nickname).finish(),
+            Self::JoinChan(chan) => f.debug_tuple("JoinChan").field(chan).finish(),
+            Self::LeaveChan(chan) => f.debug_tuple("LeaveChan").field(chan).finish(),
+            Self::Message { to, content } => f
+                .debug_struct("Message")
+                .field("to", to)
+                .field("content", content)
+                .finish(),
+            Self::MessageMany { to, content } => f
+                .debug_struct("MessageMany")
+                .field("to", to)
+                .field("content", content)
+                .finish(),
+            Self::Stats => f.write_str("Stats"),
+            Self::StatsOf(nickname) => f.debug_tuple("StatsOf").field(nickname).finish(),
+            Self::Pong(id) => f.debug_tuple("Pong").field(id).finish(),
+            Self::Ping(id) => f.debug_tuple("Ping").field(id).finish(),
+            Self::WhoIs(nickname) => f.debug_tuple("WhoIs").field(nickname).finish(),
+            Self::ListChans => f.write_str("ListChans"),
+            Self::Capabilities => f.write_str("Capabilities"),
+            Self::GhostKey(_) => f.debug_tuple("GhostKey").field(&REDACTED).finish(),
+            Self::Ghost { nickname, .. } => f
+                .debug_struct("Ghost")
+                .field("nickname", nickname)
+                .field("key", &REDACTED)
+                .finish(),
+            Self::WatchKeyword { chan, keyword } => f
+                .debug_struct("WatchKeyword")
+                .field("chan", chan)
+                .field("keyword", keyword)
+                .finish(),
+            Self::UnwatchKeyword { chan, keyword } => f
+                .debug_struct("UnwatchKeyword")
+                .field("chan", chan)
+                .field("keyword", keyword)
+                .finish(),
+            Self::Names(chan) => f.debug_tuple("Names").field(chan).finish(),
+            Self::TransferOp { chan, to } => f
+                .debug_struct("TransferOp")
+                .field("chan", chan)
+                .field("to", to)
+                .finish(),
+            Self::ClaimOp(chan) => f.debug_tuple("ClaimOp").field(chan).finish(),
+            Self::SetWelcome { chan, text } => f
+                .debug_struct("SetWelcome")
+                .field("chan", chan)
+                .field("text", text)
+                .finish(),
+            Self::Block(nickname) => f.debug_tuple("Block").field(nickname).finish(),
+            Self::Unblock(nickname) => f.debug_tuple("Unblock").field(nickname).finish(),
+            Self::Invite { chan, user } => f
+                .debug_struct("Invite")
+                .field("chan", chan)
+                .field("user", user)
+                .finish(),
+            Self::FetchHistory {
+                chan,
+                before_id,
+                limit,
+            } => f
+                .debug_struct("FetchHistory")
+                .field("chan", chan)
+                .field("before_id", before_id)
+                .field("limit", limit)
+                .finish(),
+            Self::SubscribeStats => f.write_str("SubscribeStats"),
+            Self::UnsubscribeStats => f.write_str("UnsubscribeStats"),
+        }
+    }
+}
+
+/// Préfixe des canaux système, créés par le serveur, qui fait partie de leur nom :
+/// `&annonces` désigne le canal `&annonces`, alors que `#general` désigne le canal `general`.
+pub const SYSTEM_CHANNEL_PREFIX: char = '&';
+
 /// La destinataire d'un message
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum MessageReceiver {
     User(String),
     Channel(String),
+    /// Tous les utilisateurs connectés, `$all` (administrateurs uniquement) : chacun reçoit
+    /// une [`Notice::Announcement`]
+    All,
+    /// Tous les canaux non vides dont le nom correspond au motif, `$#dev-*` (administrateurs
+    /// uniquement). `*` remplace n'importe quelle suite de caractères, `?` un seul caractère.
+    Channels(String),
+}
+
+/// Forme saisie par l'utilisateur, relue par [`MessageReceiver::from_str`] : `@bob`,
+/// `#general`, le nom d'un canal système tel quel, `$all` ou `$#motif*`.
+impl fmt::Display for MessageReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User(user) => write!(f, "@{user}"),
+            Self::Channel(chan) if chan.starts_with(SYSTEM_CHANNEL_PREFIX) => f.write_str(chan),
+            Self::Channel(chan) => write!(f, "#{chan}"),
+            Self::All => f.write_str("$all"),
+            Self::Channels(pattern) => write!(f, "$#{pattern}"),
+        }
+    }
 }
 
 impl SerdeEncryptSharedKey for MessageReceiver {
     type S = BincodeSerializer<Self>;
 }
 
+/// Destinataire mal formé, refusé par [`MessageReceiver::from_str`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseReceiverError {
+    /// Préfixe seul, sans nom
+    #[error("Channel or username must be at least one character long: {0}")]
+    TooShort(String),
+    /// Ni `#canal`, ni `&canal`, ni `@utilisateur`, ni `$all` ou `$#motif`
+    #[error("Unrecognized receiver: {0}")]
+    Unrecognized(String),
+}
+
+/// Destinataires d'un même message, saisis séparés par des virgules : `#a,#b,@carol`. Un
+/// destinataire répété n'est gardé qu'une fois.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MessageTargets(pub Vec<MessageReceiver>);
+
+impl fmt::Display for MessageTargets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, target) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{TARGET_SEPARATOR}")?;
+            }
+            write!(f, "{target}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MessageTargets {
+    type Err = ParseReceiverError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut targets = Vec::new();
+        for target in s.split(TARGET_SEPARATOR) {
+            let target: MessageReceiver = target.parse()?;
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        Ok(Self(targets))
+    }
+}
+
 impl FromStr for MessageReceiver {
-    // TODO: peut-être faire une vraie valeur d'erreur.
-    type Err = String;
+    type Err = ParseReceiverError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.len() < 2 {
-            Err(format!(
-                "Channel or username must be at least one character long: {s}"
-            ))
+            Err(ParseReceiverError::TooShort(s.to_string()))
         } else if let Some(s) = s.strip_prefix('#') {
             Ok(Self::Channel(s.to_string()))
+        } else if s.starts_with(SYSTEM_CHANNEL_PREFIX) {
+            Ok(Self::Channel(s.to_string()))
         } else if let Some(s) = s.strip_prefix('@') {
             Ok(Self::User(s.to_string()))
+        } else if s == "$all" {
+            Ok(Self::All)
+        } else if let Some(pattern) = s.strip_prefix("$#") {
+            if pattern.is_empty() {
+                return Err(ParseReceiverError::TooShort(s.to_string()));
+            }
+            Ok(Self::Channels(pattern.to_string()))
         } else {
-            Err(format!("Unrecognized receiver: {s}"))
+            Err(ParseReceiverError::Unrecognized(s.to_string()))
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum ChanOp {
-    Message { from: String, content: String },
+    Message {
+        from: String,
+        content: String,
+    },
     UserAdd(String),
     UserDel(String),
+    /// Le client n'a pas lu assez vite les messages du canal, et a manqué ce nombre de messages.
+    Missed(u64),
+    /// L'utilisateur est inactif depuis trop longtemps, et marqué absent.
+    UserAway(String),
+    /// L'utilisateur absent est de nouveau actif.
+    UserBack(String),
+    /// Nouveau propriétaire du canal, par [`Request::TransferOp`] ou [`Request::ClaimOp`].
+    Owner(String),
 }
 
 impl SerdeEncryptSharedKey for ChanOp {
@@ -83,29 +410,199 @@ impl SerdeEncryptSharedKey for ChanOp {
 }
 
 /// Une réponse mini-irc, c'est-à-dire un message envoyé par le serveur au client.
+///
+/// Les réponses ne peuvent transiter que sur un canal chiffré.
+///
+/// De nouvelles variantes peuvent être ajoutées : hors de ce crate, un `match` doit prévoir
+/// un cas par défaut pour les réponses qu'il ne connaît pas.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum Response {
-    /// Reconnaissance
+    /// Reconnaissance, envoyée une fois le canal chiffré
     Ack,
-    /// Repondre de communication sécurisé
-    Secure(Vec<u8>),
     /// Message direct d'un utilisateur.
     DirectMessage { from: String, content: String },
     /// Message d'un channel (administratif ou utilisateur)
-    Channel { op: ChanOp, chan: String },
-    /// Ack d'entrée dans un channel.
-    AckJoin { chan: String, users: Vec<String> },
+    Channel {
+        op: ChanOp,
+        chan: String,
+        /// Numéro de séquence, croissant de 1 en 1 pour chaque canal : un écart révèle des
+        /// réponses perdues. 0 pour [`ChanOp::Missed`], hors séquence.
+        seq: u64,
+    },
+    /// Ack d'entrée dans un channel, avec son propriétaire s'il en a un.
+    AckJoin {
+        chan: String,
+        users: Vec<String>,
+        owner: Option<String>,
+    },
     /// Ack de sortie d'un channel.
     AckLeave(String),
-    /// Ack de connection, réponse indiquant que la demande a pu être correctement traitée.
+    /// Ack de connection, réponse indiquant que la demande a pu être correctement traitée,
+    /// avec le nom sous lequel l'utilisateur est connecté.
     AckConnect(String),
-    /// Message d'erreur
-    Error(String),
+    /// Refus d'une requête, à traduire par le client
+    Error(ErrorCode),
+    /// Résultat de l'envoi d'un [`Request::MessageMany`] à chacun de ses destinataires, dans
+    /// l'ordre de la requête, sans doublons.
+    Delivery(Vec<(MessageReceiver, Result<(), ErrorCode>)>),
+    /// Statistiques d'une connexion, en réponse à [`Request::Stats`] ou [`Request::StatsOf`].
+    Stats(ConnectionStats),
+    /// Sonde envoyée périodiquement par le serveur, à laquelle le client répond par un
+    /// [`Request::Pong`] de même valeur pour mesurer le temps d'aller-retour.
+    Ping(u64),
+    /// Réponse à un [`Request::Ping`], avec la même valeur.
+    Pong(u64),
+    /// Informations sur un utilisateur, en réponse à [`Request::WhoIs`].
+    WhoIs {
+        user: String,
+        /// Ses canaux dont le demandeur est aussi membre
+        channels: Vec<String>,
+        /// Durée depuis sa dernière requête
+        idle: Duration,
+        away: bool,
+    },
+    /// Liste des canaux, en réponse à [`Request::ListChans`].
+    ChanList(Vec<ChanInfo>),
+    /// Limites du serveur, en réponse à [`Request::Capabilities`].
+    Capabilities(Capabilities),
+    /// Session fermée et nom libéré, en réponse à [`Request::Ghost`].
+    AckGhost(String),
+    /// Membres d'un canal, en réponse à [`Request::Names`].
+    Names { chan: String, users: Vec<String> },
+    /// Mots-clés surveillés sur un canal, en réponse à [`Request::WatchKeyword`] et
+    /// [`Request::UnwatchKeyword`].
+    Keywords { chan: String, keywords: Vec<String> },
+    /// Message contenant un mot-clé surveillé. Envoyé directement à l'opérateur, qu'il soit
+    /// membre du canal ou non.
+    KeywordAlert {
+        chan: String,
+        keyword: String,
+        from: String,
+        content: String,
+    },
+    /// Messages archivés d'un canal, du plus ancien au plus récent, en réponse à
+    /// [`Request::FetchHistory`]. `more` indique si des messages plus anciens restent archivés.
+    History {
+        chan: String,
+        messages: Vec<ArchivedMessage>,
+        more: bool,
+    },
+    /// Avis du serveur adressé à ce seul client, à distinguer d'une [`Response::Error`] :
+    /// message d'accueil d'un canal, rappel, avertissement de modération... Hors séquence pour
+    /// un canal.
+    Notice { scope: NoticeScope, notice: Notice },
+    /// Message refusé par la limite de débit du serveur, à renvoyer après `retry_after_ms`
+    /// millisecondes.
+    RateLimited { retry_after_ms: u64 },
+    /// Utilisateurs bloqués, en réponse à [`Request::Block`] et [`Request::Unblock`].
+    BlockList(Vec<String>),
+    /// Invitation à rejoindre un canal, envoyée par un [`Request::Invite`] de `by`.
+    Invited { chan: String, by: String },
+    /// État du serveur, envoyé aux connexions abonnées par [`Request::SubscribeStats`] :
+    /// utilisateurs connectés, canaux non vides, secondes depuis son démarrage, et messages
+    /// (de canal ou directs) de la dernière minute.
+    ServerStats {
+        users: usize,
+        channels: usize,
+        uptime_secs: u64,
+        msgs_per_min: u64,
+    },
 }
 
 impl SerdeEncryptSharedKey for Response {
     type S = BincodeSerializer<Self>;
 }
+
+/// Ce que concerne une [`Response::Notice`], qui indique au client où l'afficher.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum NoticeScope {
+    /// Le serveur lui-même
+    Server,
+    /// Un canal, sans `#`
+    Channel(String),
+    /// La conversation avec un utilisateur, sans `@`
+    User(String),
+}
+
+/// Message d'un canal conservé par le serveur, dans une [`Response::History`].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ArchivedMessage {
+    /// Numéro de séquence de la [`Response::Channel`] qui l'a diffusé
+    pub id: u64,
+    /// Date d'envoi, selon l'horloge du serveur
+    pub at: SystemTime,
+    pub from: String,
+    pub content: String,
+}
+
+impl SerdeEncryptSharedKey for ArchivedMessage {
+    type S = BincodeSerializer<Self>;
+}
+
+/// Limites imposées par le serveur, à respecter par les clients.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Capabilities {
+    /// Taille maximale du contenu d'un message, en octets.
+    pub max_message_len: usize,
+}
+
+impl SerdeEncryptSharedKey for Capabilities {
+    type S = BincodeSerializer<Self>;
+}
+
+/// Description d'un canal dans une [`Response::ChanList`].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ChanInfo {
+    pub name: String,
+    /// Nombre de membres
+    pub users: usize,
+    /// Sujet du canal, s'il en a un.
+    pub topic: Option<String>,
+}
+
+impl SerdeEncryptSharedKey for ChanInfo {
+    type S = BincodeSerializer<Self>;
+}
+
+/// Statistiques de la connexion d'un utilisateur, du point de vue de son client.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ConnectionStats {
+    pub user: String,
+    /// Octets envoyés au serveur, handshake compris.
+    pub bytes_sent: u64,
+    /// Octets reçus du serveur, handshake compris.
+    pub bytes_received: u64,
+    /// Messages envoyés à un canal ou à un utilisateur.
+    pub messages_sent: u64,
+    /// Canaux rejoints.
+    pub channels: Vec<String>,
+    /// Durée depuis la connexion.
+    pub connected_for: Duration,
+    /// Dernier temps d'aller-retour mesuré par un [`Response::Ping`], s'il y en a eu un.
+    pub ping_rtt: Option<Duration>,
+    /// Chiffrement de la connexion, du point de vue du serveur.
+    pub encryption: EncryptionStatus,
+}
+
+impl SerdeEncryptSharedKey for ConnectionStats {
+    type S = BincodeSerializer<Self>;
+}
+/// Politique d'écriture des trames envoyées par [`TypedWriter`] et [`AsyncTypedWriter`].
+///
+/// Dans tous les cas, chaque trame (taille et données) est écrite en un seul appel au canal
+/// sous-jacent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// Chaque trame est écrite dès son envoi, puis le canal est vidé (`flush`), ce qui est
+    /// nécessaire pour les canaux munis d'un tampon, comme [`std::io::BufWriter`].
+    #[default]
+    Auto,
+    /// Les trames sont accumulées et écrites ensemble lors d'un appel explicite à `flush`,
+    /// ou dès que leur taille dépasse un seuil. Utile pour envoyer plusieurs trames d'un coup.
+    Manual,
+}
+
 /// Canal de communication côté réception, typé et **synchrone**. Permet de recevoir un type quelconque via
 /// une socquette TCP par exemple, dès lors que le type à envoyer implémente [`Serialize`] et [`Deserialize`].
 /// La socquette doit par ailleurs implémenter [`Read`].
@@ -114,11 +611,15 @@ impl SerdeEncryptSharedKey for Response {
 ///
 /// ```no_run
 /// use std::net::TcpStream;
-/// use mini_irc_protocol::Response;
+/// use mini_irc_protocol::{HandshakeResponse, Response};
 /// use mini_irc_protocol::TypedReader;
+/// use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
 ///
+/// # let shared_key = SharedKey::generate();
 /// let stream = TcpStream::connect("serveur:port").unwrap();
-/// let mut typed_reader = TypedReader::<_, Response>::new(stream);
+/// let typed_reader = TypedReader::<_, HandshakeResponse>::new(stream);
+/// // Les réponses ne peuvent être reçues qu'une fois le canal chiffré
+/// let mut typed_reader = typed_reader.upgrade::<Response>(shared_key);
 /// let response: Response = typed_reader.recv().unwrap().unwrap();
 /// ```
 ///
@@ -126,18 +627,22 @@ impl SerdeEncryptSharedKey for Response {
 /// ou d'un [`TypedWriter`] pour le même type.
 
 #[derive(Debug)]
-pub struct TypedReader<Stream, T>
+pub struct TypedReader<Stream, T, E = Plain>
 where
     Stream: Read,
 {
     pub stream: Stream,
-    /// Utilisé pour chiffrer/déchiffrer
-    pub shared_key: Option<SharedKey>,
-    _t: std::marker::PhantomData<*const T>,
+    /// Utilisé pour déchiffrer et désérialiser les trames
+    codec: MiniIrcCodec<T, E>,
+    /// Données reçues mais pas encore décodées
+    buffer: BytesMut,
+    /// Taille de la trame empruntée par [`TypedReader::recv_borrowed`], à retirer du tampon
+    borrowed: usize,
+    /// Données déchiffrées de la dernière trame
+    plain: Vec<u8>,
+    tap: Option<WireTap>,
 }
 
-unsafe impl<Stream, T> Send for TypedReader<Stream, T> where Stream: Send + Read {}
-
 impl<Stream, T> TypedReader<Stream, T>
 where
     Stream: Read,
@@ -146,47 +651,172 @@ where
     pub fn new(stream: Stream) -> Self {
         Self {
             stream,
-            shared_key: None,
-            _t: std::marker::PhantomData,
+            codec: MiniIrcCodec::new(),
+            buffer: BytesMut::new(),
+            borrowed: 0,
+            plain: Vec::new(),
+            tap: None,
         }
     }
+
+    /// Chiffre les trames suivantes avec la clé partagée fournie. Le type des trames reçues
+    /// peut changer à cette occasion, par exemple de [`HandshakeResponse`] à [`Response`].
+    pub fn upgrade<U>(self, shared_key: SharedKey) -> TypedReader<Stream, U, Encrypted> {
+        TypedReader {
+            stream: self.stream,
+            codec: self.codec.upgrade(shared_key),
+            buffer: self.buffer,
+            borrowed: self.borrowed,
+            plain: self.plain,
+            tap: self.tap,
+        }
+    }
+
+    /// N'annonce pas de format plus récent que `max` à l'autre pair, voir [`WireVersion`].
+    pub fn set_max_wire_version(&mut self, max: WireVersion) {
+        self.codec.set_max_wire_version(max);
+    }
 }
 
-impl<Stream, T> TypedReader<Stream, T>
+impl<Stream, T, E> TypedReader<Stream, T, E>
+where
+    Stream: Read,
+    E: Encryption,
+{
+    /// Indique si les trames reçues sont chiffrées, et par quel mécanisme.
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        self.codec.encryption_status()
+    }
+
+    /// Format des trames reçues, voir [`WireVersion`].
+    pub fn wire_version(&self) -> WireVersion {
+        self.codec.wire_version()
+    }
+
+    /// Capture les trames reçues suivantes, y compris après [`TypedReader::upgrade`].
+    pub fn set_tap(&mut self, tap: WireTap) {
+        self.tap = Some(tap);
+    }
+
+    /// Lit sur le canal jusqu'à ce que la prochaine trame soit entièrement dans le tampon,
+    /// et renvoie la position de ses données.
+    fn fill_frame(&mut self) -> Result<Range<usize>, ProtocolError> {
+        self.fill_frame_with(|_| Ok(()))
+    }
+
+    /// Comme [`TypedReader::fill_frame`], en appelant `before_read` avant chaque lecture sur le
+    /// canal, par exemple pour ajuster son délai de lecture.
+    fn fill_frame_with(
+        &mut self,
+        mut before_read: impl FnMut(&Stream) -> Result<(), ProtocolError>,
+    ) -> Result<Range<usize>, ProtocolError> {
+        self.buffer.advance(std::mem::take(&mut self.borrowed));
+        loop {
+            if let Some(frame) = self.codec.next_frame(&mut self.buffer)? {
+                return Ok(frame);
+            }
+            before_read(&self.stream)?;
+            // Les données partiellement reçues restent dans le tampon, y compris en cas d'erreur
+            let len = self.buffer.len();
+            self.buffer.resize(len + READ_CHUNK_SIZE, 0);
+            let read = self.stream.read(&mut self.buffer[len..]);
+            self.buffer.truncate(len + *read.as_ref().unwrap_or(&0));
+            if read? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+        }
+    }
+}
+
+impl<Stream, T, E> TypedReader<Stream, T, E>
 where
     Stream: Read + std::fmt::Debug,
-    T: DeserializeOwned + std::fmt::Debug + SerdeEncryptSharedKey,
+    T: DeserializeOwned + std::fmt::Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
 {
     /// Reçoit un type via le canal de réception. Il doit avoir été envoyé via
     /// la fonction [`AsyncTypedWriter::send`] ou [`TypedWriter::send`].
     ///
-    /// Renvoie une erreur en cas d'erreur du canal sous-jacent, et
-    /// `None` en cas d'erreur de déserialisation.
+    /// Renvoie une erreur en cas d'erreur du canal sous-jacent ou de déchiffrement, et
+    /// `None` en cas d'erreur de déserialisation. Une fermeture propre par l'autre pair
+    /// (`close`) produit [`ProtocolError::Closed`], à toutes les réceptions suivantes. Un
+    /// délai de lecture configuré sur la socquette (voir [`SocketOptions`]) produit
+    /// [`ProtocolError::TimedOut`] à son expiration.
     #[tracing::instrument(level = "debug")]
-    pub fn recv(&mut self) -> std::io::Result<Option<T>> {
-        // Read the size, from u32
+    pub fn recv(&mut self) -> Result<Option<T>, ProtocolError> {
         info!("Receiving data");
-        let mut size = [0; 4];
-        self.stream.read_exact(&mut size)?;
-        let size = u32::from_be_bytes(size);
-        // Prepare a buffer
-        let mut buf = vec![0; size as usize];
-        self.stream.read_exact(&mut buf)?;
-
+        let frame = self.fill_frame().map_err(ProtocolError::into_timed_out)?;
         info!("Data received");
+        self.take_frame(frame)
+    }
+
+    /// Désérialise la trame entièrement reçue à la position `frame`, puis la retire du tampon.
+    fn take_frame(&mut self, frame: Range<usize>) -> Result<Option<T>, ProtocolError> {
+        let data = self
+            .codec
+            .deserialize_payload(&self.buffer[frame.clone()], &mut self.plain);
+        if let Some(tap) = &self.tap {
+            let value = data.as_ref().ok().map(|data| format!("{data:?}"));
+            tap.record(Direction::Received, &self.buffer[..frame.end], value);
+        }
+        self.buffer.advance(frame.end);
         // Deserialize the value, discard the potential deserializing error
-        if self.shared_key.is_some() {
-            let encrypted_message = EncryptedMessage::deserialize(buf).expect("error");
-            let msg =
-                T::decrypt_owned(&encrypted_message, &self.shared_key.clone().unwrap()).unwrap();
-            Ok(Some(msg))
-        } else {
-            Ok(bincode::deserialize(&buf).ok())
+        match data {
+            Ok(data) => Ok(Some(data)),
+            Err(ProtocolError::Deserialize(_)) => Ok(None),
+            Err(e) => Err(e),
         }
     }
 
-    pub fn set_shared_key(&mut self, shared_key: SharedKey) {
-        self.shared_key = Some(shared_key);
+    /// Comme [`TypedReader::recv`], mais désérialise la trame dans un type pouvant emprunter
+    /// ses données au tampon de réception, par exemple une vue `&str` d'un message. Sans
+    /// chiffrement, aucune allocation n'est effectuée.
+    pub fn recv_borrowed<'a, B>(&'a mut self) -> Result<Option<B>, ProtocolError>
+    where
+        B: Deserialize<'a>,
+    {
+        let frame = self.fill_frame().map_err(ProtocolError::into_timed_out)?;
+        // La trame sera retirée du tampon lors de la prochaine réception
+        self.borrowed = frame.end;
+        // La valeur empruntée n'est pas forcément affichable : seuls les octets sont capturés
+        if let Some(tap) = &self.tap {
+            tap.record(Direction::Received, &self.buffer[..frame.end], None);
+        }
+        match self
+            .codec
+            .deserialize_payload(&self.buffer[frame], &mut self.plain)
+        {
+            Ok(data) => Ok(Some(data)),
+            Err(ProtocolError::Deserialize(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<Stream, T, E> TypedReader<Stream, T, E>
+where
+    Stream: Read + TimeoutStream + std::fmt::Debug,
+    T: DeserializeOwned + std::fmt::Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    /// Comme [`TypedReader::recv`], mais renvoie [`ProtocolError::TimedOut`] si aucune trame
+    /// complète n'a été reçue avant la fin du délai. Le délai porte sur la trame entière, pas
+    /// sur chaque lecture : un pair qui l'envoie octet par octet ne le prolonge pas. Une trame
+    /// partiellement reçue n'est pas perdue et pourra être lue par un appel ultérieur.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<T>, ProtocolError> {
+        let previous = self.stream.read_timeout()?;
+        let deadline = Instant::now() + timeout;
+        let frame = self.fill_frame_with(|stream| {
+            // Une socquette n'accepte pas de délai nul
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(ProtocolError::TimedOut);
+            }
+            Ok(stream.set_read_timeout(Some(remaining))?)
+        });
+        self.stream.set_read_timeout(previous)?;
+        let frame = frame.map_err(ProtocolError::into_timed_out)?;
+        self.take_frame(frame)
     }
 }
 /// Canal de communication côté émission, typé et **synchrone**. Permet d'envoyer un type quelconque via
@@ -197,28 +827,33 @@ where
 ///
 /// ```no_run
 /// use std::net::TcpStream;
-/// use mini_irc_protocol::Request;
+/// use mini_irc_protocol::{HandshakeRequest, Request};
 /// use mini_irc_protocol::TypedWriter;
+/// use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
 ///
+/// # let shared_key = SharedKey::generate();
 /// let stream = TcpStream::connect("serveur:port").unwrap();
-/// let mut typed_writer = TypedWriter::<_, Request>::new(stream);
+/// let typed_writer = TypedWriter::<_, HandshakeRequest>::new(stream);
+/// // Les requêtes ne peuvent être envoyées qu'une fois le canal chiffré
+/// let mut typed_writer = typed_writer.upgrade::<Request>(shared_key);
 /// typed_writer.send(&Request::Connect("toto".to_string())).unwrap();
 /// ```
 ///
 /// Ceci enverra une requête au serveur, qui devra être reçue via un [`AsyncTypedReader`] ou
 /// un [`TypedReader`] pour le même type.
 #[derive(Debug)]
-pub struct TypedWriter<Stream, T>
+pub struct TypedWriter<Stream, T, E = Plain>
 where
     Stream: Write,
 {
     pub stream: Stream,
-    pub shared_key: Option<SharedKey>,
-    _t: std::marker::PhantomData<*const T>,
+    codec: MiniIrcCodec<T, E>,
+    /// Trames encodées mais pas encore écrites
+    buffer: BytesMut,
+    flush_policy: FlushPolicy,
+    tap: Option<WireTap>,
 }
 
-unsafe impl<Stream, T> Send for TypedWriter<Stream, T> where Stream: Send + Write {}
-
 impl<Stream, T> TypedWriter<Stream, T>
 where
     Stream: Write,
@@ -227,36 +862,250 @@ where
     pub fn new(stream: Stream) -> Self {
         Self {
             stream,
-            shared_key: None,
-            _t: std::marker::PhantomData,
+            codec: MiniIrcCodec::new(),
+            buffer: BytesMut::new(),
+            flush_policy: FlushPolicy::default(),
+            tap: None,
+        }
+    }
+
+    /// Chiffre les trames suivantes avec la clé partagée fournie. Le type des trames envoyées
+    /// peut changer à cette occasion, par exemple de [`HandshakeRequest`] à [`Request`].
+    ///
+    /// Le format des trames ne change que si la version annoncée par l'autre pair a été
+    /// reportée depuis la réception, comme le fait [`SyncTypedChannel::upgrade`] : voir
+    /// [`WireVersion`].
+    pub fn upgrade<U>(self, shared_key: SharedKey) -> TypedWriter<Stream, U, Encrypted> {
+        TypedWriter {
+            stream: self.stream,
+            codec: self.codec.upgrade(shared_key),
+            buffer: self.buffer,
+            flush_policy: self.flush_policy,
+            tap: self.tap,
         }
     }
+
+    /// N'annonce pas de format plus récent que `max` à l'autre pair, voir [`WireVersion`].
+    pub fn set_max_wire_version(&mut self, max: WireVersion) {
+        self.codec.set_max_wire_version(max);
+    }
 }
 
-impl<Stream, T> TypedWriter<Stream, T>
+impl<Stream, T, E> TypedWriter<Stream, T, E>
+where
+    Stream: Write,
+    E: Encryption,
+{
+    /// Indique si les trames envoyées sont chiffrées, et par quel mécanisme.
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        self.codec.encryption_status()
+    }
+
+    /// Format des trames envoyées, voir [`WireVersion`].
+    pub fn wire_version(&self) -> WireVersion {
+        self.codec.wire_version()
+    }
+
+    pub fn set_flush_policy(&mut self, flush_policy: FlushPolicy) {
+        self.flush_policy = flush_policy;
+    }
+
+    /// Capture les trames envoyées suivantes, y compris après [`TypedWriter::upgrade`].
+    pub fn set_tap(&mut self, tap: WireTap) {
+        self.tap = Some(tap);
+    }
+
+    /// Écrit les trames en attente puis vide le canal sous-jacent.
+    pub fn flush(&mut self) -> Result<(), ProtocolError> {
+        self.write_buffer()?;
+        Ok(self.stream.flush()?)
+    }
+
+    /// Annonce une fermeture propre à l'autre pair, qui reçoit [`ProtocolError::Closed`]
+    /// plutôt qu'une erreur du réseau, puis écrit les trames en attente. Sans format de
+    /// trames le permettant ([`WireVersion::V1`]), les trames sont seulement écrites. La
+    /// socquette reste ouverte : c'est à l'appelant de la fermer.
+    pub fn close(&mut self) -> Result<(), ProtocolError> {
+        self.codec.encode_control(FrameTag::Close, &mut self.buffer);
+        self.flush()
+    }
+
+    /// Écrit les trames en attente sur le canal sous-jacent. Les données non écrites restent
+    /// dans le tampon, y compris en cas d'erreur.
+    fn write_buffer(&mut self) -> Result<(), ProtocolError> {
+        while !self.buffer.is_empty() {
+            match self.stream.write(&self.buffer)? {
+                0 => return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()),
+                n => self.buffer.advance(n),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Stream, T, E> TypedWriter<Stream, T, E>
 where
     Stream: Write + std::fmt::Debug,
-    T: serde::Serialize + std::fmt::Debug + SerdeEncryptSharedKey,
+    T: serde::Serialize + std::fmt::Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
 {
     /// Envoie un type via le canal sélectionné. Une erreur est envoyée en cas
     /// d'erreur du canal sous-jacent.
+    ///
+    /// Selon la [`FlushPolicy`], la trame peut n'être écrite que lors du prochain
+    /// [`TypedWriter::flush`].
     #[tracing::instrument(level = "info")]
-    pub fn send(&mut self, value: &T) -> std::io::Result<()> {
-        let data: Vec<u8> = if self.shared_key.is_some() {
-            let encrypted_data = value
-                .encrypt(&self.shared_key.clone().unwrap())
-                .expect("error");
-            encrypted_data.serialize()
-        } else {
-            bincode::serialize(value).unwrap()
+    pub fn send(&mut self, value: &T) -> Result<(), ProtocolError> {
+        let start = self.buffer.len();
+        self.codec.encode(value, &mut self.buffer)?;
+        if let Some(tap) = &self.tap {
+            let value = Some(format!("{value:?}"));
+            tap.record(Direction::Sent, &self.buffer[start..], value);
+        }
+        let res = match self.flush_policy {
+            FlushPolicy::Auto => self.flush(),
+            FlushPolicy::Manual if self.buffer.len() >= BACKPRESSURE_BOUNDARY => {
+                self.write_buffer()
+            }
+            FlushPolicy::Manual => Ok(()),
         };
-        // Send the size, as u32
-        self.stream.write_all(&(data.len() as u32).to_be_bytes())?;
-        self.stream.write_all(&data)
+        // Délai d'écriture de la socquette, voir `SocketOptions`
+        res.map_err(ProtocolError::into_timed_out)
+    }
+}
+
+impl<Stream, T, E> TypedWriter<Stream, T, E>
+where
+    Stream: Write + TimeoutStream + std::fmt::Debug,
+    T: serde::Serialize + std::fmt::Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    /// Comme [`TypedWriter::send`], mais renvoie [`ProtocolError::TimedOut`] si la trame n'a pas
+    /// pu être écrite avant la fin du délai. Le reste de la trame sera écrit lors du prochain envoi.
+    pub fn send_timeout(&mut self, value: &T, timeout: Duration) -> Result<(), ProtocolError> {
+        let previous = self.stream.write_timeout()?;
+        self.stream.set_write_timeout(Some(timeout))?;
+        let res = self.send(value);
+        self.stream.set_write_timeout(previous)?;
+        res.map_err(ProtocolError::into_timed_out)
+    }
+}
+
+/// Socquette synchrone dont les délais d'attente en lecture et en écriture sont configurables,
+/// nécessaire à [`TypedReader::recv_timeout`] et [`TypedWriter::send_timeout`].
+pub trait TimeoutStream {
+    fn read_timeout(&self) -> std::io::Result<Option<Duration>>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+    fn write_timeout(&self) -> std::io::Result<Option<Duration>>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+
+    /// Sondes TCP keepalive après `idle` sans échange, ou aucune. Sans effet hors TCP.
+    fn set_keepalive(&self, _idle: Option<Duration>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TimeoutStream for std::net::TcpStream {
+    fn read_timeout(&self) -> std::io::Result<Option<Duration>> {
+        std::net::TcpStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        std::net::TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn write_timeout(&self) -> std::io::Result<Option<Duration>> {
+        std::net::TcpStream::write_timeout(self)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        std::net::TcpStream::set_write_timeout(self, timeout)
+    }
+
+    fn set_keepalive(&self, idle: Option<Duration>) -> std::io::Result<()> {
+        let socket = socket2::SockRef::from(self);
+        match idle {
+            Some(idle) => socket.set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(idle)),
+            None => socket.set_keepalive(false),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl TimeoutStream for std::os::unix::net::UnixStream {
+    fn read_timeout(&self) -> std::io::Result<Option<Duration>> {
+        std::os::unix::net::UnixStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn write_timeout(&self) -> std::io::Result<Option<Duration>> {
+        std::os::unix::net::UnixStream::write_timeout(self)
     }
 
-    pub fn set_shared_key(&mut self, shared_key: SharedKey) {
-        self.shared_key = Some(shared_key);
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        std::os::unix::net::UnixStream::set_write_timeout(self, timeout)
+    }
+}
+
+/// Réglages d'une socquette synchrone, pour qu'un [`TypedReader`] ou un [`TypedWriter`] ne
+/// reste pas bloqué indéfiniment sur un pair disparu sans fermer la connexion (câble débranché,
+/// machine éteinte...). Un délai expiré produit [`ProtocolError::TimedOut`] ; la trame en
+/// cours n'est pas perdue et l'appelant peut réessayer, ou abandonner la connexion.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use std::net::TcpStream;
+/// use std::time::Duration;
+/// use mini_irc_protocol::SocketOptions;
+///
+/// let stream = TcpStream::connect("serveur:port").unwrap();
+/// SocketOptions::new()
+///     .read_timeout(Duration::from_secs(90))
+///     .keepalive(Duration::from_secs(60))
+///     .apply(&stream)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    keepalive: Option<Duration>,
+}
+
+impl SocketOptions {
+    /// Aucun délai ni keepalive : les réglages par défaut du système.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Délai au-delà duquel une réception sans aucune donnée échoue.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Délai au-delà duquel un envoi bloqué (pair qui ne lit plus) échoue.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Sondes TCP keepalive après `idle` sans échange : le système détecte alors seul un pair
+    /// disparu, même sans rien à envoyer.
+    pub fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    /// Applique les réglages à `stream`, et à toutes ses copies (`try_clone`).
+    pub fn apply<S: TimeoutStream>(&self, stream: &S) -> std::io::Result<()> {
+        stream.set_read_timeout(self.read_timeout)?;
+        stream.set_write_timeout(self.write_timeout)?;
+        stream.set_keepalive(self.keepalive)
     }
 }
 
@@ -264,18 +1113,25 @@ where
 /// une socquette TCP par exemple, dès lors que le type à envoyer implémente [`Serialize`] et [`Deserialize`].
 /// La socquette doit par ailleurs implémenter [`AsyncReadExt`].
 ///
+/// Les trames sont découpées par un [`MiniIrcCodec`] ; la réception est donc annulable
+/// (par exemple dans un `tokio::select!`) sans perte de données.
+///
 /// # Exemple
 ///
 /// ```no_run
 /// use tokio::net::TcpStream;
-/// use mini_irc_protocol::Response;
+/// use mini_irc_protocol::{HandshakeResponse, Response};
 /// use mini_irc_protocol::AsyncTypedReader;
+/// use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
 ///
 /// # #[tokio::main]
 /// # async fn main() {
+/// # let shared_key = SharedKey::generate();
 /// let stream = TcpStream::connect("serveur:port").await.unwrap();
 /// let (reader, writer) = stream.into_split();
-/// let mut typed_reader = AsyncTypedReader::<_, Response>::new(reader);
+/// let typed_reader = AsyncTypedReader::<_, HandshakeResponse>::new(reader);
+/// // Les réponses ne peuvent être reçues qu'une fois le canal chiffré
+/// let mut typed_reader = typed_reader.upgrade::<Response>(shared_key);
 /// let response: Response = typed_reader.recv().await.unwrap().unwrap();
 /// # }
 /// ```
@@ -284,17 +1140,24 @@ where
 /// ou d'un [`TypedWriter`] pour le même type.
 
 #[derive(Debug)]
-pub struct AsyncTypedReader<Stream, T>
+pub struct AsyncTypedReader<Stream, T, E = Plain>
 where
     Stream: AsyncReadExt,
 {
     pub stream: Stream,
-    pub shared_key: Option<SharedKey>,
-    _t: std::marker::PhantomData<*const T>,
+    codec: MiniIrcCodec<T, E>,
+    /// Données reçues mais pas encore décodées
+    buffer: BytesMut,
+    /// Taille de la trame empruntée par [`AsyncTypedReader::recv_borrowed`], à retirer du tampon
+    borrowed: usize,
+    /// Données déchiffrées de la dernière trame
+    plain: Vec<u8>,
+    heartbeat: Option<Heartbeat>,
+    /// Échéance à laquelle le pair est perdu faute d'avoir rien reçu, armée à la première
+    /// réception
+    silence: Option<Pin<Box<tokio::time::Sleep>>>,
 }
 
-unsafe impl<Stream, T> Send for AsyncTypedReader<Stream, T> where Stream: Send + AsyncReadExt {}
-
 impl<Stream, T> AsyncTypedReader<Stream, T>
 where
     Stream: AsyncReadExt,
@@ -303,54 +1166,207 @@ where
     pub fn new(stream: Stream) -> Self {
         Self {
             stream,
-            shared_key: None,
-            _t: std::marker::PhantomData,
+            codec: MiniIrcCodec::new(),
+            buffer: BytesMut::new(),
+            borrowed: 0,
+            plain: Vec::new(),
+            heartbeat: None,
+            silence: None,
+        }
+    }
+
+    /// Chiffre les trames suivantes avec la clé partagée fournie. Le type des trames reçues
+    /// peut changer à cette occasion, par exemple de [`HandshakeResponse`] à [`Response`].
+    pub fn upgrade<U>(self, shared_key: SharedKey) -> AsyncTypedReader<Stream, U, Encrypted> {
+        AsyncTypedReader {
+            stream: self.stream,
+            codec: self.codec.upgrade(shared_key),
+            buffer: self.buffer,
+            borrowed: self.borrowed,
+            plain: self.plain,
+            heartbeat: self.heartbeat,
+            silence: self.silence,
         }
     }
+
+    /// N'annonce pas de format plus récent que `max` à l'autre pair, voir [`WireVersion`].
+    pub fn set_max_wire_version(&mut self, max: WireVersion) {
+        self.codec.set_max_wire_version(max);
+    }
 }
-impl<Stream, T> AsyncTypedReader<Stream, T>
+
+impl<Stream, T, E> AsyncTypedReader<Stream, T, E>
+where
+    Stream: AsyncReadExt,
+    E: Encryption,
+{
+    /// Indique si les trames reçues sont chiffrées, et par quel mécanisme.
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        self.codec.encryption_status()
+    }
+
+    /// Format des trames reçues, voir [`WireVersion`].
+    pub fn wire_version(&self) -> WireVersion {
+        self.codec.wire_version()
+    }
+
+    /// Considère le pair perdu s'il n'envoie rien pendant la tolérance de `heartbeat`, voir
+    /// [`Heartbeat`].
+    pub fn set_heartbeat(&mut self, heartbeat: Heartbeat) {
+        self.heartbeat = Some(heartbeat);
+        self.silence = None;
+    }
+}
+
+impl<Stream, T, E> AsyncTypedReader<Stream, T, E>
+where
+    Stream: AsyncReadExt + std::marker::Unpin,
+    T: DeserializeOwned + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    /// Lit sur le canal jusqu'à ce que la prochaine trame soit entièrement dans le tampon,
+    /// et renvoie la position de ses données.
+    ///
+    /// Renvoie `None` si le canal a été fermé entre deux trames.
+    fn poll_fill_frame(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<Range<usize>>, ProtocolError>> {
+        self.buffer.advance(std::mem::take(&mut self.borrowed));
+        loop {
+            if let Some(frame) = self.codec.next_frame(&mut self.buffer)? {
+                return Poll::Ready(Ok(Some(frame)));
+            }
+            let read = match poll_read_buf(Pin::new(&mut self.stream), cx, &mut self.buffer) {
+                Poll::Ready(read) => read?,
+                Poll::Pending => return self.poll_silence(cx),
+            };
+            if read == 0 {
+                return Poll::Ready(if self.buffer.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+                });
+            }
+            // Des données, même une trame de maintien : le pair est toujours là
+            if let (Some(heartbeat), Some(silence)) = (self.heartbeat, &mut self.silence) {
+                silence
+                    .as_mut()
+                    .reset(tokio::time::Instant::now() + heartbeat.deadline());
+            }
+        }
+    }
+
+    /// Attend la fin de la tolérance du [`Heartbeat`], s'il y en a un, pendant que la lecture
+    /// est en attente.
+    fn poll_silence<R>(&mut self, cx: &mut Context<'_>) -> Poll<Result<R, ProtocolError>> {
+        let Some(heartbeat) = self.heartbeat else {
+            return Poll::Pending;
+        };
+        let silence = self
+            .silence
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(heartbeat.deadline())));
+        ready!(std::future::Future::poll(silence.as_mut(), cx));
+        Poll::Ready(Err(ProtocolError::PeerLost))
+    }
+
+    /// Décode la prochaine trame, en lisant sur le canal si elle n'est pas encore complète.
+    ///
+    /// Renvoie `None` si le canal a été fermé entre deux trames.
+    fn poll_frame(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<T>, ProtocolError>> {
+        let frame = match ready!(self.poll_fill_frame(cx))? {
+            Some(frame) => frame,
+            None => return Poll::Ready(Ok(None)),
+        };
+        let data = self
+            .codec
+            .deserialize_payload(&self.buffer[frame.clone()], &mut self.plain);
+        self.buffer.advance(frame.end);
+        Poll::Ready(data.map(Some))
+    }
+}
+
+impl<Stream, T, E> AsyncTypedReader<Stream, T, E>
 where
     Stream: AsyncReadExt + std::marker::Unpin + std::fmt::Debug,
-    T: DeserializeOwned + std::fmt::Debug + SerdeEncryptSharedKey,
+    T: DeserializeOwned + std::fmt::Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
 {
     /// Reçoit un type via le canal réception. Il doit avoir été envoyé via
     /// la fonction [`AsyncTypedWriter::send`] ou [`TypedWriter::send`].
     ///
-    /// Renvoie une erreur en cas d'erreur du canal sous-jacent, et
-    /// `None` en cas d'erreur de déserialisation.
+    /// Renvoie une erreur en cas d'erreur du canal sous-jacent ou de déchiffrement, et
+    /// `None` en cas d'erreur de déserialisation. Une fermeture propre par l'autre pair
+    /// (`close`) produit [`ProtocolError::Closed`], à toutes les réceptions suivantes.
     #[tracing::instrument(level = "debug")]
-    pub async fn recv(&mut self) -> std::io::Result<Option<T>> {
-        // Read the size, from u32
+    pub async fn recv(&mut self) -> Result<Option<T>, ProtocolError> {
         info!("Receiving data");
-        let mut size = [0; 4];
-        self.stream.read_exact(&mut size).await?;
-        let size = u32::from_be_bytes(size);
-        //info!("Received size");
-        // Prepare a buffer
-        let mut buf = vec![0; size as usize];
-        self.stream.read_exact(&mut buf).await?;
-        let data: Option<T> = if self.shared_key.is_some() {
-            let encrypted_message = EncryptedMessage::deserialize(buf).expect("error");
-            let msg =
-                T::decrypt_owned(&encrypted_message, &self.shared_key.clone().unwrap()).unwrap();
-            Some(msg)
-        } else {
-            bincode::deserialize(&buf).ok()
-        };
-        match data.as_ref() {
-            Some(data) => {
+        // Deserialize the value, discard the potential deserializing error
+        match poll_fn(|cx| self.poll_frame(cx)).await {
+            Ok(Some(data)) => {
                 info!("Data received: {:?}", data);
+                Ok(Some(data))
             }
-            _ => {
+            Ok(None) => Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+            Err(ProtocolError::Deserialize(_)) => {
                 info!("Received invalid data");
+                Ok(None)
             }
+            Err(e) => Err(e),
         }
-        // Deserialize the value, discard the potential deserializing error
-        Ok(data)
     }
 
-    pub fn set_shared_key(&mut self, shared_key: SharedKey) {
-        self.shared_key = Some(shared_key);
+    /// Comme [`AsyncTypedReader::recv`], mais renvoie [`ProtocolError::TimedOut`] si aucune
+    /// trame complète n'a été reçue avant la fin du délai. Une trame partiellement reçue n'est
+    /// pas perdue et pourra être lue par un appel ultérieur.
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<T>, ProtocolError> {
+        tokio::time::timeout(timeout, self.recv())
+            .await
+            .map_err(|_| ProtocolError::TimedOut)?
+    }
+
+    /// Comme [`AsyncTypedReader::recv`], mais désérialise la trame dans un type pouvant
+    /// emprunter ses données au tampon de réception, par exemple une vue `&str` d'un message.
+    /// Sans chiffrement, aucune allocation n'est effectuée.
+    pub async fn recv_borrowed<'a, B>(&'a mut self) -> Result<Option<B>, ProtocolError>
+    where
+        B: Deserialize<'a>,
+    {
+        let frame = match poll_fn(|cx| self.poll_fill_frame(cx)).await? {
+            Some(frame) => frame,
+            None => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+        };
+        // La trame sera retirée du tampon lors de la prochaine réception
+        self.borrowed = frame.end;
+        match self
+            .codec
+            .deserialize_payload(&self.buffer[frame], &mut self.plain)
+        {
+            Ok(data) => Ok(Some(data)),
+            Err(ProtocolError::Deserialize(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Permet d'utiliser les combinateurs de [`futures::StreamExt`]. Contrairement à
+/// [`AsyncTypedReader::recv`], une trame invalide produit une erreur
+/// [`ProtocolError::Deserialize`] ; le flux n'est pas interrompu pour autant.
+/// Le flux se termine lorsque le canal sous-jacent est fermé, ou que l'autre pair annonce sa
+/// fermeture.
+impl<Stream, T, E> futures::Stream for AsyncTypedReader<Stream, T, E>
+where
+    Stream: AsyncReadExt + std::marker::Unpin,
+    T: DeserializeOwned + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
+{
+    type Item = Result<T, ProtocolError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.get_mut().poll_frame(cx)) {
+            Err(ProtocolError::Closed) => Poll::Ready(None),
+            frame => Poll::Ready(frame.transpose()),
+        }
     }
 }
 
@@ -362,15 +1378,19 @@ where
 ///
 /// ```no_run
 /// use tokio::net::TcpStream;
-/// use mini_irc_protocol::Request;
+/// use mini_irc_protocol::{HandshakeRequest, Request};
 /// use mini_irc_protocol::AsyncTypedWriter;
+/// use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
 ///
 ///
 /// # #[tokio::main]
 /// # async fn main() {
+/// # let shared_key = SharedKey::generate();
 /// let stream = TcpStream::connect("serveur:port").await.unwrap();
 /// let (reader, writer) = stream.into_split();
-/// let mut typed_writer = AsyncTypedWriter::<_, Request>::new(writer);
+/// let typed_writer = AsyncTypedWriter::<_, HandshakeRequest>::new(writer);
+/// // Les requêtes ne peuvent être envoyées qu'une fois le canal chiffré
+/// let mut typed_writer = typed_writer.upgrade::<Request>(shared_key);
 /// typed_writer.send(&Request::Connect("toto".to_string())).await.unwrap();
 /// # }
 /// ```
@@ -379,17 +1399,22 @@ where
 /// un [`TypedReader`] pour le même type.
 
 #[derive(Debug)]
-pub struct AsyncTypedWriter<Stream, T>
+pub struct AsyncTypedWriter<Stream, T, E = Plain>
 where
     Stream: AsyncWriteExt,
 {
     pub stream: Stream,
-    pub shared_key: Option<SharedKey>,
-    _t: std::marker::PhantomData<*const T>,
+    codec: MiniIrcCodec<T, E>,
+    /// Trames encodées mais pas encore écrites
+    buffer: BytesMut,
+    flush_policy: FlushPolicy,
+    heartbeat: Option<Heartbeat>,
+    /// Dernière écriture sur le canal sous-jacent
+    last_write: tokio::time::Instant,
+    /// Trame de fermeture déjà ajoutée, voir [`AsyncTypedWriter::close`]
+    closing: bool,
 }
 
-unsafe impl<Stream, T> Send for AsyncTypedWriter<Stream, T> where Stream: Send + AsyncWriteExt {}
-
 impl<Stream, T> AsyncTypedWriter<Stream, T>
 where
     Stream: AsyncWriteExt,
@@ -398,155 +1423,197 @@ where
     pub fn new(stream: Stream) -> Self {
         Self {
             stream,
-            shared_key: None,
-            _t: std::marker::PhantomData,
+            codec: MiniIrcCodec::new(),
+            buffer: BytesMut::new(),
+            flush_policy: FlushPolicy::default(),
+            heartbeat: None,
+            last_write: tokio::time::Instant::now(),
+            closing: false,
         }
     }
-}
 
-impl<Stream, T> AsyncTypedWriter<Stream, T>
-where
-    Stream: AsyncWriteExt + std::marker::Unpin + std::fmt::Debug,
-    T: serde::Serialize + std::fmt::Debug + SerdeEncryptSharedKey,
-{
-    /// Envoie un type via le canal sélectionné. Une erreur est envoyée en cas
-    /// d'erreur du canal sous-jacent.
-    #[tracing::instrument(level = "debug")]
-    pub async fn send(&mut self, value: &T) -> std::io::Result<()> {
-        let data: Vec<u8> = if self.shared_key.is_some() {
-            let encrypted_data = value
-                .encrypt(&self.shared_key.clone().unwrap())
-                .expect("error");
-            encrypted_data.serialize()
-        } else {
-            bincode::serialize(value).unwrap()
-        };
-        // Send the size, as u32
-        self.stream
-            .write_all(&(data.len() as u32).to_be_bytes())
-            .await?;
-        self.stream.write_all(&data).await
+    /// Chiffre les trames suivantes avec la clé partagée fournie. Le type des trames envoyées
+    /// peut changer à cette occasion, par exemple de [`HandshakeRequest`] à [`Request`].
+    ///
+    /// Le format des trames ne change que si la version annoncée par l'autre pair a été
+    /// reportée depuis la réception, comme le fait [`TypedChannel::upgrade`] : voir
+    /// [`WireVersion`].
+    pub fn upgrade<U>(self, shared_key: SharedKey) -> AsyncTypedWriter<Stream, U, Encrypted> {
+        AsyncTypedWriter {
+            stream: self.stream,
+            codec: self.codec.upgrade(shared_key),
+            buffer: self.buffer,
+            flush_policy: self.flush_policy,
+            heartbeat: self.heartbeat,
+            last_write: self.last_write,
+            closing: self.closing,
+        }
     }
 
-    pub fn set_shared_key(&mut self, shared_key: SharedKey) {
-        self.shared_key = Some(shared_key);
+    /// N'annonce pas de format plus récent que `max` à l'autre pair, voir [`WireVersion`].
+    pub fn set_max_wire_version(&mut self, max: WireVersion) {
+        self.codec.set_max_wire_version(max);
     }
 }
 
-pub struct BroadcastSenderWithList<T, U>
+impl<Stream, T, E> AsyncTypedWriter<Stream, T, E>
 where
-    T: Clone,
-    U: 'static + PartialEq + Clone,
+    Stream: AsyncWriteExt,
+    E: Encryption,
 {
-    sender: broadcast::Sender<T>,
-    subscribers: Arc<Mutex<Vec<U>>>,
-}
+    /// Indique si les trames envoyées sont chiffrées, et par quel mécanisme.
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        self.codec.encryption_status()
+    }
 
-pub struct BroadcastReceiverWithList<T, U>
-where
-    T: Clone,
-    U: 'static + PartialEq + Clone,
-{
-    receiver: broadcast::Receiver<T>,
-    subscribers: Arc<Mutex<Vec<U>>>,
-    identifier: U,
-}
+    /// Format des trames envoyées, voir [`WireVersion`].
+    pub fn wire_version(&self) -> WireVersion {
+        self.codec.wire_version()
+    }
 
-impl<T, U> Debug for BroadcastSenderWithList<T, U>
-where
-    T: Clone,
-    U: PartialEq + 'static + Clone,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("BroadcastSenderWithList")
-            .field("sender", &self.sender)
-            .finish()
+    pub fn set_flush_policy(&mut self, flush_policy: FlushPolicy) {
+        self.flush_policy = flush_policy;
+    }
+
+    /// Active l'envoi de trames de maintien par [`AsyncTypedWriter::heartbeat`], voir
+    /// [`Heartbeat`].
+    pub fn set_heartbeat(&mut self, heartbeat: Heartbeat) {
+        self.heartbeat = Some(heartbeat);
     }
 }
 
-impl<T, U> BroadcastSenderWithList<T, U>
+impl<Stream, T, E> AsyncTypedWriter<Stream, T, E>
 where
-    T: Clone,
-    U: PartialEq + 'static + Clone,
+    Stream: AsyncWriteExt + std::marker::Unpin,
+    E: Encryption,
 {
-    pub fn new(capacity: usize) -> Self {
-        let (sender, _) = broadcast::channel(capacity);
-        Self {
-            sender,
-            subscribers: Arc::new(Mutex::new(Vec::new())),
+    /// Écrit les trames en attente sur le canal sous-jacent.
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ProtocolError>> {
+        while !self.buffer.is_empty() {
+            let n = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.buffer))?;
+            if n == 0 {
+                return Poll::Ready(Err(
+                    std::io::Error::from(std::io::ErrorKind::WriteZero).into()
+                ));
+            }
+            self.buffer.advance(n);
+            self.last_write = tokio::time::Instant::now();
         }
+        Poll::Ready(Ok(()))
     }
 
-    pub fn subscribe(&mut self, identity: U) -> Option<BroadcastReceiverWithList<T, U>> {
-        if self
-            .subscribers
-            .lock()
-            .unwrap()
-            .iter()
-            .any(|v| v == &identity)
-        {
-            //panic!("Identity already present in subscriber list");
-            return None;
-        }
-
-        self.subscribers.lock().unwrap().push(identity.clone());
+    /// Écrit les trames en attente puis vide le canal sous-jacent.
+    fn poll_flush_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ProtocolError>> {
+        ready!(self.poll_write_buffer(cx))?;
+        Poll::Ready(Ok(ready!(Pin::new(&mut self.stream).poll_flush(cx))?))
+    }
 
-        Some(BroadcastReceiverWithList {
-            receiver: self.sender.subscribe(),
-            subscribers: self.subscribers.clone(),
-            identifier: identity,
-        })
+    /// Écrit les trames en attente puis vide le canal sous-jacent.
+    pub async fn flush(&mut self) -> Result<(), ProtocolError> {
+        poll_fn(|cx| self.poll_flush_buffer(cx)).await
     }
 
-    pub fn send(&self, data: T) -> Result<usize, tokio::sync::broadcast::error::SendError<T>> {
-        self.sender.send(data)
+    /// Ajoute la trame de fermeture, une seule fois, puis écrit les trames en attente et
+    /// ferme le canal sous-jacent.
+    fn poll_close_stream(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ProtocolError>> {
+        if !self.closing {
+            self.closing = true;
+            self.codec.encode_control(FrameTag::Close, &mut self.buffer);
+        }
+        ready!(self.poll_write_buffer(cx))?;
+        Poll::Ready(Ok(ready!(Pin::new(&mut self.stream).poll_shutdown(cx))?))
     }
 
-    pub fn subscribers(&mut self) -> &std::sync::Mutex<Vec<U>> {
-        self.subscribers.deref()
+    /// Annonce une fermeture propre à l'autre pair, qui reçoit [`ProtocolError::Closed`]
+    /// plutôt qu'une erreur du réseau, puis écrit les trames en attente et ferme le canal
+    /// sous-jacent. Sans format de trames le permettant ([`WireVersion::V1`]), le canal est
+    /// seulement fermé.
+    pub async fn close(&mut self) -> Result<(), ProtocolError> {
+        poll_fn(|cx| self.poll_close_stream(cx)).await
     }
 
-    pub fn into_subscribers(&self) -> Vec<U> {
-        self.subscribers.lock().unwrap().clone()
+    /// Attend que rien n'ait été écrit pendant l'intervalle du [`Heartbeat`], puis envoie une
+    /// trame de maintien. Ne se termine jamais si aucun [`Heartbeat`] n'est configuré.
+    ///
+    /// Annulable : à placer dans une branche de `tokio::select!`, à côté des envois, pour que
+    /// le pair sache la connexion vivante même lorsqu'il n'y a rien à lui dire.
+    pub async fn heartbeat(&mut self) -> Result<(), ProtocolError> {
+        let Some(heartbeat) = self.heartbeat else {
+            return std::future::pending().await;
+        };
+        tokio::time::sleep_until(self.last_write + heartbeat.interval).await;
+        self.codec
+            .encode_control(FrameTag::Keepalive, &mut self.buffer);
+        self.flush().await
     }
 }
 
-impl<T, U> BroadcastReceiverWithList<T, U>
+impl<Stream, T, E> AsyncTypedWriter<Stream, T, E>
 where
-    T: Clone,
-    U: PartialEq + 'static + Clone,
+    Stream: AsyncWriteExt + std::marker::Unpin + std::fmt::Debug,
+    T: serde::Serialize + std::fmt::Debug + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
 {
-    pub async fn recv(&mut self) -> Result<T, tokio::sync::broadcast::error::RecvError> {
-        self.receiver.recv().await
+    /// Envoie un type via le canal sélectionné. Une erreur est envoyée en cas
+    /// d'erreur du canal sous-jacent.
+    ///
+    /// Selon la [`FlushPolicy`], la trame peut n'être écrite que lors du prochain
+    /// [`AsyncTypedWriter::flush`].
+    #[tracing::instrument(level = "debug")]
+    pub async fn send(&mut self, value: &T) -> Result<(), ProtocolError> {
+        self.codec.encode(value, &mut self.buffer)?;
+        match self.flush_policy {
+            FlushPolicy::Auto => self.flush().await,
+            FlushPolicy::Manual if self.buffer.len() >= BACKPRESSURE_BOUNDARY => {
+                poll_fn(|cx| self.poll_write_buffer(cx)).await
+            }
+            FlushPolicy::Manual => Ok(()),
+        }
     }
 
-    pub fn into_subscribers(&self) -> Vec<U> {
-        self.subscribers.lock().unwrap().clone()
+    /// Comme [`AsyncTypedWriter::send`], mais renvoie [`ProtocolError::TimedOut`] si la trame
+    /// n'a pas pu être écrite avant la fin du délai. Le reste de la trame sera écrit lors du
+    /// prochain envoi.
+    pub async fn send_timeout(
+        &mut self,
+        value: &T,
+        timeout: Duration,
+    ) -> Result<(), ProtocolError> {
+        tokio::time::timeout(timeout, self.send(value))
+            .await
+            .map_err(|_| ProtocolError::TimedOut)?
     }
 }
 
-impl<T, U> Debug for BroadcastReceiverWithList<T, U>
+/// Permet d'utiliser les combinateurs de [`futures::SinkExt`]. Les trames sont accumulées
+/// par [`futures::Sink::start_send`] et écrites lors de [`futures::Sink::poll_flush`].
+impl<Stream, T, E> futures::Sink<T> for AsyncTypedWriter<Stream, T, E>
 where
-    T: Clone,
-    U: PartialEq + 'static + Clone,
+    Stream: AsyncWriteExt + std::marker::Unpin,
+    T: serde::Serialize + SerdeEncryptSharedKey + Transmissible<E>,
+    E: Encryption,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("BroadcastReceiverWithList")
-            .field("receiver", &self.receiver)
-            .finish()
+    type Error = ProtocolError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), ProtocolError>> {
+        let this = self.get_mut();
+        if this.buffer.len() >= BACKPRESSURE_BOUNDARY {
+            this.poll_write_buffer(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
     }
-}
 
-impl<T, U> Drop for BroadcastReceiverWithList<T, U>
-where
-    T: Clone,
-    U: PartialEq + 'static + Clone,
-{
-    // We must remove the relevant receiver from list
-    fn drop(&mut self) {
-        self.subscribers
-            .lock()
-            .unwrap()
-            .retain(|v| v != &self.identifier);
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), ProtocolError> {
+        let this = self.get_mut();
+        this.codec.encode(&item, &mut this.buffer)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), ProtocolError>> {
+        self.get_mut().poll_flush_buffer(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), ProtocolError>> {
+        self.get_mut().poll_close_stream(cx)
     }
 }
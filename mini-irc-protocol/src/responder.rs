@@ -0,0 +1,215 @@
+use crate::{Request, Response};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// Message qui peut être rattaché à d'autres par une clé de corrélation : une requête et la
+/// réponse qui lui est destinée ont la même clé.
+pub trait Correlate {
+    type Key: Eq + Hash;
+
+    /// Clé de corrélation, ou `None` si le message n'est rattaché à aucun autre (requête sans
+    /// réponse, réponse envoyée d'elle-même par le serveur).
+    fn correlation(&self) -> Option<Self::Key>;
+}
+
+/// Clé de corrélation entre une [`Request`] et sa [`Response`].
+///
+/// Les requêtes ne portent pas encore d'identifiant : la clé est tirée de leur contenu (canal,
+/// utilisateur, valeur du ping...). Deux requêtes de même clé en attente reçoivent leurs
+/// réponses dans l'ordre d'envoi, comme le serveur les traite. Une [`Response::Error`] ne dit
+/// pas quelle requête elle refuse : elle n'a pas de clé, et c'est à l'appelant de l'attribuer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Correlation {
+    /// [`Request::Connect`], [`Response::AckConnect`] : le nom accordé peut différer
+    Connect,
+    /// [`Request::JoinChan`], [`Response::AckJoin`]
+    Join(String),
+    /// [`Request::LeaveChan`], [`Response::AckLeave`]
+    Leave(String),
+    /// [`Request::Stats`] et [`Request::StatsOf`], [`Response::Stats`]
+    Stats,
+    /// [`Request::Ping`], [`Response::Pong`]
+    Ping(u64),
+    /// [`Request::WhoIs`], [`Response::WhoIs`]
+    WhoIs(String),
+    /// [`Request::ListChans`], [`Response::ChanList`]
+    ListChans,
+    /// [`Request::Capabilities`], [`Response::Capabilities`]
+    Capabilities,
+    /// [`Request::Ghost`], [`Response::AckGhost`]
+    Ghost(String),
+    /// [`Request::WatchKeyword`] et [`Request::UnwatchKeyword`], [`Response::Keywords`]
+    Keywords(String),
+    /// [`Request::Names`], [`Response::Names`]
+    Names(String),
+    /// [`Request::Block`] et [`Request::Unblock`], [`Response::BlockList`]
+    BlockList,
+    /// [`Request::FetchHistory`], [`Response::History`]
+    History(String),
+    /// [`Request::SubscribeStats`], premier [`Response::ServerStats`] : les suivants sont
+    /// envoyés d'eux-mêmes
+    ServerStats,
+    /// [`Request::UnsubscribeStats`], [`Response::Ack`]
+    Ack,
+    /// [`Request::MessageMany`], [`Response::Delivery`]
+    Delivery,
+}
+
+impl Correlate for Request {
+    type Key = Correlation;
+
+    fn correlation(&self) -> Option<Correlation> {
+        Some(match self {
+            Request::Connect(_) => Correlation::Connect,
+            Request::JoinChan(chan) => Correlation::Join(chan.clone()),
+            Request::LeaveChan(chan) => Correlation::Leave(chan.clone()),
+            Request::Stats | Request::StatsOf(_) => Correlation::Stats,
+            Request::Ping(value) => Correlation::Ping(*value),
+            Request::WhoIs(user) => Correlation::WhoIs(user.clone()),
+            Request::ListChans => Correlation::ListChans,
+            Request::Capabilities => Correlation::Capabilities,
+            Request::Ghost { nickname, .. } => Correlation::Ghost(nickname.clone()),
+            Request::WatchKeyword { chan, .. } | Request::UnwatchKeyword { chan, .. } => {
+                Correlation::Keywords(chan.clone())
+            }
+            Request::Names(chan) => Correlation::Names(chan.clone()),
+            Request::Block(_) | Request::Unblock(_) => Correlation::BlockList,
+            Request::FetchHistory { chan, .. } => Correlation::History(chan.clone()),
+            Request::SubscribeStats => Correlation::ServerStats,
+            Request::UnsubscribeStats => Correlation::Ack,
+            Request::MessageMany { .. } => Correlation::Delivery,
+            Request::Message { .. }
+            | Request::Pong(_)
+            | Request::GhostKey(_)
+            | Request::TransferOp { .. }
+            | Request::ClaimOp(_)
+            | Request::SetWelcome { .. }
+            | Request::Invite { .. } => return None,
+        })
+    }
+}
+
+impl Correlate for Response {
+    type Key = Correlation;
+
+    fn correlation(&self) -> Option<Correlation> {
+        Some(match self {
+            Response::AckConnect(_) => Correlation::Connect,
+            Response::AckJoin { chan, .. } => Correlation::Join(chan.clone()),
+            Response::AckLeave(chan) => Correlation::Leave(chan.clone()),
+            Response::Stats(_) => Correlation::Stats,
+            Response::Pong(value) => Correlation::Ping(*value),
+            Response::WhoIs { user, .. } => Correlation::WhoIs(user.clone()),
+            Response::ChanList(_) => Correlation::ListChans,
+            Response::Capabilities(_) => Correlation::Capabilities,
+            Response::AckGhost(nickname) => Correlation::Ghost(nickname.clone()),
+            Response::Keywords { chan, .. } => Correlation::Keywords(chan.clone()),
+            Response::Names { chan, .. } => Correlation::Names(chan.clone()),
+            Response::BlockList(_) => Correlation::BlockList,
+            Response::History { chan, .. } => Correlation::History(chan.clone()),
+            Response::ServerStats { .. } => Correlation::ServerStats,
+            Response::Ack => Correlation::Ack,
+            Response::Delivery(_) => Correlation::Delivery,
+            _ => return None,
+        })
+    }
+}
+
+/// Rattache les réponses reçues aux requêtes envoyées, pour attendre la réponse à une requête
+/// sans supposer que c'est la prochaine reçue : un message de canal, un ping du serveur...
+/// peuvent s'intercaler.
+///
+/// Avant d'envoyer une requête, [`RequestResponder::expect`] réserve sa réponse et renvoie
+/// le [`oneshot::Receiver`] qui la recevra, à attendre avec `.await` ou `blocking_recv`. Le
+/// lecteur du canal passe chaque réponse reçue à [`RequestResponder::dispatch`], qui la remet
+/// à la requête correspondante, ou la lui rend si aucune ne l'attend.
+///
+/// Le `RequestResponder` sert aux échanges où l'appelant attend une réponse avant de
+/// continuer, comme la connexion du client. Une boucle qui réagit à chaque réponse, comme
+/// l'interface du client, n'en a pas besoin : chaque réponse nomme ce qu'elle concerne (sa
+/// [`Correlation`]). Les [`Response::Error`] n'ont pas de clé : seul un échange qui n'a
+/// qu'une requête en attente peut leur attribuer un refus.
+///
+/// Le `RequestResponder` peut être partagé entre threads (`Arc`). Le détruire, ou appeler
+/// [`RequestResponder::cancel`], fait échouer les attentes en cours, par exemple à la perte de
+/// la connexion.
+#[derive(Debug)]
+pub struct RequestResponder<R: Correlate> {
+    pending: Mutex<HashMap<R::Key, VecDeque<oneshot::Sender<R>>>>,
+}
+
+impl<R: Correlate> Default for RequestResponder<R> {
+    fn default() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<R: Correlate> RequestResponder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Réserve la réponse à `request`, à appeler avant de l'envoyer. Renvoie `None` si
+    /// `request` n'attend aucune réponse.
+    pub fn expect<Q>(&self, request: &Q) -> Option<oneshot::Receiver<R>>
+    where
+        Q: Correlate<Key = R::Key>,
+    {
+        let key = request.correlation()?;
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .push_back(tx);
+        Some(rx)
+    }
+
+    /// Remet `response` à la plus ancienne requête qui l'attend. Si aucune ne l'attend
+    /// (ou qu'elles ont toutes abandonné), `response` est rendue à l'appelant, qui la traite
+    /// comme une réponse envoyée d'elle-même par le serveur.
+    pub fn dispatch(&self, response: R) -> Option<R> {
+        let Some(key) = response.correlation() else {
+            return Some(response);
+        };
+        let mut pending = self.pending.lock().unwrap();
+        let Some(waiting) = pending.get_mut(&key) else {
+            return Some(response);
+        };
+        let mut response = response;
+        let unclaimed = loop {
+            match waiting.pop_front() {
+                Some(tx) => match tx.send(response) {
+                    Ok(()) => break None,
+                    // Attente abandonnée : la requête suivante de même clé, s'il y en a une
+                    Err(back) => response = back,
+                },
+                None => break Some(response),
+            }
+        };
+        if waiting.is_empty() {
+            pending.remove(&key);
+        }
+        unclaimed
+    }
+
+    /// Nombre de réponses attendues.
+    pub fn pending(&self) -> usize {
+        self.pending
+            .lock()
+            .unwrap()
+            .values()
+            .map(VecDeque::len)
+            .sum()
+    }
+
+    /// Abandonne toutes les attentes : les [`oneshot::Receiver`] renvoient une erreur.
+    pub fn cancel(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::{debug, trace};
+
+/// Sens d'une trame capturée par un [`WireTap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Trame capturée par un [`WireTap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapRecord {
+    pub at: SystemTime,
+    pub direction: Direction,
+    /// Taille de la trame, en-tête compris
+    pub len: usize,
+    /// Valeur de la trame (format `Debug`), ou `None` si elle n'a pas pu être décodée
+    pub value: Option<String>,
+    /// Trame telle qu'elle a transité, en-tête compris et chiffrée le cas échéant
+    pub raw: Vec<u8>,
+}
+
+/// Capture des trames reçues et envoyées par un [`crate::TypedReader`] ou un
+/// [`crate::TypedWriter`], pour déboguer une connexion. Seules les `capacity` dernières trames
+/// sont conservées ; elles peuvent être consultées à tout moment, par exemple après une erreur.
+///
+/// Chaque trame est aussi journalisée : sa valeur au niveau `debug`, ses octets au niveau
+/// `trace`. Les copies d'un `WireTap` partagent les mêmes trames : une seule capture suffit pour
+/// les deux sens d'un canal.
+#[derive(Debug, Clone)]
+pub struct WireTap {
+    records: Arc<Mutex<VecDeque<TapRecord>>>,
+    capacity: usize,
+}
+
+impl WireTap {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Trames capturées, de la plus ancienne à la plus récente.
+    pub fn records(&self) -> Vec<TapRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub(crate) fn record(&self, direction: Direction, raw: &[u8], value: Option<String>) {
+        debug!(
+            ?direction,
+            len = raw.len(),
+            value = value.as_deref(),
+            "frame"
+        );
+        trace!(?direction, ?raw, "frame bytes");
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(TapRecord {
+            at: SystemTime::now(),
+            direction,
+            len: raw.len(),
+            value,
+            raw: raw.to_vec(),
+        });
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+
+/// Connexion asynchrone sur laquelle circulent les trames mini-irc : TCP, Unix, QUIC
+/// (fonctionnalité `quinn`) ou un canal en mémoire. Elle doit pouvoir être séparée en une
+/// partie réception et une partie émission, utilisées par un [`crate::TypedChannel`].
+pub trait Transport {
+    type ReadHalf: AsyncRead + Unpin;
+    type WriteHalf: AsyncWrite + Unpin;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf);
+}
+
+impl Transport for tokio::net::TcpStream {
+    type ReadHalf = tokio::net::tcp::OwnedReadHalf;
+    type WriteHalf = tokio::net::tcp::OwnedWriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        tokio::net::TcpStream::into_split(self)
+    }
+}
+
+impl Transport for DuplexStream {
+    type ReadHalf = tokio::io::ReadHalf<DuplexStream>;
+    type WriteHalf = tokio::io::WriteHalf<DuplexStream>;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        tokio::io::split(self)
+    }
+}
+
+#[cfg(unix)]
+impl Transport for tokio::net::UnixStream {
+    type ReadHalf = tokio::net::unix::OwnedReadHalf;
+    type WriteHalf = tokio::net::unix::OwnedWriteHalf;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        tokio::net::UnixStream::into_split(self)
+    }
+}
+
+/// Socquette synchrone pouvant être dupliquée pour recevoir et émettre depuis deux threads,
+/// utilisée par un [`crate::SyncTypedChannel`] : TCP, ou Unix pour les déploiements locaux.
+pub trait SyncTransport: Read + Write + Sized {
+    fn try_clone(&self) -> std::io::Result<Self>;
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()>;
+}
+
+impl SyncTransport for std::net::TcpStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        std::net::TcpStream::try_clone(self)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+        std::net::TcpStream::shutdown(self, how)
+    }
+}
+
+#[cfg(unix)]
+impl SyncTransport for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> std::io::Result<Self> {
+        std::os::unix::net::UnixStream::try_clone(self)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+        std::os::unix::net::UnixStream::shutdown(self, how)
+    }
+}
+
+/// Flux bidirectionnel d'une connexion QUIC, qui apporte le chiffrement TLS et la migration de
+/// connexion (changement d'adresse du client) sans changer le protocole mini-irc.
+///
+/// # Exemple
+///
+/// ```no_run
+/// use mini_irc_protocol::{HandshakeRequest, HandshakeResponse, QuicStream, TypedChannel};
+///
+/// # async fn example(connection: quinn::Connection) {
+/// let stream = QuicStream::open(&connection).await.unwrap();
+/// let mut channel = TypedChannel::<_, HandshakeRequest, HandshakeResponse>::new(stream);
+/// # }
+/// ```
+#[cfg(feature = "quinn")]
+#[derive(Debug)]
+pub struct QuicStream {
+    send: quinn::SendStream,
+    recv: quinn::RecvStream,
+}
+
+#[cfg(feature = "quinn")]
+impl QuicStream {
+    /// Ouvre un nouveau flux sur la connexion. Le pair n'en est informé qu'à la réception de
+    /// la première trame : c'est donc au client d'ouvrir le flux.
+    pub async fn open(connection: &quinn::Connection) -> Result<Self, quinn::ConnectionError> {
+        let (send, recv) = connection.open_bi().await?;
+        Ok(Self { send, recv })
+    }
+
+    /// Attend le prochain flux ouvert par le pair.
+    pub async fn accept(connection: &quinn::Connection) -> Result<Self, quinn::ConnectionError> {
+        let (send, recv) = connection.accept_bi().await?;
+        Ok(Self { send, recv })
+    }
+}
+
+#[cfg(feature = "quinn")]
+impl Transport for QuicStream {
+    type ReadHalf = quinn::RecvStream;
+    type WriteHalf = quinn::SendStream;
+
+    fn into_split(self) -> (Self::ReadHalf, Self::WriteHalf) {
+        (self.recv, self.send)
+    }
+}
@@ -0,0 +1,52 @@
+use mini_irc_protocol::BroadcastSenderWithList;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Role {
+    #[default]
+    Member,
+    Voice,
+    Operator,
+}
+
+#[test]
+fn subscribers_keep_their_metadata() {
+    let channel = BroadcastSenderWithList::<u32, String, Role>::new(16);
+    let _carol = channel.subscribe("carol".to_string()).unwrap();
+    let _alice = channel
+        .subscribe_with("alice".to_string(), Role::Operator)
+        .unwrap();
+    let _bob = channel.subscribe("bob".to_string()).unwrap();
+    assert!(channel.subscribe("bob".to_string()).is_none());
+
+    assert!(channel.update_meta(&"bob".to_string(), |role| *role = Role::Voice));
+    assert!(!channel.update_meta(&"dave".to_string(), |role| *role = Role::Voice));
+
+    // Toujours dans l'ordre d'abonnement
+    let roles: Vec<_> = channel
+        .snapshot()
+        .into_iter()
+        .map(|subscriber| (subscriber.identity, subscriber.meta))
+        .collect();
+    assert_eq!(
+        roles,
+        [
+            ("carol".to_string(), Role::Member),
+            ("alice".to_string(), Role::Operator),
+            ("bob".to_string(), Role::Voice),
+        ]
+    );
+    assert_eq!(
+        channel.subscriber(&"alice".to_string()).map(|s| s.meta),
+        Some(Role::Operator)
+    );
+}
+
+#[test]
+fn metadata_is_forgotten_on_unsubscribe() {
+    let channel = BroadcastSenderWithList::<u32, &str, Role>::new(16);
+    let alice = channel.subscribe_with("alice", Role::Operator).unwrap();
+    drop(alice);
+    assert!(channel.subscriber(&"alice").is_none());
+    let _alice = channel.subscribe("alice").unwrap();
+    assert_eq!(channel.subscriber(&"alice").unwrap().meta, Role::Member);
+}
@@ -0,0 +1,30 @@
+use mini_irc_protocol::{BroadcastEvent, BroadcastSenderWithList};
+
+#[tokio::test]
+async fn metrics_count_sent_queued_and_dropped() {
+    let channel = BroadcastSenderWithList::<u32, &str>::new(2);
+    let mut alice = channel.subscribe("alice").unwrap();
+    let _bob = channel.subscribe("bob").unwrap();
+    for value in 0..5 {
+        channel.send(value).unwrap();
+    }
+
+    let metrics = channel.metrics();
+    assert_eq!(metrics.sent, 5);
+    assert_eq!(metrics.queued, 2);
+    assert_eq!(metrics.dropped, 0);
+    assert_eq!(metrics.subscribers, [("alice", 0), ("bob", 0)]);
+
+    // Les pertes sont comptées lorsque le récepteur s'en aperçoit
+    assert_eq!(alice.recv().await.unwrap(), BroadcastEvent::Lagged(3));
+    assert_eq!(alice.recv().await.unwrap(), BroadcastEvent::Message(3));
+    let metrics = channel.metrics();
+    assert_eq!(metrics.dropped, 3);
+    assert_eq!(metrics.subscribers, [("alice", 3), ("bob", 0)]);
+
+    // Le total reste acquis après le départ de l'abonné
+    drop(alice);
+    let metrics = channel.metrics();
+    assert_eq!(metrics.dropped, 3);
+    assert_eq!(metrics.subscribers, [("bob", 0)]);
+}
@@ -0,0 +1,89 @@
+use mini_irc_protocol::{BroadcastEvent, BroadcastSenderWithList};
+use std::sync::Arc;
+use std::time::Duration;
+
+const TASKS: usize = 400;
+const ROUNDS: usize = 20;
+
+/// Des centaines de tâches s'abonnent, diffusent et se désabonnent (par `Drop` ou
+/// `unsubscribe`) en parallèle : aucune ne doit rester bloquée, et la liste doit être vide à la
+/// fin.
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_subscribe_send_drop() {
+    let channel = Arc::new(BroadcastSenderWithList::<usize, usize>::new(16));
+
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let channel = channel.clone();
+            tokio::spawn(async move {
+                for round in 0..ROUNDS {
+                    let mut receiver = channel.subscribe(task).expect("identity subscribed twice");
+                    assert!(channel.contains(&task));
+                    let _ = channel.send(round);
+                    // Lit ce qui est disponible sans attendre les autres tâches
+                    let _ = tokio::time::timeout(Duration::from_millis(1), receiver.recv()).await;
+                    if round % 2 == 0 {
+                        drop(receiver);
+                    } else {
+                        assert!(channel.unsubscribe(&task));
+                        // Les messages diffusés avant le désabonnement restent à lire, puis le
+                        // récepteur est fermé sans attendre
+                        while let Ok(event) = receiver.recv().await {
+                            assert!(matches!(event, BroadcastEvent::Message(_)));
+                        }
+                    }
+                    assert!(!channel.contains(&task));
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+
+    tokio::time::timeout(Duration::from_secs(60), async {
+        for task in tasks {
+            task.await.unwrap();
+        }
+    })
+    .await
+    .expect("deadlock between subscribe, send and drop");
+
+    assert!(channel.is_empty());
+    assert!(channel.snapshot().is_empty());
+}
+
+/// Un récepteur abandonné pendant qu'il attend un message ne doit pas laisser son abonné dans
+/// la liste, même si le même nom se réabonne aussitôt.
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn drop_while_receiving_does_not_leak() {
+    let channel = Arc::new(BroadcastSenderWithList::<usize, usize>::new(16));
+
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let channel = channel.clone();
+            tokio::spawn(async move {
+                let mut receiver = channel.subscribe(task).unwrap();
+                let waiting = tokio::spawn(async move { receiver.recv().await });
+                tokio::task::yield_now().await;
+                waiting.abort();
+                let _ = waiting.await;
+
+                let mut receiver = channel.subscribe(task).expect("stale subscriber kept");
+                channel.send(task).unwrap();
+                assert!(matches!(
+                    receiver.recv().await,
+                    Ok(BroadcastEvent::Message(_) | BroadcastEvent::Lagged(_))
+                ));
+            })
+        })
+        .collect();
+
+    tokio::time::timeout(Duration::from_secs(60), async {
+        for task in tasks {
+            task.await.unwrap();
+        }
+    })
+    .await
+    .expect("deadlock between drop and subscribe");
+
+    assert!(channel.is_empty());
+}
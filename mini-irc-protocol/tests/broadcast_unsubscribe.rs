@@ -0,0 +1,22 @@
+use mini_irc_protocol::{BroadcastEvent, BroadcastSenderWithList};
+
+#[tokio::test]
+async fn receiver_unsubscribes_itself_only() {
+    let channel = BroadcastSenderWithList::<u32, &str>::new(16);
+    let mut old = channel.subscribe("alice").unwrap();
+    channel.send(1).unwrap();
+    assert!(old.unsubscribe());
+    assert!(!old.unsubscribe());
+    assert!(!channel.contains(&"alice"));
+
+    // Messages diffusés avant le désabonnement, puis plus rien
+    channel.send(2).unwrap();
+    assert_eq!(old.recv().await.unwrap(), BroadcastEvent::Message(1));
+    assert!(old.recv().await.is_err());
+
+    // Un nouvel abonnement de la même identité n'est pas touché par l'ancien récepteur
+    let _new = channel.subscribe("alice").unwrap();
+    assert!(!old.unsubscribe());
+    drop(old);
+    assert!(channel.contains(&"alice"));
+}
@@ -0,0 +1,60 @@
+use futures::StreamExt;
+use mini_irc_protocol::{Encrypted, HandshakeRequest, ProtocolError, TypedChannel, WireVersion};
+use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
+use tokio::io::DuplexStream;
+
+type Channel = TypedChannel<DuplexStream, HandshakeRequest, HandshakeRequest>;
+type Secure = TypedChannel<DuplexStream, HandshakeRequest, HandshakeRequest, Encrypted>;
+
+/// Canaux chiffrés, au format le plus récent commun à `a` et `b`.
+async fn secure(mut a: Channel, mut b: Channel) -> (Secure, Secure) {
+    a.send(&HandshakeRequest::Secure(vec![1])).await.unwrap();
+    b.recv().await.unwrap();
+    b.send(&HandshakeRequest::Secure(vec![2])).await.unwrap();
+    a.recv().await.unwrap();
+    let key = SharedKey::generate();
+    (a.upgrade(key.clone()), b.upgrade(key))
+}
+
+#[tokio::test]
+async fn orderly_close_is_not_an_io_error() {
+    let (a, b) = Channel::duplex(1024);
+    let (mut a, mut b) = secure(a, b).await;
+    a.send(&HandshakeRequest::Shared(vec![3])).await.unwrap();
+    a.close().await.unwrap();
+    // Les trames envoyées avant la fermeture sont reçues
+    assert_eq!(
+        b.recv().await.unwrap(),
+        Some(HandshakeRequest::Shared(vec![3]))
+    );
+    assert!(matches!(b.recv().await, Err(ProtocolError::Closed)));
+    assert!(matches!(b.recv().await, Err(ProtocolError::Closed)));
+}
+
+#[tokio::test]
+async fn lost_connection_is_an_io_error() {
+    let (a, b) = Channel::duplex(1024);
+    let (a, mut b) = secure(a, b).await;
+    drop(a);
+    assert!(matches!(b.recv().await, Err(ProtocolError::Io(_))));
+}
+
+#[tokio::test]
+async fn close_without_close_frames_only_shuts_down() {
+    let (a, mut b) = Channel::duplex(1024);
+    b.set_max_wire_version(WireVersion::V1);
+    let (mut a, mut b) = secure(a, b).await;
+    a.close().await.unwrap();
+    assert!(matches!(b.recv().await, Err(ProtocolError::Io(_))));
+}
+
+#[tokio::test]
+async fn stream_ends_on_close() {
+    let (a, b) = Channel::duplex(1024);
+    let (mut a, b) = secure(a, b).await;
+    a.send(&HandshakeRequest::Shared(vec![4])).await.unwrap();
+    a.close().await.unwrap();
+    let (reader, _writer) = b.into_split();
+    let received: Vec<_> = reader.map(Result::unwrap).collect().await;
+    assert_eq!(received, [HandshakeRequest::Shared(vec![4])]);
+}
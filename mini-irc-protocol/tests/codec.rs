@@ -0,0 +1,43 @@
+use bytes::{BufMut, BytesMut};
+use mini_irc_protocol::{HandshakeRequest, MiniIrcCodec, ProtocolError, DEFAULT_MAX_FRAME_LENGTH};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[test]
+fn oversized_frames_are_refused_before_being_received() {
+    let mut codec = MiniIrcCodec::<HandshakeRequest>::new();
+    assert_eq!(codec.max_frame_length(), DEFAULT_MAX_FRAME_LENGTH);
+    // En-tête annonçant 4 Gio, sans les données
+    let mut src = BytesMut::new();
+    src.put_u32(u32::MAX);
+    let err = codec.decode(&mut src).unwrap_err();
+    assert!(matches!(
+        err,
+        ProtocolError::FrameTooLarge { len, max }
+            if len == u32::MAX as usize && max == DEFAULT_MAX_FRAME_LENGTH
+    ));
+    // Rien n'a été réservé pour la trame annoncée
+    assert!(src.capacity() < 1024);
+}
+
+#[test]
+fn frame_length_limit_is_configurable() {
+    let mut codec = MiniIrcCodec::<HandshakeRequest>::new();
+    let mut frame = BytesMut::new();
+    codec
+        .encode(HandshakeRequest::Secure(vec![0; 64]), &mut frame)
+        .unwrap();
+
+    codec.set_max_frame_length(frame.len());
+    let mut src = frame.clone();
+    assert_eq!(
+        codec.decode(&mut src).unwrap(),
+        Some(HandshakeRequest::Secure(vec![0; 64]))
+    );
+
+    codec.set_max_frame_length(16);
+    let mut src = frame;
+    assert!(matches!(
+        codec.decode(&mut src),
+        Err(ProtocolError::FrameTooLarge { max: 16, .. })
+    ));
+}
@@ -0,0 +1,67 @@
+use mini_irc_protocol::{HandshakeRequest, Heartbeat, ProtocolError, TypedChannel};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::time::Instant;
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+#[tokio::test(start_paused = true)]
+async fn silent_peer_is_lost() {
+    let (mut a, _b) = TypedChannel::<_, HandshakeRequest, HandshakeRequest>::duplex(1024);
+    a.set_heartbeat(Heartbeat::new(INTERVAL).with_tolerance(3));
+    let start = Instant::now();
+    assert!(matches!(a.recv().await, Err(ProtocolError::PeerLost)));
+    assert_eq!(start.elapsed(), INTERVAL * 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn keepalives_keep_the_peer_alive() {
+    let (a, b) = TypedChannel::<_, HandshakeRequest, HandshakeRequest>::duplex(1024);
+    let (mut reader, _writer) = a.into_split();
+    reader.set_heartbeat(Heartbeat::new(INTERVAL));
+    let (_b_reader, mut b_writer) = b.into_split();
+    b_writer.set_heartbeat(Heartbeat::new(INTERVAL));
+    tokio::spawn(async move {
+        // Silence applicatif bien plus long que la tolérance
+        let talk = tokio::time::sleep(INTERVAL * 10);
+        tokio::pin!(talk);
+        loop {
+            tokio::select! {
+                res = b_writer.heartbeat() => res.unwrap(),
+                _ = &mut talk => break,
+            }
+        }
+        b_writer
+            .send(&HandshakeRequest::Secure(vec![1]))
+            .await
+            .unwrap();
+    });
+    // Les trames de maintien ne sont pas remontées, mais repoussent l'échéance
+    assert_eq!(
+        reader.recv().await.unwrap(),
+        Some(HandshakeRequest::Secure(vec![1]))
+    );
+}
+
+#[tokio::test]
+async fn keepalive_frames_are_ignored_without_heartbeat() {
+    let (mut a, mut b) = TypedChannel::<_, HandshakeRequest, HandshakeRequest>::duplex(1024);
+    // Trame de maintien écrite à la main, sans données
+    a.writer.stream.write_all(&[0, 0, 0, 0]).await.unwrap();
+    a.send(&HandshakeRequest::Shared(vec![2])).await.unwrap();
+    assert_eq!(
+        b.recv().await.unwrap(),
+        Some(HandshakeRequest::Shared(vec![2]))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn heartbeat_waits_for_silence() {
+    let (mut a, _b) = TypedChannel::<_, HandshakeRequest, HandshakeRequest>::duplex(1024);
+    a.set_heartbeat(Heartbeat::new(INTERVAL));
+    tokio::time::sleep(INTERVAL / 2).await;
+    a.send(&HandshakeRequest::Secure(vec![])).await.unwrap();
+    let start = Instant::now();
+    a.heartbeat().await.unwrap();
+    assert_eq!(start.elapsed(), INTERVAL);
+}
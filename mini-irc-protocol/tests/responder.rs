@@ -0,0 +1,101 @@
+use mini_irc_protocol::{Capabilities, ChanOp, ErrorCode, Request, RequestResponder, Response};
+
+fn ack_join(chan: &str) -> Response {
+    Response::AckJoin {
+        chan: chan.to_string(),
+        users: vec![],
+        owner: None,
+    }
+}
+
+#[tokio::test]
+async fn broadcast_between_request_and_ack_is_passed_through() {
+    let responder = RequestResponder::new();
+    let ack = responder
+        .expect(&Request::JoinChan("rust".to_string()))
+        .unwrap();
+
+    let broadcast = Response::Channel {
+        op: ChanOp::UserAdd("bob".to_string()),
+        chan: "general".to_string(),
+        seq: 3,
+    };
+    assert_eq!(responder.dispatch(broadcast.clone()), Some(broadcast));
+    assert_eq!(responder.dispatch(ack_join("rust")), None);
+    assert_eq!(ack.await.unwrap(), ack_join("rust"));
+    assert_eq!(responder.pending(), 0);
+}
+
+#[tokio::test]
+async fn responses_are_matched_by_content() {
+    let responder = RequestResponder::new();
+    let general = responder
+        .expect(&Request::JoinChan("general".to_string()))
+        .unwrap();
+    let pong = responder.expect(&Request::Ping(7)).unwrap();
+    let rust = responder
+        .expect(&Request::JoinChan("rust".to_string()))
+        .unwrap();
+
+    // Ordre d'arrivée différent de l'ordre d'envoi
+    assert_eq!(responder.dispatch(ack_join("rust")), None);
+    assert_eq!(responder.dispatch(Response::Pong(7)), None);
+    assert_eq!(responder.dispatch(ack_join("general")), None);
+    assert_eq!(rust.await.unwrap(), ack_join("rust"));
+    assert_eq!(pong.await.unwrap(), Response::Pong(7));
+    assert_eq!(general.await.unwrap(), ack_join("general"));
+}
+
+#[tokio::test]
+async fn same_key_requests_are_answered_in_order() {
+    let responder = RequestResponder::new();
+    let first = responder
+        .expect(&Request::Block("eve".to_string()))
+        .unwrap();
+    let second = responder
+        .expect(&Request::Unblock("eve".to_string()))
+        .unwrap();
+    let blocked = Response::BlockList(vec!["eve".to_string()]);
+    let unblocked = Response::BlockList(vec![]);
+    assert_eq!(responder.dispatch(blocked.clone()), None);
+    assert_eq!(responder.dispatch(unblocked.clone()), None);
+    assert_eq!(first.await.unwrap(), blocked);
+    assert_eq!(second.await.unwrap(), unblocked);
+}
+
+#[test]
+fn abandoned_and_unexpected_responses_are_returned() {
+    let responder = RequestResponder::new();
+    // Aucune réponse attendue
+    assert!(responder
+        .expect(&Request::GhostKey("key".to_string()))
+        .is_none());
+    assert_eq!(
+        responder.dispatch(Response::Pong(1)),
+        Some(Response::Pong(1))
+    );
+    // Les refus ne sont rattachés à aucune requête
+    let _names = responder
+        .expect(&Request::Names("general".to_string()))
+        .unwrap();
+    let refused = Response::Error(ErrorCode::NotInChannel);
+    assert_eq!(responder.dispatch(refused.clone()), Some(refused));
+    // Attente abandonnée
+    drop(responder.expect(&Request::Capabilities).unwrap());
+    assert!(responder.dispatch(Response::ChanList(vec![])).is_some());
+    assert!(responder
+        .dispatch(Response::Capabilities(Capabilities {
+            max_message_len: 512
+        }))
+        .is_some());
+    assert_eq!(responder.pending(), 1);
+}
+
+#[test]
+fn cancel_fails_pending_requests() {
+    let responder: RequestResponder<Response> = RequestResponder::new();
+    let mut stats = responder.expect(&Request::Stats).unwrap();
+    responder.cancel();
+    assert!(stats.try_recv().is_err());
+    assert_eq!(responder.pending(), 0);
+}
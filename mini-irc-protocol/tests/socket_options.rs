@@ -0,0 +1,81 @@
+use mini_irc_protocol::{HandshakeRequest, ProtocolError, SocketOptions, TypedReader, TypedWriter};
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// Les deux bouts d'une connexion TCP locale.
+fn pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, server)
+}
+
+#[test]
+fn silent_peer_times_out() {
+    let (client, _server) = pair();
+    SocketOptions::new()
+        .read_timeout(Duration::from_millis(50))
+        .keepalive(Duration::from_secs(60))
+        .apply(&client)
+        .unwrap();
+    let mut reader: TypedReader<_, HandshakeRequest> = TypedReader::new(client);
+    assert!(matches!(reader.recv(), Err(ProtocolError::TimedOut)));
+    // Le lecteur reste utilisable après l'expiration
+    assert!(matches!(reader.recv(), Err(ProtocolError::TimedOut)));
+}
+
+#[test]
+fn partial_frame_survives_timeout() {
+    let (client, server) = pair();
+    SocketOptions::new()
+        .read_timeout(Duration::from_millis(50))
+        .apply(&client)
+        .unwrap();
+    let mut reader: TypedReader<_, HandshakeRequest> = TypedReader::new(client);
+
+    // Trame complète écrite à part, puis envoyée en deux fois
+    let mut frame = Vec::new();
+    let mut writer = TypedWriter::new(&mut frame);
+    writer
+        .send(&HandshakeRequest::Shared(vec![1, 2, 3]))
+        .unwrap();
+    drop(writer);
+    let (head, tail) = frame.split_at(frame.len() / 2);
+
+    let mut server = server;
+    server.write_all(head).unwrap();
+    assert!(matches!(reader.recv(), Err(ProtocolError::TimedOut)));
+    server.write_all(tail).unwrap();
+    assert_eq!(
+        reader.recv().unwrap(),
+        Some(HandshakeRequest::Shared(vec![1, 2, 3]))
+    );
+}
+
+#[test]
+fn trickling_peer_does_not_extend_recv_timeout() {
+    let (client, server) = pair();
+    let mut reader: TypedReader<_, HandshakeRequest> = TypedReader::new(client);
+
+    // En-tête d'une trame de 1000 octets, puis un octet toutes les 20 ms : chaque lecture
+    // aboutit avant le délai
+    let trickle = std::thread::spawn(move || {
+        let mut server = server;
+        server.write_all(&1000u32.to_be_bytes()).unwrap();
+        for _ in 0..50 {
+            if server.write_all(&[0]).is_err() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    });
+    let start = std::time::Instant::now();
+    assert!(matches!(
+        reader.recv_timeout(Duration::from_millis(100)),
+        Err(ProtocolError::TimedOut)
+    ));
+    assert!(start.elapsed() < Duration::from_millis(500));
+    drop(reader);
+    trickle.join().unwrap();
+}
@@ -0,0 +1,58 @@
+#![cfg(unix)]
+
+use mini_irc_protocol::{Direction, HandshakeRequest, Plain, SyncTypedChannel, WireTap};
+use std::os::unix::net::UnixStream;
+
+type Channel = SyncTypedChannel<HandshakeRequest, HandshakeRequest, Plain, UnixStream>;
+
+#[test]
+fn frames_are_captured_in_both_directions() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let mut client = Channel::new(a).unwrap();
+    let mut server = Channel::new(b).unwrap();
+    let tap = WireTap::new(16);
+    client.set_tap(tap.clone());
+
+    client.send(&HandshakeRequest::Secure(vec![1, 2])).unwrap();
+    server.send(&HandshakeRequest::Shared(vec![3])).unwrap();
+    assert_eq!(
+        server.recv().unwrap(),
+        Some(HandshakeRequest::Secure(vec![1, 2]))
+    );
+    assert_eq!(
+        client.recv().unwrap(),
+        Some(HandshakeRequest::Shared(vec![3]))
+    );
+
+    let records = tap.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].direction, Direction::Sent);
+    assert_eq!(records[0].value.as_deref(), Some("Secure([1, 2])"));
+    assert_eq!(records[1].direction, Direction::Received);
+    assert_eq!(records[1].value.as_deref(), Some("Shared([3])"));
+    // En-tête compris
+    assert_eq!(records[1].len, records[1].raw.len());
+    assert_eq!(
+        &records[1].raw[..4],
+        &(records[1].len as u32 - 4).to_be_bytes()
+    );
+}
+
+#[test]
+fn only_the_latest_frames_are_kept() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let mut client = Channel::new(a).unwrap();
+    let _server = Channel::new(b).unwrap();
+    let tap = WireTap::new(2);
+    client.set_tap(tap.clone());
+
+    for i in 0..5 {
+        client.send(&HandshakeRequest::Secure(vec![i])).unwrap();
+    }
+    let values: Vec<_> = tap
+        .records()
+        .into_iter()
+        .filter_map(|record| record.value)
+        .collect();
+    assert_eq!(values, ["Secure([3])", "Secure([4])"]);
+}
@@ -0,0 +1,102 @@
+use mini_irc_protocol::{
+    Encrypted, FrameTag, HandshakeRequest, Heartbeat, ProtocolError, TypedChannel, WireVersion,
+};
+use serde_encrypt::{shared_key::SharedKey, AsSharedKey};
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, DuplexStream};
+
+type Channel = TypedChannel<DuplexStream, HandshakeRequest, HandshakeRequest>;
+type Secure = TypedChannel<DuplexStream, HandshakeRequest, HandshakeRequest, Encrypted>;
+
+/// Échange de trames en clair, qui porte les annonces de version, puis chiffrement.
+async fn handshake(mut a: Channel, mut b: Channel) -> (Secure, Secure) {
+    a.send(&HandshakeRequest::Secure(vec![1])).await.unwrap();
+    assert_eq!(
+        b.recv().await.unwrap(),
+        Some(HandshakeRequest::Secure(vec![1]))
+    );
+    b.send(&HandshakeRequest::Secure(vec![2])).await.unwrap();
+    assert_eq!(
+        a.recv().await.unwrap(),
+        Some(HandshakeRequest::Secure(vec![2]))
+    );
+    let key = SharedKey::generate();
+    (a.upgrade(key.clone()), b.upgrade(key))
+}
+
+async fn exchange(a: &mut Secure, b: &mut Secure) {
+    a.send(&HandshakeRequest::Shared(vec![3])).await.unwrap();
+    assert_eq!(
+        b.recv().await.unwrap(),
+        Some(HandshakeRequest::Shared(vec![3]))
+    );
+    b.send(&HandshakeRequest::Shared(vec![4])).await.unwrap();
+    assert_eq!(
+        a.recv().await.unwrap(),
+        Some(HandshakeRequest::Shared(vec![4]))
+    );
+}
+
+#[tokio::test]
+async fn peers_use_the_latest_version() {
+    let (a, b) = Channel::duplex(1024);
+    assert_eq!(a.reader.wire_version(), WireVersion::V1);
+    let (mut a, mut b) = handshake(a, b).await;
+    assert_eq!(a.reader.wire_version(), WireVersion::LATEST);
+    assert_eq!(a.writer.wire_version(), WireVersion::LATEST);
+    assert_eq!(b.writer.wire_version(), WireVersion::LATEST);
+    exchange(&mut a, &mut b).await;
+}
+
+#[tokio::test]
+async fn older_peer_is_still_understood() {
+    let (a, mut b) = Channel::duplex(1024);
+    b.set_max_wire_version(WireVersion::V1);
+    let (mut a, mut b) = handshake(a, b).await;
+    assert_eq!(a.writer.wire_version(), WireVersion::V1);
+    assert_eq!(b.reader.wire_version(), WireVersion::V1);
+    exchange(&mut a, &mut b).await;
+}
+
+#[tokio::test]
+async fn peer_without_announcement_stays_in_v1() {
+    let (mut a, mut b) = Channel::duplex(1024);
+    // Trame en clair d'un pair antérieur aux versions : la valeur seule
+    let value = bincode::serialize(&HandshakeRequest::Secure(vec![2])).unwrap();
+    b.writer
+        .stream
+        .write_all(&(value.len() as u32).to_be_bytes())
+        .await
+        .unwrap();
+    b.writer.stream.write_all(&value).await.unwrap();
+    assert_eq!(
+        a.recv().await.unwrap(),
+        Some(HandshakeRequest::Secure(vec![2]))
+    );
+    let a = a.upgrade::<HandshakeRequest, HandshakeRequest>(SharedKey::generate());
+    assert_eq!(a.writer.wire_version(), WireVersion::V1);
+}
+
+#[tokio::test]
+async fn control_frames_are_not_data() {
+    let (a, b) = Channel::duplex(1024);
+    let (mut a, mut b) = handshake(a, b).await;
+    a.set_heartbeat(Heartbeat::new(Duration::from_millis(1)));
+    a.heartbeat().await.unwrap();
+    // Changement de clé, pas encore supporté
+    a.writer
+        .stream
+        .write_all(&[0, 0, 0, 1, FrameTag::Rekey as u8])
+        .await
+        .unwrap();
+    a.send(&HandshakeRequest::Shared(vec![5])).await.unwrap();
+    assert!(matches!(
+        b.recv().await,
+        Err(ProtocolError::UnsupportedFrame(tag)) if tag == FrameTag::Rekey as u8
+    ));
+    // La trame fautive est retirée, la suite est lue normalement
+    assert_eq!(
+        b.recv().await.unwrap(),
+        Some(HandshakeRequest::Shared(vec![5]))
+    );
+}
@@ -0,0 +1,34 @@
+//! Latence de bout en bout d'un message de canal, sur la boucle locale : de l'envoi par un
+//! client à sa réception par un autre membre du canal, au travers du serveur.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mini_irc_testkit::TestServer;
+
+fn loopback(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (_server, mut alice, mut bob) = runtime.block_on(async {
+        let server = TestServer::start().await;
+        let mut alice = server.connect("alice").await;
+        let mut bob = server.connect("bob").await;
+        alice.join("general").await;
+        bob.join("general").await;
+        // Accusés d'entrée et arrivées des membres
+        alice.drain().await;
+        bob.drain().await;
+        (server, alice, bob)
+    });
+
+    c.bench_function("channel_message", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                alice.say("general", "Lorem ipsum").await;
+                bob.recv().await;
+                // L'écho de l'auteur, pour ne pas saturer sa connexion
+                alice.recv().await;
+            })
+        })
+    });
+}
+
+criterion_group!(benches, loopback);
+criterion_main!(benches);
@@ -0,0 +1,331 @@
+//! Outils pour les tests de bout en bout : un [`TestServer`] lancé dans le processus courant,
+//! sur un port éphémère ou en mémoire, et un [`TestClient`] qui déroule un scénario de
+//! requêtes et de réponses attendues. Une [`Simulation`] exécute un scénario de façon
+//! déterministe, pour reproduire les courses entre connexions.
+//!
+//! ```no_run
+//! use mini_irc_protocol::{Request, Response};
+//! use mini_irc_testkit::{Step, TestServer};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let server = TestServer::start().await;
+//! let mut alice = server.connect("alice").await;
+//! alice
+//!     .run([
+//!         Step::Send(Request::JoinChan("general".to_string())),
+//!         Step::Expect(Response::AckJoin {
+//!             chan: "general".to_string(),
+//!             users: vec!["alice".to_string()],
+//!             owner: Some("alice".to_string()),
+//!         }),
+//!         Step::ExpectNothing,
+//!     ])
+//!     .await;
+//! # }
+//! ```
+
+use anyhow::{bail, Result};
+use crypto_box::PublicKey;
+use mini_irc_protocol::{
+    ChanOp, Encrypted, ErrorCode, HandshakeRequest, HandshakeResponse, MessageReceiver,
+    ProtocolError, Request, Response, Transport, TypedChannel,
+};
+use mini_irc_server::{ChannelCapacity, Server};
+use serde_encrypt::{
+    key::key_pair::SenderKeyPair, shared_key::SharedKey, traits::SerdeEncryptPublicKey,
+    AsSharedKey, SenderCombinedKey, SenderKeyPairCore,
+};
+use serde_encrypt_core::key::key_pair::public_key::ReceiverPublicKey;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::DuplexStream;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+mod simulation;
+pub use simulation::{simulate, Simulation};
+
+/// Délai au-delà duquel une réponse attendue est considérée comme manquante
+pub const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+/// Délai pendant lequel [`Step::ExpectNothing`] vérifie qu'aucune réponse n'arrive
+pub const SILENCE: Duration = Duration::from_millis(100);
+
+/// Taille des tampons des connexions en mémoire, par défaut
+const DUPLEX_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Serveur mini-irc lancé dans le processus courant, sur un port TCP éphémère. Il est arrêté
+/// lorsque la valeur est abandonnée.
+#[derive(Debug)]
+pub struct TestServer {
+    server: Server,
+    addr: SocketAddr,
+    task: JoinHandle<Result<()>>,
+}
+
+impl TestServer {
+    /// Lance un serveur avec la capacité des canaux par défaut.
+    pub async fn start() -> Self {
+        Self::with_capacity(ChannelCapacity::default()).await
+    }
+
+    pub async fn with_capacity(capacity: ChannelCapacity) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::new(capacity);
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.serve_tcp(listener).await }
+        });
+        Self { server, addr, task }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Ouvre une connexion TCP et établit le chiffrement, sans se connecter.
+    pub async fn client(&self) -> TestClient<TcpStream> {
+        let stream = TcpStream::connect(self.addr).await.unwrap();
+        TestClient::handshake(stream).await.unwrap()
+    }
+
+    /// Comme [`TestServer::client`], mais sans socket : la connexion est traitée par le même
+    /// serveur au travers d'un [`DuplexStream`].
+    pub async fn client_in_memory(&self) -> TestClient<DuplexStream> {
+        let (client, server) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+        self.server.spawn(server);
+        TestClient::handshake(client).await.unwrap()
+    }
+
+    /// Ouvre une connexion TCP et se connecte sous le nom `nickname`.
+    pub async fn connect(&self, nickname: &str) -> TestClient<TcpStream> {
+        let mut client = self.client().await;
+        client.login(nickname).await;
+        client
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Établit le chiffrement sur `stream`, comme le client mini-irc, et renvoie le canal chiffré
+/// une fois l'accusé de réception du serveur reçu.
+pub async fn handshake<S>(stream: S) -> Result<TypedChannel<S, Request, Response, Encrypted>>
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    let mut channel = exchange_keys(stream).await?;
+    match channel.recv().await? {
+        Some(Response::Ack) => Ok(channel),
+        other => bail!("unexpected handshake acknowledgement: {:?}", other),
+    }
+}
+
+/// Établit le chiffrement sur `stream`, comme [`handshake`], en attendant que le serveur refuse
+/// la connexion : renvoie la raison du refus, une fois la connexion fermée.
+pub async fn refusal<S>(stream: S) -> Result<ErrorCode>
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    let mut channel = exchange_keys(stream).await?;
+    let Some(Response::Error(code)) = channel.recv().await? else {
+        bail!("the server accepted the connection");
+    };
+    match channel.recv().await {
+        Ok(None) | Err(ProtocolError::Closed) => Ok(code),
+        other => bail!("connection still open after refusal: {:?}", other),
+    }
+}
+
+/// Opération `op` sur le canal `general`, de numéro de séquence `seq`.
+pub fn chan(op: ChanOp, seq: u64) -> Response {
+    chan_in("general", op, seq)
+}
+
+/// Opération `op` sur le canal `chan`, de numéro de séquence `seq`.
+pub fn chan_in(chan: &str, op: ChanOp, seq: u64) -> Response {
+    Response::Channel {
+        op,
+        chan: chan.to_string(),
+        seq,
+    }
+}
+
+/// Réponse d'erreur de code `code`.
+pub fn error(code: ErrorCode) -> Response {
+    Response::Error(code)
+}
+
+// Échange de clés, jusqu'au passage au canal chiffré
+async fn exchange_keys<S>(stream: S) -> Result<TypedChannel<S, Request, Response, Encrypted>>
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    let mut channel = TypedChannel::<_, HandshakeRequest, HandshakeResponse>::new(stream);
+
+    let key_pair = SenderKeyPair::generate();
+    channel
+        .send(&HandshakeRequest::Secure(
+            key_pair.public_key().as_ref().as_bytes().to_vec(),
+        ))
+        .await?;
+    let Some(HandshakeResponse::Secure(key)) = channel.recv().await? else {
+        bail!("connection closed during handshake");
+    };
+    let key_bytes: [u8; 32] = key.as_slice().try_into()?;
+    let public_key = ReceiverPublicKey::from(PublicKey::from(key_bytes));
+
+    let combined = SenderCombinedKey::new(key_pair.private_key(), &public_key);
+    let shared = SharedKey::generate();
+    let encrypted_shared_key = shared.clone().encrypt(&combined)?;
+    channel
+        .send(&HandshakeRequest::Shared(encrypted_shared_key.serialize()))
+        .await?;
+
+    Ok(channel.upgrade::<Request, Response>(shared))
+}
+
+/// Étape d'un scénario déroulé par [`TestClient::run`].
+#[derive(Debug)]
+pub enum Step {
+    /// Envoie une requête au serveur
+    Send(Request),
+    /// Attend la prochaine réponse, qui doit être celle-ci
+    Expect(Response),
+    /// Vérifie qu'aucune réponse n'arrive pendant [`SILENCE`]
+    ExpectNothing,
+    /// Attend que le serveur ferme la connexion proprement, sans autre réponse
+    ExpectClosed,
+}
+
+/// Client de test, dont le chiffrement est établi. Les attentes non satisfaites font échouer
+/// le test (panique). Les [`Response::Ping`] du serveur reçoivent une réponse automatiquement,
+/// sans être rapportés.
+#[derive(Debug)]
+pub struct TestClient<S = TcpStream>
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    channel: TypedChannel<S, Request, Response, Encrypted>,
+}
+
+impl<S> TestClient<S>
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    /// Établit le chiffrement sur `stream`, voir [`handshake`].
+    pub async fn handshake(stream: S) -> Result<Self> {
+        Ok(Self {
+            channel: handshake(stream).await?,
+        })
+    }
+
+    pub async fn send(&mut self, request: Request) {
+        self.channel.send(&request).await.unwrap();
+    }
+
+    // Prochaine réponse du serveur, en répondant aux pings au passage
+    async fn next(&mut self) -> Result<Option<Response>, ProtocolError> {
+        loop {
+            match self.channel.recv().await? {
+                Some(Response::Ping(id)) => self.channel.send(&Request::Pong(id)).await?,
+                response => return Ok(response),
+            }
+        }
+    }
+
+    /// Attend la prochaine réponse du serveur, au plus [`RECV_TIMEOUT`].
+    pub async fn recv(&mut self) -> Response {
+        match tokio::time::timeout(RECV_TIMEOUT, self.next()).await {
+            Ok(Ok(Some(response))) => response,
+            Ok(Ok(None)) | Ok(Err(ProtocolError::Closed)) => {
+                panic!("connection closed by the server")
+            }
+            Ok(Err(e)) => panic!("cannot receive a response: {e}"),
+            Err(_) => panic!("no response after {RECV_TIMEOUT:?}"),
+        }
+    }
+
+    /// Reçoit toutes les réponses qui arrivent avant un silence de [`SILENCE`].
+    pub async fn drain(&mut self) -> Vec<Response> {
+        let mut responses = Vec::new();
+        while let Ok(response) = tokio::time::timeout(SILENCE, self.next()).await {
+            match response {
+                Ok(Some(response)) => responses.push(response),
+                Ok(None) | Err(ProtocolError::Closed) => break,
+                Err(e) => panic!("cannot receive a response: {e}"),
+            }
+        }
+        responses
+    }
+
+    /// Déroule les étapes dans l'ordre.
+    pub async fn run(&mut self, script: impl IntoIterator<Item = Step>) {
+        for step in script {
+            match step {
+                Step::Send(request) => self.send(request).await,
+                Step::Expect(expected) => assert_eq!(self.recv().await, expected),
+                Step::ExpectNothing => {
+                    if let Ok(response) = tokio::time::timeout(SILENCE, self.next()).await {
+                        panic!("unexpected response: {response:?}");
+                    }
+                }
+                Step::ExpectClosed => match tokio::time::timeout(RECV_TIMEOUT, self.next()).await {
+                    Ok(Err(ProtocolError::Closed)) => {}
+                    Ok(response) => panic!("expected the connection to be closed: {response:?}"),
+                    Err(_) => panic!("connection still open after {RECV_TIMEOUT:?}"),
+                },
+            }
+        }
+    }
+
+    /// Se connecte sous le nom `nickname`, qui doit être libre.
+    pub async fn login(&mut self, nickname: &str) {
+        self.run([
+            Step::Send(Request::Connect(nickname.to_string())),
+            Step::Expect(Response::AckConnect(nickname.to_string())),
+        ])
+        .await;
+    }
+
+    pub async fn join(&mut self, channel: &str) {
+        self.send(Request::JoinChan(channel.to_string())).await;
+    }
+
+    pub async fn leave(&mut self, channel: &str) {
+        self.send(Request::LeaveChan(channel.to_string())).await;
+    }
+
+    /// Envoie un message au canal `channel`.
+    pub async fn say(&mut self, channel: &str, content: &str) {
+        self.send(Request::Message {
+            to: MessageReceiver::Channel(channel.to_string()),
+            content: content.to_string(),
+        })
+        .await;
+    }
+
+    /// Envoie un message direct à l'utilisateur `nickname`.
+    pub async fn whisper(&mut self, nickname: &str, content: &str) {
+        self.send(Request::Message {
+            to: MessageReceiver::User(nickname.to_string()),
+            content: content.to_string(),
+        })
+        .await;
+    }
+}
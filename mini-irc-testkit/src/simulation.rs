@@ -0,0 +1,190 @@
+use crate::{refusal, TestClient, DUPLEX_BUFFER_SIZE};
+use mini_irc_protocol::ErrorCode;
+use mini_irc_server::{ChannelCapacity, Dnsbl, Server};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::DuplexStream;
+
+/// Serveur simulé : les connexions passent par des [`DuplexStream`] en mémoire, et tout
+/// s'exécute sur un unique thread dont l'horloge est contrôlée (voir [`simulate`]).
+///
+/// Les tâches du serveur et des clients sont alors ordonnancées toujours de la même façon, et
+/// le temps n'avance que lorsque plus aucune tâche ne peut progresser : une attente de réponse
+/// expire instantanément, et une même course se reproduit à chaque exécution. Seules les clés
+/// de chiffrement restent aléatoires, ce qui n'influe pas sur l'ordonnancement.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    server: Server,
+    buffer_size: usize,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self::with_capacity(ChannelCapacity::default())
+    }
+
+    pub fn with_capacity(capacity: ChannelCapacity) -> Self {
+        Self {
+            server: Server::new(capacity),
+            buffer_size: DUPLEX_BUFFER_SIZE,
+        }
+    }
+
+    /// Désigne les administrateurs du serveur simulé, voir [`Server::with_admins`].
+    pub fn admins(mut self, admins: &[&str]) -> Self {
+        self.server = self
+            .server
+            .with_admins(admins.iter().map(|admin| admin.to_string()));
+        self
+    }
+
+    /// Crée des canaux système, rejoints par tous à la connexion, voir
+    /// [`Server::with_system_channels`].
+    pub fn system_channels(mut self, channels: &[&str]) -> Self {
+        self.server = self
+            .server
+            .with_system_channels(channels.iter().map(|channel| channel.to_string()));
+        self
+    }
+
+    /// Réserve aux canaux système les noms commençant par `prefixes`, voir
+    /// [`Server::with_system_prefixes`].
+    pub fn system_prefixes(mut self, prefixes: &[&str]) -> Self {
+        self.server = self
+            .server
+            .with_system_prefixes(prefixes.iter().map(|prefix| prefix.to_string()));
+        self
+    }
+
+    /// Marque absents les utilisateurs inactifs, voir [`Server::with_away_after`].
+    pub fn away_after(mut self, idle: Duration) -> Self {
+        self.server = self.server.with_away_after(idle);
+        self
+    }
+
+    /// Limite le débit des messages directs de chaque connexion, voir [`Server::with_dm_rate`].
+    pub fn dm_rate(mut self, max: usize, per: Duration) -> Self {
+        self.server = self.server.with_dm_rate(max, per);
+        self
+    }
+
+    /// Envoie périodiquement les statistiques du serveur, voir
+    /// [`Server::with_stats_interval`].
+    pub fn stats_interval(mut self, interval: Duration) -> Self {
+        self.server = self.server.with_stats_interval(interval);
+        self
+    }
+
+    /// Fait perdre leurs droits aux propriétaires de canaux absents, voir
+    /// [`Server::with_owner_expiry`].
+    pub fn owner_expiry(mut self, after: Duration) -> Self {
+        self.server = self.server.with_owner_expiry(after);
+        self
+    }
+
+    /// Limite le nombre de connexions simultanées, voir [`Server::with_max_connections`].
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.server = self.server.with_max_connections(max);
+        self
+    }
+
+    /// Vérifie l'adresse des connexions ouvertes par [`Simulation::open_from`], voir
+    /// [`Server::with_dnsbl`].
+    pub fn dnsbl(mut self, dnsbl: Dnsbl) -> Self {
+        self.server = self.server.with_dnsbl(dnsbl);
+        self
+    }
+
+    /// Réserve les connexions aux administrateurs, voir [`Server::admin_only`].
+    pub fn admin_only(mut self) -> Self {
+        self.server = self.server.admin_only();
+        self
+    }
+
+    /// Fixe la taille des tampons des connexions suivantes, dans chaque direction. Un petit
+    /// tampon simule un client lent ou un réseau saturé.
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Ouvre une connexion et établit le chiffrement, sans se connecter.
+    pub async fn client(&self) -> TestClient<DuplexStream> {
+        let (client, server) = tokio::io::duplex(self.buffer_size);
+        self.server.spawn(server);
+        TestClient::handshake(client).await.unwrap()
+    }
+
+    /// Ouvre une connexion venant de l'adresse `peer`, sans échange de clés : à passer par
+    /// exemple à [`TestClient::handshake`] ou à [`refusal`].
+    pub fn open_from(&self, peer: SocketAddr) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(self.buffer_size);
+        self.server.spawn_from_addr(server, peer);
+        client
+    }
+
+    /// Ouvre une connexion venant d'un répartiteur de charge, sans échange de clés : l'en-tête
+    /// PROXY est à écrire en premier, voir [`Server::spawn_proxied`].
+    pub fn open_proxied(&self) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(self.buffer_size);
+        self.server.spawn_proxied(server, "proxy".to_string());
+        client
+    }
+
+    /// Ouvre une connexion que le serveur doit refuser dès l'échange de clés, et renvoie la
+    /// raison du refus (voir [`refusal`]).
+    pub async fn refused(&self) -> ErrorCode {
+        let (client, server) = tokio::io::duplex(self.buffer_size);
+        self.server.spawn(server);
+        refusal(client).await.unwrap()
+    }
+
+    /// Ouvre une connexion et se connecte sous le nom `nickname`.
+    pub async fn connect(&self, nickname: &str) -> TestClient<DuplexStream> {
+        let mut client = self.client().await;
+        client.login(nickname).await;
+        client
+    }
+
+    /// Attend que plus aucune tâche ne puisse progresser, par exemple qu'un message soit
+    /// transmis à tous les membres d'un canal. L'horloge avance d'une milliseconde.
+    pub async fn settle(&self) {
+        // L'horloge en pause n'avance que lorsque toutes les tâches sont bloquées
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    /// Fait avancer l'horloge simulée de `duration`.
+    pub async fn advance(&self, duration: Duration) {
+        tokio::time::advance(duration).await;
+    }
+}
+
+/// Exécute `scenario` sur un runtime à un seul thread dont l'horloge est en pause.
+///
+/// ```
+/// use mini_irc_testkit::{simulate, Step};
+///
+/// simulate(|sim| async move {
+///     let mut alice = sim.connect("alice").await;
+///     alice.run([Step::ExpectNothing]).await;
+/// });
+/// ```
+pub fn simulate<F, Fut>(scenario: F) -> Fut::Output
+where
+    F: FnOnce(Simulation) -> Fut,
+    Fut: Future,
+{
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(scenario(Simulation::new()))
+}
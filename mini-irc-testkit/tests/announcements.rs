@@ -0,0 +1,88 @@
+use mini_irc_protocol::{
+    ChanOp, ErrorCode, MessageReceiver, Notice, NoticeScope, Request, Response,
+};
+use mini_irc_testkit::{simulate, Step};
+
+fn announce(to: MessageReceiver, content: &str) -> Request {
+    Request::Message {
+        to,
+        content: content.to_string(),
+    }
+}
+
+/// `$all` atteint chaque utilisateur connecté, annonceur compris, par un avis du serveur.
+#[test]
+fn admins_announce_to_everyone() {
+    simulate(|sim| async move {
+        let sim = sim.admins(&["root"]);
+        let mut root = sim.connect("root").await;
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+
+        alice
+            .run([
+                Step::Send(announce(MessageReceiver::All, "coucou")),
+                Step::Expect(Response::Error(ErrorCode::PermissionDenied)),
+            ])
+            .await;
+        root.send(announce(MessageReceiver::All, "redémarrage à 22h"))
+            .await;
+        let notice = Response::Notice {
+            scope: NoticeScope::Server,
+            notice: Notice::Announcement {
+                from: "root".to_string(),
+                content: "redémarrage à 22h".to_string(),
+            },
+        };
+        for client in [&mut root, &mut alice, &mut bob] {
+            client.run([Step::Expect(notice.clone())]).await;
+        }
+        bob.run([Step::ExpectNothing]).await;
+    });
+}
+
+/// `$#motif` diffuse le message dans chaque canal correspondant, sans que l'administrateur
+/// en soit membre.
+#[test]
+fn admins_announce_to_matching_channels() {
+    simulate(|sim| async move {
+        let sim = sim.admins(&["root"]);
+        let mut root = sim.connect("root").await;
+        let mut alice = sim.connect("alice").await;
+        for chan in ["dev-rust", "dev-go", "general"] {
+            alice.join(chan).await;
+        }
+        sim.settle().await;
+        alice.drain().await;
+
+        let pattern = MessageReceiver::Channels("dev-*".to_string());
+        alice
+            .run([
+                Step::Send(announce(pattern.clone(), "gel du code")),
+                Step::Expect(Response::Error(ErrorCode::PermissionDenied)),
+            ])
+            .await;
+        root.run([
+            Step::Send(announce(pattern, "gel du code")),
+            Step::Send(announce(
+                MessageReceiver::Channels("ops-*".to_string()),
+                "gel du code",
+            )),
+            Step::Expect(Response::Error(ErrorCode::UnknownChannel(
+                "ops-*".to_string(),
+            ))),
+        ])
+        .await;
+        let message = |chan: &str| Response::Channel {
+            op: ChanOp::Message {
+                from: "root".to_string(),
+                content: "gel du code".to_string(),
+            },
+            chan: chan.to_string(),
+            seq: 2,
+        };
+        let mut received = alice.drain().await;
+        received.sort_by_key(|response| format!("{response:?}"));
+        assert_eq!(received, [message("dev-go"), message("dev-rust")]);
+    });
+}
@@ -0,0 +1,133 @@
+use mini_irc_protocol::{ErrorCode, MessageReceiver, Request, Response, MAX_BLOCKED};
+use mini_irc_testkit::{simulate, Step};
+use std::time::Duration;
+
+fn dm(to: &str, content: &str) -> Request {
+    Request::Message {
+        to: MessageReceiver::User(to.to_string()),
+        content: content.to_string(),
+    }
+}
+
+fn received(from: &str, content: &str) -> Response {
+    Response::DirectMessage {
+        from: from.to_string(),
+        content: content.to_string(),
+    }
+}
+
+/// Les messages directs d'un utilisateur bloqué sont refusés à la source, jusqu'à la fin de la
+/// session du destinataire.
+#[test]
+fn blocked_users_cannot_send_direct_messages() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice
+            .run([
+                Step::Send(Request::Block("alice".to_string())),
+                Step::Expect(Response::Error(ErrorCode::CannotBlockSelf)),
+                Step::Send(Request::Block("bob".to_string())),
+                Step::Expect(Response::BlockList(vec!["bob".to_string()])),
+                Step::Send(Request::Block("carol".to_string())),
+                Step::Expect(Response::BlockList(vec![
+                    "bob".to_string(),
+                    "carol".to_string(),
+                ])),
+            ])
+            .await;
+        bob.run([
+            Step::Send(dm("alice", "coucou")),
+            Step::Expect(Response::Error(ErrorCode::Blocked)),
+        ])
+        .await;
+
+        alice
+            .run([
+                Step::Send(Request::Unblock("bob".to_string())),
+                Step::Expect(Response::BlockList(vec!["carol".to_string()])),
+            ])
+            .await;
+        bob.send(dm("alice", "coucou")).await;
+        alice.run([Step::Expect(received("bob", "coucou"))]).await;
+
+        // La session suivante du même nom n'hérite pas de la liste
+        alice
+            .run([
+                Step::Send(Request::Block("bob".to_string())),
+                Step::Expect(Response::BlockList(vec![
+                    "bob".to_string(),
+                    "carol".to_string(),
+                ])),
+            ])
+            .await;
+        drop(alice);
+        sim.settle().await;
+        let mut alice = sim.connect("alice").await;
+        bob.send(dm("alice", "re")).await;
+        alice
+            .run([
+                Step::Expect(received("bob", "re")),
+                Step::Send(Request::Unblock("carol".to_string())),
+                Step::Expect(Response::BlockList(Vec::new())),
+            ])
+            .await;
+    });
+}
+
+/// Une liste de blocage est limitée à [`MAX_BLOCKED`] utilisateurs.
+#[test]
+fn block_lists_are_capped() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        for n in 0..MAX_BLOCKED {
+            alice.send(Request::Block(format!("user{n}"))).await;
+            assert!(matches!(alice.recv().await, Response::BlockList(_)));
+        }
+        alice
+            .run([
+                Step::Send(Request::Block("extra".to_string())),
+                Step::Expect(Response::Error(ErrorCode::TooManyBlocked(MAX_BLOCKED))),
+            ])
+            .await;
+        // Bloquer de nouveau un utilisateur déjà bloqué ne fait pas grandir la liste
+        alice.send(Request::Block("user0".to_string())).await;
+        match alice.recv().await {
+            Response::BlockList(blocked) => assert_eq!(blocked.len(), MAX_BLOCKED),
+            response => panic!("unexpected response: {response:?}"),
+        }
+    });
+}
+
+/// Au-delà du débit permis, un message direct est refusé avec le délai avant que la fenêtre
+/// se libère.
+#[test]
+fn direct_messages_are_rate_limited() {
+    simulate(|sim| async move {
+        let sim = sim.dm_rate(2, Duration::from_secs(10));
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        bob.send(dm("alice", "un")).await;
+        sim.settle().await;
+        sim.advance(Duration::from_secs(4)).await;
+        bob.send(dm("alice", "deux")).await;
+        bob.send(dm("alice", "trois")).await;
+        // L'horloge simulée avance un peu pendant le traitement
+        match bob.recv().await {
+            Response::RateLimited { retry_after_ms } => {
+                assert!((5900..=6000).contains(&retry_after_ms), "{retry_after_ms}")
+            }
+            response => panic!("unexpected response: {response:?}"),
+        }
+        alice
+            .run([
+                Step::Expect(received("bob", "un")),
+                Step::Expect(received("bob", "deux")),
+            ])
+            .await;
+
+        sim.advance(Duration::from_secs(6)).await;
+        bob.send(dm("alice", "trois")).await;
+        alice.run([Step::Expect(received("bob", "trois"))]).await;
+    });
+}
@@ -0,0 +1,27 @@
+use mini_irc_protocol::ErrorCode;
+use mini_irc_testkit::{simulate, Step};
+use std::time::Duration;
+
+/// Au-delà du nombre maximal de connexions, le client apprend quand réessayer, et une place
+/// libérée peut être reprise.
+#[test]
+fn full_server_refuses_with_retry_hint() {
+    simulate(|sim| async move {
+        let sim = sim.max_connections(2);
+        let alice = sim.connect("alice").await;
+        // Une connexion compte dès l'échange de clés
+        let mut pending = sim.client().await;
+        assert_eq!(
+            sim.refused().await,
+            ErrorCode::ServerFull {
+                retry_after: Duration::from_secs(10)
+            }
+        );
+        pending.run([Step::ExpectNothing]).await;
+
+        drop(alice);
+        sim.settle().await;
+        sim.connect("bob").await.run([Step::ExpectNothing]).await;
+        assert!(matches!(sim.refused().await, ErrorCode::ServerFull { .. }));
+    });
+}
@@ -0,0 +1,58 @@
+use mini_irc_protocol::ErrorCode;
+use mini_irc_server::{Dnsbl, Resolver};
+use mini_irc_testkit::{refusal, simulate, Step, TestClient};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const LISTED: &str = "192.0.2.1:4000";
+const CLEAN: &str = "192.0.2.2:4000";
+
+// Zone qui liste seulement l'adresse de `LISTED`
+fn dnsbl() -> Dnsbl {
+    let resolver: Resolver = Arc::new(|name| {
+        let answer = if name == "1.2.0.192.dnsbl.example" {
+            Ok(vec![IpAddr::from([127, 0, 0, 2])])
+        } else {
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        };
+        Box::pin(async move { answer })
+    });
+    Dnsbl::new(["dnsbl.example".to_string()]).with_resolver(resolver)
+}
+
+fn addr(addr: &str) -> SocketAddr {
+    addr.parse().unwrap()
+}
+
+/// Un client listé apprend quelle zone le liste ; les autres se connectent normalement.
+#[test]
+fn listed_clients_are_refused() {
+    simulate(|sim| async move {
+        let sim = sim.dnsbl(dnsbl());
+        let refused = refusal(sim.open_from(addr(LISTED))).await.unwrap();
+        assert_eq!(refused, ErrorCode::Blocklisted("dnsbl.example".to_string()));
+
+        let mut client = TestClient::handshake(sim.open_from(addr(CLEAN)))
+            .await
+            .unwrap();
+        client.login("alice").await;
+        client.run([Step::ExpectNothing]).await;
+    });
+}
+
+/// Un client listé ralenti n'obtient aucune réponse avant la fermeture de sa connexion.
+#[test]
+fn listed_clients_are_tarpitted() {
+    simulate(|sim| async move {
+        let delay = Duration::from_secs(30);
+        let sim = sim.dnsbl(dnsbl().with_tarpit(delay));
+        let started = Instant::now();
+        assert!(TestClient::handshake(sim.open_from(addr(LISTED)))
+            .await
+            .is_err());
+        assert!(started.elapsed() >= delay);
+    });
+}
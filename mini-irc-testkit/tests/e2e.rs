@@ -0,0 +1,563 @@
+use mini_irc_protocol::{
+    Capabilities, ChanInfo, ChanOp, ErrorCode, HandshakeRequest, HandshakeResponse,
+    MessageReceiver, Request, Response, TypedChannel,
+};
+use mini_irc_testkit::{chan, error, Step, TestClient, TestServer, RECV_TIMEOUT};
+use tokio::net::TcpStream;
+
+fn message(from: &str, content: &str, seq: u64) -> Response {
+    chan(
+        ChanOp::Message {
+            from: from.to_string(),
+            content: content.to_string(),
+        },
+        seq,
+    )
+}
+
+// Le premier membre est le créateur du canal, qui en est propriétaire
+fn ack_join(users: &[&str]) -> Response {
+    Response::AckJoin {
+        chan: "general".to_string(),
+        users: users.iter().map(|user| user.to_string()).collect(),
+        owner: users.first().map(|user| user.to_string()),
+    }
+}
+
+#[tokio::test]
+async fn handshake() {
+    let server = TestServer::start().await;
+    // `client` vérifie l'accusé de réception chiffré du serveur
+    let mut client = server.client().await;
+    client.run([Step::ExpectNothing]).await;
+
+    let mut client = server.client_in_memory().await;
+    client
+        .run([
+            Step::Send(Request::Connect("alice".to_string())),
+            Step::Expect(Response::AckConnect("alice".to_string())),
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn handshake_out_of_order_is_rejected() {
+    let server = TestServer::start().await;
+    let stream = TcpStream::connect(server.addr()).await.unwrap();
+    let mut channel = TypedChannel::<_, HandshakeRequest, HandshakeResponse>::new(stream);
+    channel
+        .send(&HandshakeRequest::Shared(vec![0; 32]))
+        .await
+        .unwrap();
+    let closed = tokio::time::timeout(RECV_TIMEOUT, channel.recv())
+        .await
+        .expect("the server should close the connection");
+    assert!(matches!(closed, Ok(None) | Err(_)));
+}
+
+#[tokio::test]
+async fn connect_and_nick_collision() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    alice
+        .run([
+            Step::Send(Request::Connect("alice2".to_string())),
+            Step::Expect(error(ErrorCode::AlreadyConnected)),
+        ])
+        .await;
+
+    let mut impostor = server.client().await;
+    impostor
+        .run([
+            Step::Send(Request::Connect("alice".to_string())),
+            Step::Expect(error(ErrorCode::NickInUse)),
+            Step::Send(Request::Connect("bob".to_string())),
+            Step::Expect(Response::AckConnect("bob".to_string())),
+        ])
+        .await;
+
+    // Le nom est libéré à la déconnexion
+    drop(alice);
+    let mut client = server.client().await;
+    for _ in 0..50 {
+        client.send(Request::Connect("alice".to_string())).await;
+        if client.recv().await == Response::AckConnect("alice".to_string()) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("alice was not released after disconnection");
+}
+
+#[tokio::test]
+async fn reserved_and_malformed_nicknames_are_refused() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+    client
+        .run([
+            Step::Send(Request::Connect("".to_string())),
+            Step::Expect(error(ErrorCode::EmptyNickname)),
+            Step::Send(Request::Connect("Admin".to_string())),
+            Step::Expect(error(ErrorCode::ReservedNickname("Admin".to_string()))),
+            Step::Send(Request::Connect("#general".to_string())),
+            Step::Expect(error(ErrorCode::ChannelPrefixInNickname)),
+            Step::Send(Request::Connect("a b".to_string())),
+            Step::Expect(error(ErrorCode::WhitespaceInNickname)),
+            Step::Send(Request::Connect("administrator".to_string())),
+            Step::Expect(Response::AckConnect("administrator".to_string())),
+        ])
+        .await;
+}
+#[tokio::test]
+async fn ghost_session_is_taken_over() {
+    let server = TestServer::start().await;
+    let mut bob = server.connect("bob").await;
+    bob.join("general").await;
+    bob.run([
+        Step::Expect(ack_join(&["bob"])),
+        Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 1)),
+    ])
+    .await;
+
+    let mut alice = server.connect("alice").await;
+    alice
+        .run([
+            Step::Send(Request::GhostKey("secret".to_string())),
+            Step::Send(Request::JoinChan("general".to_string())),
+            Step::Expect(ack_join(&["bob", "alice"])),
+            Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 2)),
+        ])
+        .await;
+    bob.run([Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 2))])
+        .await;
+
+    let ghost = |nickname: &str, key: &str| Request::Ghost {
+        nickname: nickname.to_string(),
+        key: key.to_string(),
+    };
+    let mut again = server.client().await;
+    again
+        .run([
+            Step::Send(ghost("alice", "guess")),
+            Step::Expect(error(ErrorCode::PermissionDenied)),
+            // bob n'a pas enregistré de clé
+            Step::Send(ghost("bob", "")),
+            Step::Expect(error(ErrorCode::PermissionDenied)),
+            Step::Send(ghost("carol", "secret")),
+            Step::Expect(error(ErrorCode::UnknownUser("carol".to_string()))),
+            Step::Send(ghost("alice", "secret")),
+            Step::Expect(Response::AckGhost("alice".to_string())),
+        ])
+        .await;
+    alice
+        .run([
+            Step::Expect(error(ErrorCode::SessionTakenOver)),
+            Step::ExpectClosed,
+        ])
+        .await;
+    bob.run([Step::Expect(chan(ChanOp::UserDel("alice".to_string()), 3))])
+        .await;
+
+    // Le nom est libre, et la nouvelle session rejoint le canal normalement
+    again.login("alice").await;
+    again
+        .run([
+            Step::Send(Request::JoinChan("general".to_string())),
+            Step::Expect(ack_join(&["bob", "alice"])),
+            Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 4)),
+        ])
+        .await;
+    bob.run([Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 4))])
+        .await;
+}
+
+#[tokio::test]
+async fn client_pings_are_answered() {
+    let server = TestServer::start().await;
+    // Avant même l'identification, pour mesurer la latence dès la connexion
+    let mut client = server.client().await;
+    client
+        .run([
+            Step::Send(Request::Ping(7)),
+            Step::Expect(Response::Pong(7)),
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn requests_require_connection() {
+    let server = TestServer::start().await;
+    let mut client = server.client().await;
+    client
+        .run([
+            Step::Send(Request::JoinChan("general".to_string())),
+            Step::Expect(error(ErrorCode::NotConnected)),
+            Step::Send(Request::Message {
+                to: MessageReceiver::User("bob".to_string()),
+                content: "salut".to_string(),
+            }),
+            Step::Expect(error(ErrorCode::NotConnected)),
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn list_channels() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    let mut bob = server.connect("bob").await;
+    alice.join("general").await;
+    alice.join("rust").await;
+    bob.join("general").await;
+    alice.leave("rust").await;
+    alice.drain().await;
+    bob.drain().await;
+
+    let info = |name: &str, users| ChanInfo {
+        name: name.to_string(),
+        users,
+        topic: None,
+    };
+    // Le canal rust, vide, n'est pas listé
+    bob.run([
+        Step::Send(Request::ListChans),
+        Step::Expect(Response::ChanList(vec![info("general", 2)])),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn channel_names() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    let mut bob = server.connect("bob").await;
+    alice.join("general").await;
+    bob.join("general").await;
+    alice.join("rust").await;
+    alice.leave("rust").await;
+    alice.drain().await;
+    bob.drain().await;
+
+    // Réservé aux membres ; un canal vide est inconnu
+    bob.run([
+        Step::Send(Request::Names("general".to_string())),
+        Step::Expect(Response::Names {
+            chan: "general".to_string(),
+            users: vec!["alice".to_string(), "bob".to_string()],
+        }),
+        Step::Send(Request::Names("rust".to_string())),
+        Step::Expect(error(ErrorCode::UnknownChannel("rust".to_string()))),
+    ])
+    .await;
+    let mut carol = server.connect("carol").await;
+    carol
+        .run([
+            Step::Send(Request::Names("general".to_string())),
+            Step::Expect(error(ErrorCode::NotInChannel)),
+        ])
+        .await;
+}
+
+// Numéros et contenus d'une page d'historique, sans les dates fixées par le serveur
+fn page(response: Response) -> (Vec<(u64, String)>, bool) {
+    match response {
+        Response::History { messages, more, .. } => (
+            messages
+                .into_iter()
+                .map(|message| {
+                    assert_eq!(message.from, "alice");
+                    (message.id, message.content)
+                })
+                .collect(),
+            more,
+        ),
+        response => panic!("expected a history page, got {response:?}"),
+    }
+}
+
+#[tokio::test]
+async fn history_is_fetched_by_pages() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    alice.join("general").await;
+    for content in ["un", "deux", "trois", "quatre"] {
+        alice.say("general", content).await;
+    }
+    alice.drain().await;
+
+    // Les messages antérieurs à l'arrivée de bob restent accessibles, page par page
+    let mut bob = server.connect("bob").await;
+    bob.join("general").await;
+    bob.drain().await;
+    let fetch = |before_id, limit| Request::FetchHistory {
+        chan: "general".to_string(),
+        before_id,
+        limit,
+    };
+    bob.send(fetch(None, 2)).await;
+    assert_eq!(
+        page(bob.recv().await),
+        (
+            vec![(4, "trois".to_string()), (5, "quatre".to_string())],
+            true
+        )
+    );
+    bob.send(fetch(Some(4), 10)).await;
+    assert_eq!(
+        page(bob.recv().await),
+        (vec![(2, "un".to_string()), (3, "deux".to_string())], false)
+    );
+
+    let mut carol = server.connect("carol").await;
+    carol
+        .run([
+            Step::Send(fetch(None, 10)),
+            Step::Expect(error(ErrorCode::NotInChannel)),
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn messages_are_limited_in_length() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    alice.join("general").await;
+    alice.drain().await;
+
+    let max_message_len = mini_irc_server::DEFAULT_MAX_MESSAGE_LEN;
+    alice
+        .run([
+            Step::Send(Request::Capabilities),
+            Step::Expect(Response::Capabilities(Capabilities { max_message_len })),
+        ])
+        .await;
+    alice.say("general", &"a".repeat(max_message_len + 1)).await;
+    alice
+        .whisper("alice", &"a".repeat(max_message_len + 1))
+        .await;
+    alice
+        .run([
+            Step::Expect(error(ErrorCode::MessageTooLong)),
+            Step::Expect(error(ErrorCode::MessageTooLong)),
+        ])
+        .await;
+    alice.say("general", &"a".repeat(max_message_len)).await;
+    alice
+        .run([Step::Expect(message(
+            "alice",
+            &"a".repeat(max_message_len),
+            2,
+        ))])
+        .await;
+}
+
+#[tokio::test]
+async fn join_and_leave() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    let mut bob = server.connect("bob").await;
+
+    alice.join("general").await;
+    alice
+        .run([
+            Step::Expect(ack_join(&["alice"])),
+            Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 1)),
+        ])
+        .await;
+
+    bob.join("general").await;
+    bob.run([
+        Step::Expect(ack_join(&["alice", "bob"])),
+        Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 2)),
+    ])
+    .await;
+    alice
+        .run([Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 2))])
+        .await;
+
+    bob.join("general").await;
+    bob.run([Step::Expect(error(ErrorCode::AlreadyInChannel(
+        "general".to_string(),
+    )))])
+    .await;
+
+    bob.leave("general").await;
+    bob.run([
+        Step::Expect(Response::AckLeave("general".to_string())),
+        Step::ExpectNothing,
+    ])
+    .await;
+    alice
+        .run([Step::Expect(chan(ChanOp::UserDel("bob".to_string()), 3))])
+        .await;
+
+    bob.leave("general").await;
+    bob.run([Step::Expect(error(ErrorCode::NotInChannel))])
+        .await;
+
+    // Le départ est immédiat : bob peut revenir aussitôt
+    bob.join("general").await;
+    bob.run([
+        Step::Expect(ack_join(&["alice", "bob"])),
+        Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 4)),
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn channel_messages() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    let mut bob = server.connect("bob").await;
+    alice.join("general").await;
+    alice
+        .run([
+            Step::Expect(ack_join(&["alice"])),
+            Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 1)),
+        ])
+        .await;
+    bob.join("general").await;
+    bob.run([
+        Step::Expect(ack_join(&["alice", "bob"])),
+        Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 2)),
+    ])
+    .await;
+    alice
+        .run([Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 2))])
+        .await;
+
+    alice.say("general", "bonjour").await;
+    // L'auteur reçoit son message une seule fois
+    alice
+        .run([
+            Step::Expect(message("alice", "bonjour", 3)),
+            Step::ExpectNothing,
+        ])
+        .await;
+    bob.run([Step::Expect(message("alice", "bonjour", 3))])
+        .await;
+
+    bob.say("random", "perdu").await;
+    bob.run([Step::Expect(error(ErrorCode::NotInChannel))])
+        .await;
+    alice.run([Step::ExpectNothing]).await;
+
+    // Un membre déconnecté quitte ses canaux
+    drop(bob);
+    alice
+        .run([Step::Expect(chan(ChanOp::UserDel("bob".to_string()), 4))])
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn acks_are_ordered_with_channel_broadcasts() {
+    const ROUNDS: usize = 20;
+    let server = TestServer::start().await;
+    let mut bob = server.connect("bob").await;
+    bob.join("general").await;
+    bob.run([
+        Step::Expect(ack_join(&["bob"])),
+        Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 1)),
+    ])
+    .await;
+    let mut alice = server.connect("alice").await;
+
+    // bob parle sans arrêt pendant qu'alice entre et sort du canal
+    let spam = tokio::spawn(async move {
+        for i in 0..200 {
+            bob.say("general", &format!("message {i}")).await;
+            bob.recv().await;
+        }
+    });
+    for _ in 0..ROUNDS {
+        alice.join("general").await;
+        alice.leave("general").await;
+    }
+
+    let mut joined = false;
+    let mut last = 0;
+    let mut leaves = 0;
+    while leaves < ROUNDS {
+        match alice.recv().await {
+            Response::AckJoin { .. } => {
+                assert!(!joined, "AckJoin while in the channel");
+                joined = true;
+                // L'arrivée d'alice est la première diffusion qui suit l'accusé
+                match alice.recv().await {
+                    Response::Channel {
+                        op: ChanOp::UserAdd(user),
+                        seq,
+                        ..
+                    } if user == "alice" => last = seq,
+                    // File d'envoi pleine : l'arrivée a pu être perdue avec d'autres diffusions
+                    Response::Channel {
+                        op: ChanOp::Missed(_),
+                        ..
+                    } => {}
+                    response => panic!("expected alice's arrival, got {response:?}"),
+                }
+            }
+            Response::AckLeave(_) => {
+                assert!(joined, "AckLeave outside the channel");
+                joined = false;
+                leaves += 1;
+            }
+            // Hors séquence
+            Response::Channel {
+                op: ChanOp::Missed(_),
+                ..
+            } => assert!(joined, "Missed received outside the channel"),
+            Response::Channel { op, seq, .. } => {
+                assert!(joined, "{op:?} received outside the channel");
+                assert!(seq > last, "{seq} received after {last}");
+                last = seq;
+            }
+            response => panic!("unexpected response: {response:?}"),
+        }
+    }
+    alice.run([Step::ExpectNothing]).await;
+    spam.abort();
+}
+
+#[tokio::test]
+async fn direct_messages() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    let mut bob = server.connect("bob").await;
+
+    alice.whisper("bob", "psst").await;
+    bob.run([Step::Expect(Response::DirectMessage {
+        from: "alice".to_string(),
+        content: "psst".to_string(),
+    })])
+    .await;
+    // Le client affiche lui-même ses messages directs
+    alice.run([Step::ExpectNothing]).await;
+
+    alice.whisper("carol", "psst").await;
+    alice
+        .run([Step::Expect(error(ErrorCode::UnknownUser(
+            "carol".to_string(),
+        )))])
+        .await;
+}
+
+#[tokio::test]
+async fn in_memory_and_tcp_clients_share_the_server() {
+    let server = TestServer::start().await;
+    let mut alice = server.connect("alice").await;
+    let mut bob: TestClient<_> = server.client_in_memory().await;
+    bob.run([
+        Step::Send(Request::Connect("bob".to_string())),
+        Step::Expect(Response::AckConnect("bob".to_string())),
+    ])
+    .await;
+
+    bob.whisper("alice", "coucou").await;
+    alice
+        .run([Step::Expect(Response::DirectMessage {
+            from: "bob".to_string(),
+            content: "coucou".to_string(),
+        })])
+        .await;
+}
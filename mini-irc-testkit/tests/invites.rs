@@ -0,0 +1,58 @@
+use mini_irc_protocol::{ErrorCode, Notice, NoticeScope, Request, Response};
+use mini_irc_testkit::{simulate, Step};
+
+fn invite(user: &str) -> Request {
+    Request::Invite {
+        chan: "general".to_string(),
+        user: user.to_string(),
+    }
+}
+
+/// Un membre du canal invite un utilisateur connecté, qui reçoit l'invitation directement.
+#[test]
+fn members_invite_connected_users() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        let mut carol = sim.connect("carol").await;
+        alice.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+
+        bob.run([
+            Step::Send(invite("carol")),
+            Step::Expect(Response::Error(ErrorCode::NotInChannel)),
+        ])
+        .await;
+        alice
+            .run([
+                Step::Send(invite("alice")),
+                Step::Expect(Response::Error(ErrorCode::UserInChannel(
+                    "alice".to_string(),
+                ))),
+                Step::Send(invite("zed")),
+                Step::Expect(Response::Error(ErrorCode::UnknownUser("zed".to_string()))),
+                Step::Send(invite("bob")),
+                Step::Expect(Response::Notice {
+                    scope: NoticeScope::Channel("general".to_string()),
+                    notice: Notice::InviteSent("bob".to_string()),
+                }),
+            ])
+            .await;
+        bob.run([Step::Expect(Response::Invited {
+            chan: "general".to_string(),
+            by: "alice".to_string(),
+        })])
+        .await;
+
+        // Pas d'invitation de la part d'un utilisateur bloqué
+        carol.send(Request::Block("alice".to_string())).await;
+        sim.settle().await;
+        alice
+            .run([
+                Step::Send(invite("carol")),
+                Step::Expect(Response::Error(ErrorCode::Blocked)),
+            ])
+            .await;
+    });
+}
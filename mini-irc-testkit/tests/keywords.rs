@@ -0,0 +1,158 @@
+use mini_irc_protocol::{ErrorCode, Request, Response};
+use mini_irc_testkit::{simulate, Step};
+
+fn watch(keyword: &str) -> Request {
+    Request::WatchKeyword {
+        chan: "general".to_string(),
+        keyword: keyword.to_string(),
+    }
+}
+
+fn keywords(keywords: &[&str]) -> Response {
+    Response::Keywords {
+        chan: "general".to_string(),
+        keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+    }
+}
+
+#[test]
+fn operators_are_alerted_of_watched_keywords() {
+    simulate(|sim| async move {
+        let sim = sim.admins(&["root"]);
+        let mut bob = sim.connect("bob").await;
+        let mut alice = sim.connect("alice").await;
+        let mut root = sim.connect("root").await;
+        // Bob, arrivé le premier, est l'opérateur du canal
+        bob.join("general").await;
+        alice.join("general").await;
+        alice.drain().await;
+
+        alice
+            .run([
+                Step::Send(watch("spam")),
+                Step::Expect(Response::Error(ErrorCode::PermissionDenied)),
+            ])
+            .await;
+        // L'administrateur n'a pas besoin d'être membre du canal
+        root.run([
+            Step::Send(watch("Spam")),
+            Step::Expect(keywords(&["spam"])),
+            Step::Send(watch("scam")),
+            Step::Expect(keywords(&["spam", "scam"])),
+            Step::Send(watch("two words")),
+            Step::Expect(Response::Error(ErrorCode::InvalidKeyword(
+                "two words".to_string(),
+            ))),
+        ])
+        .await;
+
+        // Les mots-clés sont des mots entiers, quelle que soit leur casse
+        alice.say("general", "spammers are everywhere").await;
+        alice.say("general", "Buy SPAM, now!").await;
+        alice.drain().await;
+        root.run([
+            Step::Expect(Response::KeywordAlert {
+                chan: "general".to_string(),
+                keyword: "spam".to_string(),
+                from: "alice".to_string(),
+                content: "Buy SPAM, now!".to_string(),
+            }),
+            Step::ExpectNothing,
+        ])
+        .await;
+
+        root.run([
+            Step::Send(Request::UnwatchKeyword {
+                chan: "general".to_string(),
+                keyword: "spam".to_string(),
+            }),
+            Step::Expect(keywords(&["scam"])),
+        ])
+        .await;
+        alice.say("general", "spam again").await;
+        alice.drain().await;
+        root.run([Step::ExpectNothing]).await;
+    });
+}
+
+#[test]
+fn channel_operators_watch_their_channel() {
+    simulate(|sim| async move {
+        let mut bob = sim.connect("bob").await;
+        let mut alice = sim.connect("alice").await;
+        bob.join("general").await;
+        alice.join("general").await;
+        alice.join("random").await;
+        bob.drain().await;
+        alice.drain().await;
+
+        bob.run([
+            Step::Send(watch("spam")),
+            Step::Expect(keywords(&["spam"])),
+            // Seulement sur ses propres canaux
+            Step::Send(Request::WatchKeyword {
+                chan: "random".to_string(),
+                keyword: "spam".to_string(),
+            }),
+            Step::Expect(Response::Error(ErrorCode::PermissionDenied)),
+        ])
+        .await;
+
+        alice.say("general", "spam for sale").await;
+        alice.drain().await;
+        // Bob reçoit le message en tant que membre, puis l'alerte
+        let received = bob.drain().await;
+        let alert = Response::KeywordAlert {
+            chan: "general".to_string(),
+            keyword: "spam".to_string(),
+            from: "alice".to_string(),
+            content: "spam for sale".to_string(),
+        };
+        assert!(received.contains(&alert), "{received:?}");
+    });
+}
+
+/// Un opérateur qui cède le canal ou le quitte n'est plus alerté, même sans retirer ses
+/// mots-clés.
+#[test]
+fn former_operators_are_not_alerted() {
+    simulate(|sim| async move {
+        let mut bob = sim.connect("bob").await;
+        let mut alice = sim.connect("alice").await;
+        bob.join("general").await;
+        alice.join("general").await;
+        bob.drain().await;
+        alice.drain().await;
+
+        bob.run([
+            Step::Send(watch("spam")),
+            Step::Expect(keywords(&["spam"])),
+            Step::Send(Request::TransferOp {
+                chan: "general".to_string(),
+                to: "alice".to_string(),
+            }),
+        ])
+        .await;
+        alice.drain().await;
+        alice.say("general", "spam for sale").await;
+        alice.drain().await;
+        let received = bob.drain().await;
+        assert!(
+            !received
+                .iter()
+                .any(|response| matches!(response, Response::KeywordAlert { .. })),
+            "{received:?}"
+        );
+
+        // Alice, devenue opératrice, surveille le canal puis le quitte
+        alice
+            .run([Step::Send(watch("scam")), Step::Expect(keywords(&["scam"]))])
+            .await;
+        alice.leave("general").await;
+        sim.settle().await;
+        alice.drain().await;
+        bob.say("general", "scam inside").await;
+        bob.drain().await;
+        alice.run([Step::ExpectNothing]).await;
+    });
+}
@@ -0,0 +1,19 @@
+use mini_irc_protocol::{ErrorCode, Request, Response};
+use mini_irc_testkit::{simulate, Step};
+
+/// Une adresse réservée aux administrateurs refuse les autres noms.
+#[test]
+fn admin_only_listener_refuses_other_users() {
+    simulate(|sim| async move {
+        let sim = sim.admins(&["root"]).admin_only();
+        let mut client = sim.client().await;
+        client
+            .run([
+                Step::Send(Request::Connect("alice".to_string())),
+                Step::Expect(Response::Error(ErrorCode::PermissionDenied)),
+                Step::Send(Request::Connect("root".to_string())),
+                Step::Expect(Response::AckConnect("root".to_string())),
+            ])
+            .await;
+    });
+}
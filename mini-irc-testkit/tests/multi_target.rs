@@ -0,0 +1,127 @@
+use mini_irc_protocol::{
+    ChanOp, ErrorCode, MessageReceiver, Request, Response, MAX_MESSAGE_TARGETS,
+};
+use mini_irc_testkit::{simulate, Step};
+use std::time::Duration;
+
+fn chan(name: &str) -> MessageReceiver {
+    MessageReceiver::Channel(name.to_string())
+}
+
+fn user(name: &str) -> MessageReceiver {
+    MessageReceiver::User(name.to_string())
+}
+
+fn many(to: Vec<MessageReceiver>, content: &str) -> Request {
+    Request::MessageMany {
+        to,
+        content: content.to_string(),
+    }
+}
+
+/// Chaque destinataire reçoit le message comme s'il lui était seul adressé, et l'expéditeur
+/// apprend le résultat de chaque envoi.
+#[test]
+fn messages_fan_out_with_a_report_per_target() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("a").await;
+        alice.join("b").await;
+        sim.settle().await;
+        alice.drain().await;
+
+        alice
+            .send(many(
+                vec![chan("a"), chan("b"), user("bob"), chan("c"), chan("a")],
+                "salut",
+            ))
+            .await;
+        let message = |chan: &str| Response::Channel {
+            op: ChanOp::Message {
+                from: "alice".to_string(),
+                content: "salut".to_string(),
+            },
+            chan: chan.to_string(),
+            seq: 2,
+        };
+        alice
+            .run([
+                Step::Expect(message("a")),
+                Step::Expect(message("b")),
+                Step::Expect(Response::Delivery(vec![
+                    (chan("a"), Ok(())),
+                    (chan("b"), Ok(())),
+                    (user("bob"), Ok(())),
+                    (chan("c"), Err(ErrorCode::NotInChannel)),
+                ])),
+            ])
+            .await;
+        bob.run([
+            Step::Expect(Response::DirectMessage {
+                from: "alice".to_string(),
+                content: "salut".to_string(),
+            }),
+            Step::ExpectNothing,
+        ])
+        .await;
+    });
+}
+
+/// La limite de débit des messages directs s'applique à chaque destinataire, et le nombre de
+/// destinataires est borné.
+#[test]
+fn each_direct_message_counts_against_the_rate() {
+    simulate(|sim| async move {
+        let sim = sim.dm_rate(1, Duration::from_secs(10));
+        let mut alice = sim.connect("alice").await;
+        let _bob = sim.connect("bob").await;
+        let _carol = sim.connect("carol").await;
+        alice
+            .run([
+                Step::Send(many(vec![user("bob"), user("carol")], "salut")),
+                Step::Expect(Response::Delivery(vec![
+                    (user("bob"), Ok(())),
+                    (
+                        user("carol"),
+                        Err(ErrorCode::RateLimited {
+                            retry_after: Duration::from_secs(10),
+                        }),
+                    ),
+                ])),
+                Step::Send(many(
+                    (0..=MAX_MESSAGE_TARGETS)
+                        .map(|i| user(&format!("user{i}")))
+                        .collect(),
+                    "salut",
+                )),
+                Step::Expect(Response::Error(ErrorCode::TooManyTargets(
+                    MAX_MESSAGE_TARGETS,
+                ))),
+            ])
+            .await;
+    });
+}
+
+/// Le séparateur des destinataires est interdit dans les noms, qui resteraient sinon hors
+/// d'atteinte d'un message à plusieurs destinataires.
+#[test]
+fn names_cannot_contain_the_target_separator() {
+    simulate(|sim| async move {
+        let mut client = sim.client().await;
+        client
+            .run([
+                Step::Send(Request::Connect("a,b".to_string())),
+                Step::Expect(Response::Error(ErrorCode::SeparatorInNickname)),
+                Step::Send(Request::Connect("alice".to_string())),
+                Step::Expect(Response::AckConnect("alice".to_string())),
+                Step::Send(Request::JoinChan("x,y".to_string())),
+                Step::Expect(Response::Error(ErrorCode::SeparatorInChannelName(
+                    "x,y".to_string(),
+                ))),
+                Step::Send(Request::ListChans),
+                Step::Expect(Response::ChanList(Vec::new())),
+            ])
+            .await;
+    });
+}
@@ -0,0 +1,131 @@
+use mini_irc_protocol::{ChanOp, ErrorCode, Request, Response};
+use mini_irc_testkit::{chan, error, simulate, Step};
+use std::time::Duration;
+
+const OWNER_EXPIRY: Duration = Duration::from_secs(3600);
+
+fn transfer(to: &str) -> Request {
+    Request::TransferOp {
+        chan: "general".to_string(),
+        to: to.to_string(),
+    }
+}
+
+fn claim() -> Request {
+    Request::ClaimOp("general".to_string())
+}
+
+/// Le créateur d'un canal en est propriétaire, et lui seul peut transmettre le canal.
+#[test]
+fn owner_transfers_the_channel() {
+    simulate(|sim| async move {
+        let sim = sim.admins(&["root"]);
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        sim.settle().await;
+        bob.join("general").await;
+        bob.run([
+            Step::Expect(Response::AckJoin {
+                chan: "general".to_string(),
+                users: vec!["alice".to_string(), "bob".to_string()],
+                owner: Some("alice".to_string()),
+            }),
+            Step::Expect(chan(ChanOp::UserAdd("bob".to_string()), 2)),
+        ])
+        .await;
+        alice.drain().await;
+
+        bob.run([
+            Step::Send(transfer("bob")),
+            Step::Expect(error(ErrorCode::PermissionDenied)),
+            Step::Send(claim()),
+            Step::Expect(error(ErrorCode::ChannelHasOwner)),
+        ])
+        .await;
+        alice
+            .run([
+                Step::Send(transfer("carol")),
+                Step::Expect(error(ErrorCode::NotAMember("carol".to_string()))),
+                Step::Send(transfer("bob")),
+                Step::Expect(chan(ChanOp::Owner("bob".to_string()), 3)),
+            ])
+            .await;
+        bob.run([Step::Expect(chan(ChanOp::Owner("bob".to_string()), 3))])
+            .await;
+
+        // Un administrateur peut transmettre le canal sans en être membre
+        let mut root = sim.connect("root").await;
+        root.send(transfer("alice")).await;
+        alice
+            .run([Step::Expect(chan(ChanOp::Owner("alice".to_string()), 4))])
+            .await;
+    });
+}
+
+/// Un propriétaire absent trop longtemps perd ses droits : le canal peut être revendiqué, ou
+/// adopté par le prochain arrivant s'il est vide.
+#[test]
+fn absent_owner_expires() {
+    simulate(|sim| async move {
+        let sim = sim.owner_expiry(OWNER_EXPIRY);
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        sim.settle().await;
+        bob.join("general").await;
+        sim.settle().await;
+        alice.leave("general").await;
+        sim.settle().await;
+        alice.drain().await;
+        bob.drain().await;
+
+        bob.run([
+            Step::Send(claim()),
+            Step::Expect(error(ErrorCode::ChannelHasOwner)),
+        ])
+        .await;
+        sim.advance(OWNER_EXPIRY).await;
+        bob.run([
+            Step::Send(claim()),
+            Step::Expect(chan(ChanOp::Owner("bob".to_string()), 4)),
+        ])
+        .await;
+
+        // Le canal vidé depuis longtemps revient à son prochain arrivant
+        bob.leave("general").await;
+        sim.settle().await;
+        sim.advance(OWNER_EXPIRY).await;
+        alice.join("general").await;
+        alice
+            .run([Step::Expect(Response::AckJoin {
+                chan: "general".to_string(),
+                users: vec!["alice".to_string()],
+                owner: Some("alice".to_string()),
+            })])
+            .await;
+    });
+}
+
+/// Sans délai configuré, le propriétaire garde le canal pendant son absence.
+#[test]
+fn absent_owner_keeps_the_channel_by_default() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        sim.settle().await;
+        bob.join("general").await;
+        sim.settle().await;
+        drop(alice);
+        sim.settle().await;
+        bob.drain().await;
+
+        sim.advance(OWNER_EXPIRY * 24).await;
+        bob.run([
+            Step::Send(claim()),
+            Step::Expect(error(ErrorCode::ChannelHasOwner)),
+        ])
+        .await;
+    });
+}
@@ -0,0 +1,127 @@
+use mini_irc_protocol::{ChanOp, ErrorCode, Request, Response};
+use mini_irc_testkit::{chan, simulate, Step};
+use std::time::Duration;
+
+const AWAY_AFTER: Duration = Duration::from_secs(60);
+
+/// Un utilisateur ne voit, parmi les canaux d'un autre, que ceux dont il est membre.
+#[test]
+fn whois_shows_only_shared_channels() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        let mut carol = sim.connect("carol").await;
+        alice.join("general").await;
+        alice.join("secret").await;
+        bob.join("general").await;
+        sim.settle().await;
+        bob.drain().await;
+
+        let channels = |response: Response| match response {
+            Response::WhoIs { channels, .. } => channels,
+            other => panic!("unexpected response: {other:?}"),
+        };
+        bob.send(Request::WhoIs("alice".to_string())).await;
+        assert_eq!(channels(bob.recv().await), ["general"]);
+        carol.send(Request::WhoIs("alice".to_string())).await;
+        assert!(channels(carol.recv().await).is_empty());
+        alice.drain().await;
+        alice.send(Request::WhoIs("alice".to_string())).await;
+        assert_eq!(channels(alice.recv().await), ["general", "secret"]);
+    });
+}
+
+/// Un utilisateur inactif est annoncé absent à ses canaux, puis de retour à sa prochaine
+/// requête.
+#[test]
+fn idle_user_goes_away_and_comes_back() {
+    simulate(|sim| async move {
+        let sim = sim.away_after(AWAY_AFTER);
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        bob.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+        bob.drain().await;
+
+        sim.advance(Duration::from_secs(30)).await;
+        bob.send(Request::WhoIs("alice".to_string())).await;
+        match bob.recv().await {
+            Response::WhoIs {
+                user,
+                channels,
+                idle,
+                away,
+            } => {
+                assert_eq!(user, "alice");
+                assert_eq!(channels, vec!["general".to_string()]);
+                // Les silences des `drain` font aussi avancer l'horloge
+                assert!(idle >= Duration::from_secs(30) && idle < Duration::from_secs(31));
+                assert!(!away);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+
+        sim.advance(Duration::from_secs(31)).await;
+        sim.settle().await;
+        bob.run([
+            Step::Expect(chan(ChanOp::UserAway("alice".to_string()), 3)),
+            Step::ExpectNothing,
+        ])
+        .await;
+
+        alice.say("general", "me revoilà").await;
+        bob.run([
+            Step::Expect(chan(ChanOp::UserBack("alice".to_string()), 4)),
+            Step::Expect(chan(
+                ChanOp::Message {
+                    from: "alice".to_string(),
+                    content: "me revoilà".to_string(),
+                },
+                5,
+            )),
+        ])
+        .await;
+    });
+}
+
+/// Sans délai configuré, personne n'est marqué absent.
+#[test]
+fn no_auto_away_by_default() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        bob.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+        bob.drain().await;
+
+        sim.advance(Duration::from_secs(3600)).await;
+        bob.run([Step::ExpectNothing]).await;
+        bob.send(Request::WhoIs("alice".to_string())).await;
+        match bob.recv().await {
+            Response::WhoIs { idle, away, .. } => {
+                assert!(idle >= Duration::from_secs(3600));
+                assert!(!away);
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    });
+}
+
+#[test]
+fn whois_unknown_user() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        alice
+            .run([
+                Step::Send(Request::WhoIs("nobody".to_string())),
+                Step::Expect(Response::Error(ErrorCode::UnknownUser(
+                    "nobody".to_string(),
+                ))),
+            ])
+            .await;
+    });
+}
@@ -0,0 +1,57 @@
+use mini_irc_protocol::ErrorCode;
+use mini_irc_server::{Dnsbl, Resolver, PROXY_V2_SIGNATURE};
+use mini_irc_testkit::{refusal, simulate, Step, TestClient};
+use std::io;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, DuplexStream};
+
+// En-tête PROXY d'un client TCP d'adresse `ip`, port 4000
+async fn send_header(stream: &mut DuplexStream, ip: [u8; 4]) {
+    let mut header = PROXY_V2_SIGNATURE.to_vec();
+    header.extend([0x21, 0x11, 0, 12]);
+    header.extend(ip);
+    header.extend([10, 0, 0, 1, 0x0f, 0xa0, 0x18, 0xeb]);
+    stream.write_all(&header).await.unwrap();
+}
+
+// Zone qui liste seulement 192.0.2.1
+fn dnsbl() -> Dnsbl {
+    let resolver: Resolver = Arc::new(|name| {
+        let answer = if name == "1.2.0.192.dnsbl.example" {
+            Ok(vec![IpAddr::from([127, 0, 0, 2])])
+        } else {
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        };
+        Box::pin(async move { answer })
+    });
+    Dnsbl::new(["dnsbl.example".to_string()]).with_resolver(resolver)
+}
+
+/// L'adresse annoncée par le répartiteur, et non la sienne, est celle qui est vérifiée.
+#[test]
+fn client_address_comes_from_the_header() {
+    simulate(|sim| async move {
+        let sim = sim.dnsbl(dnsbl());
+        let mut listed = sim.open_proxied();
+        send_header(&mut listed, [192, 0, 2, 1]).await;
+        assert_eq!(
+            refusal(listed).await.unwrap(),
+            ErrorCode::Blocklisted("dnsbl.example".to_string())
+        );
+
+        let mut clean = sim.open_proxied();
+        send_header(&mut clean, [192, 0, 2, 2]).await;
+        let mut client = TestClient::handshake(clean).await.unwrap();
+        client.login("alice").await;
+        client.run([Step::ExpectNothing]).await;
+    });
+}
+
+/// Sans en-tête PROXY, la connexion est fermée.
+#[test]
+fn connections_without_header_are_closed() {
+    simulate(|sim| async move {
+        assert!(TestClient::handshake(sim.open_proxied()).await.is_err());
+    });
+}
@@ -0,0 +1,99 @@
+use mini_irc_protocol::{Request, Response};
+use mini_irc_testkit::{simulate, Step};
+use std::time::Duration;
+
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// Seuls les abonnés reçoivent les statistiques : aussitôt abonnés, puis à chaque période.
+#[test]
+fn server_stats_are_sent_to_subscribers() {
+    simulate(|sim| async move {
+        let sim = sim.stats_interval(INTERVAL);
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+
+        alice
+            .run([
+                Step::Send(Request::SubscribeStats),
+                Step::Expect(Response::ServerStats {
+                    users: 2,
+                    channels: 1,
+                    uptime_secs: 0,
+                    msgs_per_min: 0,
+                }),
+            ])
+            .await;
+        alice.say("general", "un").await;
+        alice.say("general", "deux").await;
+        sim.settle().await;
+        alice.drain().await;
+
+        sim.advance(INTERVAL).await;
+        alice
+            .run([Step::Expect(Response::ServerStats {
+                users: 2,
+                channels: 1,
+                uptime_secs: 60,
+                msgs_per_min: 2,
+            })])
+            .await;
+        bob.run([Step::ExpectNothing]).await;
+
+        // Les messages sortent de la fenêtre d'une minute
+        sim.advance(INTERVAL).await;
+        alice
+            .run([Step::Expect(Response::ServerStats {
+                users: 2,
+                channels: 1,
+                uptime_secs: 120,
+                msgs_per_min: 0,
+            })])
+            .await;
+    });
+}
+
+#[test]
+fn unsubscribing_stops_server_stats() {
+    simulate(|sim| async move {
+        let sim = sim.stats_interval(INTERVAL);
+        let mut alice = sim.connect("alice").await;
+        alice.send(Request::SubscribeStats).await;
+        assert!(matches!(
+            alice.recv().await,
+            Response::ServerStats { users: 1, .. }
+        ));
+        alice
+            .run([
+                Step::Send(Request::UnsubscribeStats),
+                Step::Expect(Response::Ack),
+            ])
+            .await;
+        sim.advance(INTERVAL).await;
+        alice.run([Step::ExpectNothing]).await;
+    });
+}
+
+/// Sans période configurée, l'abonnement ne donne que l'état du moment.
+#[test]
+fn server_stats_without_interval_are_sent_once() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let _bob = sim.connect("bob").await;
+        alice.whisper("bob", "salut").await;
+        alice.send(Request::SubscribeStats).await;
+        assert!(matches!(
+            alice.recv().await,
+            Response::ServerStats {
+                users: 2,
+                channels: 0,
+                msgs_per_min: 1,
+                ..
+            }
+        ));
+        sim.advance(INTERVAL).await;
+        alice.run([Step::ExpectNothing]).await;
+    });
+}
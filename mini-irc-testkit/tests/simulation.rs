@@ -0,0 +1,184 @@
+use mini_irc_protocol::{ChanOp, Request, Response};
+use mini_irc_server::ChannelCapacity;
+use mini_irc_testkit::{chan, simulate, Simulation, Step};
+use std::time::Duration;
+
+/// Quitter puis rejoindre un canal sans attendre l'accusé de départ : le désabonnement doit
+/// être effectif avant le traitement de la requête suivante.
+#[test]
+fn leave_then_rejoin_immediately() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        bob.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+        bob.drain().await;
+
+        alice.leave("general").await;
+        alice.join("general").await;
+        alice
+            .run([
+                Step::Expect(Response::AckLeave("general".to_string())),
+                Step::Expect(Response::AckJoin {
+                    chan: "general".to_string(),
+                    users: vec!["bob".to_string(), "alice".to_string()],
+                    // Le créateur du canal l'est resté pendant son absence
+                    owner: Some("alice".to_string()),
+                }),
+                Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 4)),
+                Step::ExpectNothing,
+            ])
+            .await;
+        bob.run([
+            Step::Expect(chan(ChanOp::UserDel("alice".to_string()), 3)),
+            Step::Expect(chan(ChanOp::UserAdd("alice".to_string()), 4)),
+            Step::ExpectNothing,
+        ])
+        .await;
+    });
+}
+
+/// Plusieurs clients rejoignent le même canal en même temps : l'ordre de leurs arrivées
+/// dépend de l'ordonnancement, qui est le même à chaque simulation.
+#[test]
+fn concurrent_joins_are_reproducible() {
+    fn scenario() -> Vec<Vec<Response>> {
+        simulate(|sim| async move {
+            let mut clients = Vec::new();
+            for i in 0..8 {
+                clients.push(sim.connect(&format!("user{i}")).await);
+            }
+            for client in clients.iter_mut() {
+                client.join("general").await;
+            }
+            sim.settle().await;
+            let mut transcripts = Vec::new();
+            for client in clients.iter_mut() {
+                transcripts.push(client.drain().await);
+            }
+            transcripts
+        })
+    }
+
+    let first = scenario();
+    assert_eq!(first.last().unwrap().len(), 2);
+    for _ in 0..5 {
+        assert_eq!(scenario(), first);
+    }
+}
+
+/// Un client qui ne lit plus ses réponses finit par manquer des messages du canal, et en est
+/// prévenu lorsqu'il reprend sa lecture.
+#[test]
+fn slow_reader_is_told_about_missed_messages() {
+    simulate(|_| async move {
+        let sim = Simulation::with_capacity(ChannelCapacity::parse("2").unwrap()).buffer_size(256);
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        bob.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+
+        for i in 0..200 {
+            alice.say("general", &format!("message {i}")).await;
+            alice.recv().await;
+        }
+        sim.settle().await;
+
+        let responses = bob.drain().await;
+        assert!(responses.iter().any(|response| matches!(
+            response,
+            Response::Channel {
+                op: ChanOp::Missed(_),
+                ..
+            }
+        )));
+        // Les numéros de séquence croissent, avec un écart là où des messages manquent
+        let seqs: Vec<u64> = responses
+            .iter()
+            .filter_map(|response| match response {
+                Response::Channel { seq, .. } if *seq > 0 => Some(*seq),
+                _ => None,
+            })
+            .collect();
+        assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(seqs.windows(2).any(|pair| pair[1] > pair[0] + 1));
+        // Bob reste membre du canal et reçoit les messages suivants
+        alice
+            .send(Request::Message {
+                to: "#general".parse().unwrap(),
+                content: "encore là ?".to_string(),
+            })
+            .await;
+        bob.run([Step::Expect(chan(
+            ChanOp::Message {
+                from: "alice".to_string(),
+                content: "encore là ?".to_string(),
+            },
+            203,
+        ))])
+        .await;
+    });
+}
+
+/// Un client qui ne lit plus ses réponses ne bloque pas le traitement de ses requêtes.
+#[test]
+fn slow_reader_can_still_send() {
+    simulate(|_| async move {
+        let sim = Simulation::new().buffer_size(256);
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        bob.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+
+        // Bob ne lit pas ses échos, qui remplissent sa connexion
+        for i in 0..100 {
+            bob.say("general", &format!("message {i}")).await;
+        }
+        for i in 0..100 {
+            alice
+                .run([Step::Expect(chan(
+                    ChanOp::Message {
+                        from: "bob".to_string(),
+                        content: format!("message {i}"),
+                    },
+                    3 + i,
+                ))])
+                .await;
+        }
+    });
+}
+
+/// Un client dont la file d'envoi reste pleine est déconnecté, et quitte ses canaux.
+#[test]
+fn stalled_client_is_disconnected() {
+    simulate(|_| async move {
+        let sim = Simulation::new().buffer_size(256);
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        bob.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+
+        for i in 0..300 {
+            alice.say("general", &format!("message {i}")).await;
+            alice.recv().await;
+        }
+        // Au-delà du délai accordé à une file d'envoi pleine
+        sim.advance(Duration::from_secs(10)).await;
+        sim.settle().await;
+
+        alice
+            .run([Step::Expect(chan(ChanOp::UserDel("bob".to_string()), 303))])
+            .await;
+        // Le nom est libéré
+        sim.connect("bob").await;
+        drop(bob);
+    });
+}
@@ -0,0 +1,96 @@
+use mini_irc_protocol::{
+    ConnectionStats, EncryptionStatus, ErrorCode, Mechanism, Request, Response,
+};
+use mini_irc_testkit::{simulate, Step};
+use std::time::Duration;
+
+async fn stats(
+    client: &mut mini_irc_testkit::TestClient<tokio::io::DuplexStream>,
+) -> ConnectionStats {
+    match client.recv().await {
+        Response::Stats(stats) => stats,
+        other => panic!("expected statistics, got {other:?}"),
+    }
+}
+
+#[test]
+fn own_stats() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let _bob = sim.connect("bob").await;
+        alice.join("general").await;
+        alice.say("general", "bonjour").await;
+        alice.whisper("bob", "salut").await;
+        alice.drain().await;
+        sim.advance(Duration::from_secs(10)).await;
+
+        alice.send(Request::Stats).await;
+        let stats = stats(&mut alice).await;
+        assert_eq!(stats.user, "alice");
+        assert_eq!(stats.messages_sent, 2);
+        assert_eq!(stats.channels, vec!["general".to_string()]);
+        assert!(stats.connected_for >= Duration::from_secs(10));
+        assert!(stats.bytes_sent > 0);
+        assert!(stats.bytes_received > stats.bytes_sent / 2);
+        assert_eq!(stats.ping_rtt, None);
+        assert_eq!(
+            stats.encryption,
+            EncryptionStatus::Encrypted(Mechanism::XChaCha20Poly1305)
+        );
+    });
+}
+
+#[test]
+fn stats_are_only_for_connected_users() {
+    simulate(|sim| async move {
+        let mut client = sim.client().await;
+        client
+            .run([
+                Step::Send(Request::Stats),
+                Step::Expect(Response::Error(ErrorCode::NotConnected)),
+            ])
+            .await;
+    });
+}
+
+/// Le client de test répond aux pings : le temps d'aller-retour est mesuré.
+#[test]
+fn ping_rtt_is_measured() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        sim.advance(Duration::from_secs(31)).await;
+        alice.run([Step::ExpectNothing]).await;
+
+        alice.send(Request::Stats).await;
+        assert!(stats(&mut alice).await.ping_rtt.is_some());
+    });
+}
+
+#[test]
+fn only_admins_see_others_stats() {
+    simulate(|sim| async move {
+        let sim = sim.admins(&["root"]);
+        let mut alice = sim.connect("alice").await;
+        let mut root = sim.connect("root").await;
+        alice.join("general").await;
+        alice.drain().await;
+
+        alice
+            .run([
+                Step::Send(Request::StatsOf("root".to_string())),
+                Step::Expect(Response::Error(ErrorCode::PermissionDenied)),
+            ])
+            .await;
+        root.send(Request::StatsOf("alice".to_string())).await;
+        let stats = stats(&mut root).await;
+        assert_eq!(stats.user, "alice");
+        assert_eq!(stats.channels, vec!["general".to_string()]);
+        root.run([
+            Step::Send(Request::StatsOf("nobody".to_string())),
+            Step::Expect(Response::Error(ErrorCode::UnknownUser(
+                "nobody".to_string(),
+            ))),
+        ])
+        .await;
+    });
+}
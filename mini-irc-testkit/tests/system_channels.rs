@@ -0,0 +1,124 @@
+use mini_irc_protocol::{ChanOp, ErrorCode, MessageReceiver, Request, Response};
+use mini_irc_testkit::{chan_in, error, simulate, Step};
+
+const ANNOUNCEMENTS: &str = "&annonces";
+
+/// Chaque utilisateur rejoint les canaux système dès sa connexion, sans en devenir
+/// propriétaire.
+#[test]
+fn system_channels_are_joined_on_connect() {
+    simulate(|sim| async move {
+        let sim = sim.system_channels(&[ANNOUNCEMENTS]);
+        let mut alice = sim.connect("alice").await;
+        alice
+            .run([
+                Step::Expect(Response::AckJoin {
+                    chan: ANNOUNCEMENTS.to_string(),
+                    users: vec!["alice".to_string()],
+                    owner: None,
+                }),
+                Step::Expect(chan_in(
+                    ANNOUNCEMENTS,
+                    ChanOp::UserAdd("alice".to_string()),
+                    1,
+                )),
+                Step::Send(Request::ClaimOp(ANNOUNCEMENTS.to_string())),
+                Step::Expect(error(ErrorCode::PermissionDenied)),
+                Step::ExpectNothing,
+            ])
+            .await;
+
+        // Un canal système quitté peut être rejoint de nouveau
+        alice.leave(ANNOUNCEMENTS).await;
+        alice.join(ANNOUNCEMENTS).await;
+        alice
+            .run([
+                Step::Expect(Response::AckLeave(ANNOUNCEMENTS.to_string())),
+                Step::Expect(Response::AckJoin {
+                    chan: ANNOUNCEMENTS.to_string(),
+                    users: vec!["alice".to_string()],
+                    owner: None,
+                }),
+                Step::Expect(chan_in(
+                    ANNOUNCEMENTS,
+                    ChanOp::UserAdd("alice".to_string()),
+                    3,
+                )),
+            ])
+            .await;
+    });
+}
+
+/// Seuls les administrateurs écrivent dans un canal système.
+#[test]
+fn only_admins_post_to_system_channels() {
+    simulate(|sim| async move {
+        let sim = sim.system_channels(&[ANNOUNCEMENTS]).admins(&["root"]);
+        let mut alice = sim.connect("alice").await;
+        let mut root = sim.connect("root").await;
+        sim.settle().await;
+        alice.drain().await;
+        root.drain().await;
+
+        alice
+            .run([
+                Step::Send(Request::Message {
+                    to: MessageReceiver::Channel(ANNOUNCEMENTS.to_string()),
+                    content: "hello".to_string(),
+                }),
+                Step::Expect(error(ErrorCode::PermissionDenied)),
+            ])
+            .await;
+        root.say(ANNOUNCEMENTS, "maintenance à 22h").await;
+        let announcement = chan_in(
+            ANNOUNCEMENTS,
+            ChanOp::Message {
+                from: "root".to_string(),
+                content: "maintenance à 22h".to_string(),
+            },
+            3,
+        );
+        alice.run([Step::Expect(announcement.clone())]).await;
+        root.run([Step::Expect(announcement)]).await;
+    });
+}
+
+/// Un nom réservé aux canaux système ne peut pas servir à créer un canal ordinaire.
+#[test]
+fn reserved_names_cannot_be_joined() {
+    simulate(|sim| async move {
+        let sim = sim.system_channels(&[ANNOUNCEMENTS]);
+        let mut alice = sim.connect("alice").await;
+        alice.drain().await;
+        alice
+            .run([
+                Step::Send(Request::JoinChan("&ops".to_string())),
+                Step::Expect(error(ErrorCode::UnknownChannel("&ops".to_string()))),
+                Step::Send(Request::ListChans),
+            ])
+            .await;
+        let Response::ChanList(channels) = alice.recv().await else {
+            panic!("expected the channel list");
+        };
+        let names: Vec<_> = channels.into_iter().map(|channel| channel.name).collect();
+        assert_eq!(names, [ANNOUNCEMENTS]);
+    });
+}
+
+/// Un nom d'utilisateur ne peut pas commencer par un préfixe de canal système configuré.
+#[test]
+fn nicknames_cannot_take_system_prefixes() {
+    simulate(|sim| async move {
+        let sim = sim.system_prefixes(&["!"]);
+        let mut client = sim.client().await;
+        client
+            .run([
+                Step::Send(Request::Connect("!alice".to_string())),
+                Step::Expect(error(ErrorCode::ChannelPrefixInNickname)),
+                // `&` n'est plus réservé
+                Step::Send(Request::Connect("&alice".to_string())),
+                Step::Expect(Response::AckConnect("&alice".to_string())),
+            ])
+            .await;
+    });
+}
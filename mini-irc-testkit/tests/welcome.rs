@@ -0,0 +1,85 @@
+use mini_irc_protocol::{ChanOp, ErrorCode, Notice, NoticeScope, Request, Response};
+use mini_irc_testkit::{simulate, Step};
+
+fn set_welcome(text: &str) -> Request {
+    Request::SetWelcome {
+        chan: "general".to_string(),
+        text: text.to_string(),
+    }
+}
+
+fn notice(notice: Notice) -> Response {
+    Response::Notice {
+        scope: NoticeScope::Channel("general".to_string()),
+        notice,
+    }
+}
+
+/// Le message d'accueil fixé par le propriétaire suit l'accusé d'entrée de chaque arrivant.
+#[test]
+fn welcome_follows_ack_join() {
+    simulate(|sim| async move {
+        let mut alice = sim.connect("alice").await;
+        let mut bob = sim.connect("bob").await;
+        alice.join("general").await;
+        sim.settle().await;
+        alice.drain().await;
+        alice
+            .run([
+                Step::Send(set_welcome("Bienvenue sur #general")),
+                Step::Expect(notice(Notice::WelcomeSet(
+                    "Bienvenue sur #general".to_string(),
+                ))),
+            ])
+            .await;
+
+        bob.join("general").await;
+        bob.run([
+            Step::Expect(Response::AckJoin {
+                chan: "general".to_string(),
+                users: vec!["alice".to_string(), "bob".to_string()],
+                owner: Some("alice".to_string()),
+            }),
+            Step::Expect(notice(Notice::Welcome(
+                "Bienvenue sur #general".to_string(),
+            ))),
+            Step::Expect(Response::Channel {
+                op: ChanOp::UserAdd("bob".to_string()),
+                chan: "general".to_string(),
+                seq: 2,
+            }),
+        ])
+        .await;
+
+        // Seul le propriétaire le modifie ; un texte vide le supprime
+        bob.run([
+            Step::Send(set_welcome("Spam")),
+            Step::Expect(Response::Error(ErrorCode::PermissionDenied)),
+        ])
+        .await;
+        alice.drain().await;
+        alice
+            .run([
+                Step::Send(set_welcome("")),
+                Step::Expect(notice(Notice::WelcomeCleared)),
+            ])
+            .await;
+        bob.leave("general").await;
+        sim.settle().await;
+        bob.drain().await;
+        bob.join("general").await;
+        bob.run([
+            Step::Expect(Response::AckJoin {
+                chan: "general".to_string(),
+                users: vec!["alice".to_string(), "bob".to_string()],
+                owner: Some("alice".to_string()),
+            }),
+            Step::Expect(Response::Channel {
+                op: ChanOp::UserAdd("bob".to_string()),
+                chan: "general".to_string(),
+                seq: 4,
+            }),
+        ])
+        .await;
+    });
+}
@@ -0,0 +1,108 @@
+use crate::widgets::grapheme_width;
+use std::borrow::Cow;
+use std::str::FromStr;
+use unicode_bidi::{BidiInfo, Level};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Base direction of a text. By default, it is the direction of its first letter.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TextDirection {
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl TextDirection {
+    fn level(self) -> Option<Level> {
+        match self {
+            TextDirection::Auto => None,
+            TextDirection::LeftToRight => Some(Level::ltr()),
+            TextDirection::RightToLeft => Some(Level::rtl()),
+        }
+    }
+}
+
+impl FromStr for TextDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(TextDirection::Auto),
+            "ltr" => Ok(TextDirection::LeftToRight),
+            "rtl" => Ok(TextDirection::RightToLeft),
+            _ => Err(format!(
+                "Unknown direction '{s}': expected auto, ltr or rtl."
+            )),
+        }
+    }
+}
+
+/// Whether some of `text` is displayed right to left.
+pub(crate) fn has_rtl(text: &str, direction: TextDirection) -> bool {
+    direction == TextDirection::RightToLeft || BidiInfo::new(text, direction.level()).has_rtl()
+}
+
+/// Byte offsets of the grapheme clusters of `text` in display order, from left to right, with
+/// whether each cluster is displayed right to left. Combining marks stay with their letter.
+pub(crate) fn visual_order(text: &str, direction: TextDirection) -> Vec<(usize, bool)> {
+    let info = BidiInfo::new(text, direction.level());
+    // A run may end inside a cluster (a joiner has its own level): each cluster goes with the
+    // run it starts in
+    let starts = text
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mut order = Vec::with_capacity(starts.len());
+    for paragraph in &info.paragraphs {
+        let (levels, runs) = info.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            let graphemes = starts.iter().filter(|i| run.contains(i)).map(|i| (*i, rtl));
+            if rtl {
+                order.extend(graphemes.rev());
+            } else {
+                order.extend(graphemes);
+            }
+        }
+    }
+    order
+}
+
+/// Characters of `text` in display order: the terminal writes them from left to right.
+#[allow(dead_code)] // Unused by the input fuzzer
+pub fn reorder(text: &str, direction: TextDirection) -> Cow<'_, str> {
+    if !has_rtl(text, direction) {
+        return Cow::Borrowed(text);
+    }
+    visual_order(text, direction)
+        .into_iter()
+        .filter_map(|(i, _)| text[i..].graphemes(true).next())
+        .collect::<String>()
+        .into()
+}
+
+/// Column at which each cursor position of `text` (the byte offset of the next grapheme
+/// cluster, or the length of the text) is displayed. In a right-to-left run, the cursor is on
+/// the right of the next cluster.
+pub(crate) fn cursor_columns(text: &str, direction: TextDirection) -> Vec<(usize, u16)> {
+    let mut columns = Vec::with_capacity(text.len() + 1);
+    let mut column = 0;
+    // Column of the end of the text: after the last character in logical order
+    let mut end = (0, 0);
+    for (i, rtl) in visual_order(text, direction) {
+        let width = grapheme_width(text[i..].graphemes(true).next().unwrap_or(" ")) as u16;
+        let (before, after) = if rtl {
+            (column + width, column)
+        } else {
+            (column, column + width)
+        };
+        columns.push((i, before));
+        if i >= end.0 {
+            end = (i, after);
+        }
+        column += width;
+    }
+    columns.push((text.len(), if text.is_empty() { 0 } else { end.1 }));
+    columns
+}
@@ -0,0 +1,40 @@
+/// Clipboard of the cut, copied and pasted text. With the `clipboard` feature, the system
+/// clipboard is used when available; otherwise the text is only kept inside the UI.
+pub(crate) struct Clipboard {
+    text: String,
+    #[cfg(feature = "clipboard")]
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub(crate) fn new() -> Self {
+        Self {
+            text: String::new(),
+            // No clipboard without a display server, for instance over SSH
+            #[cfg(feature = "clipboard")]
+            system: arboard::Clipboard::new().ok(),
+        }
+    }
+
+    pub(crate) fn set(&mut self, text: String) {
+        #[cfg(feature = "clipboard")]
+        if let Some(system) = &mut self.system {
+            if system.set_text(text.clone()).is_ok() {
+                return;
+            }
+        }
+        self.text = text;
+    }
+
+    pub(crate) fn get(&mut self) -> String {
+        #[cfg(feature = "clipboard")]
+        if let Some(text) = self
+            .system
+            .as_mut()
+            .and_then(|system| system.get_text().ok())
+        {
+            return text;
+        }
+        self.text.clone()
+    }
+}
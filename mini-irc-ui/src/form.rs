@@ -0,0 +1,219 @@
+use crate::widgets::Input;
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Width of the forms, borders included.
+const FORM_WIDTH: u16 = 50;
+
+/// Connection parameters entered on the connect screen.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConnectInfo {
+    /// `ip:port` or `unix:///path/to/socket`
+    pub address: String,
+    pub nickname: String,
+    /// Close a session left open under the same nickname by this client, e.g. after a crash.
+    pub takeover: bool,
+}
+
+#[derive(Debug)]
+enum FieldKind {
+    Text(Input),
+    Toggle(bool),
+}
+
+#[derive(Debug)]
+struct Field {
+    label: &'static str,
+    kind: FieldKind,
+}
+
+impl Field {
+    fn text(label: &'static str, value: &str) -> Self {
+        Self {
+            label,
+            kind: FieldKind::Text(input_with_text(value)),
+        }
+    }
+
+    fn toggle(label: &'static str, value: bool) -> Self {
+        Self {
+            label,
+            kind: FieldKind::Toggle(value),
+        }
+    }
+
+    fn input_mut(&mut self) -> Option<&mut Input> {
+        match &mut self.kind {
+            FieldKind::Text(input) => Some(input),
+            FieldKind::Toggle(_) => None,
+        }
+    }
+
+    fn text_value(&self) -> String {
+        match &self.kind {
+            FieldKind::Text(input) => input.text.clone(),
+            FieldKind::Toggle(_) => String::new(),
+        }
+    }
+}
+
+fn input_with_text(value: &str) -> Input {
+    let mut input = Input {
+        display_width: FORM_WIDTH - 2,
+        ..Default::default()
+    };
+    value.chars().for_each(|c| input.insert_at_cursor(c));
+    input
+}
+
+/// What a key press did to a form.
+pub(crate) enum FormReaction {
+    Submit,
+    Cancel,
+}
+
+/// Multi-field form: Tab / Down and Shift+Tab / Up move the focus between fields, Space
+/// switches toggles, Enter submits and Esc cancels.
+#[derive(Debug)]
+pub(crate) struct Form {
+    title: &'static str,
+    fields: Vec<Field>,
+    focus: usize,
+    /// Error displayed under the fields
+    pub(crate) error: Option<String>,
+}
+
+impl Form {
+    pub(crate) fn connect(info: &ConnectInfo) -> Self {
+        Self {
+            title: "Connect to a mini-irc server",
+            fields: vec![
+                Field::text("Server (ip:port or unix:///path)", &info.address),
+                Field::text("Nickname", &info.nickname),
+                Field::toggle("Take over an open session", info.takeover),
+            ],
+            focus: if info.address.is_empty() { 0 } else { 1 },
+            error: None,
+        }
+    }
+
+    pub(crate) fn connect_info(&self) -> ConnectInfo {
+        ConnectInfo {
+            address: self.fields[0].text_value().trim().to_string(),
+            nickname: self.fields[1].text_value().trim().to_string(),
+            takeover: matches!(self.fields[2].kind, FieldKind::Toggle(true)),
+        }
+    }
+
+    /// Checks the fields before submitting the connect form.
+    pub(crate) fn validate_connect(&self) -> Result<ConnectInfo, String> {
+        let info = self.connect_info();
+        if info.address.is_empty() {
+            Err("The server address is required.".to_string())
+        } else if info.nickname.is_empty() {
+            Err("The nickname is required.".to_string())
+        } else if info.nickname.contains(char::is_whitespace) {
+            Err("The nickname cannot contain spaces.".to_string())
+        } else {
+            Ok(info)
+        }
+    }
+
+    pub(crate) fn react_to_key(&mut self, key: KeyEvent) -> Option<FormReaction> {
+        match key.code {
+            KeyCode::Enter => return Some(FormReaction::Submit),
+            KeyCode::Esc => return Some(FormReaction::Cancel),
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1) % self.fields.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                self.focus = (self.focus + self.fields.len() - 1) % self.fields.len()
+            }
+            code => {
+                let field = &mut self.fields[self.focus];
+                if let FieldKind::Toggle(value) = &mut field.kind {
+                    if code == KeyCode::Char(' ') {
+                        *value = !*value;
+                    }
+                } else if let Some(input) = field.input_mut() {
+                    match code {
+                        KeyCode::Char(c) => input.insert_at_cursor(c),
+                        KeyCode::Backspace => input.delete_behind_cursor(),
+                        KeyCode::Delete => input.delete_at_cursor(),
+                        KeyCode::Left => input.cursor_move_left(),
+                        KeyCode::Right => input.cursor_move_right(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub(crate) fn render<B: Backend>(&mut self, f: &mut Frame<B>) {
+        let area = f.size();
+        let height = 3 * self.fields.len() as u16 + 4;
+        let area = Rect::new(
+            area.x + area.width.saturating_sub(FORM_WIDTH) / 2,
+            area.y + area.height.saturating_sub(height) / 2,
+            FORM_WIDTH.min(area.width),
+            height.min(area.height),
+        );
+        f.render_widget(
+            Block::default().borders(Borders::ALL).title(self.title),
+            area,
+        );
+
+        let mut constraints = vec![Constraint::Length(3); self.fields.len()];
+        constraints.push(Constraint::Length(1));
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(constraints)
+            .split(area);
+
+        for (index, field) in self.fields.iter_mut().enumerate() {
+            let focused = index == self.focus;
+            let chunk = chunks[index];
+            let style = if focused {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            let block = Block::default().borders(Borders::ALL).title(field.label);
+            let text = match &mut field.kind {
+                FieldKind::Text(input) => {
+                    input.resize(chunk.width.saturating_sub(2));
+                    input.get_display_spans()
+                }
+                FieldKind::Toggle(value) => Spans::from(format!(
+                    "[{}] (Space to switch)",
+                    if *value { "x" } else { " " }
+                )),
+            };
+            f.render_widget(Paragraph::new(text).style(style).block(block), chunk);
+            if focused {
+                if let Some(input) = field.input_mut() {
+                    f.set_cursor(chunk.x + input.get_cursor_offset() + 1, chunk.y + 1);
+                }
+            }
+        }
+
+        let status = match &self.error {
+            Some(error) => Span::styled(error.clone(), Style::default().fg(Color::Red)),
+            None => Span::styled(
+                "Enter to connect, Esc to quit",
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+        };
+        f.render_widget(
+            Paragraph::new(Spans::from(status)),
+            chunks[self.fields.len()],
+        );
+    }
+}
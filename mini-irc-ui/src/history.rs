@@ -0,0 +1,158 @@
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// An entry of the history of a tab.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HistoryEntry {
+    UserMessage {
+        from: String,
+        content: String,
+    },
+    /// Message describing what the user does, displayed as `* from content`
+    Action {
+        from: String,
+        content: String,
+    },
+    Join(String),
+    Leave(String),
+    TopicChange {
+        by: String,
+        topic: String,
+    },
+    /// Information from the client or the server
+    Notice(String),
+    Error(String),
+}
+
+/// An entry of the history of a tab, with the time it was sent or received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimedEntry {
+    pub at: SystemTime,
+    pub entry: HistoryEntry,
+}
+
+impl TimedEntry {
+    /// An entry received now.
+    pub fn now(entry: HistoryEntry) -> Self {
+        Self {
+            at: SystemTime::now(),
+            entry,
+        }
+    }
+}
+
+/// Kinds of history entries, whose visibility can be toggled per tab.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    Messages,
+    Actions,
+    Joins,
+    Leaves,
+    Topics,
+    Notices,
+    Errors,
+}
+
+impl HistoryEntry {
+    pub fn kind(&self) -> EntryKind {
+        match self {
+            HistoryEntry::UserMessage { .. } => EntryKind::Messages,
+            HistoryEntry::Action { .. } => EntryKind::Actions,
+            HistoryEntry::Join(_) => EntryKind::Joins,
+            HistoryEntry::Leave(_) => EntryKind::Leaves,
+            HistoryEntry::TopicChange { .. } => EntryKind::Topics,
+            HistoryEntry::Notice(_) => EntryKind::Notices,
+            HistoryEntry::Error(_) => EntryKind::Errors,
+        }
+    }
+
+    /// Author of a message or an action.
+    pub fn author(&self) -> Option<&str> {
+        match self {
+            HistoryEntry::UserMessage { from, .. } | HistoryEntry::Action { from, .. } => {
+                Some(from)
+            }
+            _ => None,
+        }
+    }
+
+    /// Events about the channel rather than messages from its users.
+    pub fn is_event(&self) -> bool {
+        !matches!(
+            self,
+            HistoryEntry::UserMessage { .. } | HistoryEntry::Action { .. }
+        )
+    }
+
+    /// Text displayed in the history.
+    pub fn text(&self) -> String {
+        match self {
+            HistoryEntry::UserMessage { from, content } => format!("{from}: {content}"),
+            HistoryEntry::Action { from, content } => format!("* {from} {content}"),
+            HistoryEntry::Join(user) => format!("{user} joined"),
+            HistoryEntry::Leave(user) => format!("{user} left"),
+            HistoryEntry::TopicChange { by, topic } => format!("{by} changed the topic: {topic}"),
+            HistoryEntry::Notice(notice) => notice.clone(),
+            HistoryEntry::Error(error) => format!("Error: {error}"),
+        }
+    }
+}
+
+/// Summary of consecutive joins and leaves, such as "alice joined, 5 users reconnected": each
+/// user is counted once, according to their first and last event. Other entries are ignored.
+pub fn summarize_presence<'a>(entries: impl IntoIterator<Item = &'a HistoryEntry>) -> String {
+    // Users in order of appearance, with whether their first and last events are joins
+    let mut users: Vec<(&str, bool, bool)> = Vec::new();
+    for entry in entries {
+        let (name, joined) = match entry {
+            HistoryEntry::Join(name) => (name, true),
+            HistoryEntry::Leave(name) => (name, false),
+            _ => continue,
+        };
+        match users.iter_mut().find(|(user, _, _)| user == name) {
+            Some((_, _, last)) => *last = joined,
+            None => users.push((name, joined, joined)),
+        }
+    }
+    let outcomes = [
+        ((true, true), "joined"),
+        ((false, false), "left"),
+        ((false, true), "reconnected"),
+        ((true, false), "joined and left"),
+    ];
+    outcomes
+        .iter()
+        .filter_map(|((first, last), outcome)| {
+            let names: Vec<&str> = users
+                .iter()
+                .filter(|(_, f, l)| (f, l) == (first, last))
+                .map(|(name, _, _)| *name)
+                .collect();
+            match names[..] {
+                [] => None,
+                [name] => Some(format!("{name} {outcome}")),
+                _ => Some(format!("{} users {outcome}", names.len())),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl FromStr for EntryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "messages" => Ok(EntryKind::Messages),
+            "actions" => Ok(EntryKind::Actions),
+            "joins" => Ok(EntryKind::Joins),
+            "leaves" => Ok(EntryKind::Leaves),
+            "topics" => Ok(EntryKind::Topics),
+            "notices" => Ok(EntryKind::Notices),
+            "errors" => Ok(EntryKind::Errors),
+            _ => Err(format!(
+                "Unknown kind '{s}': expected messages, actions, joins, leaves, topics, notices or errors."
+            )),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use tui::style::{Color, Style};
+use tui::text::Span;
+
+/// Background colors of the badges, all readable with black letters.
+const PALETTE: [Color; 12] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+];
+
+/// Small colored badge of a nickname, shown before it in DM tab titles and user lists to tell
+/// similar nicknames apart at a glance.
+///
+/// The badge only depends on the nickname: the same user gets the same badge everywhere, and
+/// from one run to the next.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Identicon {
+    /// Initial of the nickname, then a letter picked by its hash.
+    pub letters: [char; 2],
+    pub color: Color,
+}
+
+// FNV-1a: unlike `DefaultHasher`, stable across Rust versions
+fn hash(nickname: &str) -> u64 {
+    nickname.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Identicon {
+    pub fn new(nickname: &str) -> Self {
+        let hash = hash(nickname);
+        let initial = nickname
+            .chars()
+            .find(|c| c.is_ascii_alphanumeric())
+            .map_or('?', |c| c.to_ascii_uppercase());
+        let letter = (b'A' + (hash % 26) as u8) as char;
+        Self {
+            letters: [initial, letter],
+            color: PALETTE[(hash >> 8) as usize % PALETTE.len()],
+        }
+    }
+
+    pub(crate) fn span(self) -> Span<'static> {
+        Span::styled(
+            self.letters.iter().collect::<String>(),
+            Style::default().fg(Color::Black).bg(self.color),
+        )
+    }
+}
@@ -1,3 +1,4 @@
+mod bidi;
 mod widgets;
 use crate::widgets::Input;
 use rand::prelude::*;
@@ -9,38 +10,92 @@ fn main() {
             let mut history = Vec::new();
             let mut input = Input {
                 display_width,
+                invalid_from: Some(8),
                 ..Default::default()
             };
-            let chars = vec!['a', 'é', '字']; //, '𒈙'];
+            // Combining acute accent and zero width joiner, to build grapheme clusters
+            let chars = ['a', 'é', '字', ' ', '.', 'א', '\u{301}', '\u{200d}', '👨']; //, '𒈙'];
             let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                 for _ in 0..10_000 {
                     let r: u8 = rng.gen();
-                    if r < 100 {
+                    if r < 75 {
                         let c = chars[rng.gen::<usize>() % chars.len()];
                         history.push(c.to_string());
                         //println!("{}", c);
                         input.insert_at_cursor(c);
+                    } else if r < 80 {
+                        history.push("Shift+<-".to_string());
+                        input.start_selection();
+                        input.cursor_move_left();
+                    } else if r < 85 {
+                        history.push("Shift+->".to_string());
+                        input.start_selection();
+                        input.cursor_move_right();
+                    } else if r < 88 {
+                        history.push("Ctrl+X".to_string());
+                        input.delete_selection();
+                    } else if r < 91 {
+                        history.push("Ctrl+V".to_string());
+                        input.insert_str_at_cursor("a 字\n👨\u{200d}👩\u{200d}👧");
+                    } else if r < 95 {
+                        history.push("Ctrl+Z".to_string());
+                        input.undo();
+                    } else if r < 100 {
+                        history.push("Ctrl+Y".to_string());
+                        input.redo();
                     } else if r < 140 {
                         history.push("<-".to_string());
                         // println!("<-");
+                        input.clear_selection();
                         input.cursor_move_left();
                     } else if r < 180 {
                         history.push("->".to_string());
                         //println!("->");
+                        input.clear_selection();
                         input.cursor_move_right();
                     } else if r < 200 {
                         history.push("Del".to_string());
                         //println!("Bac<k");
                         input.delete_at_cursor();
-                    } else {
+                    } else if r < 220 {
                         history.push("Back".to_string());
                         //  println!("Del");
                         input.delete_behind_cursor();
+                    } else if r < 225 {
+                        history.push("Visual <-".to_string());
+                        input.clear_selection();
+                        input.cursor_move_visual(false);
+                    } else if r < 230 {
+                        history.push("Visual ->".to_string());
+                        input.clear_selection();
+                        input.cursor_move_visual(true);
+                    } else if r < 235 {
+                        history.push("Home".to_string());
+                        input.cursor_move_home();
+                    } else if r < 240 {
+                        history.push("End".to_string());
+                        input.cursor_move_end();
+                    } else if r < 245 {
+                        history.push("Ctrl+<-".to_string());
+                        input.cursor_move_word_left();
+                    } else if r < 250 {
+                        history.push("Ctrl+->".to_string());
+                        input.cursor_move_word_right();
+                    } else if r < 255 {
+                        history.push("Ctrl+W".to_string());
+                        input.delete_word_behind_cursor();
+                    } else {
+                        history.push("Ctrl+U".to_string());
+                        input.clear();
                     }
                     history.push(format!(
-                        "text: \"{}\", cursor_offset: {}, text_offset: {}",
-                        input.text, input.cursor_offset, input.text_offset
+                        "text: \"{}\", cursor_offset: {}, text_offset: {}, selection: {:?}",
+                        input.text,
+                        input.cursor_offset,
+                        input.text_offset,
+                        input.selected_text()
                     ));
+                    input.get_display_spans();
                 }
             }));
             if res.is_err() {
@@ -1,43 +1,134 @@
+mod bidi;
+mod clipboard;
+mod form;
+mod history;
+mod identicon;
+mod markdown;
+mod notification;
+mod recall;
+mod spoiler;
+mod users;
 mod widgets;
 
+use clipboard::Clipboard;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseEventKind},
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEventKind, KeyModifiers, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
-use std::{
-    collections::BTreeSet,
-    io::{self, Stdout},
-};
+use form::{Form, FormReaction};
+use notification::Notification;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Stdout};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime};
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     symbols::DOT,
     text::{Span, Spans, Text},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap},
     Frame, Terminal,
 };
-use widgets::Input;
+use users::{User, UserList, UserPopup};
+use widgets::{Input, SelectableList};
+
+pub use widgets::{MessageList, MessageListState};
+
+pub use bidi::{reorder, TextDirection};
+pub use form::ConnectInfo;
+pub use history::{summarize_presence, EntryKind, HistoryEntry, TimedEntry};
+pub use identicon::Identicon;
+pub use markdown::{render_markdown, CODE_STYLE};
+pub use notification::{Severity, DEFAULT_NOTIFICATION_DURATION};
+pub use recall::{InputHistory, INPUT_HISTORY_LEN};
+pub use spoiler::{has_spoiler, mask_spoilers, SPOILER_DELIMITER};
+pub use users::{Presence, Role, UserDetails};
 
 pub type MyTerminal = Terminal<CrosstermBackend<io::Stdout>>;
 
+/// Name of the tab listing the channels of the server.
+pub const BROWSE_TAB: &str = "browse";
+
+/// Name of the tab gathering the events of the server that belong to no other tab. Unlike
+/// other tabs, it cannot be removed.
+pub const NETWORK_TAB: &str = "network";
+
+/// Lag from which the lag meter turns red.
+pub const LAG_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Appended to a nickname completed at the beginning of a line, by default.
+pub const DEFAULT_COMPLETION_SUFFIX: &str = ": ";
+
+/// Share of the maximum message length from which the input shows a counter, in percent.
+const COUNTER_THRESHOLD: usize = 80;
+
+/// Smallest terminal in which the interface fits: a placeholder is displayed in smaller ones.
+pub const MIN_WIDTH: u16 = 24;
+pub const MIN_HEIGHT: u16 = 13;
+
 #[derive(Copy, Clone, PartialEq)]
 enum InputMode {
     Normal,
     Editing,
+    /// Browsing the user list of the current tab
+    UserList,
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct Tab {
     name: String,
-    history: Vec<(String, String)>,
-    offset: usize,
-    users: BTreeSet<String>,
+    history: Vec<TimedEntry>,
+    /// Kinds of history entries that are not displayed
+    hidden: HashSet<EntryKind>,
+    scroll: MessageListState,
+    users: UserList,
+    /// Index of the selected user in the user list
+    selected_user: usize,
     /// Current value of the input box
     input: Input,
     has_unread_message: bool,
+    /// Whether an unread message mentions the user
+    mentioned: bool,
+    /// Index in the history of the first message that arrived while the tab was not read,
+    /// until the user leaves the tab
+    unread_from: Option<usize>,
+    /// Channels to choose from, if this is the browse tab
+    channels: Option<SelectableList<ChannelEntry>>,
+    /// Whether the channel is being joined, until the server acknowledges it
+    pending: bool,
+    /// Whether new messages are kept without setting the unread and mention indicators
+    muted: bool,
+    /// Whether older messages were requested with [`KeyReaction::LoadOlder`], until they are
+    /// given to [`App::prepend_history`]
+    loading_older: bool,
+    /// Whether the history starts with the oldest message available
+    complete: bool,
+    /// Indices in the history of the messages whose spoilers are revealed
+    revealed: HashSet<usize>,
+}
+
+/// Protection of the connection to the server, shown at the right of the help line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Security {
+    /// The traffic is readable by anyone on the way.
+    Plain,
+    /// The traffic is encrypted with the given mechanism, for instance "XChaCha20-Poly1305".
+    Encrypted(String),
+}
+
+/// A channel in the browse tab.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelEntry {
+    pub name: String,
+    pub users: usize,
+    pub topic: Option<String>,
 }
 
 impl Tab {
@@ -63,11 +154,74 @@ pub struct AppState {
     /// Tabs: one for every chan joined and private conversation
     tabs: Vec<Tab>,
     /// Notification to display.
-    notif: Option<String>,
+    notif: Option<Notification>,
+    /// How long notifications stay displayed, forever if `None`.
+    notif_duration: Option<Duration>,
     /// Index of the current tab.
     current_tab: Option<usize>,
     /// Empty tab.
     empty_tab: Box<Tab>,
+    /// User popup, opened from the user list.
+    popup: Option<UserPopup>,
+    /// Users whose messages are not displayed.
+    ignored: HashSet<String>,
+    /// Maximum length of a message accepted by the server, in bytes.
+    max_message_len: Option<usize>,
+    /// Over-limit message that will be split if it is submitted again.
+    pending_split: Option<String>,
+    /// Nickname of the user, to detect mentions.
+    nickname: Option<String>,
+    /// Appended to a nickname completed at the beginning of a line.
+    completion_suffix: String,
+    /// Text cut or copied from the input.
+    clipboard: Clipboard,
+    /// Whether consecutive joins and leaves are summarized in one line.
+    collapse_presence: bool,
+    /// Whether `*bold*`, `_italic_` and `` `code` `` are rendered in messages.
+    markdown: bool,
+    /// Whether a one-shot direct message opens a tab for the conversation.
+    msg_opens_tab: bool,
+    /// Number of ticks received, for animations.
+    ticks: u64,
+    /// Address of the server the connection was established with, once connected.
+    peer: Option<SocketAddr>,
+    /// Protection of the connection, once connected.
+    security: Option<Security>,
+    /// Round-trip time to the server, once measured.
+    lag: Option<Duration>,
+    /// Health of the server, once received.
+    server_stats: Option<ServerStats>,
+    /// Invitations to channels not joined yet, the most recent last.
+    invites: Vec<Invite>,
+    /// Inputs submitted in each tab, by tab name: they outlive the tab, to be recalled when it
+    /// is opened again.
+    recall: HashMap<String, InputHistory>,
+}
+
+/// Invitation to join a channel, received from another user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invite {
+    /// Channel name, without `#`.
+    pub chan: String,
+    pub by: String,
+}
+
+/// Health of the server, shown at the right of the help line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    pub users: usize,
+    pub channels: usize,
+    pub msgs_per_min: u64,
+}
+
+/// Layout of a tab, to restore it when the client restarts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TabLayout {
+    pub name: String,
+    /// Scroll offset, in entries from the bottom of the history.
+    pub scroll: usize,
+    /// Time of the last entry read, if any: later entries are marked unread when restored.
+    pub last_read: Option<SystemTime>,
 }
 
 impl Default for AppState {
@@ -76,17 +230,67 @@ impl Default for AppState {
             input_mode: InputMode::Normal,
             tabs: Vec::new(),
             notif: None,
+            notif_duration: Some(DEFAULT_NOTIFICATION_DURATION),
             current_tab: None,
             empty_tab: Box::new(Tab::default()),
+            popup: None,
+            ignored: HashSet::new(),
+            max_message_len: None,
+            pending_split: None,
+            nickname: None,
+            completion_suffix: DEFAULT_COMPLETION_SUFFIX.to_string(),
+            clipboard: Clipboard::new(),
+            collapse_presence: true,
+            markdown: true,
+            msg_opens_tab: false,
+            ticks: 0,
+            peer: None,
+            security: None,
+            lag: None,
+            server_stats: None,
+            invites: Vec::new(),
+            recall: HashMap::new(),
         }
     }
 }
 
 impl App {
+    /// Start the TUI. Does nothing if it is already started.
     pub fn start(&mut self) -> io::Result<()> {
-        self.terminal = Some(start_ui()?);
+        if self.terminal.is_none() {
+            self.terminal = Some(start_ui()?);
+        }
         Ok(())
     }
+
+    /// Show the connect screen, pre-filled with `initial`, until the user submits valid
+    /// parameters. `error` is displayed under the form, for instance why the previous attempt
+    /// failed. Returns `None` if the user quits. The TUI is started if needed.
+    pub fn connect_screen(
+        &mut self,
+        initial: &ConnectInfo,
+        error: Option<String>,
+    ) -> io::Result<Option<ConnectInfo>> {
+        self.start()?;
+        let mut form = Form::connect(initial);
+        form.error = error;
+        loop {
+            self.terminal.as_mut().unwrap().draw(|f| form.render(f))?;
+            if let Event::Key(key) = crossterm::event::read()? {
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
+                match form.react_to_key(key) {
+                    Some(FormReaction::Submit) => match form.validate_connect() {
+                        Ok(info) => return Ok(Some(info)),
+                        Err(e) => form.error = Some(e),
+                    },
+                    Some(FormReaction::Cancel) => return Ok(None),
+                    None => {}
+                }
+            }
+        }
+    }
     pub fn draw(&mut self) -> io::Result<()> {
         self.terminal.as_mut().expect("App::draw() can only be called after a successful call to App::start(), and cannot be called after an errorring call to App::draw()")
         .draw(|f| ui(f, &mut self.state)).map(|_| ())
@@ -103,13 +307,64 @@ impl Drop for App {
 
 impl AppState {
     pub(crate) fn get_mut_current_tab(&mut self) -> &mut Tab {
-        if !self.tabs.is_empty() && self.current_tab.is_some() {
-            self.tabs.get_mut(self.current_tab.unwrap()).unwrap()
+        match self.current_tab {
+            Some(index) if !self.tabs.is_empty() => self.tabs.get_mut(index).unwrap(),
+            _ => &mut self.empty_tab,
+        }
+    }
+
+    /// Remember an input submitted in the current tab, to recall it later.
+    pub(crate) fn remember_input(&mut self, input: &str) {
+        let Some(tab) = self.current_tab.and_then(|index| self.tabs.get(index)) else {
+            return;
+        };
+        self.recall.entry(tab.name.clone()).or_default().push(input);
+    }
+
+    /// Replace the input of the current tab by the input submitted before the recalled one if
+    /// `older`, or after it otherwise. Does nothing past the ends of the history.
+    pub(crate) fn recall_input(&mut self, older: bool) {
+        let Some(tab) = self.current_tab.and_then(|index| self.tabs.get_mut(index)) else {
+            return;
+        };
+        let Some(history) = self.recall.get_mut(&tab.name) else {
+            return;
+        };
+        let recalled = if older {
+            history.older(&tab.input.text)
         } else {
-            &mut self.empty_tab
+            history.newer()
+        };
+        if let Some(text) = recalled {
+            tab.input.replace_text(text);
         }
     }
 
+    /// Adapt the tabs to a new terminal width: the inputs scroll to keep their cursor visible,
+    /// and the histories cannot be scrolled past their first message.
+    pub(crate) fn resize(&mut self, width: u16) {
+        // Margins and borders around the input
+        let input_width = width.saturating_sub(4);
+        for tab in self
+            .tabs
+            .iter_mut()
+            .chain(std::iter::once(&mut *self.empty_tab))
+        {
+            tab.input.resize(input_width);
+            tab.scroll.offset = std::cmp::min(tab.history.len(), tab.scroll.offset);
+        }
+    }
+
+    pub(crate) fn notify(&mut self, severity: Severity, text: String) {
+        self.notif = Some(Notification {
+            text,
+            severity,
+            expires_at: self
+                .notif_duration
+                .map(|duration| Instant::now() + duration),
+        });
+    }
+
     pub(crate) fn get_mut_tab_or_insert(&mut self, tab: String) -> &mut Tab {
         if let Some(index) = self.get_tab_index(&tab) {
             self.tabs.get_mut(index)
@@ -133,20 +388,38 @@ impl AppState {
     }
 
     pub fn unset_unread_message(&mut self) {
-        self.get_mut_current_tab().has_unread_message = false;
+        let tab = self.get_mut_current_tab();
+        tab.has_unread_message = false;
+        tab.mentioned = false;
     }
 
-    pub fn current_users(&self) -> Option<impl Iterator<Item = &String>> {
-        if !self.tabs.is_empty() && self.current_tab.is_some() {
-            Some(
-                self.tabs
-                    .get(self.current_tab.unwrap())
-                    .unwrap()
-                    .users
-                    .iter(),
-            )
-        } else {
-            None
+    /// Switch to the tab at `index`. The unread separator of the tab we leave is removed.
+    pub(crate) fn select_tab(&mut self, index: usize) {
+        if self.current_tab != Some(index) {
+            self.get_mut_current_tab().unread_from = None;
+        }
+        self.current_tab = Some(index);
+        self.unset_unread_message();
+    }
+
+    /// Next tab with unread messages after the current one, wrapping around: tabs mentioning
+    /// the user first.
+    pub(crate) fn next_unread_tab(&self) -> Option<usize> {
+        let start = self.current_tab.map_or(0, |index| index + 1);
+        let in_order = (0..self.tabs.len()).map(|i| (start + i) % self.tabs.len());
+        let unread = in_order.filter(|&index| self.tabs[index].has_unread_message);
+        unread
+            .clone()
+            .find(|&index| self.tabs[index].mentioned)
+            .or_else(|| unread.clone().next())
+    }
+
+    pub(crate) fn current_users(&self) -> Option<impl Iterator<Item = &User>> {
+        match self.current_tab {
+            Some(index) if !self.tabs.is_empty() => {
+                Some(self.tabs.get(index).unwrap().users.iter())
+            }
+            _ => None,
         }
     }
 }
@@ -154,7 +427,12 @@ impl AppState {
 pub fn start_ui() -> io::Result<MyTerminal> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     let backend = CrosstermBackend::new(stdout);
     Terminal::new(backend)
 }
@@ -165,30 +443,122 @@ pub fn stop_ui(terminal: &mut MyTerminal) -> io::Result<()> {
     terminal
         .backend_mut()
         .execute(LeaveAlternateScreen)?
-        .execute(DisableMouseCapture)?;
+        .execute(DisableMouseCapture)?
+        .execute(DisableBracketedPaste)?;
     terminal.show_cursor()
 }
 
 pub enum KeyReaction {
     UserInput(String),
+    /// The user popup was opened: details about this user should be fetched, then given to
+    /// [`App::show_user_details`].
+    UserDetails(String),
+    /// A channel was picked in the browse tab.
+    JoinChannel(String),
+    /// A message too long for the server, split in several ones with [`split_message`].
+    UserInputs(Vec<String>),
+    /// The history of this tab was scrolled up to its first entry: older messages should be
+    /// fetched, then given to [`App::prepend_history`].
+    LoadOlder(String),
     Quit,
 }
 
 impl App {
     pub fn react_to_event(&mut self, event: Event) -> Option<KeyReaction> {
+        // Since crossterm 0.26, Windows also reports key releases: only presses (and repeats
+        // while a key is held down) are handled, otherwise every key would be handled twice
+        if let Event::Key(key) = event {
+            if key.kind == KeyEventKind::Release {
+                return None;
+            }
+        }
+
         // Mode-indepent actions
         let input_mode = self.state.input_mode;
+        let max_message_len = self.state.max_message_len;
+        let nickname = self.state.nickname.clone();
+        let completion_suffix = self.state.completion_suffix.clone();
+
+        if let Event::Key(key) = event {
+            if key.modifiers.contains(KeyModifiers::CONTROL) {
+                // Clipboard: Ctrl+C copies the selection of the input, if any, and quits otherwise
+                let editing = input_mode == InputMode::Editing;
+                let selection = editing
+                    .then(|| self.state.get_mut_current_tab().input.selected_text())
+                    .flatten()
+                    .map(str::to_string);
+                match key.code {
+                    KeyCode::Char('c') => {
+                        return match selection {
+                            Some(text) => {
+                                self.state.clipboard.set(text);
+                                None
+                            }
+                            None => Some(KeyReaction::Quit),
+                        };
+                    }
+                    KeyCode::Char('x') if editing => {
+                        if let Some(text) = selection {
+                            self.state.clipboard.set(text);
+                            self.state.get_mut_current_tab().input.delete_selection();
+                        }
+                        return None;
+                    }
+                    KeyCode::Char('v') if editing => {
+                        let text = self.state.clipboard.get();
+                        self.state
+                            .get_mut_current_tab()
+                            .input
+                            .insert_str_at_cursor(&text);
+                        return None;
+                    }
+                    _ => {}
+                }
+            }
+            // F3: accept the most recent invitation
+            if key.code == KeyCode::F(3) {
+                return self
+                    .accept_invite(None)
+                    .map(|invite| KeyReaction::JoinChannel(invite.chan));
+            }
+            // Alt+A: jump to the next unread tab
+            if key.code == KeyCode::Char('a') && key.modifiers.contains(KeyModifiers::ALT) {
+                if let Some(index) = self.state.next_unread_tab() {
+                    self.state.select_tab(index);
+                }
+                return None;
+            }
+        }
+
+        if let Event::Resize(width, _) = event {
+            self.state.resize(width);
+            return None;
+        }
+
+        // Up and Down recall the inputs submitted in the tab, like in a shell
+        if let Event::Key(key) = event {
+            if input_mode == InputMode::Editing && matches!(key.code, KeyCode::Up | KeyCode::Down) {
+                self.state.recall_input(key.code == KeyCode::Up);
+                return None;
+            }
+        }
+
         let tab = self.state.get_mut_current_tab();
 
         if let Event::Mouse(mouse_event) = event {
             match mouse_event.kind {
                 MouseEventKind::ScrollUp => {
-                    tab.offset = std::cmp::min(tab.history.len(), tab.offset + 1);
+                    tab.scroll.offset = std::cmp::min(tab.history.len(), tab.scroll.offset + 1);
+                    if tab.scroll.offset == tab.history.len() && !tab.loading_older && !tab.complete
+                    {
+                        tab.loading_older = true;
+                        return Some(KeyReaction::LoadOlder(tab.name.clone()));
+                    }
                 }
 
                 MouseEventKind::ScrollDown => {
-                    tab.offset = tab.offset.saturating_sub(1);
-                    if tab.offset == 0 {
+                    tab.scroll.offset = tab.scroll.offset.saturating_sub(1);
+                    if tab.scroll.offset == 0 {
                         tab.has_unread_message = false;
                     }
                 }
@@ -207,27 +577,47 @@ impl App {
                         KeyCode::Char('q') => {
                             return Some(KeyReaction::Quit);
                         }
+                        KeyCode::Up => {
+                            if let Some(channels) = &mut tab.channels {
+                                channels.select_previous();
+                            }
+                        }
+                        KeyCode::Down => {
+                            if let Some(channels) = &mut tab.channels {
+                                channels.select_next();
+                            }
+                        }
+                        KeyCode::Enter => {
+                            if let Some(channel) =
+                                tab.channels.as_ref().and_then(SelectableList::selected)
+                            {
+                                return Some(KeyReaction::JoinChannel(channel.name.clone()));
+                            }
+                        }
+                        KeyCode::Char('u') if !tab.users.is_empty() => {
+                            tab.selected_user = tab.selected_user.min(tab.users.len() - 1);
+                            self.state.input_mode = InputMode::UserList;
+                        }
                         KeyCode::Left
                             if self.state.current_tab.is_some() && !self.state.tabs.is_empty() =>
                         {
                             let index = self.state.current_tab.unwrap();
-                            self.state.current_tab = if index == 0 {
-                                Some(self.state.tabs.len() - 1)
+                            self.state.select_tab(if index == 0 {
+                                self.state.tabs.len() - 1
                             } else {
-                                Some(index - 1)
-                            };
-                            self.state.unset_unread_message();
+                                index - 1
+                            });
                         }
-                        KeyCode::Right => {
-                            if self.state.current_tab.is_some() && !self.state.tabs.is_empty() {
-                                let index = self.state.current_tab.unwrap();
-                                self.state.current_tab = if index == self.state.tabs.len() - 1 {
-                                    Some(0)
+                        KeyCode::Right
+                            if self.state.current_tab.is_some() && !self.state.tabs.is_empty() =>
+                        {
+                            let index = self.state.current_tab.unwrap();
+                            self.state
+                                .select_tab(if index == self.state.tabs.len() - 1 {
+                                    0
                                 } else {
-                                    Some(index + 1)
-                                };
-                                self.state.unset_unread_message();
-                            }
+                                    index + 1
+                                });
                         }
                         _ => {}
                     }
@@ -235,18 +625,106 @@ impl App {
             }
 
             InputMode::Editing => {
+                if let Event::Paste(text) = &event {
+                    tab.input.insert_str_at_cursor(text);
+                }
                 if let Event::Key(key) = event {
+                    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                    // Moving the cursor with Shift selects text
+                    let movement = matches!(
+                        key.code,
+                        KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End
+                    ) || (ctrl
+                        && matches!(key.code, KeyCode::Char('a') | KeyCode::Char('e')));
+                    if movement && key.modifiers.contains(KeyModifiers::SHIFT) {
+                        tab.input.start_selection();
+                    } else if movement {
+                        tab.input.clear_selection();
+                    }
                     match key.code {
                         KeyCode::Enter => {
-                            let s = tab.input.submit();
-                            let res = KeyReaction::UserInput(s);
-                            return Some(res);
+                            // Commands are not limited, only messages
+                            let over_limit = max_message_len.filter(|max| {
+                                !tab.input.text.starts_with('/') && tab.input.text.len() > *max
+                            });
+                            let Some(max) = over_limit else {
+                                let s = tab.input.submit();
+                                self.state.remember_input(&s);
+                                self.state.pending_split = None;
+                                let res = KeyReaction::UserInput(s);
+                                return Some(res);
+                            };
+                            let text = tab.input.text.clone();
+                            let parts = split_message(&text, max);
+                            if self.state.pending_split.as_ref() == Some(&text) {
+                                // Second submission: the user accepted to split the message
+                                self.state.pending_split = None;
+                                self.state.get_mut_current_tab().input.submit();
+                                self.state.remember_input(&text);
+                                return Some(KeyReaction::UserInputs(parts));
+                            }
+                            self.state.notify(
+                                Severity::Warning,
+                                format!(
+                                    "Message too long: {}/{max} bytes. Press Enter again to send it in {} messages.",
+                                    text.len(),
+                                    parts.len()
+                                ),
+                            );
+                            self.state.pending_split = Some(text);
                         }
 
+                        // Complete the nickname before the cursor, addressing its user at the
+                        // beginning of a line
+                        KeyCode::Tab => {
+                            let (start, word) = tab.input.word_behind_cursor();
+                            let word = word.to_lowercase();
+                            let completion = tab
+                                .users
+                                .iter()
+                                .map(|user| user.name.as_str())
+                                .filter(|name| Some(*name) != nickname.as_deref())
+                                .filter(|name| {
+                                    !word.is_empty() && name.to_lowercase().starts_with(&word)
+                                })
+                                .min_by_key(|name| name.to_lowercase());
+                            if let Some(name) = completion {
+                                let suffix = if tab.input.text[..start].trim().is_empty() {
+                                    completion_suffix.as_str()
+                                } else {
+                                    ""
+                                };
+                                let completed = format!("{name}{suffix}");
+                                tab.input.replace_behind_cursor(start, &completed);
+                            }
+                        }
+                        KeyCode::Char('u') if ctrl => {
+                            tab.input.clear();
+                        }
+                        KeyCode::Char('w') if ctrl => {
+                            tab.input.delete_word_behind_cursor();
+                        }
+                        KeyCode::Char('a') if ctrl => {
+                            tab.input.cursor_move_home();
+                        }
+                        KeyCode::Char('e') if ctrl => {
+                            tab.input.cursor_move_end();
+                        }
+                        KeyCode::Char('z') if ctrl => {
+                            tab.input.undo();
+                        }
+                        KeyCode::Char('y') if ctrl => {
+                            tab.input.redo();
+                        }
+                        // Other shortcuts are not typed
+                        KeyCode::Char(_) if ctrl => {}
                         KeyCode::Char(c) => {
                             //Find the first character for which the cumulated width is larger than current offset
                             tab.input.insert_at_cursor(c);
                         }
+                        KeyCode::Backspace if key.modifiers.contains(KeyModifiers::ALT) => {
+                            tab.input.delete_word_behind_cursor();
+                        }
                         KeyCode::Backspace => {
                             tab.input.delete_behind_cursor();
                         }
@@ -257,11 +735,56 @@ impl App {
                         KeyCode::Esc => {
                             self.state.input_mode = InputMode::Normal;
                         }
+                        KeyCode::Left if ctrl => {
+                            tab.input.cursor_move_word_left();
+                        }
+                        KeyCode::Right if ctrl => {
+                            tab.input.cursor_move_word_right();
+                        }
                         KeyCode::Left => {
-                            tab.input.cursor_move_left();
+                            tab.input.cursor_move_visual(false);
                         }
                         KeyCode::Right => {
-                            tab.input.cursor_move_right();
+                            tab.input.cursor_move_visual(true);
+                        }
+                        KeyCode::Home => {
+                            tab.input.cursor_move_home();
+                        }
+                        KeyCode::End => {
+                            tab.input.cursor_move_end();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            InputMode::UserList => {
+                if let Event::Key(key) = event {
+                    if self.state.popup.is_some() {
+                        self.react_to_popup_key(key.code);
+                        return None;
+                    }
+                    let tab = self.state.get_mut_current_tab();
+                    match key.code {
+                        KeyCode::Up => {
+                            tab.selected_user = tab.selected_user.saturating_sub(1);
+                        }
+                        KeyCode::Down => {
+                            tab.selected_user =
+                                (tab.selected_user + 1).min(tab.users.len().saturating_sub(1));
+                        }
+                        KeyCode::Enter => {
+                            if let Some(user) = tab.users.get(tab.selected_user) {
+                                let name = user.name.clone();
+                                self.state.popup = Some(UserPopup {
+                                    name: name.clone(),
+                                    details: None,
+                                });
+                                return Some(KeyReaction::UserDetails(name));
+                            }
+                        }
+                        KeyCode::Esc => {
+                            self.state.input_mode = InputMode::Normal;
                         }
                         _ => {}
                     }
@@ -272,6 +795,46 @@ impl App {
         None
     }
 
+    // Quick actions of the user popup
+    fn react_to_popup_key(&mut self, code: KeyCode) {
+        let Some(popup) = &self.state.popup else {
+            return;
+        };
+        let name = popup.name.clone();
+        match code {
+            // Open a DM with the user
+            KeyCode::Char('d') => {
+                let tab = format!("@{name}");
+                self.add_tab(tab.clone());
+                if let Some(index) = self.state.get_tab_index(&tab) {
+                    self.state.select_tab(index);
+                }
+                self.state.popup = None;
+                self.state.input_mode = InputMode::Editing;
+            }
+            // Toggle: a user who was ignored is removed by the guard, others are added
+            KeyCode::Char('i') if !self.state.ignored.remove(&name) => {
+                self.state.ignored.insert(name);
+            }
+            KeyCode::Esc => {
+                self.state.popup = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Fill the user popup with the details requested by [`KeyReaction::UserDetails`].
+    /// Returns false if the popup is not waiting for this user anymore.
+    pub fn show_user_details(&mut self, details: UserDetails) -> bool {
+        match &mut self.state.popup {
+            Some(popup) if popup.name == details.name => {
+                popup.details = Some(details);
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn add_user(&mut self, username: String, tab: String) {
         let tab = self.state.get_mut_tab_or_insert(tab);
         tab.users.insert(username);
@@ -284,6 +847,29 @@ impl App {
         }
     }
 
+    /// Set the owner of a channel tab: they are shown with the owner prefix, even after leaving
+    /// and joining again, until another owner is set.
+    pub fn set_owner(&mut self, username: &str, tab: &str) {
+        if let Some(index) = self.state.get_tab_index(tab) {
+            self.state.tabs[index].users.set_owner(username);
+        }
+    }
+
+    /// Set the role of a user in a tab, shown as a prefix (`~`, `@`, `+`).
+    pub fn set_user_role(&mut self, username: &str, role: Role, tab: String) {
+        if let Some(index) = self.state.get_tab_index(&tab) {
+            let tab = self.state.tabs.get_mut(index).unwrap();
+            tab.users.update(username, |user| user.role = role);
+        }
+    }
+
+    /// Set the presence of a user in every tab they appear in.
+    pub fn set_user_presence(&mut self, username: &str, presence: Presence) {
+        for tab in self.state.tabs.iter_mut() {
+            tab.users.update(username, |user| user.presence = presence);
+        }
+    }
+
     pub fn add_tab(&mut self, tab: String) {
         if self.state.get_tab_index(&tab).is_none() {
             self.state.tabs.push(Tab::new(tab));
@@ -294,22 +880,80 @@ impl App {
         }
     }
 
+    /// Add the tab of a joined channel, or confirm the one opened by [`App::add_pending_tab`].
     pub fn add_tab_with_users(&mut self, tab: String, users: Vec<String>) {
-        if self.state.get_tab_index(&tab).is_none() {
-            let mut tab = Tab::new(tab);
-            users.into_iter().for_each(|nickname| {
-                tab.users.insert(nickname);
-            });
-            self.state.tabs.push(tab);
-        }
+        // Joined one way or another: the invitation is no longer pending
+        self.state
+            .invites
+            .retain(|invite| tab.strip_prefix('#') != Some(invite.chan.as_str()));
+        let tab = self.state.get_mut_tab_or_insert(tab);
+        tab.pending = false;
+        users.into_iter().for_each(|nickname| {
+            tab.users.insert(nickname);
+        });
 
         if self.state.current_tab.is_none() {
             self.state.current_tab = Some(0);
         }
     }
 
-    /// Remove a tab.
+    /// Replace the users of a tab, for instance with the full member list of a channel when
+    /// joins or leaves may have been missed. Does nothing if the tab does not exist.
+    pub fn set_users(&mut self, tab: &str, users: Vec<String>) {
+        if let Some(index) = self.state.get_tab_index(tab) {
+            let tab = &mut self.state.tabs[index];
+            tab.users.replace(users);
+            tab.selected_user = tab.selected_user.min(tab.users.len().saturating_sub(1));
+        }
+    }
+
+    /// Open and select the tab of a channel being joined, before the server answers. It is
+    /// confirmed by [`App::add_tab_with_users`], or removed by [`App::cancel_pending_tab`] if
+    /// the server refuses the join.
+    pub fn add_pending_tab(&mut self, tab: String) {
+        if self.state.get_tab_index(&tab).is_none() {
+            let mut pending = Tab::new(tab.clone());
+            pending.pending = true;
+            if let Some(nickname) = &self.state.nickname {
+                pending.users.insert(nickname.clone());
+            }
+            self.state.tabs.push(pending);
+        }
+        if let Some(index) = self.state.get_tab_index(&tab) {
+            self.state.select_tab(index);
+        }
+    }
+
+    /// Remove a tab opened by [`App::add_pending_tab`] and not confirmed yet, whose join was
+    /// refused. Returns `false`, leaving the tab alone, if it is not pending.
+    pub fn cancel_pending_tab(&mut self, tab: &str) -> bool {
+        let pending = self
+            .state
+            .tabs
+            .iter()
+            .any(|candidate| candidate.pending && candidate.name == tab);
+        if pending {
+            self.remove_tab(tab.to_string());
+        }
+        pending
+    }
+
+    /// Fill the browse tab with the channels of the server, and switch to it.
+    pub fn show_channel_list(&mut self, channels: Vec<ChannelEntry>) {
+        let tab = self.state.get_mut_tab_or_insert(BROWSE_TAB.to_string());
+        tab.channels
+            .get_or_insert_with(SelectableList::default)
+            .set_items(channels);
+        if let Some(index) = self.state.get_tab_index(BROWSE_TAB) {
+            self.state.select_tab(index);
+        }
+    }
+
+    /// Remove a tab, except the [`NETWORK_TAB`].
     pub fn remove_tab(&mut self, tab: String) {
+        if tab == NETWORK_TAB {
+            return;
+        }
         if let (Some(index), Some(current_index)) =
             (self.state.get_tab_index(&tab), self.state.current_tab)
         {
@@ -323,45 +967,377 @@ impl App {
     }
 
     pub fn push_message(&mut self, from: String, message: String, tab_name: String) {
+        self.push_entry(
+            HistoryEntry::UserMessage {
+                from,
+                content: message,
+            },
+            tab_name,
+        );
+    }
+
+    /// Add an entry to the history of a tab, if the tab exists.
+    pub fn push_entry(&mut self, entry: HistoryEntry, tab_name: String) {
+        if entry
+            .author()
+            .is_some_and(|from| self.state.ignored.contains(from))
+        {
+            return;
+        }
         if let Some(index) = self.state.get_tab_index(&tab_name) {
             // Tab exists for sure here.
             let is_current_tab = self.state.is_current_tab(index);
+            let mentioned = match (&self.state.nickname, &entry) {
+                (
+                    Some(nickname),
+                    HistoryEntry::UserMessage { from, content }
+                    | HistoryEntry::Action { from, content },
+                ) => from != nickname && content.to_lowercase().contains(&nickname.to_lowercase()),
+                _ => false,
+            };
             let tab = self.state.get_mut_tab_or_insert(tab_name.clone());
-            tab.history.push((from, message));
-            if tab.offset != 0 || !is_current_tab {
+            // Joins and leaves are not worth a look at the tab
+            let noise = matches!(entry, HistoryEntry::Join(_) | HistoryEntry::Leave(_))
+                || tab.hidden.contains(&entry.kind());
+            tab.history.push(TimedEntry::now(entry));
+            if noise || tab.muted {
+                return;
+            }
+            if tab.scroll.offset != 0 || !is_current_tab {
                 tab.has_unread_message = true;
+                tab.mentioned |= mentioned;
+                tab.unread_from.get_or_insert(tab.history.len() - 1);
+            }
+        }
+    }
+
+    /// Insert older entries at the beginning of the history of a tab, answering
+    /// [`KeyReaction::LoadOlder`]. `more` tells whether even older entries can be loaded: if
+    /// not, scrolling to the top of the tab does not ask for them anymore.
+    pub fn prepend_history(&mut self, tab_name: &str, entries: Vec<TimedEntry>, more: bool) {
+        let ignored = &self.state.ignored;
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|timed| {
+                timed
+                    .entry
+                    .author()
+                    .is_none_or(|from| !ignored.contains(from))
+            })
+            .collect();
+        if let Some(index) = self.state.get_tab_index(tab_name) {
+            let tab = &mut self.state.tabs[index];
+            // The scroll offset counts from the bottom: the displayed entries do not move
+            if let Some(unread_from) = &mut tab.unread_from {
+                *unread_from += entries.len();
             }
+            tab.revealed = tab.revealed.iter().map(|i| i + entries.len()).collect();
+            tab.history.splice(0..0, entries);
+            tab.loading_older = false;
+            tab.complete = !more;
         }
     }
 
+    /// History of a tab, from the oldest entry, if the tab exists.
+    pub fn history(&self, tab_name: &str) -> Option<&[TimedEntry]> {
+        let index = self.state.get_tab_index(tab_name)?;
+        Some(&self.state.tabs[index].history)
+    }
+
     pub fn get_current_tab(&self) -> String {
-        if !self.state.tabs.is_empty() && self.state.current_tab.is_some() {
-            self.state
-                .tabs
-                .get(self.state.current_tab.unwrap())
-                .unwrap()
-        } else {
-            &self.state.empty_tab
+        match self.state.current_tab {
+            Some(index) if !self.state.tabs.is_empty() => self.state.tabs.get(index).unwrap(),
+            _ => &self.state.empty_tab,
         }
         .name
         .clone()
     }
 
+    /// Set what is appended to a nickname completed with Tab at the beginning of a line,
+    /// [`DEFAULT_COMPLETION_SUFFIX`] by default. Nicknames completed elsewhere are inserted
+    /// alone.
+    pub fn set_completion_suffix(&mut self, suffix: String) {
+        self.state.completion_suffix = suffix;
+    }
+
+    /// Set the nickname of the user: tabs with unread messages mentioning it are highlighted,
+    /// and visited first by Alt+A.
+    pub fn set_nickname(&mut self, nickname: String) {
+        self.state.nickname = Some(nickname);
+    }
+
+    /// Set the maximum length of a message accepted by the server, in bytes. Longer messages
+    /// are refused, or split if submitted twice.
+    pub fn set_max_message_len(&mut self, len: usize) {
+        self.state.max_message_len = Some(len);
+    }
+
     /// Set a new notification to print.
     /// Might erase an old one.
     pub fn set_notification(&mut self, notif: String) {
-        self.state.notif = Some(notif);
+        self.notify(Severity::Info, notif);
+    }
+
+    /// Set a new notification with the given severity, which gives its color.
+    /// Might erase an old one.
+    pub fn notify(&mut self, severity: Severity, notif: String) {
+        self.state.notify(severity, notif);
+    }
+
+    /// Set how long the next notifications stay displayed, `None` to keep them until they are
+    /// cleared or replaced.
+    pub fn set_notification_duration(&mut self, duration: Option<Duration>) {
+        self.state.notif_duration = duration;
+    }
+
+    /// When the current notification expires, if ever: the application has to be drawn again
+    /// then.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.state.notif.as_ref().and_then(|notif| notif.expires_at)
+    }
+
+    /// Remove the current notification if it has expired at `now`. Returns whether it was
+    /// removed.
+    pub fn expire_notification(&mut self, now: Instant) -> bool {
+        let expired = self.next_expiry().is_some_and(|expiry| expiry <= now);
+        if expired {
+            self.state.notif = None;
+        }
+        expired
+    }
+
+    /// Show the address of the server the connection was established with, among those of
+    /// its name.
+    pub fn set_peer_address(&mut self, peer: SocketAddr) {
+        self.state.peer = Some(peer);
+    }
+
+    /// Show whether the connection to the server is encrypted.
+    pub fn set_security(&mut self, security: Security) {
+        self.state.security = Some(security);
+    }
+
+    /// Show the round-trip time to the server at the right of the help line, in red above
+    /// [`LAG_THRESHOLD`]: it tells whether slowness comes from the network or the server.
+    pub fn set_lag(&mut self, lag: Duration) {
+        self.state.lag = Some(lag);
+    }
+
+    /// Show the health of the server at the right of the help line.
+    pub fn set_server_stats(&mut self, stats: ServerStats) {
+        self.state.server_stats = Some(stats);
+    }
+
+    /// Summarize consecutive joins and leaves in one line ("5 users reconnected"), which is
+    /// the default, or display them all.
+    pub fn set_collapse_presence(&mut self, collapse: bool) {
+        self.state.collapse_presence = collapse;
+    }
+
+    /// Render or not the markdown subset of messages, see [`render_markdown`]. On by default.
+    pub fn set_markdown(&mut self, markdown: bool) {
+        self.state.markdown = markdown;
+    }
+
+    /// Open a tab for the conversation when a one-shot direct message is sent to a user
+    /// without one, which is not done by default.
+    pub fn set_msg_opens_tab(&mut self, open: bool) {
+        self.state.msg_opens_tab = open;
+    }
+
+    pub fn msg_opens_tab(&self) -> bool {
+        self.state.msg_opens_tab
+    }
+
+    /// Set the base direction of the input of the current tab, for right-to-left scripts.
+    pub fn set_input_direction(&mut self, direction: TextDirection) {
+        self.state.get_mut_current_tab().input.direction = direction;
+    }
+
+    /// Show or hide a kind of history entries in the current tab.
+    pub fn set_visible(&mut self, kind: EntryKind, visible: bool) {
+        let tab = self.state.get_mut_current_tab();
+        if visible {
+            tab.hidden.remove(&kind);
+        } else {
+            tab.hidden.insert(kind);
+        }
+    }
+
+    /// Mute or unmute a tab: the messages of a muted tab are kept, but never mark it unread
+    /// or mentioned.
+    pub fn set_muted(&mut self, tab: &str, muted: bool) {
+        if let Some(index) = self.state.get_tab_index(tab) {
+            let tab = &mut self.state.tabs[index];
+            tab.muted = muted;
+            if muted {
+                tab.has_unread_message = false;
+                tab.mentioned = false;
+            }
+        }
+    }
+
+    /// Whether the tab is muted, see [`App::set_muted`].
+    pub fn is_muted(&self, tab: &str) -> bool {
+        self.state
+            .get_tab_index(tab)
+            .is_some_and(|index| self.state.tabs[index].muted)
+    }
+
+    /// Reveal the spoilers of the `n`-th latest message containing some in the current tab,
+    /// 1 being the latest, or mask them again if they are revealed. Returns whether there is
+    /// such a message.
+    pub fn toggle_spoilers(&mut self, n: usize) -> bool {
+        let tab = self.state.get_mut_current_tab();
+        let found = tab
+            .history
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, timed)| match &timed.entry {
+                HistoryEntry::UserMessage { content, .. }
+                | HistoryEntry::Action { content, .. } => has_spoiler(content),
+                _ => false,
+            })
+            .nth(n.wrapping_sub(1));
+        let Some((index, _)) = found else {
+            return false;
+        };
+        if !tab.revealed.remove(&index) {
+            tab.revealed.insert(index);
+        }
+        true
+    }
+
+    /// Switch to the tab named `tab`. Returns whether it exists.
+    pub fn select_tab(&mut self, tab: &str) -> bool {
+        match self.state.get_tab_index(tab) {
+            Some(index) => {
+                self.state.select_tab(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Layout of the open tabs, in order, to save when the client exits. The [`NETWORK_TAB`]
+    /// and the [`BROWSE_TAB`] are left out, as they are opened again when needed.
+    pub fn layout(&self) -> Vec<TabLayout> {
+        self.state
+            .tabs
+            .iter()
+            .enumerate()
+            .filter(|(_, tab)| tab.name != NETWORK_TAB && tab.name != BROWSE_TAB)
+            .map(|(index, tab)| {
+                // What the current tab displays at the bottom is read
+                let read = match tab.unread_from {
+                    Some(unread_from)
+                        if !self.state.is_current_tab(index) || tab.scroll.offset != 0 =>
+                    {
+                        &tab.history[..unread_from]
+                    }
+                    _ => &tab.history[..],
+                };
+                TabLayout {
+                    name: tab.name.clone(),
+                    scroll: tab.scroll.offset,
+                    last_read: read.last().map(|timed| timed.at),
+                }
+            })
+            .collect()
+    }
+
+    /// Inputs submitted in each tab, oldest first, to be saved when the client quits.
+    pub fn input_history(&self) -> BTreeMap<String, Vec<String>> {
+        self.state
+            .recall
+            .iter()
+            .map(|(tab, history)| (tab.clone(), history.entries()))
+            .filter(|(_, entries)| !entries.is_empty())
+            .collect()
+    }
+
+    /// Restore the inputs saved by [`App::input_history`], to recall them with Up and Down.
+    pub fn restore_input_history(&mut self, saved: BTreeMap<String, Vec<String>>) {
+        self.state.recall = saved
+            .into_iter()
+            .map(|(tab, entries)| (tab, InputHistory::new(entries)))
+            .collect();
+    }
+
+    /// Restore the scroll offset and the unread separator of a tab saved by [`App::layout`],
+    /// once its history is loaded again. Does nothing if the tab does not exist.
+    pub fn restore_layout(&mut self, layout: &TabLayout) {
+        let Some(index) = self.state.get_tab_index(&layout.name) else {
+            return;
+        };
+        let is_current_tab = self.state.is_current_tab(index);
+        let tab = &mut self.state.tabs[index];
+        tab.scroll.offset = layout.scroll.min(tab.history.len());
+        let Some(last_read) = layout.last_read else {
+            return;
+        };
+        if let Some(first) = tab.history.iter().position(|timed| timed.at > last_read) {
+            tab.unread_from = Some(first);
+            if !tab.muted && !is_current_tab {
+                tab.has_unread_message = true;
+            }
+        }
+    }
+
+    /// Let time pass, to be called every 250ms: animates the interface and removes
+    /// the expired notification.
+    pub fn tick(&mut self, now: Instant) {
+        self.state.ticks = self.state.ticks.wrapping_add(1);
+        self.expire_notification(now);
     }
 
     /// Clear the current notification.
     pub fn clear_notif(&mut self) {
         self.state.notif.take();
     }
+
+    /// Record an invitation to `chan` (without `#`) and tell the user how to accept it. A
+    /// channel already joined is ignored, and a new invitation to the same channel replaces
+    /// the previous one.
+    pub fn add_invite(&mut self, chan: String, by: String) {
+        if self.state.get_tab_index(&format!("#{chan}")).is_some() {
+            return;
+        }
+        self.state.invites.retain(|invite| invite.chan != chan);
+        self.notify(
+            Severity::Info,
+            format!("{by} invites you: press F3 or /accept-invite to join #{chan}"),
+        );
+        self.state.invites.push(Invite { chan, by });
+    }
+
+    /// Pending invitations, the most recent last.
+    pub fn invites(&self) -> &[Invite] {
+        &self.state.invites
+    }
+
+    /// Remove and return the invitation to `chan` (without `#`), or the most recent one if
+    /// `None`. The channel is then to be joined.
+    pub fn accept_invite(&mut self, chan: Option<&str>) -> Option<Invite> {
+        let invites = &mut self.state.invites;
+        let index = match chan {
+            Some(chan) => invites.iter().position(|invite| invite.chan == chan)?,
+            None => invites.len().checked_sub(1)?,
+        };
+        Some(invites.remove(index))
+    }
 }
 
 pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
     let input_mode = app_state.input_mode;
 
+    let size = f.size();
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        render_too_small(f, size);
+        return;
+    }
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
@@ -384,7 +1360,9 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
                 Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(" to exit, "),
                 Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to enter messages."),
+                Span::raw(" to enter messages, "),
+                Span::styled("Alt+A", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" for the next unread tab."),
             ],
             Style::default(),
             //Style::default().add_modifier(Modifier::RAPID_BLINK),
@@ -399,11 +1377,62 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
             ],
             Style::default(),
         ),
+        InputMode::UserList if app_state.popup.is_some() => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to open a DM, "),
+                Span::styled("i", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to ignore, "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to close."),
+            ],
+            Style::default(),
+        ),
+        InputMode::UserList => (
+            vec![
+                Span::raw("Press "),
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" for details, "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to go back."),
+            ],
+            Style::default(),
+        ),
     };
     let mut text = Text::from(Spans::from(msg));
     text.patch_style(style);
     let help_message = Paragraph::new(text);
-    f.render_widget(help_message, chunks[1]);
+    // Server health, lag, address and protection of the connection, at the right
+    let indicators: Vec<Span> = [
+        app_state.server_stats.map(server_stats_span),
+        app_state.lag.map(lag_span),
+        app_state.peer.map(peer_span),
+        app_state.security.as_ref().map(security_span),
+    ]
+    .into_iter()
+    .flatten()
+    .flat_map(|indicator| [Span::raw(" "), indicator])
+    .skip(1)
+    .collect();
+    let help_area = if indicators.is_empty() {
+        chunks[1]
+    } else {
+        let indicators = Spans::from(indicators);
+        let areas = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Min(1),
+                    Constraint::Length(indicators.width() as u16),
+                ]
+                .as_ref(),
+            )
+            .split(chunks[1]);
+        f.render_widget(Paragraph::new(indicators), areas[1]);
+        areas[0]
+    };
+    f.render_widget(help_message, help_area);
 
     // Channel list
     if app_state.tabs.is_empty() {
@@ -420,18 +1449,30 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
             .tabs
             .iter()
             .map(|tab| {
-                if tab.has_unread_message {
-                    Span::styled(
-                        tab.name.clone(),
-                        Style::default().add_modifier(Modifier::BOLD),
-                    )
+                // Mentions blink, every other tick
+                let style = if tab.mentioned && (app_state.ticks / 2).is_multiple_of(2) {
+                    Style::default()
+                        .fg(Color::LightRed)
+                        .add_modifier(Modifier::BOLD)
+                } else if tab.pending {
+                    Style::default().add_modifier(Modifier::DIM | Modifier::ITALIC)
+                } else if tab.muted {
+                    Style::default().add_modifier(Modifier::DIM)
+                } else if tab.has_unread_message {
+                    Style::default().add_modifier(Modifier::BOLD)
                 } else {
-                    Span::from(tab.name.clone())
+                    Style::default()
+                };
+                let name = Span::styled(tab.name.clone(), style);
+                // Direct messages: badge of the peer before the name
+                match tab.name.strip_prefix('@') {
+                    Some(peer) => {
+                        Spans::from(vec![Identicon::new(peer).span(), Span::raw(" "), name])
+                    }
+                    None => Spans::from(name),
                 }
             })
-            .map(Spans::from)
             .collect();
-        // TODO add bold style for tabs with unread messages
         let tabs = Tabs::new(titles)
             .block(
                 Block::default()
@@ -446,20 +1487,33 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
         f.render_widget(tabs, chunks[3]);
     }
 
+    let max_message_len = app_state.max_message_len;
+    let collapse_presence = app_state.collapse_presence;
+    let markdown = app_state.markdown;
     let messages = app_state.get_mut_current_tab();
+    let mut user_list_state = ListState::default();
+    if input_mode == InputMode::UserList {
+        user_list_state.select(Some(messages.selected_user));
+    }
 
-    messages.input.resize(chunks[2].width - 2);
-    let input = Paragraph::new(messages.input.get_display_string())
+    messages.input.resize(chunks[2].width.saturating_sub(2));
+    // Commands are not limited, only messages
+    messages.input.invalid_from = max_message_len.filter(|_| !messages.input.text.starts_with('/'));
+    let input_title = match max_message_len {
+        Some(max) => input_counter(&messages.input.text, max),
+        None => Span::raw("Input"),
+    };
+    let input = Paragraph::new(messages.input.get_display_spans())
         .style(match input_mode {
-            InputMode::Normal => Style::default(),
+            InputMode::Normal | InputMode::UserList => Style::default(),
             InputMode::Editing => Style::default().fg(Color::Yellow),
         })
-        .block(Block::default().borders(Borders::ALL).title("Input"));
+        .block(Block::default().borders(Borders::ALL).title(input_title));
 
     f.render_widget(input, chunks[2]);
 
     match input_mode {
-        InputMode::Normal =>
+        InputMode::Normal | InputMode::UserList =>
             // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
             {}
 
@@ -467,7 +1521,7 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
             // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
             f.set_cursor(
                 // Put cursor past the end of the input text
-                chunks[2].x + messages.input.get_cursor_offset() as u16 + 1,
+                chunks[2].x + messages.input.get_cursor_offset() + 1,
                 // Move one line down, from the border to the input line
                 chunks[2].y + 1,
             )
@@ -476,49 +1530,56 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
 
     let main_windows = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Min(1), Constraint::Length(15)].as_ref())
+        // Room for the badge, a role prefix and a 12-letter nickname
+        .constraints([Constraint::Min(1), Constraint::Length(18)].as_ref())
         .split(chunks[0]);
 
-    let max_messages = (main_windows[0].height - 2) as usize;
-    let to_skip = if messages.history.len() <= max_messages {
-        0
+    if let Some(channels) = &messages.channels {
+        render_channel_list(f, channels, main_windows[0]);
     } else {
-        messages.offset = std::cmp::min(messages.offset, messages.history.len() - max_messages);
-        (messages.history.len() - max_messages).saturating_sub(messages.offset)
-    };
-
-    let messages: Vec<ListItem> = messages
-        .history
-        .iter()
-        .skip(to_skip)
-        .map(|m| {
-            let content = vec![Spans::from(Span::raw(format!("{}: {}", m.0, m.1)))];
-            ListItem::new(content)
-        })
-        .collect();
-    let mut all_messages = vec![ListItem::new(" "); max_messages.saturating_sub(messages.len())];
-    all_messages.extend(messages);
-    let messages =
-        List::new(all_messages).block(Block::default().borders(Borders::ALL).title("Messages"));
-
-    f.render_widget(messages, main_windows[0]);
+        render_history(f, messages, main_windows[0], collapse_presence, markdown);
+    }
 
     let users = if let Some(users) = app_state.current_users() {
         List::new(
             users
-                .map(|s| ListItem::new(s.to_string()))
+                .map(|user| {
+                    let item = ListItem::new(Spans::from(vec![
+                        Identicon::new(&user.name).span(),
+                        Span::raw(" "),
+                        Span::raw(user.display_name()),
+                    ]));
+                    match user.presence {
+                        Presence::Active => item,
+                        Presence::Away => item.style(Style::default().fg(Color::DarkGray)),
+                    }
+                })
                 .collect::<Vec<_>>(),
         )
     } else {
         List::new(vec![ListItem::new("".to_string())])
     }
-    .block(Block::default().borders(Borders::ALL).title("Connected"));
-    f.render_widget(users, main_windows[1]);
+    .block(Block::default().borders(Borders::ALL).title("Connected"))
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(users, main_windows[1], &mut user_list_state);
+
+    if let Some(popup) = &app_state.popup {
+        let area = centered_rect(50, 9, main_windows[0]);
+        let ignored = app_state.ignored.contains(&popup.name);
+        f.render_widget(Clear, area);
+        f.render_widget(user_popup(popup, ignored), area);
+    }
 
     // Zone de notification pour les messages d'erreur
-    let notif = app_state.notif.as_deref().unwrap_or_default();
+    let notif = match &app_state.notif {
+        Some(notif) => Span::styled(
+            notif.text.as_str(),
+            Style::default().fg(notif.severity.color()),
+        ),
+        None => Span::raw(""),
+    };
 
-    let notif = Paragraph::new(Text::from(notif)).block(
+    let notif = Paragraph::new(Text::from(Spans::from(notif))).block(
         Block::default()
             .borders(Borders::ALL)
             .title("Notifications"),
@@ -528,3 +1589,288 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app_state: &mut AppState) {
 
     // f.render_widget(main_windows, chunks[0]);
 }
+
+fn server_stats_span(stats: ServerStats) -> Span<'static> {
+    Span::styled(
+        format!(
+            "{} users, {} chans, {} msg/min",
+            stats.users, stats.channels, stats.msgs_per_min
+        ),
+        Style::default().add_modifier(Modifier::DIM),
+    )
+}
+
+fn lag_span(lag: Duration) -> Span<'static> {
+    let text = if lag < Duration::from_secs(1) {
+        format!("lag {}ms", lag.as_millis())
+    } else {
+        format!("lag {:.1}s", lag.as_secs_f32())
+    };
+    let style = if lag > LAG_THRESHOLD {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    Span::styled(text, style)
+}
+
+fn peer_span(peer: SocketAddr) -> Span<'static> {
+    Span::styled(
+        peer.to_string(),
+        Style::default().add_modifier(Modifier::DIM),
+    )
+}
+
+fn security_span(security: &Security) -> Span<'static> {
+    match security {
+        Security::Encrypted(mechanism) => {
+            Span::styled(format!("🔒 {mechanism}"), Style::default().fg(Color::Green))
+        }
+        Security::Plain => Span::styled(
+            "⚠ unencrypted",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+    }
+}
+
+// Placeholder for terminals smaller than `MIN_WIDTH` x `MIN_HEIGHT`
+fn render_too_small<B: Backend>(f: &mut Frame<B>, area: Rect) {
+    let text = Text::from(vec![
+        Spans::from(Span::styled(
+            "Terminal too small",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(format!(
+            "{}x{}, {}x{} needed",
+            area.width, area.height, MIN_WIDTH, MIN_HEIGHT
+        )),
+    ]);
+    let height = std::cmp::min(area.height, 2);
+    let area = Rect::new(
+        area.x,
+        area.y + (area.height - height) / 2,
+        area.width,
+        height,
+    );
+    f.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        area,
+    );
+}
+
+fn user_popup(popup: &UserPopup, ignored: bool) -> Paragraph<'static> {
+    let mut lines = match &popup.details {
+        None => vec![Spans::from("Loading...")],
+        Some(details) => vec![
+            Spans::from(format!("Channels: {}", details.channels.join(" "))),
+            Spans::from(format!("Idle: {}s", details.idle.as_secs())),
+            Spans::from(if details.away { "Away" } else { "Active" }),
+        ],
+    };
+    if ignored {
+        lines.push(Spans::from("Ignored"));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(Span::styled(
+        "[d] DM  [i] ignore  [Esc] close",
+        Style::default().fg(Color::DarkGray),
+    )));
+    Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(popup.name.clone()),
+    )
+}
+
+// Area of at most `width` x `height` cells, centered in `area`
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn render_history<B: Backend>(
+    f: &mut Frame<B>,
+    messages: &mut Tab,
+    area: Rect,
+    collapse_presence: bool,
+    markdown: bool,
+) {
+    let width = area.width.saturating_sub(2) as usize;
+    let mut lines: Vec<Spans> = Vec::new();
+    // Consecutive joins and leaves not displayed yet
+    let mut presence: Vec<&HistoryEntry> = Vec::new();
+    for (index, TimedEntry { entry, .. }) in messages.history.iter().enumerate() {
+        let unread = messages.unread_from == Some(index);
+        let visible = !messages.hidden.contains(&entry.kind());
+        let collapsed = collapse_presence
+            && visible
+            && matches!(entry, HistoryEntry::Join(_) | HistoryEntry::Leave(_));
+        if unread || (visible && !collapsed) {
+            push_presence(&mut lines, &mut presence, width);
+        }
+        // Separator before the first unread message
+        if unread {
+            lines.push(Spans::from(Span::styled(
+                format!("{:-^width$}", " unread "),
+                Style::default().fg(Color::LightRed),
+            )));
+        }
+        if collapsed {
+            presence.push(entry);
+        } else if visible {
+            let entry = if messages.revealed.contains(&index) {
+                Cow::Borrowed(entry)
+            } else {
+                masked(entry)
+            };
+            lines.push(history_spans(&entry, width, markdown));
+        }
+    }
+    push_presence(&mut lines, &mut presence, width);
+
+    let list =
+        MessageList::new(lines).block(Block::default().borders(Borders::ALL).title("Messages"));
+    f.render_stateful_widget(list, area, &mut messages.scroll);
+}
+
+// Display consecutive joins and leaves in one line, and empty `presence`
+fn push_presence(lines: &mut Vec<Spans>, presence: &mut Vec<&HistoryEntry>, width: usize) {
+    match presence[..] {
+        [] => {}
+        [entry] => lines.push(history_spans(entry, width, false)),
+        _ => lines.push(Spans::from(Span::styled(
+            format!("{:^width$}", summarize_presence(presence.iter().copied())),
+            Style::default().add_modifier(Modifier::DIM),
+        ))),
+    }
+    presence.clear();
+}
+
+// Message with its spoilers masked until the user reveals them, the history keeping the raw
+// content
+fn masked(entry: &HistoryEntry) -> Cow<'_, HistoryEntry> {
+    match entry {
+        HistoryEntry::UserMessage { from, content } if has_spoiler(content) => {
+            Cow::Owned(HistoryEntry::UserMessage {
+                from: from.clone(),
+                content: mask_spoilers(content).into_owned(),
+            })
+        }
+        HistoryEntry::Action { from, content } if has_spoiler(content) => {
+            Cow::Owned(HistoryEntry::Action {
+                from: from.clone(),
+                content: mask_spoilers(content).into_owned(),
+            })
+        }
+        _ => Cow::Borrowed(entry),
+    }
+}
+
+// Messages are aligned on the left, channel events are dimmed and centered. With `markdown`,
+// the content of messages is rendered, unless it is reordered for a right-to-left script.
+fn history_spans(entry: &HistoryEntry, width: usize, markdown: bool) -> Spans<'static> {
+    let text = entry.text();
+    let style = match entry {
+        HistoryEntry::UserMessage { .. } => Style::default(),
+        HistoryEntry::Action { .. } => Style::default().add_modifier(Modifier::ITALIC),
+        HistoryEntry::Error(_) => Style::default().fg(Color::Red),
+        _ => Style::default().add_modifier(Modifier::DIM),
+    };
+    let reordered = reorder(&text, TextDirection::Auto);
+    let markdown = markdown && matches!(reordered, Cow::Borrowed(_));
+    match entry {
+        HistoryEntry::UserMessage { content, .. } | HistoryEntry::Action { content, .. }
+            if markdown =>
+        {
+            let prefix = &text[..text.len() - content.len()];
+            let mut spans = vec![Span::styled(prefix.to_string(), style)];
+            spans.extend(render_markdown(content, style));
+            Spans::from(spans)
+        }
+        _ if entry.is_event() => Spans::from(Span::styled(format!("{reordered:^width$}"), style)),
+        _ => Spans::from(Span::styled(reordered.into_owned(), style)),
+    }
+}
+
+fn render_channel_list<B: Backend>(
+    f: &mut Frame<B>,
+    channels: &SelectableList<ChannelEntry>,
+    area: Rect,
+) {
+    let items: Vec<ListItem> = channels
+        .items()
+        .iter()
+        .map(|channel| {
+            let mut line = format!("#{} ({})", channel.name, channel.users);
+            if let Some(topic) = &channel.topic {
+                line.push_str(&format!(" {topic}"));
+            }
+            ListItem::new(line)
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(channels.selected_index());
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Channels (Enter to join)"),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+// Title of the input, with a counter when the message gets close to the server's limit
+fn input_counter(text: &str, max: usize) -> Span<'static> {
+    if text.starts_with('/') || text.len() * 100 < max * COUNTER_THRESHOLD {
+        return Span::raw("Input");
+    }
+    let counter = format!(
+        "Input ({} chars, {}/{max} bytes)",
+        text.chars().count(),
+        text.len()
+    );
+    if text.len() > max {
+        Span::styled(counter, Style::default().fg(Color::Red))
+    } else {
+        Span::raw(counter)
+    }
+}
+
+/// Split `text` in messages of at most `max_len` bytes, preferably between words. A word
+/// longer than `max_len` is cut between two characters.
+pub fn split_message(text: &str, max_len: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+    while rest.len() > max_len {
+        let mut end = max_len;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if let Some(space) = rest[..end].rfind(char::is_whitespace) {
+            if space > 0 {
+                end = space;
+            }
+        }
+        // At least one character per message, even if it does not fit
+        if end == 0 {
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        parts.push(rest[..end].trim_end().to_string());
+        rest = rest[end..].trim_start();
+    }
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
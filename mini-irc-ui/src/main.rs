@@ -1,4 +1,4 @@
-use mini_irc_ui::{App, KeyReaction};
+use mini_irc_ui::{App, KeyReaction, Presence, Role, UserDetails};
 use std::error::Error;
 fn main() -> Result<(), Box<dyn Error>> {
     // Etape 1: créer la structure
@@ -15,6 +15,9 @@ fn main() -> Result<(), Box<dyn Error>> {
         app.add_user("BarFoo".into(), tab.to_string());
         app.add_user("Baz".into(), tab.to_string());
     }
+    // Les opérateurs sont affichés en premier, les absents après les présents
+    app.set_user_role("Baz", Role::Operator, "#general".into());
+    app.set_user_presence("BarFoo", Presence::Away);
 
     // Etape 2: on démarre la TUI
     app.start()?;
@@ -41,6 +44,29 @@ fn main() -> Result<(), Box<dyn Error>> {
                     let current_tab = app.get_current_tab();
                     app.push_message("test".to_string(), s, current_tab);
                 }
+                Some(KeyReaction::UserDetails(name)) => {
+                    // TODO les détails devront être demandés au serveur (`WhoIs`)
+                    app.show_user_details(UserDetails {
+                        name,
+                        channels: vec!["general".to_string()],
+                        idle: std::time::Duration::from_secs(42),
+                        away: false,
+                    });
+                }
+                Some(KeyReaction::UserInputs(inputs)) => {
+                    let current_tab = app.get_current_tab();
+                    for s in inputs {
+                        app.push_message("test".to_string(), s, current_tab.clone());
+                    }
+                }
+                Some(KeyReaction::JoinChannel(name)) => {
+                    app.add_tab(format!("#{name}"));
+                }
+                Some(KeyReaction::LoadOlder(tab)) => {
+                    // TODO les messages plus anciens devront être demandés au serveur
+                    // (`FetchHistory`) : ici, il n'y en a pas
+                    app.prepend_history(&tab, Vec::new(), false);
+                }
                 None => {} // Rien à faire, géré en interne
             }
         }
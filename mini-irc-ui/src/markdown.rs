@@ -0,0 +1,85 @@
+use tui::style::{Color, Modifier, Style};
+use tui::text::Span;
+
+/// Style of a code span: a contrasting color, as every character is already monospace.
+pub const CODE_STYLE: Style = Style {
+    fg: Some(Color::Yellow),
+    bg: Some(Color::Black),
+    add_modifier: Modifier::empty(),
+    sub_modifier: Modifier::empty(),
+};
+
+/// Render the minimal markdown subset of messages on top of `style`: `*bold*`, `_italic_`
+/// and `` `code` ``. Delimiters are removed when they are paired; an unpaired delimiter, or
+/// one inside a word like in `snake_case`, is displayed as is. Nothing is rendered inside a
+/// code span.
+pub fn render_markdown(text: &str, style: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    // Start of the text not rendered yet
+    let mut plain = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap();
+        let span = match c {
+            '`' => closing(text, i, '`', false).map(|end| (end, CODE_STYLE)),
+            '*' | '_' if at_word_start(text, i) => closing(text, i, c, true).map(|end| {
+                let modifier = if c == '*' {
+                    Modifier::BOLD
+                } else {
+                    Modifier::ITALIC
+                };
+                (end, style.add_modifier(modifier))
+            }),
+            _ => None,
+        };
+        match span {
+            Some((end, span_style)) => {
+                if plain < i {
+                    spans.push(Span::styled(text[plain..i].to_string(), style));
+                }
+                spans.push(Span::styled(text[i + 1..end].to_string(), span_style));
+                i = end + 1;
+                plain = i;
+            }
+            None => i += c.len_utf8(),
+        }
+    }
+    if plain < text.len() || spans.is_empty() {
+        spans.push(Span::styled(text[plain..].to_string(), style));
+    }
+    spans
+}
+
+// Whether a delimiter at `i` can open a span: not inside a word
+fn at_word_start(text: &str, i: usize) -> bool {
+    text[..i]
+        .chars()
+        .next_back()
+        .is_none_or(|c| !c.is_alphanumeric())
+}
+
+// Index of the delimiter closing the one at `open`, around a non-empty text. With `word`,
+// the text cannot start or end with a space and the delimiter cannot be followed by a letter,
+// so that `2 * 3 * 4` or `snake_case_name` are left alone.
+fn closing(text: &str, open: usize, delimiter: char, word: bool) -> Option<usize> {
+    let start = open + delimiter.len_utf8();
+    let inner = &text[start..];
+    let end = start + inner.find(delimiter)?;
+    let content = &text[start..end];
+    if content.is_empty() {
+        return None;
+    }
+    if word {
+        let spaced =
+            content.starts_with(char::is_whitespace) || content.ends_with(char::is_whitespace);
+        let glued = text[end + delimiter.len_utf8()..]
+            .chars()
+            .next()
+            .is_some_and(char::is_alphanumeric);
+        if spaced || glued {
+            return None;
+        }
+    }
+    Some(end)
+}
@@ -0,0 +1,31 @@
+use std::time::{Duration, Instant};
+use tui::style::Color;
+
+/// How long a notification stays displayed by default.
+pub const DEFAULT_NOTIFICATION_DURATION: Duration = Duration::from_secs(10);
+
+/// Severity of a notification, which gives its color.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub(crate) fn color(self) -> Color {
+        match self {
+            Severity::Info => Color::Reset,
+            Severity::Warning => Color::Yellow,
+            Severity::Error => Color::Red,
+        }
+    }
+}
+
+/// Notification displayed under the tabs.
+pub(crate) struct Notification {
+    pub(crate) text: String,
+    pub(crate) severity: Severity,
+    /// When the notification disappears, if ever.
+    pub(crate) expires_at: Option<Instant>,
+}
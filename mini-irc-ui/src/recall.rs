@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+
+/// Number of inputs remembered per tab, the oldest being forgotten beyond.
+pub const INPUT_HISTORY_LEN: usize = 500;
+
+/// Inputs submitted in a tab, recalled with Up and Down like the history of a shell.
+#[derive(Clone, Debug, Default)]
+pub struct InputHistory {
+    /// Oldest first
+    entries: VecDeque<String>,
+    /// Index of the recalled entry, while browsing
+    position: Option<usize>,
+    /// Text typed before browsing, given back after the most recent entry
+    draft: String,
+}
+
+impl InputHistory {
+    /// History made of `entries`, oldest first, keeping the last [`INPUT_HISTORY_LEN`].
+    pub fn new(entries: Vec<String>) -> Self {
+        let skip = entries.len().saturating_sub(INPUT_HISTORY_LEN);
+        Self {
+            entries: entries.into_iter().skip(skip).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Remembered inputs, oldest first.
+    pub fn entries(&self) -> Vec<String> {
+        self.entries.iter().cloned().collect()
+    }
+
+    /// Remember a submitted input and stop browsing. Blank inputs, repetitions of the last
+    /// one, and inputs starting with a space (to keep one out of the history) are skipped.
+    pub fn push(&mut self, input: &str) {
+        self.position = None;
+        self.draft.clear();
+        if input.trim().is_empty()
+            || input.starts_with(' ')
+            || self.entries.back().map(String::as_str) == Some(input)
+        {
+            return;
+        }
+        if self.entries.len() == INPUT_HISTORY_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(input.to_string());
+    }
+
+    /// The entry before the recalled one, or the most recent one when starting to browse with
+    /// `current` in the input. `None` at the oldest entry.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(position) => position - 1,
+        };
+        self.position = Some(position);
+        self.entries.get(position).map(String::as_str)
+    }
+
+    /// The entry after the recalled one, then the text typed before browsing. `None` when not
+    /// browsing.
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            self.entries.get(position).map(String::as_str)
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::widgets::text_width;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Delimiter of a spoiler, as in `the answer is ||42||`.
+pub const SPOILER_DELIMITER: &str = "||";
+
+/// Character repeated to mask a spoiler.
+const MASK: char = '▒';
+
+/// Byte ranges of the spoilers of `text`, delimiters included. An unpaired or empty
+/// spoiler is not one.
+fn spoilers(text: &str) -> Vec<Range<usize>> {
+    let delimiter = SPOILER_DELIMITER.len();
+    let mut ranges = Vec::new();
+    let mut from = 0;
+    while let Some(open) = text[from..].find(SPOILER_DELIMITER).map(|i| from + i) {
+        let start = open + delimiter;
+        let Some(close) = text[start..].find(SPOILER_DELIMITER).map(|i| start + i) else {
+            break;
+        };
+        if close == start {
+            // `||||`: nothing to hide, the second delimiter may open a spoiler
+            from = start;
+            continue;
+        }
+        ranges.push(open..close + delimiter);
+        from = close + delimiter;
+    }
+    ranges
+}
+
+/// Whether `text` contains a `||spoiler||`.
+pub fn has_spoiler(text: &str) -> bool {
+    !spoilers(text).is_empty()
+}
+
+/// `text` with each spoiler replaced by a mask as wide as its content, to display it until
+/// the user reveals it.
+pub fn mask_spoilers(text: &str) -> Cow<'_, str> {
+    let ranges = spoilers(text);
+    if ranges.is_empty() {
+        return Cow::Borrowed(text);
+    }
+    let delimiter = SPOILER_DELIMITER.len();
+    let mut masked = String::with_capacity(text.len());
+    let mut plain = 0;
+    for range in ranges {
+        masked.push_str(&text[plain..range.start]);
+        let content = &text[range.start + delimiter..range.end - delimiter];
+        masked.extend(std::iter::repeat_n(MASK, text_width(content)));
+        plain = range.end;
+    }
+    masked.push_str(&text[plain..]);
+    Cow::Owned(masked)
+}
@@ -0,0 +1,167 @@
+use std::cmp::Ordering;
+use std::time::Duration;
+
+/// Role of a user in a channel. Roles are declared by decreasing rank.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    Owner,
+    Operator,
+    Voiced,
+    #[default]
+    Member,
+}
+
+impl Role {
+    /// Prefix displayed before the nickname.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Role::Owner => "~",
+            Role::Operator => "@",
+            Role::Voiced => "+",
+            Role::Member => "",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Presence {
+    #[default]
+    Active,
+    Away,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct User {
+    pub(crate) name: String,
+    pub(crate) role: Role,
+    pub(crate) presence: Presence,
+}
+
+impl User {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            role: Role::default(),
+            presence: Presence::default(),
+        }
+    }
+
+    pub(crate) fn display_name(&self) -> String {
+        format!("{}{}", self.role.prefix(), self.name)
+    }
+}
+
+/// Sidebar order: role first, then presence, then case-insensitive name.
+impl Ord for User {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.role
+            .cmp(&other.role)
+            .then(self.presence.cmp(&other.presence))
+            .then_with(|| self.name.to_lowercase().cmp(&other.name.to_lowercase()))
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl PartialOrd for User {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Users of a tab, kept sorted for display. Nicknames are unique.
+#[derive(Debug, Default)]
+pub(crate) struct UserList {
+    users: Vec<User>,
+    /// Owner of the channel, even while they are not in it
+    owner: Option<String>,
+}
+
+impl UserList {
+    pub(crate) fn insert(&mut self, name: String) {
+        if self.position(&name).is_none() {
+            let mut user = User::new(name);
+            if self.owner.as_ref() == Some(&user.name) {
+                user.role = Role::Owner;
+            }
+            self.users.push(user);
+            self.users.sort();
+        }
+    }
+
+    /// Make `name` the only owner of the channel; the previous one becomes a member.
+    pub(crate) fn set_owner(&mut self, name: &str) {
+        for user in self.users.iter_mut() {
+            if user.name == name {
+                user.role = Role::Owner;
+            } else if user.role == Role::Owner {
+                user.role = Role::Member;
+            }
+        }
+        self.users.sort();
+        self.owner = Some(name.to_string());
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) {
+        if let Some(index) = self.position(name) {
+            self.users.remove(index);
+        }
+    }
+
+    /// Keeps exactly the users in `names`: the role and presence of the users already known
+    /// are kept.
+    pub(crate) fn replace(&mut self, names: Vec<String>) {
+        self.users.retain(|user| names.contains(&user.name));
+        for name in names {
+            self.insert(name);
+        }
+    }
+
+    /// Applies `f` to a user, then restores the order. Returns false if there is no such user.
+    pub(crate) fn update(&mut self, name: &str, f: impl FnOnce(&mut User)) -> bool {
+        match self.position(name) {
+            Some(index) => {
+                f(&mut self.users[index]);
+                self.users.sort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &User> {
+        self.users.iter()
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&User> {
+        self.users.get(index)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.users.iter().position(|user| user.name == name)
+    }
+}
+
+/// Details about a user, shown in the user popup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserDetails {
+    pub name: String,
+    pub channels: Vec<String>,
+    /// Time since the user's last activity
+    pub idle: Duration,
+    pub away: bool,
+}
+
+/// Popup opened over the current tab for a user, until their details arrive.
+#[derive(Debug)]
+pub(crate) struct UserPopup {
+    pub(crate) name: String,
+    pub(crate) details: Option<UserDetails>,
+}
@@ -1,38 +1,90 @@
-pub use crossterm;
+use crate::bidi::{self, TextDirection};
+use std::ops::Range;
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, StatefulWidget, Widget},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+/// Word boundaries split the text between words, spaces and punctuation: only the
+/// segments containing letters or digits are words.
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(char::is_alphanumeric)
+}
+
+/// Width of a grapheme cluster, the unit of the cursor: a character with its combining marks,
+/// an emoji sequence...
+pub(crate) fn grapheme_width(grapheme: &str) -> usize {
+    std::cmp::max(1, grapheme.width())
+}
+
+/// Width of a text, as the sum of the widths of its grapheme clusters
+pub(crate) fn text_width(text: &str) -> usize {
+    text.graphemes(true).map(grapheme_width).sum()
+}
 
-fn get_byte_offset(input: &str, offset: u16) -> Option<(usize, char)> {
+/// Last grapheme cluster boundary at or before `byte`
+fn previous_boundary(text: &str, byte: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(i, _)| i)
+        .take_while(|i| *i <= byte)
+        .last()
+        .unwrap_or(0)
+}
+
+/// First grapheme cluster boundary at or after `byte`
+fn next_boundary(text: &str, byte: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(i, _)| i)
+        .find(|i| *i >= byte)
+        .unwrap_or(text.len())
+}
+
+fn get_byte_offset(input: &str, offset: u16) -> Option<(usize, &str)> {
     let mut prefix_width = 0;
-    for (i, c) in input.char_indices() {
+    for (i, g) in input.grapheme_indices(true) {
         if prefix_width >= offset as usize {
-            return Some((i, c));
+            return Some((i, g));
         } else {
-            prefix_width += c.width().unwrap_or(1);
+            prefix_width += grapheme_width(g);
         }
     }
     None
 }
-fn get_byte_offset_before(input: &str, offset: u16) -> Option<(usize, char)> {
+fn get_byte_offset_before(input: &str, offset: u16) -> Option<(usize, &str)> {
     let mut prefix_width = 0;
     if offset == 0 {
         return None;
     }
-    for (i, c) in input.char_indices() {
-        prefix_width += c.width().unwrap_or(1);
+    for (i, g) in input.grapheme_indices(true) {
+        prefix_width += grapheme_width(g);
         if prefix_width >= offset as usize {
-            return Some((i, c));
+            return Some((i, g));
         }
     }
     panic!("Should not happen");
     //    return None;
 }
 
+/// Maximum number of edits that can be undone
+const UNDO_DEPTH: usize = 100;
+
+/// Text and cursor (in bytes) before an edit, to undo it
+#[derive(Hash, PartialEq, PartialOrd, Eq, Ord, Debug, Default)]
+pub(crate) struct Snapshot {
+    text: String,
+    cursor: usize,
+}
+
 #[derive(Hash, PartialEq, PartialOrd, Eq, Ord, Debug, Default)]
 pub struct Input {
     /// Text contained in the input widget
     pub text: String,
-    /// Cursor offset, relative to the text offset, in caracter width
+    /// Cursor offset, relative to the text offset, in caracter width (see [`grapheme_width`])
     pub cursor_offset: u16,
     /// Text offset from which the texte should be displayed, in bytes
     pub text_offset: usize,
@@ -41,54 +93,93 @@ pub struct Input {
 
     /// Display width in the UI
     pub display_width: u16,
+
+    /// Edits since the last submission, most recent last
+    pub(crate) undo: Vec<Snapshot>,
+    pub(crate) redo: Vec<Snapshot>,
+    /// Cursor after the last typed character: typing a word is undone at once
+    pub(crate) typing_at: Option<usize>,
+    /// Other end of the selection (in bytes), the cursor being the first one
+    pub(crate) selection_anchor: Option<usize>,
+    /// Byte of the text from which it is invalid, for instance over a length limit
+    pub invalid_from: Option<usize>,
+    /// Base direction of the text, for right-to-left scripts
+    pub direction: TextDirection,
 }
 
 impl Input {
+    /// Change the width of the displayed text, scrolling it to keep the cursor visible. Widths
+    /// under 2 columns are raised to 2, the room needed by a wide character.
     #[allow(dead_code)] // To satisfy clippy
     pub fn resize(&mut self, new_size: u16) {
-        if new_size < 2 {
-            panic!("Cannot display such a small input widget");
+        let new_size = std::cmp::max(2, new_size);
+        if new_size == self.display_width {
+            return;
         }
-
+        let cursor = self.cursor_byte();
         self.display_width = new_size;
-        if self.cursor_offset >= new_size {
-            self.cursor_offset = new_size
-                - get_byte_offset_before(self.get_display_string(), new_size)
-                    .unwrap_or((0, ' '))
-                    .1
-                    .width()
-                    .unwrap_or(1) as u16;
-        }
+        self.set_cursor_byte(cursor);
     }
     pub fn get_display_string(&self) -> &str {
         &self.text[self.text_offset..]
     }
 
+    /// Column of the cursor in the displayed text
     #[allow(dead_code)] // To satisfy clippy
     pub fn get_cursor_offset(&self) -> u16 {
-        self.cursor_offset
+        let displayed = self.get_display_string();
+        if !bidi::has_rtl(displayed, self.direction) {
+            return self.cursor_offset;
+        }
+        let cursor = self.cursor_byte() - self.text_offset;
+        bidi::cursor_columns(displayed, self.direction)
+            .into_iter()
+            .find(|(i, _)| *i == cursor)
+            .map_or(self.cursor_offset, |(_, column)| column)
     }
 
     #[allow(dead_code)] // To satisfy clippy
     pub fn submit(&mut self) -> String {
+        self.selection_anchor = None;
+        self.undo.clear();
+        self.redo.clear();
+        self.typing_at = None;
         self.cursor_offset = 0;
         self.text_offset = 0;
         self.text.drain(..).collect()
     }
 
     pub fn insert_at_cursor(&mut self, c: char) {
-        // Find the byte offset in the string corresponding to the current cursor
-        match get_byte_offset(self.get_display_string(), self.cursor_offset) {
-            None => {
-                self.text.push(c);
-            }
-            Some((i, _)) => {
-                self.text.insert(i + self.text_offset, c);
-            }
+        let before = self.snapshot();
+        // Typing replaces the selection
+        let typing =
+            !self.remove_selection() && self.typing_at == Some(before.cursor) && !c.is_whitespace();
+        self.insert_char(c);
+        if !typing {
+            self.record(before);
         }
+        self.typing_at = Some(self.cursor_byte());
+    }
+
+    /// Insert pasted text, undone at once. Line breaks are replaced by spaces.
+    pub fn insert_str_at_cursor(&mut self, text: &str) {
+        let before = self.snapshot();
+        self.remove_selection();
+        text.chars()
+            .map(|c| if c == '\n' || c == '\r' { ' ' } else { c })
+            .for_each(|c| self.insert_char(c));
+        self.record(before);
+    }
 
-        // Move the cursor
-        self.cursor_offset += c.width().unwrap_or(1) as u16;
+    fn insert_char(&mut self, c: char) {
+        let at = self.cursor_byte();
+        self.text.insert(at, c);
+
+        // Move the cursor. The character may be combined with its neighbours (accents, emoji
+        // sequences...): the cursor goes after the cluster it is part of.
+        let cursor = next_boundary(&self.text, at + c.len_utf8());
+        self.text_offset = previous_boundary(&self.text, self.text_offset);
+        self.cursor_offset = text_width(&self.text[self.text_offset..cursor]) as u16;
 
         // If the cursor leaves the current displayed widget, apply an offset to the displayed string
         if self.cursor_offset >= self.display_width {
@@ -98,7 +189,7 @@ impl Input {
                 self.text_offset += extra_offset;
                 self.cursor_offset = std::cmp::min(
                     self.cursor_offset.saturating_sub(input_shift),
-                    self.get_display_string().width() as u16,
+                    text_width(self.get_display_string()) as u16,
                 );
             }
         }
@@ -110,31 +201,29 @@ impl Input {
             let old_text_offset = self.text_offset;
             let input_shift = std::cmp::max(1, self.display_width / 2);
             let text_shift =
-                (self.text[0..self.text_offset].width() as u16).saturating_sub(input_shift);
+                (text_width(&self.text[0..self.text_offset]) as u16).saturating_sub(input_shift);
             let (new_offset, _) =
-                get_byte_offset_before(&self.text, text_shift).unwrap_or((0, ' '));
-            self.cursor_offset = self.text[new_offset..old_text_offset].width() as u16;
+                get_byte_offset_before(&self.text, text_shift).unwrap_or((0, " "));
+            self.cursor_offset = text_width(&self.text[new_offset..old_text_offset]) as u16;
             self.text_offset = new_offset;
         }
         {
-            if let Some((_, c)) =
+            if let Some((_, g)) =
                 get_byte_offset_before(self.get_display_string(), self.cursor_offset)
             {
-                self.cursor_offset = self
-                    .cursor_offset
-                    .saturating_sub(std::cmp::max(1, c.width().unwrap_or(1) as u16));
+                self.cursor_offset = self.cursor_offset.saturating_sub(grapheme_width(g) as u16);
             }
         }
     }
 
     pub fn cursor_move_right(&mut self) {
-        let current_char = get_byte_offset(self.get_display_string(), self.cursor_offset)
-            .unwrap_or((0, ' '))
+        let current_grapheme = get_byte_offset(self.get_display_string(), self.cursor_offset)
+            .unwrap_or((0, " "))
             .1;
         if self.cursor_offset
             >= self
                 .display_width
-                .saturating_sub(current_char.width().unwrap_or(1) as u16)
+                .saturating_sub(grapheme_width(current_grapheme) as u16)
         {
             //Move right !
             let input_shift = std::cmp::max(1, self.display_width / 2);
@@ -142,52 +231,75 @@ impl Input {
             {
                 let old_text_offset = self.text_offset;
                 self.text_offset += extra_offset;
-                self.cursor_offset = self
-                    .cursor_offset
-                    .saturating_sub(self.text[old_text_offset..self.text_offset].width() as u16);
+                self.cursor_offset = self.cursor_offset.saturating_sub(text_width(
+                    &self.text[old_text_offset..self.text_offset],
+                ) as u16);
                 // the text was (visually) moved this much
             }
         }
 
-        if let Some((_, c)) = get_byte_offset(self.get_display_string(), self.cursor_offset) {
+        if let Some((_, g)) = get_byte_offset(self.get_display_string(), self.cursor_offset) {
             self.cursor_offset = std::cmp::min(
-                self.cursor_offset
-                    .saturating_add(std::cmp::max(1, c.width().unwrap_or(1) as u16)),
-                self.text.width().try_into().unwrap_or(u16::MAX),
+                self.cursor_offset.saturating_add(grapheme_width(g) as u16),
+                text_width(&self.text).try_into().unwrap_or(u16::MAX),
             );
         }
     }
 
     pub fn delete_at_cursor(&mut self) {
+        let before = self.snapshot();
+        if self.remove_selection() {
+            self.record(before);
+            return;
+        }
         match get_byte_offset(self.get_display_string(), self.cursor_offset) {
             None => {}
-            Some((i, _)) => {
-                self.text.remove(i + self.text_offset);
+            Some((i, g)) => {
+                let start = i + self.text_offset;
+                self.text.replace_range(start..start + g.len(), "");
             }
         };
+        self.text_offset = previous_boundary(&self.text, self.text_offset);
+        self.record(before);
     }
 
     pub fn delete_behind_cursor(&mut self) {
-        let deleted_c = match get_byte_offset_before(
+        let before = self.snapshot();
+        if self.remove_selection() {
+            self.record(before);
+            return;
+        }
+        let deleted = match get_byte_offset_before(
             self.get_display_string(),
             std::cmp::min(
                 self.cursor_offset,
-                self.text.width().try_into().unwrap_or(u16::MAX),
+                text_width(&self.text).try_into().unwrap_or(u16::MAX),
             ),
         ) {
             None =>
-            // Delete the last character before the displayed string
+            // Delete the last grapheme before the displayed string
             {
-                match self.text[0..self.text_offset].char_indices().last() {
-                    Some((i, _)) => {
+                match self.text[0..self.text_offset]
+                    .grapheme_indices(true)
+                    .next_back()
+                {
+                    Some((i, g)) => {
+                        let width = grapheme_width(g);
                         self.text_offset = i;
-                        Some(self.text.remove(i))
+                        self.text.replace_range(i..i + g.len(), "");
+                        Some(width)
                     }
                     None => None,
                 }
             }
-            Some((i, _)) => Some(self.text.remove(i + self.text_offset)),
+            Some((i, g)) => {
+                let width = grapheme_width(g);
+                let start = i + self.text_offset;
+                self.text.replace_range(start..start + g.len(), "");
+                Some(width)
+            }
         };
+        self.text_offset = previous_boundary(&self.text, self.text_offset);
 
         if self.cursor_offset == 0 {
             self.cursor_move_left();
@@ -195,10 +307,412 @@ impl Input {
             self.cursor_move_right();
             self.cursor_move_right();
             //}
-        } else if let Some(c) = deleted_c {
-            self.cursor_offset = self
-                .cursor_offset
-                .saturating_sub(c.width().unwrap_or(1) as u16);
+        } else if let Some(width) = deleted {
+            self.cursor_offset = self.cursor_offset.saturating_sub(width as u16);
+        }
+        self.record(before);
+    }
+
+    /// Byte offset of the cursor in the text
+    fn cursor_byte(&self) -> usize {
+        self.text_offset
+            + get_byte_offset(self.get_display_string(), self.cursor_offset)
+                .map_or(self.get_display_string().len(), |(i, _)| i)
+    }
+
+    /// Place the cursor before the byte `byte` of the text, scrolling the displayed text if needed
+    fn set_cursor_byte(&mut self, byte: usize) {
+        // Removing text may join the clusters around it
+        let byte = previous_boundary(&self.text, byte);
+        let mut offset = previous_boundary(&self.text, self.text_offset);
+        if byte < offset {
+            // Scroll left, keeping up to half the width of text before the cursor
+            offset = byte;
+            for (i, _) in self.text[..byte].grapheme_indices(true).rev() {
+                if text_width(&self.text[i..byte]) > (self.display_width / 2) as usize {
+                    break;
+                }
+                offset = i;
+            }
+        }
+        // Scroll right, leaving room for a wide character after the cursor
+        let max_width = self.display_width.saturating_sub(2) as usize;
+        while text_width(&self.text[offset..byte]) > max_width {
+            offset += self.text[offset..]
+                .graphemes(true)
+                .next()
+                .map_or(1, str::len);
+        }
+        self.text_offset = offset;
+        self.cursor_offset = text_width(&self.text[offset..byte]) as u16;
+    }
+
+    pub fn clear(&mut self) {
+        let before = self.snapshot();
+        self.selection_anchor = None;
+        self.text.clear();
+        self.text_offset = 0;
+        self.cursor_offset = 0;
+        self.record(before);
+    }
+
+    pub fn cursor_move_home(&mut self) {
+        self.set_cursor_byte(0);
+    }
+
+    pub fn cursor_move_end(&mut self) {
+        self.set_cursor_byte(self.text.len());
+    }
+
+    /// Byte offset of the start of the word before the cursor
+    fn previous_word_start(&self) -> usize {
+        self.text[..self.cursor_byte()]
+            .split_word_bound_indices()
+            .rev()
+            .find(|(_, segment)| is_word(segment))
+            .map_or(0, |(i, _)| i)
+    }
+
+    /// Byte offset of the end of the word after the cursor
+    fn next_word_end(&self) -> usize {
+        let start = self.cursor_byte();
+        self.text[start..]
+            .split_word_bound_indices()
+            .find(|(_, segment)| is_word(segment))
+            .map_or(self.text.len(), |(i, segment)| start + i + segment.len())
+    }
+
+    pub fn cursor_move_word_left(&mut self) {
+        self.set_cursor_byte(self.previous_word_start());
+    }
+
+    pub fn cursor_move_word_right(&mut self) {
+        self.set_cursor_byte(self.next_word_end());
+    }
+
+    /// Word before the cursor, delimited by whitespace, with its start in bytes
+    #[allow(dead_code)] // To satisfy clippy
+    pub fn word_behind_cursor(&self) -> (usize, &str) {
+        let cursor = self.cursor_byte();
+        let start = self.text[..cursor]
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        (start, &self.text[start..cursor])
+    }
+
+    /// Replace the text from the byte `start` up to the cursor by `text`, undone at once
+    #[allow(dead_code)] // To satisfy clippy
+    pub fn replace_behind_cursor(&mut self, start: usize, text: &str) {
+        let before = self.snapshot();
+        self.selection_anchor = None;
+        self.text.replace_range(start..before.cursor, text);
+        self.set_cursor_byte(start + text.len());
+        self.record(before);
+    }
+
+    /// Replace the whole text, for instance by a recalled input, with the cursor at its end
+    #[allow(dead_code)] // To satisfy clippy
+    pub fn replace_text(&mut self, text: &str) {
+        let before = self.snapshot();
+        self.selection_anchor = None;
+        self.text = text.to_string();
+        self.text_offset = 0;
+        self.cursor_offset = 0;
+        self.set_cursor_byte(self.text.len());
+        self.record(before);
+    }
+
+    /// Delete from the start of the word before the cursor up to the cursor
+    pub fn delete_word_behind_cursor(&mut self) {
+        let before = self.snapshot();
+        if self.remove_selection() {
+            self.record(before);
+            return;
+        }
+        let start = self.previous_word_start();
+        self.text.replace_range(start..self.cursor_byte(), "");
+        self.set_cursor_byte(start);
+        self.record(before);
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            text: self.text.clone(),
+            cursor: self.cursor_byte(),
+        }
+    }
+
+    /// Remember the state `before` an edit, if the edit changed the text
+    fn record(&mut self, before: Snapshot) {
+        self.typing_at = None;
+        if before.text != self.text {
+            if self.undo.len() == UNDO_DEPTH {
+                self.undo.remove(0);
+            }
+            self.undo.push(before);
+            self.redo.clear();
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.selection_anchor = None;
+        self.text = snapshot.text;
+        self.text_offset = 0;
+        self.set_cursor_byte(snapshot.cursor);
+        self.typing_at = None;
+    }
+
+    /// Cancel the last edit, if any
+    pub fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.redo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// Apply again the last undone edit, if any
+    pub fn redo(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            self.undo.push(self.snapshot());
+            self.restore(snapshot);
+        }
+    }
+
+    /// Start selecting from the cursor, unless a selection is already started. The selection
+    /// then follows the cursor until [`Input::clear_selection`] or an edit.
+    pub fn start_selection(&mut self) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.cursor_byte());
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection_anchor = None;
+    }
+
+    /// Selected bytes of the text, if the selection is not empty
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.selection_anchor?;
+        let cursor = self.cursor_byte();
+        (anchor != cursor).then(|| anchor.min(cursor)..anchor.max(cursor))
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|range| &self.text[range])
+    }
+
+    /// Delete the selected text, if any
+    pub fn delete_selection(&mut self) {
+        let before = self.snapshot();
+        if self.remove_selection() {
+            self.record(before);
+        }
+    }
+
+    // Returns false if nothing was selected
+    fn remove_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.selection_anchor = None;
+        match selection {
+            Some(range) => {
+                let start = range.start;
+                self.text.replace_range(range, "");
+                self.set_cursor_byte(start);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Style of the character starting at byte `byte` of the text
+    fn style_at(&self, byte: usize, grapheme: &str, selection: &Option<Range<usize>>) -> Style {
+        let mut style = Style::default();
+        if self
+            .invalid_from
+            .is_some_and(|from| byte + grapheme.len() > from)
+        {
+            style = style.fg(Color::Red);
+        }
+        if selection
+            .as_ref()
+            .is_some_and(|range| range.contains(&byte))
+        {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        style
+    }
+
+    /// Displayed text, styled: the selection in reverse video, and the invalid part (see
+    /// [`Input::invalid_from`]) in red.
+    pub fn get_display_spans(&self) -> Spans<'_> {
+        let displayed = self.get_display_string();
+        let selection = self.selection();
+        if bidi::has_rtl(displayed, self.direction) {
+            return self.get_visual_spans(&selection);
+        }
+        let mut spans = Vec::new();
+        // Start and style of the current span
+        let mut current: Option<(usize, Style)> = None;
+        for (i, g) in displayed.grapheme_indices(true) {
+            let style = self.style_at(self.text_offset + i, g, &selection);
+            match current {
+                Some((_, current_style)) if current_style == style => {}
+                Some((start, current_style)) => {
+                    spans.push(Span::styled(&displayed[start..i], current_style));
+                    current = Some((i, style));
+                }
+                None => current = Some((i, style)),
+            }
+        }
+        if let Some((start, style)) = current {
+            spans.push(Span::styled(&displayed[start..], style));
+        }
+        Spans::from(spans)
+    }
+
+    // Spans of a displayed text containing right-to-left runs, in display order
+    fn get_visual_spans(&self, selection: &Option<Range<usize>>) -> Spans<'static> {
+        let displayed = self.get_display_string();
+        let mut spans: Vec<Span> = Vec::new();
+        for (i, _) in bidi::visual_order(displayed, self.direction) {
+            let g = displayed[i..].graphemes(true).next().unwrap_or(" ");
+            let style = self.style_at(self.text_offset + i, g, selection);
+            match spans.last_mut() {
+                Some(span) if span.style == style => span.content.to_mut().push_str(g),
+                _ => spans.push(Span::styled(g.to_string(), style)),
+            }
+        }
+        Spans::from(spans)
+    }
+
+    /// Move the cursor one column to the left, or to the right, on screen. In a right-to-left
+    /// run, the cursor moves in the opposite direction in the text.
+    pub fn cursor_move_visual(&mut self, right: bool) {
+        if !bidi::has_rtl(&self.text, self.direction) {
+            if right {
+                self.cursor_move_right();
+            } else {
+                self.cursor_move_left();
+            }
+            return;
+        }
+        let columns = bidi::cursor_columns(&self.text, self.direction);
+        let cursor = self.cursor_byte();
+        let column = columns
+            .iter()
+            .find(|(i, _)| *i == cursor)
+            .map_or(0, |(_, column)| *column);
+        // Nearest column in that direction, then nearest position in the text
+        let target = columns
+            .iter()
+            .filter(|(_, c)| if right { *c > column } else { *c < column })
+            .min_by_key(|(i, c)| (c.abs_diff(column), i.abs_diff(cursor)))
+            .map(|(i, _)| *i);
+        if let Some(target) = target {
+            self.set_cursor_byte(target);
+        }
+    }
+}
+
+/// List of items with a selected one, moved with [`SelectableList::select_next`] and
+/// [`SelectableList::select_previous`].
+#[derive(Debug)]
+pub struct SelectableList<T> {
+    items: Vec<T>,
+    selected: usize,
+}
+
+impl<T> Default for SelectableList<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            selected: 0,
+        }
+    }
+}
+
+#[allow(dead_code)] // Unused by the input fuzzer
+impl<T> SelectableList<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Self { items, selected: 0 }
+    }
+
+    /// Replace the items, keeping the selection within bounds.
+    pub fn set_items(&mut self, items: Vec<T>) {
+        self.items = items;
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> Option<&T> {
+        self.items.get(self.selected)
+    }
+
+    pub fn selected_index(&self) -> Option<usize> {
+        (!self.items.is_empty()).then_some(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+}
+
+/// Scroll position of a [`MessageList`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MessageListState {
+    /// Number of lines scrolled up from the bottom. Clamped when rendering.
+    pub offset: usize,
+}
+
+/// List of lines aligned at the bottom of its area, like a chat history: the last line is at
+/// the bottom, unless the list is scrolled up, and blank lines fill the top when the list is
+/// short.
+#[allow(dead_code)] // Unused by the input fuzzer
+pub struct MessageList<'a> {
+    lines: Vec<Spans<'a>>,
+    block: Option<Block<'a>>,
+}
+
+#[allow(dead_code)] // Unused by the input fuzzer
+impl<'a> MessageList<'a> {
+    pub fn new(lines: Vec<Spans<'a>>) -> Self {
+        Self { lines, block: None }
+    }
+
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl<'a> StatefulWidget for MessageList<'a> {
+    type State = MessageListState;
+
+    fn render(mut self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let area = match self.block.take() {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        let height = area.height as usize;
+        state.offset = state.offset.min(self.lines.len().saturating_sub(height));
+
+        let end = self.lines.len() - state.offset;
+        let start = end.saturating_sub(height);
+        let top = area.y + (height - (end - start)) as u16;
+        for (i, line) in self.lines[start..end].iter().enumerate() {
+            buf.set_spans(area.x, top + i as u16, line, area.width);
         }
     }
 }
@@ -0,0 +1,38 @@
+use mini_irc_ui::{reorder, TextDirection};
+
+#[test]
+fn left_to_right_text_is_unchanged() {
+    assert_eq!(reorder("hello, world", TextDirection::Auto), "hello, world");
+}
+
+#[test]
+fn hebrew_is_reversed() {
+    // "shalom"
+    assert_eq!(reorder("שלום", TextDirection::Auto), "םולש");
+}
+
+#[test]
+fn arabic_in_a_latin_message() {
+    // "marhaban" in a left-to-right paragraph: only the Arabic word is reversed
+    assert_eq!(
+        reorder("alice: مرحبا!", TextDirection::Auto),
+        "alice: ابحرم!"
+    );
+}
+
+#[test]
+fn numbers_keep_their_order_in_right_to_left_text() {
+    assert_eq!(reorder("שלום 123", TextDirection::Auto), "123 םולש");
+}
+
+#[test]
+fn direction_can_be_forced() {
+    assert_eq!(
+        reorder("hello שלום", TextDirection::RightToLeft),
+        "םולש hello"
+    );
+    assert_eq!(
+        reorder("שלום hello", TextDirection::LeftToRight),
+        "םולש hello"
+    );
+}
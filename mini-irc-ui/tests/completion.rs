@@ -0,0 +1,39 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use mini_irc_ui::{App, KeyReaction};
+
+fn press(app: &mut App, code: KeyCode) -> Option<KeyReaction> {
+    app.react_to_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+}
+
+// Type `text`, press Tab, and return the submitted input
+fn complete(app: &mut App, text: &str) -> String {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c));
+    }
+    press(app, KeyCode::Tab);
+    match press(app, KeyCode::Enter) {
+        Some(KeyReaction::UserInput(input)) => input,
+        _ => panic!("the input was not submitted"),
+    }
+}
+
+#[test]
+fn nicknames_are_completed_with_a_suffix_at_line_start() {
+    let mut app = App::default();
+    app.set_nickname("alice".to_string());
+    app.add_tab_with_users(
+        "#general".to_string(),
+        vec!["alice".into(), "bob".into(), "Albert".into()],
+    );
+    press(&mut app, KeyCode::Char('e'));
+
+    // The user's own nickname is never proposed
+    assert_eq!(complete(&mut app, "al"), "Albert: ");
+    assert_eq!(complete(&mut app, "hi BO"), "hi bob");
+    assert_eq!(complete(&mut app, "hi zed"), "hi zed");
+
+    app.set_completion_suffix(", ".to_string());
+    assert_eq!(complete(&mut app, "  b"), "  bob, ");
+    app.set_completion_suffix(String::new());
+    assert_eq!(complete(&mut app, "b"), "bob");
+}
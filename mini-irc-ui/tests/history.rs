@@ -0,0 +1,58 @@
+use mini_irc_ui::{summarize_presence, EntryKind, HistoryEntry};
+
+#[test]
+fn entry_kinds_are_parsed() {
+    assert_eq!("joins".parse(), Ok(EntryKind::Joins));
+    assert_eq!("notices".parse(), Ok(EntryKind::Notices));
+    assert!("everything".parse::<EntryKind>().is_err());
+}
+
+#[test]
+fn only_messages_and_actions_have_an_author() {
+    let message = HistoryEntry::UserMessage {
+        from: "alice".to_string(),
+        content: "hello".to_string(),
+    };
+    let action = HistoryEntry::Action {
+        from: "bob".to_string(),
+        content: "waves".to_string(),
+    };
+    assert_eq!(message.author(), Some("alice"));
+    assert_eq!(action.author(), Some("bob"));
+    assert_eq!(action.text(), "* bob waves");
+    assert_eq!(HistoryEntry::Join("alice".to_string()).author(), None);
+    assert!(HistoryEntry::Leave("alice".to_string()).is_event());
+    assert!(!message.is_event());
+}
+
+fn join(name: &str) -> HistoryEntry {
+    HistoryEntry::Join(name.to_string())
+}
+
+fn leave(name: &str) -> HistoryEntry {
+    HistoryEntry::Leave(name.to_string())
+}
+
+#[test]
+fn presence_is_summarized_per_user() {
+    let entries = [
+        leave("alice"),
+        leave("bob"),
+        join("carol"),
+        join("alice"),
+        join("bob"),
+        leave("dave"),
+    ];
+    assert_eq!(
+        summarize_presence(&entries),
+        "carol joined, dave left, 2 users reconnected"
+    );
+}
+
+#[test]
+fn repeated_events_count_once() {
+    let entries = [join("alice"), leave("alice"), join("alice"), leave("alice")];
+    assert_eq!(summarize_presence(&entries), "alice joined and left");
+    let entries = [join("alice"), join("bob"), join("carol")];
+    assert_eq!(summarize_presence(&entries), "3 users joined");
+}
@@ -0,0 +1,19 @@
+use mini_irc_ui::Identicon;
+
+#[test]
+fn badges_are_deterministic() {
+    assert_eq!(Identicon::new("alice"), Identicon::new("alice"));
+    assert_eq!(Identicon::new("alice").letters[0], 'A');
+    assert_eq!(Identicon::new("_bob").letters[0], 'B');
+    assert_eq!(Identicon::new("__").letters[0], '?');
+}
+
+#[test]
+fn similar_nicknames_get_different_badges() {
+    let names = ["alice", "alice_", "Alice", "alice2", "aIice"];
+    for (i, a) in names.iter().enumerate() {
+        for b in &names[i + 1..] {
+            assert_ne!(Identicon::new(a), Identicon::new(b), "{a} and {b}");
+        }
+    }
+}
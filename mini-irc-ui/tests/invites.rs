@@ -0,0 +1,57 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use mini_irc_ui::{App, Invite, KeyReaction};
+
+fn invite(chan: &str, by: &str) -> Invite {
+    Invite {
+        chan: chan.to_string(),
+        by: by.to_string(),
+    }
+}
+
+#[test]
+fn invites_are_tracked_until_joined() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    app.add_invite("rust".to_string(), "alice".to_string());
+    app.add_invite("go".to_string(), "bob".to_string());
+    // Already joined
+    app.add_invite("general".to_string(), "bob".to_string());
+    // Replaces the previous invitation to #rust, now the most recent
+    app.add_invite("rust".to_string(), "carol".to_string());
+    assert_eq!(
+        app.invites(),
+        [invite("go", "bob"), invite("rust", "carol")]
+    );
+
+    app.add_tab_with_users("#go".to_string(), vec!["bob".to_string()]);
+    assert_eq!(app.invites(), [invite("rust", "carol")]);
+}
+
+#[test]
+fn accepted_invite_is_removed() {
+    let mut app = App::default();
+    app.add_invite("rust".to_string(), "alice".to_string());
+    app.add_invite("go".to_string(), "bob".to_string());
+    assert_eq!(app.accept_invite(Some("zig")), None);
+    assert_eq!(
+        app.accept_invite(Some("rust")),
+        Some(invite("rust", "alice"))
+    );
+    assert_eq!(app.accept_invite(None), Some(invite("go", "bob")));
+    assert_eq!(app.accept_invite(None), None);
+}
+
+#[test]
+fn f3_joins_the_most_recent_invite() {
+    let mut app = App::default();
+    let f3 = || Event::Key(KeyEvent::new(KeyCode::F(3), KeyModifiers::NONE));
+    assert!(app.react_to_event(f3()).is_none());
+
+    app.add_invite("rust".to_string(), "alice".to_string());
+    app.add_invite("go".to_string(), "bob".to_string());
+    assert!(matches!(
+        app.react_to_event(f3()),
+        Some(KeyReaction::JoinChannel(chan)) if chan == "go"
+    ));
+    assert_eq!(app.invites(), [invite("rust", "alice")]);
+}
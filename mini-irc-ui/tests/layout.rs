@@ -0,0 +1,38 @@
+use mini_irc_ui::{ui, AppState, MIN_HEIGHT, MIN_WIDTH};
+use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+fn render(width: u16, height: u16, state: &mut AppState) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| ui(f, state)).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+fn contains(buffer: &Buffer, text: &str) -> bool {
+    let area = buffer.area;
+    (0..area.height).any(|y| {
+        (0..area.width)
+            .map(|x| buffer.get(x, y).symbol.as_str())
+            .collect::<String>()
+            .contains(text)
+    })
+}
+
+#[test]
+fn tiny_terminals_do_not_panic() {
+    let mut state = AppState::default();
+    for width in 0..MIN_WIDTH + 4 {
+        for height in 0..MIN_HEIGHT + 4 {
+            render(width, height, &mut state);
+        }
+    }
+}
+
+#[test]
+fn small_terminals_show_a_placeholder() {
+    let mut state = AppState::default();
+    let buffer = render(MIN_WIDTH - 1, MIN_HEIGHT, &mut state);
+    assert!(contains(&buffer, "Terminal too small"));
+    let buffer = render(MIN_WIDTH, MIN_HEIGHT, &mut state);
+    assert!(!contains(&buffer, "Terminal too small"));
+    assert!(contains(&buffer, "Notifications"));
+}
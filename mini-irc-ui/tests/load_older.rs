@@ -0,0 +1,45 @@
+use crossterm::event::{Event, KeyModifiers, MouseEvent, MouseEventKind};
+use mini_irc_ui::{App, HistoryEntry, KeyReaction, TimedEntry};
+
+// Scroll up once, and return the tab whose older messages are requested, if any
+fn scroll_up(app: &mut App) -> Option<String> {
+    let event = Event::Mouse(MouseEvent {
+        kind: MouseEventKind::ScrollUp,
+        column: 0,
+        row: 0,
+        modifiers: KeyModifiers::NONE,
+    });
+    match app.react_to_event(event) {
+        Some(KeyReaction::LoadOlder(tab)) => Some(tab),
+        _ => None,
+    }
+}
+
+fn message(content: &str) -> TimedEntry {
+    TimedEntry::now(HistoryEntry::UserMessage {
+        from: "bob".to_string(),
+        content: content.to_string(),
+    })
+}
+
+#[test]
+fn older_messages_are_requested_at_the_top() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    app.push_message("bob".into(), "hello".into(), "#general".into());
+    app.push_message("bob".into(), "again".into(), "#general".into());
+
+    assert_eq!(scroll_up(&mut app), None);
+    assert_eq!(scroll_up(&mut app), Some("#general".to_string()));
+    // Only once until the messages arrive
+    assert_eq!(scroll_up(&mut app), None);
+
+    app.prepend_history("#general", vec![message("one"), message("two")], true);
+    assert_eq!(scroll_up(&mut app), None);
+    assert_eq!(scroll_up(&mut app), Some("#general".to_string()));
+
+    // Nothing older: the top of the history is final
+    app.prepend_history("#general", vec![message("zero")], false);
+    assert_eq!(scroll_up(&mut app), None);
+    assert_eq!(scroll_up(&mut app), None);
+}
@@ -0,0 +1,70 @@
+use mini_irc_ui::{render_markdown, CODE_STYLE};
+use tui::style::{Modifier, Style};
+
+// Content and style of each span
+fn render(text: &str) -> Vec<(String, Style)> {
+    render_markdown(text, Style::default())
+        .into_iter()
+        .map(|span| (span.content.into_owned(), span.style))
+        .collect()
+}
+
+fn plain(text: &str) -> (String, Style) {
+    (text.to_string(), Style::default())
+}
+
+#[test]
+fn bold_italic_and_code_are_rendered() {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let italic = Style::default().add_modifier(Modifier::ITALIC);
+    assert_eq!(
+        render("a *big* and _slanted_ `x = 1`"),
+        [
+            plain("a "),
+            ("big".to_string(), bold),
+            plain(" and "),
+            ("slanted".to_string(), italic),
+            plain(" "),
+            ("x = 1".to_string(), CODE_STYLE),
+        ]
+    );
+}
+
+#[test]
+fn nothing_is_rendered_inside_code() {
+    assert_eq!(
+        render("`*not bold*` _yes_"),
+        [
+            ("*not bold*".to_string(), CODE_STYLE),
+            plain(" "),
+            (
+                "yes".to_string(),
+                Style::default().add_modifier(Modifier::ITALIC)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unpaired_or_inner_delimiters_are_kept() {
+    for text in [
+        "snake_case_name",
+        "2 * 3 * 4",
+        "a *b",
+        "``",
+        "**",
+        "file_*.rs",
+        "",
+        "_trailing _",
+    ] {
+        assert_eq!(render(text), [plain(text)], "{text}");
+    }
+}
+
+#[test]
+fn style_of_the_message_is_kept() {
+    let action = Style::default().add_modifier(Modifier::ITALIC);
+    let spans = render_markdown("waves *hard*", action);
+    assert_eq!(spans[0].style, action);
+    assert_eq!(spans[1].style, action.add_modifier(Modifier::BOLD));
+}
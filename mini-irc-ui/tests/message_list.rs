@@ -0,0 +1,46 @@
+use mini_irc_ui::{MessageList, MessageListState};
+use tui::{backend::TestBackend, buffer::Buffer, text::Spans, Terminal};
+
+// Render `count` numbered lines in a 5x3 area
+fn render(count: usize, state: &mut MessageListState) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(5, 3)).unwrap();
+    let lines = (1..=count)
+        .map(|i| Spans::from(format!("line{i}")))
+        .collect();
+    terminal
+        .draw(|f| f.render_stateful_widget(MessageList::new(lines), f.size(), state))
+        .unwrap();
+    terminal.backend().buffer().clone()
+}
+
+#[test]
+fn short_history_is_at_the_bottom() {
+    let buffer = render(2, &mut MessageListState::default());
+    assert_eq!(buffer, Buffer::with_lines(vec!["", "line1", "line2"]));
+}
+
+#[test]
+fn long_history_shows_the_last_lines() {
+    let buffer = render(5, &mut MessageListState::default());
+    assert_eq!(buffer, Buffer::with_lines(vec!["line3", "line4", "line5"]));
+}
+
+#[test]
+fn offset_scrolls_up() {
+    let mut state = MessageListState { offset: 1 };
+    let buffer = render(5, &mut state);
+    assert_eq!(buffer, Buffer::with_lines(vec!["line2", "line3", "line4"]));
+    assert_eq!(state.offset, 1);
+}
+
+#[test]
+fn offset_is_clamped_to_the_first_line() {
+    let mut state = MessageListState { offset: 10 };
+    let buffer = render(5, &mut state);
+    assert_eq!(buffer, Buffer::with_lines(vec!["line1", "line2", "line3"]));
+    assert_eq!(state.offset, 2);
+
+    let mut state = MessageListState { offset: 10 };
+    render(2, &mut state);
+    assert_eq!(state.offset, 0);
+}
@@ -0,0 +1,32 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use mini_irc_ui::App;
+
+fn next_unread(app: &mut App) {
+    let alt_a = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT);
+    app.react_to_event(Event::Key(alt_a));
+}
+
+#[test]
+fn muted_tabs_are_never_unread() {
+    let mut app = App::default();
+    app.set_nickname("alice".to_string());
+    for tab in ["#general", "#rust", "#go"] {
+        app.add_tab(tab.to_string());
+    }
+    app.set_muted("#rust", true);
+    assert!(app.is_muted("#rust"));
+
+    // Even a mention does not put the muted tab first
+    app.push_message("bob".into(), "alice, look".into(), "#rust".into());
+    app.push_message("bob".into(), "hello".into(), "#go".into());
+    next_unread(&mut app);
+    assert_eq!(app.get_current_tab(), "#go");
+    next_unread(&mut app);
+    assert_eq!(app.get_current_tab(), "#go");
+
+    app.set_muted("#rust", false);
+    assert!(!app.is_muted("#rust"));
+    app.push_message("bob".into(), "again".into(), "#rust".into());
+    next_unread(&mut app);
+    assert_eq!(app.get_current_tab(), "#rust");
+}
@@ -0,0 +1,13 @@
+use mini_irc_ui::{App, NETWORK_TAB};
+
+#[test]
+fn network_tab_cannot_be_removed() {
+    let mut app = App::default();
+    app.add_tab(NETWORK_TAB.to_string());
+    app.add_tab("#general".to_string());
+    app.remove_tab(NETWORK_TAB.to_string());
+    assert!(app.history(NETWORK_TAB).is_some());
+    app.remove_tab("#general".to_string());
+    assert!(app.history("#general").is_none());
+    assert_eq!(app.get_current_tab(), NETWORK_TAB);
+}
@@ -0,0 +1,22 @@
+use mini_irc_ui::{App, Severity, DEFAULT_NOTIFICATION_DURATION};
+use std::time::{Duration, Instant};
+
+#[test]
+fn notifications_expire() {
+    let mut app = App::default();
+    app.notify(Severity::Error, "Connection lost".to_string());
+    let expiry = app.next_expiry().unwrap();
+    assert!(expiry <= Instant::now() + DEFAULT_NOTIFICATION_DURATION);
+    assert!(!app.expire_notification(expiry - Duration::from_millis(1)));
+    assert!(app.expire_notification(expiry));
+    assert_eq!(app.next_expiry(), None);
+}
+
+#[test]
+fn notifications_can_persist() {
+    let mut app = App::default();
+    app.set_notification_duration(None);
+    app.set_notification("Welcome".to_string());
+    assert_eq!(app.next_expiry(), None);
+    assert!(!app.expire_notification(Instant::now() + Duration::from_secs(3600)));
+}
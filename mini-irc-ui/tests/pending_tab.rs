@@ -0,0 +1,31 @@
+use mini_irc_ui::App;
+
+#[test]
+fn pending_tab_is_selected_at_once() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    app.add_pending_tab("#rust".to_string());
+    assert_eq!(app.get_current_tab(), "#rust");
+}
+
+#[test]
+fn acknowledged_tab_is_not_cancelled() {
+    let mut app = App::default();
+    app.add_pending_tab("#rust".to_string());
+    app.add_tab_with_users("#rust".to_string(), vec!["alice".to_string()]);
+    assert!(!app.cancel_pending_tab("#rust"));
+    assert_eq!(app.get_current_tab(), "#rust");
+}
+
+#[test]
+fn refused_join_is_rolled_back() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    app.add_pending_tab("#rust".to_string());
+    app.add_pending_tab("#go".to_string());
+    assert!(app.cancel_pending_tab("#go"));
+    assert_eq!(app.get_current_tab(), "#rust");
+    assert!(app.cancel_pending_tab("#rust"));
+    assert!(!app.cancel_pending_tab("#rust"));
+    assert_eq!(app.get_current_tab(), "#general");
+}
@@ -0,0 +1,89 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use mini_irc_ui::{App, InputHistory, KeyReaction, INPUT_HISTORY_LEN};
+use std::collections::BTreeMap;
+
+fn press(app: &mut App, code: KeyCode) -> Option<KeyReaction> {
+    app.react_to_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+}
+
+fn submit(app: &mut App, text: &str) -> Option<String> {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c));
+    }
+    match press(app, KeyCode::Enter) {
+        Some(KeyReaction::UserInput(input)) => Some(input),
+        _ => None,
+    }
+}
+
+// Press `codes`, then submit the recalled input
+fn recall(app: &mut App, codes: &[KeyCode]) -> Option<String> {
+    for code in codes {
+        press(app, *code);
+    }
+    submit(app, "")
+}
+
+#[test]
+fn up_and_down_browse_the_inputs_of_the_tab() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    press(&mut app, KeyCode::Char('e'));
+    submit(&mut app, "first");
+    submit(&mut app, "/join #rust");
+
+    assert_eq!(recall(&mut app, &[KeyCode::Up]).unwrap(), "/join #rust");
+    // Going past the oldest input stays on it
+    let codes = [KeyCode::Up, KeyCode::Up, KeyCode::Up, KeyCode::Up];
+    assert_eq!(recall(&mut app, &codes).unwrap(), "first");
+    // Recalled inputs are remembered again once submitted
+    let codes = [KeyCode::Up, KeyCode::Up, KeyCode::Up, KeyCode::Down];
+    assert_eq!(recall(&mut app, &codes).unwrap(), "/join #rust");
+
+    // Coming back gives the text typed before browsing
+    for c in "draft".chars() {
+        press(&mut app, KeyCode::Char(c));
+    }
+    let codes = [KeyCode::Up, KeyCode::Down];
+    assert_eq!(recall(&mut app, &codes).unwrap(), "draft");
+
+    // Each tab has its own inputs
+    app.add_tab("#rust".to_string());
+    app.select_tab("#rust");
+    assert_eq!(recall(&mut app, &[KeyCode::Up]).unwrap(), "");
+}
+
+#[test]
+fn saved_inputs_are_recalled() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    press(&mut app, KeyCode::Char('e'));
+    submit(&mut app, "hello");
+    // Blank inputs and repetitions are not remembered, nor inputs starting with a space
+    submit(&mut app, "  ");
+    submit(&mut app, "hello");
+    submit(&mut app, " secret");
+    let saved = app.input_history();
+    assert_eq!(
+        saved,
+        BTreeMap::from([("#general".to_string(), vec!["hello".to_string()])])
+    );
+
+    let mut app = App::default();
+    app.restore_input_history(saved);
+    app.add_tab("#general".to_string());
+    press(&mut app, KeyCode::Char('e'));
+    assert_eq!(recall(&mut app, &[KeyCode::Up]).unwrap(), "hello");
+}
+
+#[test]
+fn only_the_last_inputs_are_kept() {
+    let entries: Vec<String> = (0..INPUT_HISTORY_LEN + 10).map(|i| i.to_string()).collect();
+    let mut history = InputHistory::new(entries);
+    assert_eq!(history.entries().len(), INPUT_HISTORY_LEN);
+    assert_eq!(history.entries()[0], "10");
+    history.push("new");
+    assert_eq!(history.entries().len(), INPUT_HISTORY_LEN);
+    assert_eq!(history.entries()[0], "11");
+    assert_eq!(history.older(""), Some("new"));
+}
@@ -0,0 +1,64 @@
+use mini_irc_ui::{App, HistoryEntry, TabLayout, TimedEntry, NETWORK_TAB};
+use std::time::{Duration, SystemTime};
+
+fn message(at: SystemTime, content: &str) -> TimedEntry {
+    TimedEntry {
+        at,
+        entry: HistoryEntry::UserMessage {
+            from: "bob".to_string(),
+            content: content.to_string(),
+        },
+    }
+}
+
+#[test]
+fn layout_skips_the_network_tab_and_marks_what_was_read() {
+    let mut app = App::default();
+    for tab in [NETWORK_TAB, "#general", "#rust", "@bob"] {
+        app.add_tab(tab.to_string());
+    }
+    assert!(app.select_tab("#general"));
+    assert!(!app.select_tab("#nowhere"));
+    app.push_message("bob".into(), "read".into(), "#rust".into());
+    let layout = app.layout();
+    let names: Vec<&str> = layout.iter().map(|tab| tab.name.as_str()).collect();
+    assert_eq!(names, ["#general", "#rust", "@bob"]);
+    // Nothing of #rust was read yet
+    assert_eq!(layout[1].last_read, None);
+
+    // Displayed by the current tab: read
+    app.select_tab("#rust");
+    let read_at = app.history("#rust").unwrap()[0].at;
+    assert_eq!(app.layout()[1].last_read, Some(read_at));
+    assert_eq!(app.layout()[0].last_read, None);
+}
+
+#[test]
+fn restored_layout_marks_later_entries_unread() {
+    let mut app = App::default();
+    app.add_tab("#general".to_string());
+    app.add_tab("#rust".to_string());
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let history: Vec<_> = (0..5)
+        .map(|i| message(start + Duration::from_secs(i), &format!("message {i}")))
+        .collect();
+    app.prepend_history("#rust", history, false);
+
+    app.restore_layout(&TabLayout {
+        name: "#rust".to_string(),
+        scroll: 100,
+        last_read: Some(start + Duration::from_secs(2)),
+    });
+    let layout = app.layout();
+    // The scroll offset cannot go past the first entry
+    assert_eq!(layout[1].scroll, 5);
+    assert_eq!(layout[1].last_read, Some(start + Duration::from_secs(2)));
+
+    // Restoring a closed tab does nothing
+    app.restore_layout(&TabLayout {
+        name: "#closed".to_string(),
+        scroll: 1,
+        last_read: None,
+    });
+    assert_eq!(app.layout().len(), 2);
+}
@@ -0,0 +1,43 @@
+use mini_irc_ui::split_message;
+
+#[test]
+fn short_message_is_kept() {
+    assert_eq!(split_message("bonjour", 10), vec!["bonjour"]);
+}
+
+#[test]
+fn splits_between_words() {
+    assert_eq!(
+        split_message("un deux trois quatre", 9),
+        vec!["un deux", "trois", "quatre"]
+    );
+}
+
+#[test]
+fn cuts_long_words() {
+    assert_eq!(split_message("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+}
+
+#[test]
+fn never_cuts_inside_a_character() {
+    // 'é' is two bytes long
+    let parts = split_message("éééé", 3);
+    assert_eq!(parts, vec!["é", "é", "é", "é"]);
+    assert!(parts.iter().all(|part| part.len() <= 3));
+}
+
+#[test]
+fn parts_respect_the_limit() {
+    let text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor";
+    for max in 5..30 {
+        let parts = split_message(text, max);
+        assert!(
+            parts.iter().all(|part| part.len() <= max),
+            "{max}: {parts:?}"
+        );
+        // Words are only cut when longer than the limit ("consectetur")
+        if max >= "consectetur".len() {
+            assert_eq!(parts.join(" "), text);
+        }
+    }
+}
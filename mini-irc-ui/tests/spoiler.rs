@@ -0,0 +1,37 @@
+use mini_irc_ui::{has_spoiler, mask_spoilers, App, HistoryEntry};
+
+#[test]
+fn spoilers_are_masked_to_their_width() {
+    assert_eq!(mask_spoilers("answer: ||42||!"), "answer: ▒▒!");
+    assert_eq!(mask_spoilers("||a|| and ||bc||"), "▒ and ▒▒");
+    // Wide characters take two cells
+    assert_eq!(mask_spoilers("||猫||"), "▒▒");
+}
+
+#[test]
+fn unpaired_or_empty_spoilers_are_kept() {
+    for text in ["a || b", "||||", "|| |"] {
+        assert_eq!(mask_spoilers(text), text, "{text}");
+        assert!(!has_spoiler(text), "{text}");
+    }
+    assert_eq!(mask_spoilers("x || y || z ||"), "x ▒▒▒ z ||");
+    assert!(has_spoiler("||||x||"));
+}
+
+#[test]
+fn toggling_reveals_then_masks_again() {
+    let mut app = App::default();
+    app.add_tab("#quiz".to_string());
+    assert!(!app.toggle_spoilers(1));
+    app.push_message("bob".into(), "||first||".into(), "#quiz".into());
+    app.push_entry(
+        HistoryEntry::Notice("||not a message||".into()),
+        "#quiz".into(),
+    );
+    app.push_message("bob".into(), "||second||".into(), "#quiz".into());
+    assert!(app.toggle_spoilers(1));
+    assert!(app.toggle_spoilers(2));
+    assert!(!app.toggle_spoilers(3));
+    assert!(!app.toggle_spoilers(0));
+    assert!(app.toggle_spoilers(1));
+}
@@ -0,0 +1,88 @@
+//! Contention sur le registre des canaux : 1 000 clients rejoignent et écrivent dans des canaux
+//! tirés parmi 10 000, avec un seul verrou ou avec des fragments. Les écarts n'apparaissent
+//! qu'avec plusieurs cœurs.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use mini_irc_protocol::ChanOp;
+use mini_irc_server::ChannelRegistry;
+use std::sync::Arc;
+
+const CHANNELS: u64 = 10_000;
+const CLIENTS: u64 = 1_000;
+/// Canaux rejoints, puis messages envoyés, par client
+const OPERATIONS: u64 = 10;
+
+/// Générateur pseudo-aléatoire déterministe, propre à chaque client
+fn channels(client: u64) -> impl Iterator<Item = String> {
+    let mut state = client.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+    std::iter::repeat_with(move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        format!("chan{}", state % CHANNELS)
+    })
+}
+
+async fn client(registry: Arc<ChannelRegistry>, client: u64) {
+    let user = format!("user{client}");
+    let mut joined = Vec::new();
+    for channel in channels(client).take(OPERATIONS as usize) {
+        if let Some(membership) = registry.join(&channel, &user, 32, None).await {
+            joined.push(membership);
+        }
+    }
+    for (handle, _) in joined.iter() {
+        handle
+            .send(
+                &user,
+                ChanOp::Message {
+                    from: user.clone(),
+                    content: "Lorem ipsum".to_string(),
+                },
+            )
+            .await;
+        tokio::task::yield_now().await;
+    }
+}
+
+fn contention(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+    let mut group = c.benchmark_group("registry");
+    group.throughput(Throughput::Elements(CLIENTS * OPERATIONS * 2));
+    group.sample_size(20);
+
+    for shards in [1, 16, 64] {
+        group.bench_with_input(BenchmarkId::new("shards", shards), &shards, |b, &shards| {
+            b.iter_batched(
+                || {
+                    // Les canaux existent déjà : seuls les abonnements et envois sont mesurés
+                    let registry = Arc::new(ChannelRegistry::new(shards));
+                    runtime.block_on(async {
+                        for channel in 0..CHANNELS {
+                            registry
+                                .subscribe(&format!("chan{channel}"), "owner", 32)
+                                .await;
+                        }
+                    });
+                    registry
+                },
+                |registry| {
+                    runtime.block_on(async {
+                        let tasks: Vec<_> = (0..CLIENTS)
+                            .map(|id| tokio::spawn(client(registry.clone(), id)))
+                            .collect();
+                        for task in tasks {
+                            task.await.unwrap();
+                        }
+                    });
+                    registry
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, contention);
+criterion_main!(benches);
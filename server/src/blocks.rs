@@ -0,0 +1,88 @@
+use mini_irc_protocol::{ErrorCode, MAX_BLOCKED};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{RwLock, RwLockWriteGuard};
+
+/// Utilisateurs bloqués par chaque utilisateur connecté : leurs messages directs sont refusés
+/// dès l'envoi.
+///
+/// Une liste appartient à la session de son propriétaire et disparaît à sa déconnexion : la
+/// session suivante du même nom ne l'hérite pas. Chaque message direct et chaque invitation y sont vérifiés : tant que personne n'a
+/// bloqué personne, la vérification ne prend aucun verrou, et ensuite seulement un verrou en
+/// lecture.
+#[derive(Debug, Default)]
+pub(crate) struct BlockLists {
+    lists: RwLock<HashMap<String, HashSet<String>>>,
+    // Nombre de listes non vides, mis à jour sous le verrou en écriture
+    owners: AtomicUsize,
+}
+
+// Verrou en écriture, qui met à jour le nombre de listes en étant relâché
+struct WriteGuard<'a> {
+    lists: RwLockWriteGuard<'a, HashMap<String, HashSet<String>>>,
+    owners: &'a AtomicUsize,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        self.owners.store(self.lists.len(), Ordering::Release);
+    }
+}
+
+impl BlockLists {
+    fn write(&self) -> WriteGuard<'_> {
+        WriteGuard {
+            lists: self.lists.write().unwrap(),
+            owners: &self.owners,
+        }
+    }
+
+    /// Ajoute `target` aux utilisateurs bloqués par `user`, et renvoie sa liste, d'au plus
+    /// [`MAX_BLOCKED`] utilisateurs.
+    pub(crate) fn block(&self, user: &str, target: &str) -> Result<Vec<String>, ErrorCode> {
+        let mut guard = self.write();
+        let blocked = guard.lists.entry(user.to_string()).or_default();
+        if blocked.len() >= MAX_BLOCKED && !blocked.contains(target) {
+            return Err(ErrorCode::TooManyBlocked(MAX_BLOCKED));
+        }
+        blocked.insert(target.to_string());
+        Ok(sorted(blocked))
+    }
+
+    /// Retire `target` des utilisateurs bloqués par `user`, et renvoie ceux qui restent.
+    pub(crate) fn unblock(&self, user: &str, target: &str) -> Vec<String> {
+        let mut guard = self.write();
+        let Some(blocked) = guard.lists.get_mut(user) else {
+            return Vec::new();
+        };
+        blocked.remove(target);
+        let remaining = sorted(blocked);
+        if remaining.is_empty() {
+            guard.lists.remove(user);
+        }
+        remaining
+    }
+
+    /// Oublie la liste de `user`, à sa déconnexion.
+    pub(crate) fn forget(&self, user: &str) {
+        self.write().lists.remove(user);
+    }
+
+    /// Indique si `user` a bloqué `from`.
+    pub(crate) fn blocks(&self, user: &str, from: &str) -> bool {
+        if self.owners.load(Ordering::Acquire) == 0 {
+            return false;
+        }
+        self.lists
+            .read()
+            .unwrap()
+            .get(user)
+            .is_some_and(|blocked| blocked.contains(from))
+    }
+}
+
+fn sorted(blocked: &HashSet<String>) -> Vec<String> {
+    let mut blocked: Vec<String> = blocked.iter().cloned().collect();
+    blocked.sort();
+    blocked
+}
@@ -0,0 +1,625 @@
+//! Tâche propre à chaque canal : elle seule modifie l'état du canal (membres, numérotation,
+//! archive, propriétaire, message d'accueil), sans verrou, en traitant une à une les commandes
+//! reçues de ses [`ChannelHandle`]. Les diffusions partent vers les files d'envoi des membres
+//! par leur abonnement au canal.
+//!
+//! Un canal système ([`ChannelHandle::spawn_system`]) n'a jamais de propriétaire.
+//!
+//! Chaque arrivée reçoit un numéro d'adhésion : un départ n'est annoncé que pour l'adhésion
+//! en cours, jamais pour une adhésion plus ancienne du même nom, ni pour un nom absent.
+
+use mini_irc_protocol::{
+    ArchivedMessage, BroadcastMetrics, BroadcastReceiverWithList, BroadcastSenderWithList, ChanOp,
+    ErrorCode, Notice, Response,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// Nombre de messages archivés par canal, les plus anciens étant oubliés au-delà
+pub const ARCHIVE_LEN: usize = 1000;
+
+/// Commandes en attente de traitement par la tâche d'un canal, au-delà desquelles les
+/// expéditeurs attendent
+const COMMAND_QUEUE_SIZE: usize = 256;
+
+type Reply<R> = oneshot::Sender<R>;
+
+/// Demande adressée à la tâche d'un canal, avec de quoi lui répondre.
+enum Command {
+    Join {
+        user: String,
+        expiry: Option<Duration>,
+        reply: Reply<Option<Joined>>,
+    },
+    Leave {
+        user: String,
+        id: u64,
+        reply: Reply<bool>,
+    },
+    /// Départ d'un membre déjà désabonné, à annoncer
+    Depart {
+        user: String,
+        id: u64,
+    },
+    Message {
+        from: String,
+        op: ChanOp,
+        reply: Reply<bool>,
+    },
+    Announce {
+        op: ChanOp,
+    },
+    TransferOp {
+        by: String,
+        to: String,
+        admin: bool,
+        reply: Reply<Result<(), ErrorCode>>,
+    },
+    ClaimOp {
+        user: String,
+        expiry: Option<Duration>,
+        reply: Reply<Result<(), ErrorCode>>,
+    },
+    SetWelcome {
+        user: String,
+        text: String,
+        admin: bool,
+        max_len: usize,
+        reply: Reply<Result<Notice, ErrorCode>>,
+    },
+    Operates {
+        user: String,
+        reply: Reply<bool>,
+    },
+    History {
+        user: String,
+        before_id: Option<u64>,
+        limit: usize,
+        reply: Reply<Option<(Vec<ArchivedMessage>, bool)>>,
+    },
+    Sweep {
+        before: SystemTime,
+        live: Arc<HashSet<String>>,
+        reply: Reply<Vec<String>>,
+    },
+}
+
+/// Arrivée dans un canal, par [`ChannelHandle::join`].
+#[derive(Debug)]
+pub struct Joined {
+    /// Abonnement aux diffusions du canal, à commencer par l'annonce de l'arrivée
+    pub receiver: BroadcastReceiverWithList<Response, String>,
+    /// Numéro d'adhésion, pour quitter le canal
+    pub id: u64,
+    /// Membres à l'arrivée, arrivant compris
+    pub users: Vec<String>,
+    pub owner: Option<String>,
+    /// Message d'accueil du canal
+    pub welcome: Option<String>,
+}
+
+/// Propriétaire d'un canal : son créateur, jusqu'à ce qu'il transmette le canal.
+#[derive(Debug, Default)]
+struct Ownership {
+    owner: Option<String>,
+    /// Départ du propriétaire, s'il n'est plus membre
+    absent_since: Option<Instant>,
+}
+
+impl Ownership {
+    // Sans propriétaire, ou le sien est absent depuis plus de `expiry`
+    fn lapsed(&self, expiry: Option<Duration>) -> bool {
+        match (&self.owner, self.absent_since, expiry) {
+            (None, _, _) => true,
+            (Some(_), Some(since), Some(expiry)) => since.elapsed() >= expiry,
+            _ => false,
+        }
+    }
+}
+
+/// État d'un canal, propriété de sa tâche.
+struct Channel {
+    name: String,
+    sender: Arc<BroadcastSenderWithList<Response, String>>,
+    // Adhésions dont l'arrivée a été annoncée et pas encore le départ, par membre
+    members: HashMap<String, u64>,
+    // Numéro de la dernière adhésion
+    last_id: u64,
+    // Numéro de la dernière réponse créée
+    seq: u64,
+    // Derniers messages, par numéros croissants
+    archive: VecDeque<ArchivedMessage>,
+    ownership: Ownership,
+    welcome: Option<String>,
+    // Canal système : personne ne peut en devenir propriétaire
+    system: bool,
+}
+
+impl Channel {
+    fn handle(&mut self, command: Command) {
+        // Une réponse que plus personne n'attend est abandonnée
+        match command {
+            Command::Join {
+                user,
+                expiry,
+                reply,
+            } => {
+                let _ = reply.send(self.join(&user, expiry));
+            }
+            Command::Leave { user, id, reply } => {
+                let _ = reply.send(self.remove(&user, id));
+            }
+            Command::Depart { user, id } => {
+                self.depart(&user, id);
+            }
+            // Renvoyé aussi à l'auteur, qui l'affiche avec son numéro
+            Command::Message { from, op, reply } => {
+                let member = self.sender.contains(&from);
+                if member {
+                    self.broadcast(op);
+                }
+                let _ = reply.send(member);
+            }
+            Command::Announce { op } => self.broadcast(op),
+            Command::TransferOp {
+                by,
+                to,
+                admin,
+                reply,
+            } => {
+                let _ = reply.send(self.transfer_op(&by, &to, admin));
+            }
+            Command::ClaimOp {
+                user,
+                expiry,
+                reply,
+            } => {
+                let _ = reply.send(self.claim_op(&user, expiry));
+            }
+            Command::SetWelcome {
+                user,
+                text,
+                admin,
+                max_len,
+                reply,
+            } => {
+                let _ = reply.send(self.set_welcome(&user, text, admin, max_len));
+            }
+            Command::Operates { user, reply } => {
+                let operates = self.sender.contains(&user)
+                    && self.ownership.owner.as_deref() == Some(user.as_str());
+                let _ = reply.send(operates);
+            }
+            Command::History {
+                user,
+                before_id,
+                limit,
+                reply,
+            } => {
+                let history = self
+                    .sender
+                    .contains(&user)
+                    .then(|| self.history(before_id, limit));
+                let _ = reply.send(history);
+            }
+            Command::Sweep {
+                before,
+                live,
+                reply,
+            } => {
+                let removed = self
+                    .sender
+                    .snapshot()
+                    .into_iter()
+                    .filter(|subscriber| {
+                        subscriber.joined_at < before && !live.contains(&subscriber.identity)
+                    })
+                    .filter_map(|subscriber| {
+                        let id = *self.members.get(&subscriber.identity)?;
+                        self.remove(&subscriber.identity, id)
+                            .then_some(subscriber.identity)
+                    })
+                    .collect();
+                let _ = reply.send(removed);
+            }
+        }
+    }
+
+    // Abonne `user` et annonce son arrivée : les commandes étant traitées une à une, aucune
+    // autre arrivée ou départ ne peut s'intercaler. Il devient propriétaire d'un canal qui
+    // n'en a pas, ou que son propriétaire a quitté depuis plus de `expiry` s'il en est le seul
+    // membre.
+    fn join(&mut self, user: &str, expiry: Option<Duration>) -> Option<Joined> {
+        if self.sender.contains(&user.to_string()) {
+            return None;
+        }
+        // Adhésion précédente du même nom, désabonnée sans que son départ ait encore été
+        // traité : il est annoncé avant la nouvelle arrivée
+        if let Some(&id) = self.members.get(user) {
+            self.depart(user, id);
+        }
+        let receiver = self.sender.subscribe(user.to_string())?;
+        let users = receiver.subscribers();
+        self.last_id += 1;
+        self.members.insert(user.to_string(), self.last_id);
+        self.broadcast(ChanOp::UserAdd(user.to_string()));
+        if self.ownership.owner.as_deref() == Some(user) {
+            self.ownership.absent_since = None;
+        } else if !self.system && self.ownership.lapsed(expiry) && users == [user] {
+            self.ownership = Ownership {
+                owner: Some(user.to_string()),
+                absent_since: None,
+            };
+        }
+        Some(Joined {
+            receiver,
+            id: self.last_id,
+            users,
+            owner: self.ownership.owner.clone(),
+            welcome: self.welcome.clone(),
+        })
+    }
+
+    // Désabonne `user` et annonce son départ, si `id` est son adhésion en cours. `false`
+    // sinon, en particulier s'il n'est pas membre.
+    fn remove(&mut self, user: &str, id: u64) -> bool {
+        if self.members.get(user) != Some(&id) {
+            return false;
+        }
+        // Le récepteur est fermé avant l'annonce
+        self.sender.unsubscribe(&user.to_string());
+        self.depart(user, id)
+    }
+
+    // Annonce le départ de `user`, déjà désabonné, si `id` est son adhésion en cours. S'il
+    // est propriétaire, son absence commence.
+    fn depart(&mut self, user: &str, id: u64) -> bool {
+        if self.members.get(user) != Some(&id) {
+            return false;
+        }
+        self.members.remove(user);
+        if self.ownership.owner.as_deref() == Some(user) {
+            self.ownership.absent_since = Some(Instant::now());
+        }
+        self.broadcast(ChanOp::UserDel(user.to_string()));
+        true
+    }
+
+    fn transfer_op(&mut self, by: &str, to: &str, admin: bool) -> Result<(), ErrorCode> {
+        if !self.sender.contains(&by.to_string()) && !admin {
+            Err(ErrorCode::NotInChannel)
+        } else if self.system || (self.ownership.owner.as_deref() != Some(by) && !admin) {
+            Err(ErrorCode::PermissionDenied)
+        } else if !self.sender.contains(&to.to_string()) {
+            Err(ErrorCode::NotAMember(to.to_string()))
+        } else {
+            self.set_owner(to);
+            Ok(())
+        }
+    }
+
+    // Sans délai, un propriétaire absent le reste indéfiniment
+    fn claim_op(&mut self, user: &str, expiry: Option<Duration>) -> Result<(), ErrorCode> {
+        if !self.sender.contains(&user.to_string()) {
+            Err(ErrorCode::NotInChannel)
+        } else if self.system {
+            Err(ErrorCode::PermissionDenied)
+        } else if !self.ownership.lapsed(expiry) {
+            Err(ErrorCode::ChannelHasOwner)
+        } else {
+            self.set_owner(user);
+            Ok(())
+        }
+    }
+
+    fn set_owner(&mut self, user: &str) {
+        self.ownership = Ownership {
+            owner: Some(user.to_string()),
+            absent_since: None,
+        };
+        self.broadcast(ChanOp::Owner(user.to_string()));
+    }
+
+    // Un texte vide supprime le message d'accueil
+    fn set_welcome(
+        &mut self,
+        user: &str,
+        text: String,
+        admin: bool,
+        max_len: usize,
+    ) -> Result<Notice, ErrorCode> {
+        if !self.sender.contains(&user.to_string()) && !admin {
+            Err(ErrorCode::NotInChannel)
+        } else if self.ownership.owner.as_deref() != Some(user) && !admin {
+            Err(ErrorCode::PermissionDenied)
+        } else if text.len() > max_len {
+            Err(ErrorCode::MessageTooLong)
+        } else if text.trim().is_empty() {
+            self.welcome = None;
+            Ok(Notice::WelcomeCleared)
+        } else {
+            self.welcome = Some(text.clone());
+            Ok(Notice::WelcomeSet(text))
+        }
+    }
+
+    // Diffuse `op` avec le numéro de séquence suivant du canal (à partir de 1). Les messages
+    // sont archivés sous ce numéro.
+    fn broadcast(&mut self, op: ChanOp) {
+        self.seq += 1;
+        if let ChanOp::Message { from, content } = &op {
+            if self.archive.len() == ARCHIVE_LEN {
+                self.archive.pop_front();
+            }
+            self.archive.push_back(ArchivedMessage {
+                id: self.seq,
+                at: SystemTime::now(),
+                from: from.clone(),
+                content: content.clone(),
+            });
+        }
+        let _ = self.sender.send(Response::Channel {
+            op,
+            chan: self.name.clone(),
+            seq: self.seq,
+        });
+    }
+
+    // Au plus `limit` messages archivés, antérieurs au numéro `before_id` (les plus récents
+    // si `None`), du plus ancien au plus récent. Indique aussi si de plus anciens restent.
+    fn history(&self, before_id: Option<u64>, limit: usize) -> (Vec<ArchivedMessage>, bool) {
+        let end = match before_id {
+            Some(id) => self.archive.partition_point(|message| message.id < id),
+            None => self.archive.len(),
+        };
+        let start = end.saturating_sub(limit);
+        (self.archive.range(start..end).cloned().collect(), start > 0)
+    }
+}
+
+/// Accès à un canal, depuis n'importe quelle connexion : les modifications sont confiées à
+/// la tâche du canal, les lectures de la liste des membres se font sans attendre ni verrou.
+/// Une connexion conserve l'accès aux canaux qu'elle a rejoints : ses messages ne passent
+/// ainsi plus par le registre.
+#[derive(Debug, Clone)]
+pub struct ChannelHandle {
+    commands: mpsc::Sender<Command>,
+    sender: Arc<BroadcastSenderWithList<Response, String>>,
+    system: bool,
+}
+
+impl ChannelHandle {
+    /// Lance la tâche du canal `name`, qui peut conserver `capacity` diffusions non lues par
+    /// un membre avant que celui-ci ne les manque. La tâche s'arrête avec le dernier accès.
+    pub fn spawn(name: &str, capacity: usize) -> Self {
+        Self::spawn_with(name, capacity, false)
+    }
+
+    /// Comme [`ChannelHandle::spawn`], pour un canal système, créé par le serveur : personne
+    /// n'en devient propriétaire, ni ne peut le revendiquer ou se le voir transmettre. Seuls
+    /// les administrateurs en changent le message d'accueil.
+    pub fn spawn_system(name: &str, capacity: usize) -> Self {
+        Self::spawn_with(name, capacity, true)
+    }
+
+    fn spawn_with(name: &str, capacity: usize, system: bool) -> Self {
+        let (commands, mut rx) = mpsc::channel(COMMAND_QUEUE_SIZE);
+        let sender = Arc::new(BroadcastSenderWithList::new(capacity));
+        let mut channel = Channel {
+            name: name.to_string(),
+            sender: sender.clone(),
+            members: HashMap::new(),
+            last_id: 0,
+            seq: 0,
+            archive: VecDeque::new(),
+            ownership: Ownership::default(),
+            welcome: None,
+            system,
+        };
+        tokio::spawn(async move {
+            while let Some(command) = rx.recv().await {
+                channel.handle(command);
+            }
+        });
+        Self {
+            commands,
+            sender,
+            system,
+        }
+    }
+
+    // Envoie une commande et attend sa réponse, ou `None` si la tâche est arrêtée
+    async fn request<R>(&self, command: impl FnOnce(Reply<R>) -> Command) -> Option<R> {
+        let (reply, response) = oneshot::channel();
+        self.commands.send(command(reply)).await.ok()?;
+        response.await.ok()
+    }
+
+    /// Fait rejoindre le canal à `user`, et annonce son arrivée. Il en devient propriétaire si
+    /// le canal n'en a pas, ou que le sien l'a quitté depuis plus de `expiry` et qu'il en est
+    /// le seul membre. `None` s'il en est déjà membre.
+    pub async fn join(&self, user: &str, expiry: Option<Duration>) -> Option<Joined> {
+        self.request(|reply| Command::Join {
+            user: user.to_string(),
+            expiry,
+            reply,
+        })
+        .await
+        .flatten()
+    }
+
+    /// Désabonne `user` et annonce son départ, si `id` est son adhésion en cours
+    /// ([`Joined::id`]). `false` sinon, en particulier s'il n'est pas membre.
+    pub async fn leave(&self, user: &str, id: u64) -> bool {
+        self.request(|reply| Command::Leave {
+            user: user.to_string(),
+            id,
+            reply,
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    /// Annonce le départ de `user`, déjà désabonné (par exemple par
+    /// [`BroadcastReceiverWithList::unsubscribe`]), si `id` est son adhésion en cours. N'attend
+    /// pas : utilisable depuis un `Drop`.
+    pub fn depart(&self, user: &str, id: u64) {
+        let command = Command::Depart {
+            user: user.to_string(),
+            id,
+        };
+        if let Err(mpsc::error::TrySendError::Full(command)) = self.commands.try_send(command) {
+            let commands = self.commands.clone();
+            tokio::spawn(async move {
+                let _ = commands.send(command).await;
+            });
+        }
+    }
+
+    /// Désabonne `user` et annonce son départ comme [`ChannelHandle::depart`], sans attendre :
+    /// utilisable depuis un `Drop`, pour l'adhésion `id` d'une connexion qui se termine.
+    pub fn depart_now(&self, user: &str, id: u64) {
+        self.sender.unsubscribe(&user.to_string());
+        self.depart(user, id);
+    }
+
+    /// Diffuse `op` au nom de `from`, s'il est membre du canal. L'auteur le reçoit comme les
+    /// autres membres : les réponses d'un canal arrivent ainsi dans l'ordre de leurs numéros.
+    pub async fn send(&self, from: &str, op: ChanOp) -> bool {
+        self.request(|reply| Command::Message {
+            from: from.to_string(),
+            op,
+            reply,
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    /// Diffuse `op` sans attendre, par exemple un changement de présence d'un membre.
+    pub async fn announce(&self, op: ChanOp) {
+        let _ = self.commands.send(Command::Announce { op }).await;
+    }
+
+    /// Transmet la propriété du canal de `by`, propriétaire ou administrateur (`admin`), à
+    /// `to`, membre du canal.
+    pub async fn transfer_op(&self, by: &str, to: &str, admin: bool) -> Result<(), ErrorCode> {
+        self.request(|reply| Command::TransferOp {
+            by: by.to_string(),
+            to: to.to_string(),
+            admin,
+            reply,
+        })
+        .await
+        .unwrap_or(Err(ErrorCode::NotInChannel))
+    }
+
+    /// Donne le canal à `user`, membre, s'il n'a pas de propriétaire ou que le sien est absent
+    /// depuis plus de `expiry`.
+    pub async fn claim_op(&self, user: &str, expiry: Option<Duration>) -> Result<(), ErrorCode> {
+        self.request(|reply| Command::ClaimOp {
+            user: user.to_string(),
+            expiry,
+            reply,
+        })
+        .await
+        .unwrap_or(Err(ErrorCode::NotInChannel))
+    }
+
+    /// Remplace le message d'accueil par `text`, d'au plus `max_len` octets, à la demande de
+    /// `user`, propriétaire ou administrateur (`admin`). Un texte vide le supprime.
+    pub async fn set_welcome(
+        &self,
+        user: &str,
+        text: String,
+        admin: bool,
+        max_len: usize,
+    ) -> Result<Notice, ErrorCode> {
+        self.request(|reply| Command::SetWelcome {
+            user: user.to_string(),
+            text,
+            admin,
+            max_len,
+            reply,
+        })
+        .await
+        .unwrap_or(Err(ErrorCode::NotInChannel))
+    }
+
+    /// Indique si `user` est membre et propriétaire, c'est-à-dire opérateur, du canal.
+    pub async fn operates(&self, user: &str) -> bool {
+        self.request(|reply| Command::Operates {
+            user: user.to_string(),
+            reply,
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    /// Au plus `limit` messages archivés, antérieurs au numéro `before_id` (les plus récents
+    /// si `None`), du plus ancien au plus récent, et s'il en reste de plus anciens. `None` si
+    /// `user` n'est pas membre.
+    pub async fn history(
+        &self,
+        user: &str,
+        before_id: Option<u64>,
+        limit: usize,
+    ) -> Option<(Vec<ArchivedMessage>, bool)> {
+        self.request(|reply| Command::History {
+            user: user.to_string(),
+            before_id,
+            limit,
+            reply,
+        })
+        .await
+        .flatten()
+    }
+
+    /// Désabonne, en annonçant leur départ, les membres abonnés avant `before` absents de
+    /// `live`. Renvoie les membres retirés.
+    pub async fn sweep(&self, before: SystemTime, live: Arc<HashSet<String>>) -> Vec<String> {
+        self.request(|reply| Command::Sweep {
+            before,
+            live,
+            reply,
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Indique si `user` est membre du canal.
+    pub fn contains(&self, user: &str) -> bool {
+        self.sender.contains(&user.to_string())
+    }
+
+    /// Membres, dans l'ordre d'arrivée.
+    pub fn subscribers(&self) -> Vec<String> {
+        self.sender.subscribers()
+    }
+
+    /// Nombre de membres.
+    pub fn len(&self) -> usize {
+        self.sender.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sender.is_empty()
+    }
+
+    /// Indique si le canal est un canal système ([`ChannelHandle::spawn_system`]).
+    pub fn is_system(&self) -> bool {
+        self.system
+    }
+
+    /// Indique si les deux accès mènent au même canal.
+    pub(crate) fn same_channel(&self, other: &ChannelHandle) -> bool {
+        Arc::ptr_eq(&self.sender, &other.sender)
+    }
+
+    /// État des diffusions du canal, voir [`BroadcastSenderWithList::metrics`].
+    pub fn metrics(&self) -> BroadcastMetrics<String> {
+        self.sender.metrics()
+    }
+}
@@ -0,0 +1,907 @@
+//! Traitement d'une connexion : échange de clés, puis requêtes du client jusqu'à la
+//! déconnexion. L'état propre à la connexion est réuni dans une [`Connection`], dont chaque
+//! requête passe par [`Connection::handle_request`].
+
+use crate::{
+    add_user_to_chan, alert_operators, announce_to_chans, check_nickname, connect_user,
+    disconnect_user, error, ghost, message_op, send_to_session, send_to_user, server_stats,
+    sessions, stats_of, whois, ChannelHandle, ConnectionState, Joined, Outbound, RateWindow,
+    Server, Session, SessionStats, Takeover,
+};
+use anyhow::{anyhow, bail, Result};
+use crypto_box::PublicKey;
+use mini_irc_protocol::{
+    BroadcastEvent, BroadcastReceiverWithList, ByteCounter, ByteCounts, Capabilities, ChanInfo,
+    ChanOp, Encrypted, EncryptionStatus, ErrorCode, HandshakeRequest, HandshakeResponse,
+    MessageReceiver, Notice, NoticeScope, ProtocolError, Request, Response, Transport,
+    TypedChannel, MAX_HISTORY_FETCH, MAX_MESSAGE_TARGETS, TARGET_SEPARATOR,
+};
+use serde_encrypt::{
+    key::key_pair::ReceiverKeyPair, shared_key::SharedKey, traits::SerdeEncryptPublicKey,
+    EncryptedMessage, ReceiverCombinedKey, ReceiverKeyPairCore,
+};
+use serde_encrypt_core::key::key_pair::public_key::SenderPublicKey;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval};
+use tracing::{debug, info, warn};
+
+/// Intervalle entre deux [`Response::Ping`] envoyés à un client
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+/// Délai accordé à un client refusé pour l'échange de clés
+const REFUSAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// État d'une connexion établie : utilisateur, canaux rejoints, file d'envoi... Les requêtes du
+/// client sont traitées par [`Connection::handle_request`], les réponses envoyées par
+/// [`Connection::deliver`], et [`Connection::cleanup`] libère le nom et quitte les canaux à la
+/// déconnexion.
+///
+/// Une connexion sans transport ([`Connection::detached`]) permet de traiter des requêtes
+/// directement, par exemple pour les tests.
+#[derive(Debug)]
+pub struct Connection {
+    server: Server,
+    // Adresse du client, pour les journaux
+    peer: String,
+    state: ConnectionState,
+    // Vide tant que l'utilisateur ne s'est pas identifié
+    user: String,
+    stats: Arc<SessionStats>,
+    takeover: Arc<Takeover>,
+    // Réponses, messages des canaux et messages directs passent par la file d'envoi
+    outbound: Outbound,
+    // Canaux rejoints : les messages sont confiés directement à la tâche du canal, sans passer
+    // par le registre
+    joined: HashMap<String, JoinedChannel>,
+    // Dernier ping envoyé et sans réponse, pour mesurer le temps d'aller-retour
+    ping_id: u64,
+    ping_sent: Option<(u64, Instant)>,
+    // Statistiques du serveur, envoyées périodiquement une fois le client abonné
+    stats_tick: Option<Interval>,
+    stats_subscribed: bool,
+    // Messages directs récents, pour en limiter le débit
+    dm_window: Option<RateWindow>,
+}
+
+impl Connection {
+    fn new(
+        server: Server,
+        peer: String,
+        state: ConnectionState,
+        stats: Arc<SessionStats>,
+        outbound: Outbound,
+    ) -> Self {
+        let stats_tick = server
+            .stats_interval
+            .map(|interval| tokio::time::interval_at(Instant::now() + interval, interval));
+        let dm_window = server.dm_rate.map(|(max, per)| RateWindow::new(max, per));
+        Self {
+            server,
+            peer,
+            state,
+            user: String::new(),
+            stats,
+            takeover: Arc::new(Takeover::default()),
+            outbound,
+            joined: HashMap::new(),
+            ping_id: 0,
+            ping_sent: None,
+            stats_tick,
+            stats_subscribed: false,
+            dm_window,
+        }
+    }
+
+    /// Connexion à `server` dont le chiffrement est déjà établi, sans transport : tout ce qui
+    /// est destiné au client arrive sur le récepteur renvoyé.
+    pub fn detached(server: &Server) -> (Self, mpsc::Receiver<Response>) {
+        let (outbound, rx) = Outbound::new();
+        let stats = Arc::new(SessionStats::new(
+            ByteCounts::default(),
+            EncryptionStatus::Plain,
+        ));
+        let connection = Self::new(
+            server.clone(),
+            "detached".to_string(),
+            ConnectionState::Authenticated,
+            stats,
+            outbound,
+        );
+        (connection, rx)
+    }
+
+    /// Nom de l'utilisateur, vide tant qu'il ne s'est pas identifié.
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Place `response` dans la file d'envoi du client.
+    pub async fn deliver(&self, response: Response) {
+        self.outbound.send(response).await;
+    }
+
+    /// Traite une requête du client, et renvoie la réponse à lui envoyer, s'il y en a une.
+    /// Certaines réponses passent directement par la file d'envoi, pour être ordonnées avec
+    /// les diffusions des canaux ([`Response::AckJoin`]).
+    pub async fn handle_request(&mut self, request: Request) -> Option<Response> {
+        if !matches!(request, Request::Pong(_) | Request::Ping(_)) {
+            *self.stats.last_activity.lock().unwrap() = Instant::now();
+            if self.stats.away.swap(false, Ordering::Relaxed) {
+                self.announce(ChanOp::UserBack(self.user.clone())).await;
+            }
+        }
+        // Requête inattendue à cette étape de la connexion : refusée avant tout traitement
+        if let Err(e) = self.state.check(&request) {
+            return Some(error(e.into()));
+        }
+        match request {
+            Request::Connect(username) => self.connect(username).await,
+            Request::JoinChan(channel) => self.join(channel).await,
+            Request::LeaveChan(channel) => Some(self.leave(channel).await),
+            Request::Message { to, content } => match self.message(to, content).await {
+                Ok(()) => None,
+                // Arrondi à la milliseconde supérieure, pour que le renvoi ne devance pas la
+                // fenêtre
+                Err(ErrorCode::RateLimited { retry_after }) => Some(Response::RateLimited {
+                    retry_after_ms: retry_after.as_nanos().div_ceil(1_000_000) as u64,
+                }),
+                Err(e) => Some(error(e)),
+            },
+            Request::MessageMany { to, content } => Some(self.message_many(to, content).await),
+            Request::Stats => Some(Response::Stats(self.stats.snapshot(&self.user))),
+            Request::SubscribeStats => {
+                self.stats_subscribed = true;
+                // Le prochain envoi périodique attend une période entière
+                if let Some(tick) = self.stats_tick.as_mut() {
+                    tick.reset();
+                }
+                Some(self.server_stats())
+            }
+            Request::UnsubscribeStats => {
+                self.stats_subscribed = false;
+                Some(Response::Ack)
+            }
+            Request::StatsOf(other) => {
+                if !self.is_admin() {
+                    Some(error(ErrorCode::PermissionDenied))
+                } else {
+                    Some(stats_of(&other, self.server.db.clone()).await)
+                }
+            }
+            Request::Pong(id) => {
+                self.pong(id);
+                None
+            }
+            Request::Ping(id) => Some(Response::Pong(id)),
+            Request::WhoIs(other) => {
+                let mut response = whois(&other, self.server.db.clone()).await;
+                // Comme leurs listes de membres, les canaux ne sont montrés qu'à leurs membres
+                if let Response::WhoIs { channels, .. } = &mut response {
+                    channels.retain(|chan| self.joined.contains_key(chan));
+                }
+                Some(response)
+            }
+            Request::Capabilities => Some(Response::Capabilities(Capabilities {
+                max_message_len: self.server.max_message_len,
+            })),
+            Request::ListChans => {
+                let channels = self
+                    .server
+                    .db_chan
+                    .list()
+                    .into_iter()
+                    .map(|(name, users)| ChanInfo {
+                        name,
+                        users,
+                        topic: None,
+                    })
+                    .collect();
+                Some(Response::ChanList(channels))
+            }
+            Request::WatchKeyword { chan, keyword } => {
+                Some(self.watch_keyword(chan, keyword).await)
+            }
+            Request::UnwatchKeyword { chan, keyword } => {
+                let watched = self.server.keywords.unwatch(&chan, &self.user, &keyword);
+                Some(Response::Keywords {
+                    chan,
+                    keywords: watched,
+                })
+            }
+            Request::Block(target) => {
+                if target == self.user {
+                    Some(error(ErrorCode::CannotBlockSelf))
+                } else {
+                    match self.server.blocks.block(&self.user, &target) {
+                        Ok(blocked) => Some(Response::BlockList(blocked)),
+                        Err(e) => Some(error(e)),
+                    }
+                }
+            }
+            Request::Unblock(target) => Some(Response::BlockList(
+                self.server.blocks.unblock(&self.user, &target),
+            )),
+            Request::Invite { chan, user } => Some(self.invite(chan, user)),
+            Request::Names(chan) => {
+                match self
+                    .server
+                    .db_chan
+                    .get(&chan)
+                    .map(|handle| handle.subscribers())
+                {
+                    // Comme l'historique, la liste des membres est réservée aux membres
+                    Some(users) if users.contains(&self.user) => {
+                        Some(Response::Names { chan, users })
+                    }
+                    Some(users) if !users.is_empty() => Some(error(ErrorCode::NotInChannel)),
+                    _ => Some(error(ErrorCode::UnknownChannel(chan))),
+                }
+            }
+            Request::TransferOp { chan, to } => {
+                let admin = self.is_admin();
+                let transferred = match self.server.db_chan.get(&chan) {
+                    Some(handle) => handle.transfer_op(&self.user, &to, admin).await,
+                    None => Err(ErrorCode::NotInChannel),
+                };
+                transferred.err().map(error)
+            }
+            Request::ClaimOp(chan) => {
+                let claimed = match self.server.db_chan.get(&chan) {
+                    Some(handle) => handle.claim_op(&self.user, self.server.owner_expiry).await,
+                    None => Err(ErrorCode::NotInChannel),
+                };
+                claimed.err().map(error)
+            }
+            Request::SetWelcome { chan, text } => Some(self.set_welcome(chan, text).await),
+            Request::FetchHistory {
+                chan,
+                before_id,
+                limit,
+            } => {
+                let limit = limit.min(MAX_HISTORY_FETCH) as usize;
+                let history = match self.server.db_chan.get(&chan) {
+                    Some(handle) => handle.history(&self.user, before_id, limit).await,
+                    None => None,
+                };
+                match history {
+                    Some((messages, more)) => Some(Response::History {
+                        chan,
+                        messages,
+                        more,
+                    }),
+                    None => Some(error(ErrorCode::NotInChannel)),
+                }
+            }
+            Request::GhostKey(key) => {
+                *self.takeover.key.lock().unwrap() = Some(key);
+                None
+            }
+            Request::Ghost { nickname, key } => {
+                Some(ghost(&self.user, &nickname, &key, self.server.db.clone()).await)
+            }
+        }
+    }
+
+    /// Quitte les canaux de l'utilisateur en annonçant son départ, puis libère son nom, sauf
+    /// s'il a été repris. Une connexion détruite sans `cleanup`, par exemple par une panique,
+    /// est libérée de même, sans attendre les annonces de départ.
+    pub async fn cleanup(mut self) {
+        info!(user = %self.user, peer = %self.peer, "user disconnected");
+        self.stats.channels.lock().unwrap().clear();
+        for (channel, joined) in std::mem::take(&mut self.joined) {
+            if !joined.handle.leave(&self.user, joined.id).await {
+                joined.forwarder.abort();
+            }
+            self.server.db_chan.release(&channel);
+        }
+    }
+
+    // Libère la connexion sans attendre : utilisable depuis `Drop`, y compris pendant une
+    // panique, où rien ne doit paniquer de nouveau
+    fn release(&mut self) {
+        // Canaux non quittés : l'utilisateur est désabonné tout de suite, pour que les canaux
+        // vidés quittent le registre comme dans `cleanup`. La tâche de transmission arrêtée
+        // n'annonce alors pas une seconde fois son départ (voir `Membership`).
+        for (channel, joined) in self.joined.drain() {
+            joined.forwarder.abort();
+            joined.handle.depart_now(&self.user, joined.id);
+            self.server.db_chan.release(&channel);
+        }
+        if let Ok(mut channels) = self.stats.channels.lock() {
+            channels.clear();
+        }
+        disconnect_user(&self.user, &self.server.db, &self.takeover);
+        self.server.keywords.forget(&self.user);
+        self.server.blocks.forget(&self.user);
+        self.takeover.closed.notify_one();
+    }
+
+    fn is_admin(&self) -> bool {
+        self.server.admins.contains(&self.user)
+    }
+
+    fn server_stats(&self) -> Response {
+        server_stats(&self.server.db, &self.server.db_chan, &self.server.metrics)
+    }
+
+    // Annonce un changement de présence aux canaux rejoints
+    async fn announce(&self, op: ChanOp) {
+        announce_to_chans(self.joined.values().map(|joined| &joined.handle), op).await;
+    }
+
+    // Les canaux système sont rejoints dès la connexion, après son accusé
+    async fn connect(&mut self, username: String) -> Option<Response> {
+        let prefixes = &self.server.system_prefixes;
+        if let Err(e) = check_nickname(&username, &self.server.reserved, prefixes) {
+            return Some(error(e));
+        }
+        if self.server.admin_only && !self.server.admins.contains(&username) {
+            return Some(error(ErrorCode::PermissionDenied));
+        }
+        let session = Session {
+            tx: self.outbound.tx.clone(),
+            stats: self.stats.clone(),
+            takeover: self.takeover.clone(),
+        };
+        match connect_user(username.clone(), self.server.db.clone(), session).await {
+            Some(res) => {
+                self.user = username;
+                self.state.activate();
+                if self.server.system_channels.is_empty() {
+                    return Some(res);
+                }
+                self.deliver(res).await;
+                for channel in self.server.system_channels.clone().iter() {
+                    if let Some(refused) = self.join(channel.clone()).await {
+                        self.deliver(refused).await;
+                    }
+                }
+                None
+            }
+            None => Some(error(ErrorCode::NickInUse)),
+        }
+    }
+
+    async fn join(&mut self, channel: String) -> Option<Response> {
+        // Un message à plusieurs destinataires ne pourrait pas atteindre le canal
+        if channel.contains(TARGET_SEPARATOR) {
+            return Some(error(ErrorCode::SeparatorInChannelName(channel)));
+        }
+        // Les noms réservés n'appartiennent qu'aux canaux système configurés
+        let system = self.server.is_system_channel(&channel);
+        if !system && self.server.is_reserved_channel(&channel) {
+            return Some(error(ErrorCode::UnknownChannel(channel)));
+        }
+        let Some((
+            handle,
+            Joined {
+                receiver,
+                id,
+                users,
+                owner,
+                welcome,
+            },
+        )) = add_user_to_chan(
+            &self.user,
+            channel.clone(),
+            self.server.db_chan.clone(),
+            &self.server.capacity,
+            self.server.owner_expiry,
+            system,
+        )
+        .await
+        else {
+            return Some(error(ErrorCode::AlreadyInChannel(channel)));
+        };
+        // L'accusé précède dans la file d'envoi tout ce que le canal diffuse, à commencer par
+        // l'annonce de l'arrivée
+        self.deliver(Response::AckJoin {
+            chan: channel.clone(),
+            users,
+            owner,
+        })
+        .await;
+        if let Some(text) = welcome {
+            self.deliver(Response::Notice {
+                scope: NoticeScope::Channel(channel.clone()),
+                notice: Notice::Welcome(text),
+            })
+            .await;
+        }
+        let outbound = self.outbound.clone();
+        let chan = channel.clone();
+        let mut membership = Membership {
+            receiver,
+            chan: channel.clone(),
+            user: self.user.clone(),
+            handle: handle.clone(),
+            id,
+            stats: self.stats.clone(),
+        };
+
+        // Spawn un thread pour transferer messages de Broadcast
+        let forwarder = tokio::spawn(async move {
+            loop {
+                let mess = membership.receiver.recv().await;
+                match mess {
+                    Ok(BroadcastEvent::Message(m)) => {
+                        outbound.send(m).await;
+                    }
+                    // Le client est trop lent: on le prévient des messages perdus
+                    Ok(BroadcastEvent::Lagged(missed)) => {
+                        // Hors séquence : l'écart des numéros suivants le signale aussi
+                        outbound
+                            .send(Response::Channel {
+                                op: ChanOp::Missed(missed),
+                                chan: chan.clone(),
+                                seq: 0,
+                            })
+                            .await;
+                    }
+                    // Canal quitté, ou fermé
+                    Err(_) => break,
+                }
+            }
+        });
+        let joined = JoinedChannel {
+            handle,
+            id,
+            forwarder,
+        };
+        self.joined.insert(channel.clone(), joined);
+        self.stats.channels.lock().unwrap().push(channel);
+        None
+    }
+
+    async fn leave(&mut self, channel: String) -> Response {
+        // Seul un canal rejoint par cette connexion peut être quitté : l'adhésion d'une autre
+        // connexion du même nom n'est pas touchée
+        let Some(joined) = self.joined.remove(&channel) else {
+            return error(ErrorCode::NotInChannel);
+        };
+        let left = joined.handle.leave(&self.user, joined.id).await;
+        self.server.db_chan.release(&channel);
+        if left {
+            // Les messages du canal déjà transmis à la file d'envoi précèdent l'accusé, et
+            // plus aucun ne le suit
+            let _ = joined.forwarder.await;
+        } else {
+            // Adhésion déjà retirée par le canal : la tâche de transmission ne doit pas lui
+            // survivre
+            joined.forwarder.abort();
+        }
+        self.stats
+            .channels
+            .lock()
+            .unwrap()
+            .retain(|chan| chan != &channel);
+        if left {
+            Response::AckLeave(channel)
+        } else {
+            error(ErrorCode::NotInChannel)
+        }
+    }
+
+    async fn message(&mut self, to: MessageReceiver, content: String) -> Result<(), ErrorCode> {
+        match to {
+            MessageReceiver::Channel(channel) => self.message_channel(channel, content).await,
+            MessageReceiver::User(user) => self.message_user(user, content).await,
+            MessageReceiver::All => self.announce_to_all(content),
+            MessageReceiver::Channels(pattern) => self.announce_to_channels(pattern, content).await,
+        }
+    }
+
+    // Annonce d'un administrateur à chaque utilisateur connecté, lui compris, sans attendre les
+    // plus lents
+    fn announce_to_all(&mut self, content: String) -> Result<(), ErrorCode> {
+        if !self.is_admin() {
+            return Err(ErrorCode::PermissionDenied);
+        }
+        if content.len() > self.server.max_message_len {
+            return Err(ErrorCode::MessageTooLong);
+        }
+        let notice = Response::Notice {
+            scope: NoticeScope::Server,
+            notice: Notice::Announcement {
+                from: self.user.clone(),
+                content,
+            },
+        };
+        let users: Vec<String> = sessions(&self.server.db).keys().cloned().collect();
+        for user in users {
+            if send_to_session(&user, notice.clone(), self.server.db.clone()).is_err() {
+                debug!(%user, "announcement not delivered");
+            }
+        }
+        self.stats.messages_sent.fetch_add(1, Ordering::Relaxed);
+        self.server.metrics.record_message(Instant::now());
+        Ok(())
+    }
+
+    // Message d'un administrateur diffusé dans chaque canal correspondant au motif, qu'il en
+    // soit membre ou non
+    async fn announce_to_channels(
+        &mut self,
+        pattern: String,
+        content: String,
+    ) -> Result<(), ErrorCode> {
+        if !self.is_admin() {
+            return Err(ErrorCode::PermissionDenied);
+        }
+        if content.len() > self.server.max_message_len {
+            return Err(ErrorCode::MessageTooLong);
+        }
+        let channels = self.server.db_chan.matching(&pattern);
+        if channels.is_empty() {
+            return Err(ErrorCode::UnknownChannel(pattern));
+        }
+        for (_, handle) in channels {
+            handle
+                .announce(message_op(&self.user, content.clone()))
+                .await;
+        }
+        self.stats.messages_sent.fetch_add(1, Ordering::Relaxed);
+        self.server.metrics.record_message(Instant::now());
+        Ok(())
+    }
+
+    // Envoie le message à chaque destinataire, un doublon n'étant servi qu'une fois, et
+    // rapporte le résultat de chaque envoi
+    async fn message_many(&mut self, to: Vec<MessageReceiver>, content: String) -> Response {
+        if to.len() > MAX_MESSAGE_TARGETS {
+            return error(ErrorCode::TooManyTargets(MAX_MESSAGE_TARGETS));
+        }
+        let mut results: Vec<(MessageReceiver, Result<(), ErrorCode>)> = Vec::new();
+        for target in to {
+            if results.iter().any(|(sent, _)| *sent == target) {
+                continue;
+            }
+            let result = self.message(target.clone(), content.clone()).await;
+            results.push((target, result));
+        }
+        Response::Delivery(results)
+    }
+
+    async fn message_channel(&mut self, channel: String, content: String) -> Result<(), ErrorCode> {
+        if content.len() > self.server.max_message_len {
+            return Err(ErrorCode::MessageTooLong);
+        }
+        if self.server.is_system_channel(&channel) && !self.is_admin() {
+            return Err(ErrorCode::PermissionDenied);
+        }
+        let alerts = self.server.keywords.matches(&channel, &content);
+        let op = message_op(&self.user, content.clone());
+        let Some(handle) = self
+            .joined
+            .get(&channel)
+            .map(|joined| joined.handle.clone())
+        else {
+            return Err(ErrorCode::NotInChannel);
+        };
+        if !handle.send(&self.user, op).await {
+            return Err(ErrorCode::NotInChannel);
+        }
+        self.stats.messages_sent.fetch_add(1, Ordering::Relaxed);
+        self.server.metrics.record_message(Instant::now());
+        // Un opérateur qui a depuis quitté le canal ou cédé sa place n'en reçoit plus rien
+        let mut current = Vec::new();
+        for (operator, keyword) in alerts {
+            if self.server.admins.contains(&operator) || handle.operates(&operator).await {
+                current.push((operator, keyword));
+            }
+        }
+        alert_operators(
+            current,
+            &self.user,
+            &channel,
+            &content,
+            self.server.db.clone(),
+        );
+        Ok(())
+    }
+
+    async fn message_user(&mut self, to: String, content: String) -> Result<(), ErrorCode> {
+        let now = Instant::now();
+        if content.len() > self.server.max_message_len {
+            Err(ErrorCode::MessageTooLong)
+        } else if self.server.blocks.blocks(&to, &self.user) {
+            Err(ErrorCode::Blocked)
+        } else if let Some(Err(retry_after)) =
+            self.dm_window.as_mut().map(|window| window.try_send(now))
+        {
+            Err(ErrorCode::RateLimited { retry_after })
+        } else {
+            // L'auteur affiche lui-même son message : rien à lui renvoyer
+            let sent = send_to_user(&self.user, &to, content, self.server.db.clone()).await;
+            if sent.is_ok() {
+                self.stats.messages_sent.fetch_add(1, Ordering::Relaxed);
+                self.server.metrics.record_message(now);
+            }
+            sent
+        }
+    }
+
+    // Une réponse à un ping plus ancien n'est pas mesurée
+    fn pong(&mut self, id: u64) {
+        if let Some((sent_id, sent_at)) = self.ping_sent {
+            if sent_id == id {
+                *self.stats.ping_rtt.lock().unwrap() = Some(sent_at.elapsed());
+                self.ping_sent = None;
+            }
+        }
+    }
+
+    // Réservé à l'opérateur du canal, ou aux administrateurs
+    async fn watch_keyword(&self, chan: String, keyword: String) -> Response {
+        let operator = match self.server.db_chan.get(&chan) {
+            Some(handle) => handle.operates(&self.user).await,
+            None => false,
+        };
+        if !operator && !self.is_admin() {
+            error(ErrorCode::PermissionDenied)
+        } else if keyword.is_empty() || keyword.contains(|c: char| !c.is_alphanumeric()) {
+            error(ErrorCode::InvalidKeyword(keyword))
+        } else {
+            let watched = self.server.keywords.watch(&chan, &self.user, &keyword);
+            Response::Keywords {
+                chan,
+                keywords: watched,
+            }
+        }
+    }
+
+    fn invite(&self, chan: String, to: String) -> Response {
+        let member = self
+            .server
+            .db_chan
+            .get(&chan)
+            .map(|handle| (handle.contains(&self.user), handle.contains(&to)));
+        match member {
+            Some((false, _)) | None => error(ErrorCode::NotInChannel),
+            Some((true, true)) => error(ErrorCode::UserInChannel(to)),
+            _ if self.server.blocks.blocks(&to, &self.user) => error(ErrorCode::Blocked),
+            _ => {
+                let invited = Response::Invited {
+                    chan: chan.clone(),
+                    by: self.user.clone(),
+                };
+                match send_to_session(&to, invited, self.server.db.clone()) {
+                    Ok(()) => Response::Notice {
+                        scope: NoticeScope::Channel(chan),
+                        notice: Notice::InviteSent(to),
+                    },
+                    Err(e) => error(e),
+                }
+            }
+        }
+    }
+
+    async fn set_welcome(&self, chan: String, text: String) -> Response {
+        let set = match self.server.db_chan.get(&chan) {
+            Some(handle) => {
+                handle
+                    .set_welcome(
+                        &self.user,
+                        text,
+                        self.is_admin(),
+                        self.server.max_message_len,
+                    )
+                    .await
+            }
+            None => Err(ErrorCode::NotInChannel),
+        };
+        match set {
+            Ok(notice) => Response::Notice {
+                scope: NoticeScope::Channel(chan),
+                notice,
+            },
+            Err(e) => error(e),
+        }
+    }
+
+    // Échéance d'inactivité, une fois connecté et tant que l'utilisateur n'est pas absent
+    fn away_at(&self) -> Option<Instant> {
+        self.server
+            .away_after
+            .filter(|_| !self.user.is_empty() && !self.stats.away.load(Ordering::Relaxed))
+            .map(|idle| *self.stats.last_activity.lock().unwrap() + idle)
+    }
+
+    async fn mark_away(&mut self) {
+        self.stats.away.store(true, Ordering::Relaxed);
+        self.announce(ChanOp::UserAway(self.user.clone())).await;
+    }
+
+    fn ping(&mut self) -> Response {
+        self.ping_id += 1;
+        self.ping_sent = Some((self.ping_id, Instant::now()));
+        Response::Ping(self.ping_id)
+    }
+
+    // Prochain envoi périodique des statistiques du serveur, jamais sans abonnement
+    async fn stats_due(&mut self) {
+        match self.stats_tick.as_mut() {
+            Some(tick) if self.stats_subscribed => {
+                tick.tick().await;
+            }
+            _ => std::future::pending().await,
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            tracing::error!(user = %self.user, peer = %self.peer, "connection task panicked, releasing session");
+        }
+        self.release();
+    }
+}
+
+// Canal rejoint par une connexion
+#[derive(Debug)]
+struct JoinedChannel {
+    handle: ChannelHandle,
+    // Numéro d'adhésion, voir `Joined::id`
+    id: u64,
+    // Tâche transmettant les diffusions du canal à la file d'envoi
+    forwarder: JoinHandle<()>,
+}
+
+// Abonnement d'une connexion à un canal, tenu par la tâche qui transmet ses diffusions. Si la
+// tâche se termine sans que l'utilisateur ait quitté le canal (panique), il est désabonné et
+// son départ annoncé : il peut ainsi rejoindre le canal de nouveau.
+struct Membership {
+    receiver: BroadcastReceiverWithList<Response, String>,
+    chan: String,
+    user: String,
+    handle: ChannelHandle,
+    id: u64,
+    stats: Arc<SessionStats>,
+}
+
+impl Drop for Membership {
+    fn drop(&mut self) {
+        if !self.receiver.unsubscribe() {
+            return;
+        }
+        warn!(user = %self.user, chan = %self.chan, "channel forwarder stopped unexpectedly");
+        // Verrou empoisonné si la panique l'a interrompu : la liste est laissée telle quelle
+        if let Ok(mut channels) = self.stats.channels.lock() {
+            channels.retain(|chan| chan != &self.chan);
+        }
+        self.handle.depart(&self.user, self.id);
+    }
+}
+
+// Etablit une communication chiffrée avec le client, avant tout autre échange. Une fois
+// chiffré, le canal n'accepte plus de `HandshakeRequest` : les clés ne peuvent plus changer.
+// Échange de clés, conclu par `ack` : `Response::Ack`, ou le refus de la connexion
+async fn handshake<S>(
+    socket: S,
+    ack: Response,
+) -> Result<(
+    TypedChannel<S, Response, Request, Encrypted>,
+    ConnectionState,
+)>
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    let key_pair = ReceiverKeyPair::generate();
+    let mut channel = TypedChannel::<_, HandshakeResponse, HandshakeRequest>::new(socket);
+    let mut state = ConnectionState::default();
+
+    let Some(request) = channel.recv().await? else {
+        bail!("connection closed");
+    };
+    let key = state.on_handshake(request)?;
+    let key_bytes: [u8; 32] = key.as_slice().try_into()?;
+    let public_key_other = SenderPublicKey::from(PublicKey::from(key_bytes));
+    let combined = ReceiverCombinedKey::new(&public_key_other, key_pair.private_key());
+    channel
+        .send(&HandshakeResponse::Secure(
+            key_pair.public_key().as_ref().as_bytes().to_vec(),
+        ))
+        .await?;
+
+    let Some(request) = channel.recv().await? else {
+        bail!("connection closed");
+    };
+    let key = state.on_handshake(request)?;
+    let encrypted_message = EncryptedMessage::deserialize(key)?;
+    let shared = SharedKey::decrypt_owned(&encrypted_message, &combined)?;
+    let mut channel = channel.upgrade(shared);
+    channel.send(&ack).await?;
+    Ok((channel, state))
+}
+
+// Refuse une connexion : l'échange de clés a lieu, pour que le client puisse lire la raison du
+// refus (serveur complet, adresse listée...), puis la connexion est fermée.
+pub(crate) async fn refuse<S>(socket: S, refusal: ErrorCode, peer: String)
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug,
+{
+    match tokio::time::timeout(REFUSAL_TIMEOUT, handshake(socket, error(refusal))).await {
+        Ok(Ok((mut channel, _))) => {
+            let _ = channel.close().await;
+        }
+        Ok(Err(e)) => debug!(%peer, "handshake failed: {}", e),
+        Err(_) => debug!(%peer, "handshake timed out"),
+    }
+}
+
+pub(crate) async fn process<S>(socket: S, server: Server, peer: String)
+where
+    S: Transport,
+    S::ReadHalf: Debug,
+    S::WriteHalf: Debug + Send + 'static,
+{
+    let socket = ByteCounter::new(socket);
+    let counts = socket.counts();
+    let (channel, state) = match handshake(socket, Response::Ack).await {
+        Ok(handshake) => handshake,
+        Err(e) => {
+            // Y compris les simples tests de connexion (`--healthcheck`)
+            debug!(%peer, "handshake failed: {}", e);
+            return;
+        }
+    };
+    let stats = Arc::new(SessionStats::new(counts, channel.encryption_status()));
+    let (mut typed_reader, typed_writer) = channel.into_split();
+    let (outbound, writer) = Outbound::spawn(typed_writer);
+    let stalled = outbound.stalled.clone();
+    let mut connection = Connection::new(server, peer, state, stats, outbound);
+    let takeover = connection.takeover.clone();
+    let mut ping = tokio::time::interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+
+    // Fin de la connexion : `Ok` si elle est voulue, par le client ou par le serveur
+    let end: Result<()> = loop {
+        let away_at = connection.away_at();
+        let res: Option<Response> = tokio::select! {
+            val = typed_reader.recv() => match val {
+                Ok(Some(rq)) => connection.handle_request(rq).await,
+                Ok(None) => break Err(anyhow!("invalid request")),
+                Err(ProtocolError::Closed) => break Ok(()),
+                Err(e) => break Err(e.into()),
+            },
+            _ = tokio::time::sleep_until(away_at.unwrap_or_else(Instant::now)), if away_at.is_some() => {
+                connection.mark_away().await;
+                None
+            },
+            _ = ping.tick() => Some(connection.ping()),
+            _ = connection.stats_due() => Some(connection.server_stats()),
+            _ = takeover.kick.notified() => {
+                info!(user = %connection.user(), "session taken over");
+                connection.deliver(error(ErrorCode::SessionTakenOver)).await;
+                break Ok(());
+            },
+            _ = stalled.notified() => {
+                warn!(user = %connection.user(), "outbound queue full, disconnecting");
+                writer.abort();
+                break Ok(());
+            }
+        };
+        if let Some(r) = res {
+            connection.deliver(r).await;
+        }
+    };
+    let (user, peer) = (connection.user(), &connection.peer);
+    match end {
+        Ok(()) => debug!(%user, %peer, "connection closed"),
+        Err(e) => info!(%user, %peer, "connection lost: {}", e),
+    }
+    connection.cleanup().await;
+}
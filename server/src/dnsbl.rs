@@ -0,0 +1,236 @@
+//! Listes de blocage DNS (DNSBL) : l'adresse d'un client est cherchée dans des zones DNS
+//! publiques, `4.3.2.1.zone` pour `1.2.3.4`. Une réponse dans `127.0.0.0/8` signifie que
+//! l'adresse est listée ; `127.255.255.0/24` est réservé aux erreurs de la zone (requêtes
+//! refusées...) et ignoré.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+/// Durée de conservation d'un résultat, par défaut
+pub const DEFAULT_DNSBL_CACHE_TTL: Duration = Duration::from_secs(600);
+/// Délai accordé à chaque zone pour répondre, par défaut
+pub const DEFAULT_DNSBL_TIMEOUT: Duration = Duration::from_secs(2);
+/// Nombre d'adresses en cache au-delà duquel les résultats expirés sont oubliés
+const CACHE_CAPACITY: usize = 4096;
+
+/// Résolution d'un nom DNS en adresses. Celle du système par défaut ; les tests peuvent la
+/// remplacer ([`Dnsbl::with_resolver`]).
+pub type Resolver = Arc<
+    dyn Fn(String) -> Pin<Box<dyn Future<Output = io::Result<Vec<IpAddr>>> + Send>> + Send + Sync,
+>;
+
+/// Traitement d'un client dont l'adresse est listée.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsblAction {
+    /// La connexion est refusée à la fin de l'échange de clés, par un
+    /// [`ErrorCode::Blocklisted`](mini_irc_protocol::ErrorCode::Blocklisted) qui nomme la zone.
+    Reject,
+    /// La connexion reste ouverte sans réponse pendant cette durée, puis est fermée : un robot
+    /// qui se reconnecte en boucle est ainsi ralenti.
+    Tarpit(Duration),
+}
+
+/// Compteurs des vérifications depuis le démarrage, voir [`Dnsbl::metrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DnsblMetrics {
+    /// Adresses vérifiées
+    pub checks: u64,
+    /// Vérifications dont le résultat était en cache
+    pub cache_hits: u64,
+    /// Vérifications d'une adresse listée
+    pub listed: u64,
+    /// Requêtes restées sans réponse dans le délai
+    pub timeouts: u64,
+}
+
+/// Zones DNSBL consultées à chaque connexion TCP, voir
+/// [`Server::with_dnsbl`](crate::Server::with_dnsbl). Les zones sont interrogées en parallèle ;
+/// la première qui liste l'adresse l'emporte. Les résultats sont conservés
+/// [`DEFAULT_DNSBL_CACHE_TTL`], sauf ceux d'une recherche dont une zone n'a pas répondu.
+pub struct Dnsbl {
+    zones: Vec<String>,
+    action: DnsblAction,
+    cache_ttl: Duration,
+    timeout: Duration,
+    resolver: Resolver,
+    // Zone qui liste l'adresse, ou aucune, jusqu'à la date d'expiration
+    cache: Mutex<HashMap<IpAddr, (Instant, Option<String>)>>,
+    checks: AtomicU64,
+    cache_hits: AtomicU64,
+    listed: AtomicU64,
+    timeouts: AtomicU64,
+}
+
+impl fmt::Debug for Dnsbl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dnsbl")
+            .field("zones", &self.zones)
+            .field("action", &self.action)
+            .field("cache_ttl", &self.cache_ttl)
+            .field("timeout", &self.timeout)
+            .field("metrics", &self.metrics())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Dnsbl {
+    /// Consulte les zones `zones`, par exemple `zen.spamhaus.org`, en refusant les adresses
+    /// listées.
+    pub fn new(zones: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            zones: zones.into_iter().collect(),
+            action: DnsblAction::Reject,
+            cache_ttl: DEFAULT_DNSBL_CACHE_TTL,
+            timeout: DEFAULT_DNSBL_TIMEOUT,
+            resolver: Arc::new(|name| Box::pin(lookup(name))),
+            cache: Mutex::new(HashMap::new()),
+            checks: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            listed: AtomicU64::new(0),
+            timeouts: AtomicU64::new(0),
+        }
+    }
+
+    /// Ralentit les clients listés au lieu de les refuser, voir [`DnsblAction::Tarpit`].
+    pub fn with_tarpit(mut self, delay: Duration) -> Self {
+        self.action = DnsblAction::Tarpit(delay);
+        self
+    }
+
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Délai accordé à chaque zone : une zone muette ne retarde pas la connexion davantage, et
+    /// l'adresse est alors considérée non listée par elle.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    pub fn action(&self) -> DnsblAction {
+        self.action
+    }
+
+    pub fn metrics(&self) -> DnsblMetrics {
+        DnsblMetrics {
+            checks: self.checks.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            listed: self.listed.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Zone qui liste `ip`, ou `None` si aucune ne la liste.
+    pub async fn check(&self, ip: IpAddr) -> Option<String> {
+        // Adresse IPv4 d'un client connecté à une socket IPv6
+        let ip = ip.to_canonical();
+        self.checks.fetch_add(1, Ordering::Relaxed);
+        let cached = self.cache.lock().unwrap().get(&ip).cloned();
+        let zone = match cached {
+            Some((expires, zone)) if Instant::now() < expires => {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                zone
+            }
+            _ => {
+                let (zone, complete) = self.lookup(ip).await;
+                if complete || zone.is_some() {
+                    self.remember(ip, zone.clone());
+                }
+                zone
+            }
+        };
+        if zone.is_some() {
+            self.listed.fetch_add(1, Ordering::Relaxed);
+        }
+        zone
+    }
+
+    // Interroge toutes les zones ; faux si l'une d'elles n'a pas répondu
+    async fn lookup(&self, ip: IpAddr) -> (Option<String>, bool) {
+        let reversed = reversed(ip);
+        let mut lookups = JoinSet::new();
+        for zone in &self.zones {
+            let query = (self.resolver)(format!("{reversed}.{zone}"));
+            let timeout = self.timeout;
+            let zone = zone.clone();
+            lookups.spawn(async move { (zone, tokio::time::timeout(timeout, query).await) });
+        }
+        let mut complete = true;
+        while let Some(lookup) = lookups.join_next().await {
+            match lookup {
+                // Les recherches restantes sont abandonnées avec `lookups`
+                Ok((zone, Ok(Ok(addrs)))) if addrs.iter().any(is_listing) => {
+                    return (Some(zone), complete)
+                }
+                Ok((_, Err(_))) => {
+                    self.timeouts.fetch_add(1, Ordering::Relaxed);
+                    complete = false;
+                }
+                // Nom inexistant : l'adresse n'est pas listée
+                _ => {}
+            }
+        }
+        (None, complete)
+    }
+
+    fn remember(&self, ip: IpAddr, zone: Option<String>) {
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= CACHE_CAPACITY {
+            cache.retain(|_, (expires, _)| now < *expires);
+        }
+        if cache.len() < CACHE_CAPACITY {
+            cache.insert(ip, (now + self.cache_ttl, zone));
+        }
+    }
+}
+
+async fn lookup(name: String) -> io::Result<Vec<IpAddr>> {
+    let addrs = tokio::net::lookup_host((name.as_str(), 0)).await?;
+    Ok(addrs.map(|addr| addr.ip()).collect())
+}
+
+// Nom de l'adresse dans une zone, sans la zone : octets en ordre inverse pour IPv4, chiffres
+// hexadécimaux en ordre inverse pour IPv6
+fn reversed(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}")
+        }
+        IpAddr::V6(ip) => ip
+            .octets()
+            .iter()
+            .rev()
+            .flat_map(|byte| [byte & 0xf, byte >> 4])
+            .map(|nibble| format!("{nibble:x}"))
+            .collect::<Vec<_>>()
+            .join("."),
+    }
+}
+
+fn is_listing(addr: &IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => {
+            let [a, b, c, _] = addr.octets();
+            a == 127 && (b, c) != (255, 255)
+        }
+        IpAddr::V6(_) => false,
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{RwLock, RwLockWriteGuard};
+
+// Mots-clés par opérateur
+type Watchers = HashMap<String, Vec<String>>;
+
+/// Mots-clés surveillés par les opérateurs, par canal. Un message qui en contient un est
+/// signalé aux opérateurs concernés par un [`mini_irc_protocol::Response::KeywordAlert`].
+///
+/// Les mots-clés sont comparés aux mots des messages, sans tenir compte de la casse. Chaque
+/// message de canal y est cherché : tant qu'aucun canal n'est surveillé, la recherche ne prend
+/// aucun verrou, et ensuite seulement un verrou en lecture.
+#[derive(Debug, Default)]
+pub(crate) struct KeywordWatches {
+    channels: RwLock<HashMap<String, Watchers>>,
+    // Nombre de canaux surveillés, mis à jour sous le verrou en écriture
+    watched: AtomicUsize,
+}
+
+// Verrou en écriture, qui met à jour le nombre de canaux surveillés en étant relâché
+struct WriteGuard<'a> {
+    channels: RwLockWriteGuard<'a, HashMap<String, Watchers>>,
+    watched: &'a AtomicUsize,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        self.watched.store(self.channels.len(), Ordering::Release);
+    }
+}
+
+// Mots d'un message, en minuscules
+fn words(content: &str) -> impl Iterator<Item = String> + '_ {
+    content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+impl KeywordWatches {
+    fn write(&self) -> WriteGuard<'_> {
+        WriteGuard {
+            channels: self.channels.write().unwrap(),
+            watched: &self.watched,
+        }
+    }
+
+    /// Ajoute `keyword` aux mots-clés de `operator` sur le canal, et renvoie ses mots-clés.
+    pub(crate) fn watch(&self, channel: &str, operator: &str, keyword: &str) -> Vec<String> {
+        let mut guard = self.write();
+        let keywords = guard
+            .channels
+            .entry(channel.to_string())
+            .or_default()
+            .entry(operator.to_string())
+            .or_default();
+        let keyword = keyword.to_lowercase();
+        if !keywords.contains(&keyword) {
+            keywords.push(keyword);
+        }
+        keywords.clone()
+    }
+
+    /// Retire `keyword` des mots-clés de `operator` sur le canal, et renvoie ceux qui restent.
+    pub(crate) fn unwatch(&self, channel: &str, operator: &str, keyword: &str) -> Vec<String> {
+        let mut guard = self.write();
+        let Some(watchers) = guard.channels.get_mut(channel) else {
+            return Vec::new();
+        };
+        let keyword = keyword.to_lowercase();
+        let remaining = match watchers.get_mut(operator) {
+            Some(keywords) => {
+                keywords.retain(|watched| watched != &keyword);
+                keywords.clone()
+            }
+            None => Vec::new(),
+        };
+        if remaining.is_empty() {
+            watchers.remove(operator);
+        }
+        if watchers.is_empty() {
+            guard.channels.remove(channel);
+        }
+        remaining
+    }
+
+    /// Oublie tous les mots-clés de `operator`, par exemple à sa déconnexion.
+    pub(crate) fn forget(&self, operator: &str) {
+        self.write().channels.retain(|_, watchers| {
+            watchers.remove(operator);
+            !watchers.is_empty()
+        });
+    }
+
+    /// Opérateurs à prévenir d'un message sur le canal, avec le premier mot-clé trouvé.
+    pub(crate) fn matches(&self, channel: &str, content: &str) -> Vec<(String, String)> {
+        if self.watched.load(Ordering::Acquire) == 0 {
+            return Vec::new();
+        }
+        let channels = self.channels.read().unwrap();
+        let Some(watchers) = channels.get(channel) else {
+            return Vec::new();
+        };
+        let words: Vec<String> = words(content).collect();
+        watchers
+            .iter()
+            .filter_map(|(operator, keywords)| {
+                keywords
+                    .iter()
+                    .find(|keyword| words.contains(keyword))
+                    .map(|keyword| (operator.clone(), keyword.clone()))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,796 @@
+//! Serveur mini-irc. Le binaire `server` écoute sur TCP ou sur une socket Unix ; un
+//! [`Server`] peut aussi être lancé dans le processus courant, par exemple pour les tests.
+
+mod blocks;
+mod channel;
+mod connection;
+mod dnsbl;
+mod keywords;
+mod listeners;
+mod metrics;
+mod proxy;
+mod rate;
+mod registry;
+mod state;
+
+pub use channel::{ChannelHandle, Joined};
+pub use connection::Connection;
+pub use dnsbl::{
+    Dnsbl, DnsblAction, DnsblMetrics, Resolver, DEFAULT_DNSBL_CACHE_TTL, DEFAULT_DNSBL_TIMEOUT,
+};
+pub use listeners::Listener;
+pub use proxy::{read_proxy_header, PROXY_V2_SIGNATURE};
+pub use registry::ChannelRegistry;
+pub use state::{ConnectionState, HandshakeStep, StateError};
+
+use anyhow::{bail, Result};
+use mini_irc_protocol::{
+    AsyncTypedWriter, ByteCounts, ChanOp, ConnectionStats, Encrypted, EncryptionStatus, ErrorCode,
+    Response, Transport, TARGET_SEPARATOR,
+};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::error::SendTimeoutError;
+use tokio::sync::{mpsc, Notify, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+use blocks::BlockLists;
+use keywords::KeywordWatches;
+use metrics::ServerMetrics;
+use rate::RateWindow;
+use std::any::Any;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime};
+
+// Utilisateurs connectés, avec de quoi leur transmettre des messages directs
+type DB = Arc<Mutex<HashMap<String, Session>>>;
+
+// Verrouille les utilisateurs connectés. La panique d'une connexion, verrou tenu, n'arrête
+// qu'elle : les autres continuent d'utiliser les sessions, que sa libération rend cohérentes.
+fn sessions(db: &DB) -> MutexGuard<'_, HashMap<String, Session>> {
+    db.lock().unwrap_or_else(PoisonError::into_inner)
+}
+type DBChan = Arc<ChannelRegistry>;
+
+/// Nombre de réponses en attente d'envoi à un client
+const OUTBOUND_QUEUE_SIZE: usize = 128;
+/// Durée pendant laquelle la file d'envoi d'un client peut rester pleine avant qu'il ne soit
+/// déconnecté
+const OUTBOUND_TIMEOUT: Duration = Duration::from_secs(5);
+/// Taille maximale du contenu d'un message, en octets, par défaut
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 512;
+/// Nombre de messages qu'un canal conserve pour ses membres les plus lents, par défaut
+const DEFAULT_CHANNEL_CAPACITY: usize = 32;
+/// Délai accordé à un répartiteur de charge pour envoyer l'en-tête PROXY d'une connexion
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Délai après lequel un client refusé faute de place peut réessayer
+const SERVER_FULL_RETRY_AFTER: Duration = Duration::from_secs(10);
+/// Noms d'utilisateur réservés par défaut, qu'aucun client ne peut prendre
+pub const DEFAULT_RESERVED_NICKNAMES: &[&str] = &["server", "admin", "services"];
+/// Préfixes des noms de canaux réservés aux canaux système, par défaut
+pub const DEFAULT_SYSTEM_PREFIXES: &[&str] = &["&"];
+
+/// Capacité des canaux de diffusion, lue dans la variable d'environnement
+/// `MINI_IRC_CHANNEL_CAPACITY`: par exemple `64,general=256` fixe la capacité par défaut à 64
+/// et celle du canal general à 256.
+#[derive(Debug)]
+pub struct ChannelCapacity {
+    default: usize,
+    per_channel: HashMap<String, usize>,
+}
+
+impl Default for ChannelCapacity {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_CHANNEL_CAPACITY,
+            per_channel: HashMap::new(),
+        }
+    }
+}
+
+impl ChannelCapacity {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("MINI_IRC_CHANNEL_CAPACITY") {
+            Ok(spec) => Self::parse(&spec),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut capacity = Self::default();
+        for item in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+        {
+            let (channel, value) = match item.split_once('=') {
+                Some((channel, value)) => (Some(channel.trim()), value.trim()),
+                None => (None, item),
+            };
+            let value: usize = value.parse()?;
+            if value == 0 {
+                bail!("channel capacity must be positive: {}", item);
+            }
+            match channel {
+                Some(channel) => {
+                    capacity.per_channel.insert(channel.to_string(), value);
+                }
+                None => capacity.default = value,
+            }
+        }
+        Ok(capacity)
+    }
+
+    fn get(&self, channel: &str) -> usize {
+        *self.per_channel.get(channel).unwrap_or(&self.default)
+    }
+}
+
+/// État partagé du serveur : utilisateurs connectés et canaux. Chaque connexion acceptée est
+/// traitée dans sa propre tâche.
+#[derive(Debug, Clone)]
+pub struct Server {
+    db: DB,
+    db_chan: DBChan,
+    capacity: Arc<ChannelCapacity>,
+    admins: Arc<HashSet<String>>,
+    keywords: Arc<KeywordWatches>,
+    blocks: Arc<BlockLists>,
+    // En minuscules : la comparaison ignore la casse
+    reserved: Arc<HashSet<String>>,
+    // Canaux créés par le serveur, et préfixes que les autres canaux ne peuvent pas prendre
+    system_channels: Arc<Vec<String>>,
+    system_prefixes: Arc<Vec<String>>,
+    away_after: Option<Duration>,
+    max_message_len: usize,
+    owner_expiry: Option<Duration>,
+    // Nombre de messages directs permis par durée
+    dm_rate: Option<(usize, Duration)>,
+    stats_interval: Option<Duration>,
+    metrics: Arc<ServerMetrics>,
+    // Places restantes, si le nombre de connexions simultanées est limité
+    slots: Option<Arc<Semaphore>>,
+    dnsbl: Option<Arc<Dnsbl>>,
+    // Propres à l'adresse d'écoute : les copies du serveur partagent tout le reste
+    admin_only: bool,
+    proxy_protocol: bool,
+}
+
+impl Server {
+    pub fn new(capacity: ChannelCapacity) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(HashMap::new())),
+            db_chan: Arc::new(ChannelRegistry::default()),
+            capacity: Arc::new(capacity),
+            admins: Arc::new(HashSet::new()),
+            keywords: Arc::new(KeywordWatches::default()),
+            blocks: Arc::new(BlockLists::default()),
+            reserved: Arc::new(
+                DEFAULT_RESERVED_NICKNAMES
+                    .iter()
+                    .map(|nickname| nickname.to_string())
+                    .collect(),
+            ),
+            system_channels: Arc::new(Vec::new()),
+            system_prefixes: Arc::new(
+                DEFAULT_SYSTEM_PREFIXES
+                    .iter()
+                    .map(|prefix| prefix.to_string())
+                    .collect(),
+            ),
+            away_after: None,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            owner_expiry: None,
+            dm_rate: None,
+            stats_interval: None,
+            metrics: Arc::new(ServerMetrics::new()),
+            slots: None,
+            dnsbl: None,
+            admin_only: false,
+            proxy_protocol: false,
+        }
+    }
+
+    /// Désigne les administrateurs, par nom d'utilisateur : eux seuls peuvent consulter les
+    /// statistiques des autres connexions, et surveiller des mots-clés en opérateurs des
+    /// canaux.
+    pub fn with_admins(mut self, admins: impl IntoIterator<Item = String>) -> Self {
+        self.admins = Arc::new(admins.into_iter().collect());
+        self
+    }
+
+    /// Remplace les noms d'utilisateur réservés ([`DEFAULT_RESERVED_NICKNAMES`] par défaut),
+    /// refusés à la connexion quelle que soit leur casse.
+    pub fn with_reserved_nicknames(mut self, nicknames: impl IntoIterator<Item = String>) -> Self {
+        self.reserved = Arc::new(
+            nicknames
+                .into_iter()
+                .map(|nickname| nickname.to_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Crée les canaux système `channels`, par exemple `&annonces`, que chaque utilisateur
+    /// rejoint à sa connexion. Seuls les administrateurs y écrivent, et aucun utilisateur n'en
+    /// devient propriétaire. Un utilisateur qui en part peut les rejoindre de nouveau.
+    pub fn with_system_channels(mut self, channels: impl IntoIterator<Item = String>) -> Self {
+        self.system_channels = Arc::new(channels.into_iter().collect());
+        self
+    }
+
+    /// Remplace les préfixes des noms de canaux réservés aux canaux système
+    /// ([`DEFAULT_SYSTEM_PREFIXES`] par défaut) : un canal de ce nom ne peut être rejoint que
+    /// s'il fait partie de [`Server::with_system_channels`].
+    pub fn with_system_prefixes(mut self, prefixes: impl IntoIterator<Item = String>) -> Self {
+        self.system_prefixes = Arc::new(prefixes.into_iter().collect());
+        self
+    }
+
+    /// Refuse les messages dont le contenu dépasse `len` octets. La limite est annoncée aux
+    /// clients par [`Request::Capabilities`](mini_irc_protocol::Request::Capabilities).
+    pub fn with_max_message_len(mut self, len: usize) -> Self {
+        self.max_message_len = len;
+        self
+    }
+
+    /// Marque absents les utilisateurs inactifs depuis `idle`, en l'annonçant à leurs canaux
+    /// ([`ChanOp::UserAway`]) ; leur prochaine requête les marque de retour.
+    pub fn with_away_after(mut self, idle: Duration) -> Self {
+        self.away_after = Some(idle);
+        self
+    }
+
+    /// Un propriétaire de canal absent depuis `after` perd ses droits : un membre peut alors
+    /// revendiquer le canal ([`Request::ClaimOp`](mini_irc_protocol::Request::ClaimOp)), et le
+    /// prochain arrivant dans le canal vide en devient propriétaire. Par défaut, un propriétaire
+    /// absent le reste indéfiniment.
+    pub fn with_owner_expiry(mut self, after: Duration) -> Self {
+        self.owner_expiry = Some(after);
+        self
+    }
+
+    /// Limite chaque connexion à `max` messages directs (au moins 1) par période `per` : les
+    /// suivants sont refusés par un [`Response::RateLimited`]. Par défaut, rien ne les limite.
+    pub fn with_dm_rate(mut self, max: usize, per: Duration) -> Self {
+        self.dm_rate = Some((max.max(1), per));
+        self
+    }
+
+    /// Envoie toutes les `interval` les statistiques du serveur ([`Response::ServerStats`])
+    /// aux clients abonnés par
+    /// [`Request::SubscribeStats`](mini_irc_protocol::Request::SubscribeStats). Sans période,
+    /// ils ne les reçoivent qu'à l'abonnement.
+    pub fn with_stats_interval(mut self, interval: Duration) -> Self {
+        self.stats_interval = Some(interval);
+        self
+    }
+
+    /// Limite à `max` le nombre de connexions simultanées, échange de clés compris. Les
+    /// suivantes ne restent ouvertes que le temps de l'échange de clés, conclu par un
+    /// [`ErrorCode::ServerFull`] qui indique au client quand réessayer, plutôt que d'attendre
+    /// sans réponse dans la file du système. Par défaut, rien ne les limite.
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.slots = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// Cherche l'adresse de chaque client TCP dans les zones de `dnsbl` avant de traiter sa
+    /// connexion : un client listé est refusé ou ralenti, selon [`Dnsbl::action`]. Les clients
+    /// d'une socket Unix ne sont pas vérifiés.
+    pub fn with_dnsbl(mut self, dnsbl: Dnsbl) -> Self {
+        self.dnsbl = Some(Arc::new(dnsbl));
+        self
+    }
+
+    /// Compteurs des vérifications DNSBL, si elles sont activées.
+    pub fn dnsbl_metrics(&self) -> Option<DnsblMetrics> {
+        self.dnsbl.as_ref().map(|dnsbl| dnsbl.metrics())
+    }
+
+    /// Copie du serveur, partageant ses utilisateurs et ses canaux, dont les connexions sont
+    /// réservées aux administrateurs : les autres noms sont refusés à la connexion. Voir
+    /// [`Listener`] pour écouter sur plusieurs adresses.
+    pub fn admin_only(mut self) -> Self {
+        self.admin_only = true;
+        self
+    }
+
+    /// Copie du serveur, partageant ses utilisateurs et ses canaux, dont les connexions viennent
+    /// d'un répartiteur de charge : chacune commence par un en-tête PROXY qui donne l'adresse
+    /// réelle du client (voir [`Server::spawn_proxied`]).
+    pub fn behind_proxy(mut self) -> Self {
+        self.proxy_protocol = true;
+        self
+    }
+
+    fn is_system_channel(&self, channel: &str) -> bool {
+        self.system_channels.iter().any(|system| system == channel)
+    }
+
+    // Nom réservé aux canaux système, qu'ils existent ou non
+    fn is_reserved_channel(&self, channel: &str) -> bool {
+        self.system_prefixes
+            .iter()
+            .any(|prefix| channel.starts_with(prefix.as_str()))
+    }
+
+    /// Retire des canaux les membres dont la connexion n'existe plus, par exemple après la
+    /// panique de la tâche qui la traitait : sans cela, ils ne pourraient plus les rejoindre
+    /// ("déjà membre"). Renvoie le nombre d'abonnements retirés.
+    pub async fn sweep(&self) -> usize {
+        // Les abonnements plus récents que la liste des connexions ne sont pas examinés
+        let before = SystemTime::now();
+        let live: HashSet<String> = sessions(&self.db).keys().cloned().collect();
+        let removed = self.db_chan.sweep(before, live).await;
+        for (chan, user) in &removed {
+            warn!(%user, %chan, "removed stale channel member");
+        }
+        removed.len()
+    }
+
+    /// Appelle [`Server::sweep`] toutes les `interval`, dans une tâche dédiée.
+    pub fn spawn_sweeper(&self, interval: Duration) -> JoinHandle<()> {
+        let server = self.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval_at(Instant::now() + interval, interval);
+            loop {
+                tick.tick().await;
+                server.sweep().await;
+            }
+        })
+    }
+
+    /// Traite les connexions acceptées par `listener`, jusqu'à la première erreur.
+    pub async fn serve_tcp(&self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (socket, peer) = listener.accept().await?;
+            if self.proxy_protocol {
+                self.spawn_proxied(socket, peer.to_string());
+            } else {
+                self.spawn_from_addr(socket, peer);
+            }
+        }
+    }
+
+    /// Écoute sur la socket Unix `path`, en remplaçant celle d'une exécution précédente.
+    #[cfg(unix)]
+    pub async fn serve_unix(&self, path: &str) -> Result<()> {
+        remove_stale_socket(path)?;
+        let listener = tokio::net::UnixListener::bind(path)?;
+        loop {
+            let (socket, _) = listener.accept().await?;
+            // Les clients d'une socket Unix n'ont généralement pas d'adresse
+            if self.proxy_protocol {
+                self.spawn_proxied(socket, format!("unix:{path}"));
+            } else {
+                self.spawn_from(socket, format!("unix:{path}"));
+            }
+        }
+    }
+
+    /// Traite une connexion dans une nouvelle tâche, quel que soit son transport : un
+    /// [`tokio::io::DuplexStream`] permet par exemple de se passer de socket.
+    pub fn spawn<S>(&self, socket: S)
+    where
+        S: Transport + Send + 'static,
+        S::ReadHalf: Debug + Send,
+        S::WriteHalf: Debug + Send,
+    {
+        self.spawn_from(socket, "local".to_string());
+    }
+
+    /// Comme [`Server::spawn_from`], pour une connexion venant de l'adresse IP `peer`, d'abord
+    /// cherchée dans les zones de [`Server::with_dnsbl`]. La recherche a lieu dans la tâche de
+    /// la connexion : elle ne retarde pas les suivantes.
+    pub fn spawn_from_addr<S>(&self, socket: S, peer: SocketAddr)
+    where
+        S: Transport + Send + 'static,
+        S::ReadHalf: Debug + Send,
+        S::WriteHalf: Debug + Send,
+    {
+        let Some(dnsbl) = self.dnsbl.clone() else {
+            return self.spawn_from(socket, peer.to_string());
+        };
+        let server = self.clone();
+        tokio::spawn(async move {
+            let Some(zone) = dnsbl.check(peer.ip()).await else {
+                return server.spawn_from(socket, peer.to_string());
+            };
+            match dnsbl.action() {
+                DnsblAction::Reject => {
+                    warn!(%peer, %zone, "listed in DNSBL, refusing connection");
+                    let refusal = ErrorCode::Blocklisted(zone);
+                    connection::refuse(socket, refusal, peer.to_string()).await;
+                }
+                DnsblAction::Tarpit(delay) => {
+                    warn!(%peer, %zone, "listed in DNSBL, tarpitting connection");
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        });
+    }
+
+    /// Traite une connexion reçue d'un répartiteur de charge, `proxy` dans les journaux : son
+    /// en-tête PROXY ([`read_proxy_header`]) donne l'adresse du client, qui la remplace dans
+    /// les journaux et les vérifications ([`Server::spawn_from_addr`]). Sans en-tête valide, la
+    /// connexion est fermée.
+    pub fn spawn_proxied<S>(&self, mut socket: S, proxy: String)
+    where
+        S: Transport + AsyncRead + Unpin + Send + 'static,
+        S::ReadHalf: Debug + Send,
+        S::WriteHalf: Debug + Send,
+    {
+        let server = self.clone();
+        tokio::spawn(async move {
+            let header =
+                tokio::time::timeout(PROXY_HEADER_TIMEOUT, read_proxy_header(&mut socket)).await;
+            match header {
+                Ok(Ok(Some(peer))) => {
+                    debug!(%proxy, %peer, "proxied connection");
+                    server.spawn_from_addr(socket, peer);
+                }
+                // Connexion du répartiteur lui-même
+                Ok(Ok(None)) => server.spawn_from(socket, proxy),
+                // Par exemple une vérification de santé qui ne fait que se connecter
+                Ok(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    debug!(%proxy, "connection closed before PROXY header");
+                }
+                Ok(Err(e)) => warn!(%proxy, "{}", e),
+                Err(_) => warn!(%proxy, "no PROXY header received"),
+            }
+        });
+    }
+
+    /// Comme [`Server::spawn`], pour une connexion venant de `peer`, nommé dans les journaux.
+    /// Une panique pendant son traitement n'arrête qu'elle : elle est journalisée, et la
+    /// session libérée (nom, canaux).
+    pub fn spawn_from<S>(&self, socket: S, peer: String)
+    where
+        S: Transport + Send + 'static,
+        S::ReadHalf: Debug + Send,
+        S::WriteHalf: Debug + Send,
+    {
+        let slot = match &self.slots {
+            Some(slots) => match slots.clone().try_acquire_owned() {
+                Ok(slot) => Some(slot),
+                Err(_) => {
+                    warn!(%peer, "server full, refusing connection");
+                    let refusal = ErrorCode::ServerFull {
+                        retry_after: SERVER_FULL_RETRY_AFTER,
+                    };
+                    tokio::spawn(connection::refuse(socket, refusal, peer));
+                    return;
+                }
+            },
+            None => None,
+        };
+        let server = self.clone();
+        tokio::spawn(async move {
+            // Place libérée à la fin de la connexion, y compris après une panique
+            let _slot = slot;
+            let task = tokio::spawn(connection::process(socket, server, peer.clone()));
+            if let Err(e) = task.await {
+                if e.is_panic() {
+                    error!(%peer, "connection task panicked: {}", panic_message(&*e.into_panic()));
+                }
+            }
+        });
+    }
+}
+
+// Message d'une panique, s'il s'agit d'un texte comme pour `panic!`
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string payload")
+}
+
+// Supprime la socket laissée par une exécution précédente, sans toucher aux autres fichiers
+#[cfg(unix)]
+fn remove_stale_socket(path: &str) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => Ok(std::fs::remove_file(path)?),
+        Ok(_) => bail!("{} exists and is not a socket", path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Utilisateur connecté
+#[derive(Debug, Clone)]
+struct Session {
+    tx: mpsc::Sender<Response>,
+    stats: Arc<SessionStats>,
+    takeover: Arc<Takeover>,
+}
+
+// Reprise d'une session par une autre connexion du même client, voir `Request::Ghost`
+#[derive(Debug, Default)]
+struct Takeover {
+    // Clé enregistrée par le client : sans elle, la session ne peut pas être reprise
+    key: Mutex<Option<String>>,
+    // Notifié pour fermer la session
+    kick: Notify,
+    // Notifié une fois la session fermée et ses canaux quittés
+    closed: Notify,
+}
+
+// Compteurs d'une connexion, consultables depuis les autres connexions
+#[derive(Debug)]
+struct SessionStats {
+    bytes: ByteCounts,
+    messages_sent: AtomicU64,
+    channels: Mutex<Vec<String>>,
+    connected_at: Instant,
+    ping_rtt: Mutex<Option<Duration>>,
+    // Dernière requête du client, hors réponses aux pings
+    last_activity: Mutex<Instant>,
+    away: AtomicBool,
+    encryption: EncryptionStatus,
+}
+
+impl SessionStats {
+    fn new(bytes: ByteCounts, encryption: EncryptionStatus) -> Self {
+        Self {
+            bytes,
+            encryption,
+            messages_sent: AtomicU64::new(0),
+            channels: Mutex::new(Vec::new()),
+            connected_at: Instant::now(),
+            ping_rtt: Mutex::new(None),
+            last_activity: Mutex::new(Instant::now()),
+            away: AtomicBool::new(false),
+        }
+    }
+
+    fn idle(&self) -> Duration {
+        self.last_activity.lock().unwrap().elapsed()
+    }
+
+    // Les octets lus par le serveur sont ceux envoyés par le client, et inversement
+    fn snapshot(&self, user: &str) -> ConnectionStats {
+        ConnectionStats {
+            user: user.to_string(),
+            bytes_sent: self.bytes.read(),
+            bytes_received: self.bytes.written(),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            channels: self.channels.lock().unwrap().clone(),
+            connected_for: self.connected_at.elapsed(),
+            ping_rtt: *self.ping_rtt.lock().unwrap(),
+            encryption: self.encryption,
+        }
+    }
+}
+
+// File d'envoi d'une connexion, vidée par une tâche dédiée : un client lent ne bloque ainsi
+// ni la lecture de ses requêtes, ni les canaux qu'il a rejoints.
+#[derive(Debug, Clone)]
+struct Outbound {
+    tx: mpsc::Sender<Response>,
+    // Notifié lorsque la file reste pleine plus de `OUTBOUND_TIMEOUT`
+    stalled: Arc<Notify>,
+}
+
+impl Outbound {
+    fn spawn<W>(mut writer: AsyncTypedWriter<W, Response, Encrypted>) -> (Self, JoinHandle<()>)
+    where
+        W: AsyncWrite + Unpin + Debug + Send + 'static,
+    {
+        let (outbound, mut rx) = Self::new();
+        let task = tokio::spawn(async move {
+            while let Some(response) = rx.recv().await {
+                if writer.send(&response).await.is_err() {
+                    return;
+                }
+            }
+            // Plus rien à envoyer : le client sait que la fermeture est voulue
+            let _ = writer.close().await;
+        });
+        (outbound, task)
+    }
+
+    // File sans tâche pour la vider : les réponses sont à lire dans le récepteur renvoyé
+    fn new() -> (Self, mpsc::Receiver<Response>) {
+        let (tx, rx) = mpsc::channel(OUTBOUND_QUEUE_SIZE);
+        let outbound = Self {
+            tx,
+            stalled: Arc::new(Notify::new()),
+        };
+        (outbound, rx)
+    }
+
+    async fn send(&self, response: Response) {
+        if let Err(SendTimeoutError::Timeout(_)) =
+            self.tx.send_timeout(response, OUTBOUND_TIMEOUT).await
+        {
+            self.stalled.notify_one();
+        }
+    }
+}
+
+fn error(code: ErrorCode) -> Response {
+    Response::Error(code)
+}
+
+// Vérifie qu'un nom d'utilisateur peut être pris, indépendamment des utilisateurs connectés.
+// `system_prefixes` sont les préfixes des canaux système configurés.
+fn check_nickname(
+    nickname: &str,
+    reserved: &HashSet<String>,
+    system_prefixes: &[String],
+) -> Result<(), ErrorCode> {
+    let system = system_prefixes
+        .iter()
+        .any(|prefix| nickname.starts_with(prefix.as_str()));
+    if nickname.is_empty() {
+        Err(ErrorCode::EmptyNickname)
+    } else if nickname.starts_with('#') || system {
+        // Syntaxe des noms de canaux
+        Err(ErrorCode::ChannelPrefixInNickname)
+    } else if nickname.contains(char::is_whitespace) {
+        Err(ErrorCode::WhitespaceInNickname)
+    } else if nickname.contains(TARGET_SEPARATOR) {
+        // Un message à plusieurs destinataires ne pourrait pas l'atteindre
+        Err(ErrorCode::SeparatorInNickname)
+    } else if reserved.contains(&nickname.to_lowercase()) {
+        Err(ErrorCode::ReservedNickname(nickname.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+async fn connect_user(username: String, db: DB, session: Session) -> Option<Response> {
+    let mut db = sessions(&db);
+    match db.entry(username) {
+        Entry::Occupied(_) => None,
+        Entry::Vacant(entry) => {
+            let nickname = entry.key().clone();
+            entry.insert(session);
+            Some(Response::AckConnect(nickname))
+        }
+    }
+}
+
+// Libère le nom, sauf s'il a déjà été repris par une autre session
+fn disconnect_user(username: &str, db: &DB, takeover: &Arc<Takeover>) {
+    if !username.is_empty() {
+        let mut db = sessions(db);
+        if let Entry::Occupied(entry) = db.entry(username.to_string()) {
+            if Arc::ptr_eq(&entry.get().takeover, takeover) {
+                entry.remove();
+            }
+        }
+    }
+}
+
+// Ferme la session de `nickname` si `key` est celle qu'elle a enregistrée, et attend qu'elle ait
+// quitté ses canaux : le nom peut alors être repris.
+async fn ghost(user: &str, nickname: &str, key: &str, db: DB) -> Response {
+    if nickname == user {
+        return error(ErrorCode::CannotGhostSelf);
+    }
+    let takeover = match sessions(&db).get(nickname) {
+        Some(session) => session.takeover.clone(),
+        None => return error(ErrorCode::UnknownUser(nickname.to_string())),
+    };
+    if takeover.key.lock().unwrap().as_deref() != Some(key) {
+        return error(ErrorCode::PermissionDenied);
+    }
+    takeover.kick.notify_one();
+    // La session peut être bloquée sur sa file d'envoi pendant `OUTBOUND_TIMEOUT`
+    let _ = tokio::time::timeout(2 * OUTBOUND_TIMEOUT, takeover.closed.notified()).await;
+    Response::AckGhost(nickname.to_string())
+}
+
+async fn add_user_to_chan(
+    username: &str,
+    channel: String,
+    db_chan: DBChan,
+    capacity: &ChannelCapacity,
+    owner_expiry: Option<Duration>,
+    system: bool,
+) -> Option<(ChannelHandle, Joined)> {
+    let capacity = capacity.get(&channel);
+    if system {
+        db_chan.join_system(&channel, username, capacity).await
+    } else {
+        db_chan
+            .join(&channel, username, capacity, owner_expiry)
+            .await
+    }
+}
+
+// Transmet un message direct au destinataire, sans attendre : un destinataire trop lent ne doit
+// pas bloquer l'expéditeur.
+async fn send_to_user(from: &str, to: &str, content: String, db: DB) -> Result<(), ErrorCode> {
+    let mess = Response::DirectMessage {
+        from: from.to_string(),
+        content,
+    };
+    send_to_session(to, mess, db)
+}
+
+// Envoie une réponse à la session de l'utilisateur `to`, sans attendre
+fn send_to_session(to: &str, response: Response, db: DB) -> Result<(), ErrorCode> {
+    let db = sessions(&db);
+    let Some(Session { tx, .. }) = db.get(to) else {
+        return Err(ErrorCode::UnknownUser(to.to_string()));
+    };
+    tx.try_send(response)
+        .map_err(|_| ErrorCode::Unreachable(to.to_string()))
+}
+
+// Signale un message aux opérateurs qui surveillent l'un de ses mots-clés, sans attendre
+fn alert_operators(alerts: Vec<(String, String)>, from: &str, chan: &str, content: &str, db: DB) {
+    let db = sessions(&db);
+    for (operator, keyword) in alerts {
+        if operator == from {
+            continue;
+        }
+        if let Some(session) = db.get(&operator) {
+            let _ = session.tx.try_send(Response::KeywordAlert {
+                chan: chan.to_string(),
+                keyword,
+                from: from.to_string(),
+                content: content.to_string(),
+            });
+        }
+    }
+}
+
+// Statistiques de l'ensemble du serveur, pour les connexions abonnées
+fn server_stats(db: &DB, db_chan: &DBChan, metrics: &ServerMetrics) -> Response {
+    let now = Instant::now();
+    Response::ServerStats {
+        users: sessions(db).len(),
+        channels: db_chan.list().len(),
+        uptime_secs: metrics.uptime(now).as_secs(),
+        msgs_per_min: metrics.messages_per_minute(now),
+    }
+}
+
+async fn stats_of(username: &str, db: DB) -> Response {
+    match sessions(&db).get(username) {
+        Some(session) => Response::Stats(session.stats.snapshot(username)),
+        None => error(ErrorCode::UnknownUser(username.to_string())),
+    }
+}
+
+async fn whois(username: &str, db: DB) -> Response {
+    match sessions(&db).get(username) {
+        Some(session) => Response::WhoIs {
+            user: username.to_string(),
+            channels: session.stats.channels.lock().unwrap().clone(),
+            idle: session.stats.idle(),
+            away: session.stats.away.load(Ordering::Relaxed),
+        },
+        None => error(ErrorCode::UnknownUser(username.to_string())),
+    }
+}
+
+// Annonce un changement de présence aux canaux d'un utilisateur
+async fn announce_to_chans<'a>(channels: impl Iterator<Item = &'a ChannelHandle>, op: ChanOp) {
+    for channel in channels {
+        channel.announce(op.clone()).await;
+    }
+}
+
+fn message_op(username: &str, content: String) -> ChanOp {
+    ChanOp::Message {
+        from: username.to_string(),
+        content,
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{bail, Result};
+
+/// Adresse d'écoute du serveur, avec ses réglages. Plusieurs adresses peuvent être données,
+/// séparées par des virgules : `127.0.0.1:6379,[::1]:6379,unix:///run/mini-irc.sock;admin-only`
+/// écoute en IPv4, en IPv6 et sur une socket Unix réservée aux administrateurs. Toutes les
+/// connexions partagent les mêmes utilisateurs et canaux.
+///
+/// Les réglages suivent l'adresse, séparés par des `;` :
+/// - `admin-only` : seuls les administrateurs peuvent se connecter par cette adresse.
+/// - `proxy-protocol` : les connexions viennent d'un répartiteur de charge, qui envoie
+///   l'adresse du client dans un en-tête PROXY version 2, voir
+///   [`Server::behind_proxy`](crate::Server::behind_proxy).
+///
+/// Le chiffrement est exigé sur toutes les adresses. TLS n'est pas encore supporté : une
+/// adresse `tls://` est refusée.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listener {
+    /// `ip:port` ou `unix:///chemin/socket`
+    pub address: String,
+    pub admin_only: bool,
+    pub proxy_protocol: bool,
+}
+
+impl Listener {
+    /// Adresses d'une liste séparée par des virgules, dans l'ordre.
+    pub fn parse_all(spec: &str) -> Result<Vec<Self>> {
+        let listeners = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(Self::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if listeners.is_empty() {
+            bail!("no listen address: {:?}", spec);
+        }
+        Ok(listeners)
+    }
+
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut parts = spec.split(';').map(str::trim);
+        let address = parts.next().unwrap_or_default();
+        if address.is_empty() {
+            bail!("empty listen address: {:?}", spec);
+        }
+        if address.starts_with("tls://") {
+            bail!("TLS listeners are not supported yet: {}", address);
+        }
+        let mut listener = Self {
+            address: address.to_string(),
+            admin_only: false,
+            proxy_protocol: false,
+        };
+        for option in parts {
+            match option {
+                "admin-only" => listener.admin_only = true,
+                "proxy-protocol" => listener.proxy_protocol = true,
+                option => bail!("unknown listener option for {}: {}", address, option),
+            }
+        }
+        Ok(listener)
+    }
+
+    /// Chemin de la socket Unix, pour une adresse `unix://`.
+    pub fn unix_path(&self) -> Option<&str> {
+        self.address.strip_prefix("unix://")
+    }
+}
@@ -1,236 +1,260 @@
-use anyhow::Result;
-use crypto_box::PublicKey;
-use mini_irc_protocol::{
-    AsyncTypedReader, AsyncTypedWriter, BroadcastReceiverWithList, BroadcastSenderWithList, ChanOp,
-    MessageReceiver, Request, Response,
-};
-use serde_encrypt::{
-    key::key_pair::ReceiverKeyPair, shared_key::SharedKey, traits::SerdeEncryptPublicKey,
-    EncryptedMessage, ReceiverCombinedKey, ReceiverKeyPairCore,
-};
-use serde_encrypt_core::key::key_pair::public_key::SenderPublicKey;
+use anyhow::{Context, Result};
+use mini_irc_server::{ChannelCapacity, Dnsbl, Listener, Server};
+use std::io::IsTerminal;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
 
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::mpsc;
+/// Adresse d'écoute par défaut
+const DEFAULT_ADDRESS: &str = "127.0.0.1:6379";
+/// Intervalle par défaut entre deux envois des statistiques du serveur aux clients
+const DEFAULT_STATS_INTERVAL: Duration = Duration::from_secs(60);
+/// Intervalle entre deux recherches de membres de canaux sans connexion
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+/// Délai de connexion accordé à `--healthcheck`
+const HEALTHCHECK_TIMEOUT: Duration = Duration::from_secs(2);
 
-use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, Mutex};
-
-type DB = Arc<Mutex<HashSet<String>>>;
-type DBChan = Arc<Mutex<HashMap<String, BroadcastSenderWithList<Response, String>>>>;
 #[tokio::main]
 async fn main() -> Result<()> {
-    let listener = TcpListener::bind("127.0.0.1:6379").await?;
+    // Utilisation: server [--healthcheck] [adresses]
+    let mut args = std::env::args().skip(1).peekable();
+    let healthcheck = args.next_if(|arg| arg == "--healthcheck").is_some();
+    // Les adresses d'écoute, `ip:port` ou `unix:///chemin/socket` séparées par des virgules et
+    // suivies de leurs réglages (voir `Listener`): premier argument, sinon la variable
+    // d'environnement `MINI_IRC_ADDRESS`
+    let address = args
+        .next()
+        .or_else(|| std::env::var("MINI_IRC_ADDRESS").ok())
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listeners = Listener::parse_all(&address)?;
 
-    let db: DB = Arc::new(Mutex::new(HashSet::new()));
-    let db_chan: DBChan = Arc::new(Mutex::new(HashMap::new()));
-    loop {
-        let (socket, _) = listener.accept().await?;
-        let db = db.clone();
-        let db_chan = db_chan.clone();
-        tokio::spawn(async move {
-            process(socket, db, db_chan).await;
-        });
+    if healthcheck {
+        for listener in &listeners {
+            probe(&listener.address).await?;
+        }
+        return Ok(());
     }
-}
-
-fn error(message: String) -> Response {
-    Response::Error(message)
-}
 
-async fn connect_user(username: String, db: DB) -> Option<Response> {
-    let mut db = db.lock().unwrap();
-    if db.insert(username) {
-        Some(Response::AckConnect("Welcome".to_string()))
+    init_logging();
+    // Administrateurs: noms d'utilisateur séparés par des virgules dans `MINI_IRC_ADMINS`
+    let admins = std::env::var("MINI_IRC_ADMINS").unwrap_or_default();
+    let server = Server::new(ChannelCapacity::from_env()?).with_admins(
+        admins
+            .split(',')
+            .map(str::trim)
+            .filter(|admin| !admin.is_empty())
+            .map(str::to_string),
+    );
+    // Noms d'utilisateur réservés, séparés par des virgules dans `MINI_IRC_RESERVED_NICKS`
+    let server = match std::env::var("MINI_IRC_RESERVED_NICKS") {
+        Ok(nicknames) => server.with_reserved_nicknames(
+            nicknames
+                .split(',')
+                .map(str::trim)
+                .filter(|nickname| !nickname.is_empty())
+                .map(str::to_string),
+        ),
+        Err(_) => server,
+    };
+    // Canaux système, rejoints par tous à la connexion, séparés par des virgules dans
+    // `MINI_IRC_SYSTEM_CHANNELS` ; `MINI_IRC_SYSTEM_PREFIXES` remplace les préfixes réservés
+    let server = match std::env::var("MINI_IRC_SYSTEM_CHANNELS") {
+        Ok(channels) => server.with_system_channels(
+            channels
+                .split(',')
+                .map(str::trim)
+                .filter(|channel| !channel.is_empty())
+                .map(str::to_string),
+        ),
+        Err(_) => server,
+    };
+    let server = match std::env::var("MINI_IRC_SYSTEM_PREFIXES") {
+        Ok(prefixes) => server.with_system_prefixes(
+            prefixes
+                .split(',')
+                .map(str::trim)
+                .filter(|prefix| !prefix.is_empty())
+                .map(str::to_string),
+        ),
+        Err(_) => server,
+    };
+    // Délai d'inactivité, en secondes, après lequel un utilisateur est marqué absent
+    let server = match std::env::var("MINI_IRC_AWAY_AFTER") {
+        Ok(secs) => server.with_away_after(Duration::from_secs(
+            secs.parse()
+                .with_context(|| format!("invalid MINI_IRC_AWAY_AFTER: {secs}"))?,
+        )),
+        Err(_) => server,
+    };
+    // Délai, en secondes, après lequel un propriétaire de canal absent perd ses droits
+    let server = match std::env::var("MINI_IRC_OWNER_EXPIRY") {
+        Ok(secs) => server.with_owner_expiry(Duration::from_secs(
+            secs.parse()
+                .with_context(|| format!("invalid MINI_IRC_OWNER_EXPIRY: {secs}"))?,
+        )),
+        Err(_) => server,
+    };
+    // Débit des messages directs par connexion, `nombre/secondes` : `5/10` en permet 5 toutes
+    // les 10 secondes
+    let server = match std::env::var("MINI_IRC_DM_RATE") {
+        Ok(rate) => {
+            let (max, secs) = rate
+                .split_once('/')
+                .and_then(|(max, secs)| Some((max.parse().ok()?, secs.parse().ok()?)))
+                .with_context(|| format!("invalid MINI_IRC_DM_RATE: {rate}"))?;
+            server.with_dm_rate(max, Duration::from_secs(secs))
+        }
+        Err(_) => server,
+    };
+    // Intervalle, en secondes, entre deux envois des statistiques du serveur aux clients
+    // abonnés ; 0 les désactive, sauf à l'abonnement
+    let stats_interval = match std::env::var("MINI_IRC_STATS_INTERVAL") {
+        Ok(secs) => Duration::from_secs(
+            secs.parse()
+                .with_context(|| format!("invalid MINI_IRC_STATS_INTERVAL: {secs}"))?,
+        ),
+        Err(_) => DEFAULT_STATS_INTERVAL,
+    };
+    let server = if stats_interval.is_zero() {
+        server
     } else {
-        None
+        server.with_stats_interval(stats_interval)
+    };
+    // Taille maximale des messages, en octets
+    let server = match std::env::var("MINI_IRC_MAX_MESSAGE_LEN") {
+        Ok(len) => server.with_max_message_len(
+            len.parse()
+                .with_context(|| format!("invalid MINI_IRC_MAX_MESSAGE_LEN: {len}"))?,
+        ),
+        Err(_) => server,
+    };
+
+    // Nombre maximal de connexions simultanées ; au-delà, les clients sont invités à réessayer
+    let server = match std::env::var("MINI_IRC_MAX_CONNECTIONS") {
+        Ok(max) => server.with_max_connections(
+            max.parse()
+                .with_context(|| format!("invalid MINI_IRC_MAX_CONNECTIONS: {max}"))?,
+        ),
+        Err(_) => server,
+    };
+    // Zones DNSBL consultées pour chaque client TCP, séparées par des virgules ; les clients
+    // listés sont refusés, ou ralentis pendant `MINI_IRC_DNSBL_TARPIT` secondes
+    let server = match std::env::var("MINI_IRC_DNSBL") {
+        Ok(zones) => {
+            let dnsbl = Dnsbl::new(
+                zones
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|zone| !zone.is_empty())
+                    .map(str::to_string),
+            );
+            let dnsbl = match std::env::var("MINI_IRC_DNSBL_TARPIT") {
+                Ok(secs) => dnsbl.with_tarpit(Duration::from_secs(
+                    secs.parse()
+                        .with_context(|| format!("invalid MINI_IRC_DNSBL_TARPIT: {secs}"))?,
+                )),
+                Err(_) => dnsbl,
+            };
+            server.with_dnsbl(dnsbl)
+        }
+        Err(_) => server,
+    };
+
+    // Filet de sécurité : une connexion interrompue par une panique ne bloque pas ses canaux
+    server.spawn_sweeper(SWEEP_INTERVAL);
+
+    // Toutes les adresses partagent les utilisateurs et les canaux du même serveur
+    let mut serving = JoinSet::new();
+    for listener in &listeners {
+        info!(
+            address = %listener.address,
+            admin_only = listener.admin_only,
+            proxy_protocol = listener.proxy_protocol,
+            "listening"
+        );
+        let mut server = server.clone();
+        if listener.admin_only {
+            server = server.admin_only();
+        }
+        if listener.proxy_protocol {
+            server = server.behind_proxy();
+        }
+        serving.spawn(serve(server, listener.address.clone()));
+    }
+    // Exécuté en tant que PID 1 dans un conteneur, le serveur doit gérer lui-même SIGTERM
+    tokio::select! {
+        // La première adresse en erreur arrête le serveur
+        Some(res) = serving.join_next() => res?,
+        res = shutdown_signal() => {
+            res?;
+            info!("shutting down");
+            #[cfg(unix)]
+            for path in listeners.iter().filter_map(Listener::unix_path) {
+                let _ = std::fs::remove_file(path);
+            }
+            Ok(())
+        }
     }
 }
 
-async fn disconnect_user(username: String, db: DB) {
-    if !username.is_empty() {
-        let mut db = db.lock().unwrap();
-        db.remove(&username);
+// Traite les connexions reçues sur `address`, jusqu'à la première erreur
+async fn serve(server: Server, address: String) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(path) = address.strip_prefix("unix://") {
+        return server.serve_unix(path).await;
     }
+    let listener = TcpListener::bind(&address)
+        .await
+        .with_context(|| format!("cannot listen on {address}"))?;
+    server.serve_tcp(listener).await
 }
 
-async fn add_user_to_chan(
-    username: &str,
-    channel: String,
-    db_chan: DBChan,
-) -> Option<BroadcastReceiverWithList<Response, String>> {
-    let mut db_chan = db_chan.lock().unwrap();
-    if db_chan.contains_key(&channel) {
-        let users = db_chan.get_mut(&channel).unwrap();
-        users.subscribe(username.to_string())
+// Logs sur la sortie standard, en JSON si `LOG_FORMAT=json`. Le niveau est fixé par `RUST_LOG`;
+// par défaut, le contenu des trames reçues n'est pas tracé.
+fn init_logging() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info,mini_irc_protocol=warn"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(std::io::stdout().is_terminal())
+        .with_writer(std::io::stdout);
+    if std::env::var("LOG_FORMAT").as_deref() == Ok("json") {
+        builder.json().init();
     } else {
-        let mut users = BroadcastSenderWithList::<Response, String>::new(32);
-        let reciever = users.subscribe(username.to_string());
-        db_chan.insert(channel, users);
-        reciever
+        builder.init();
     }
 }
 
-async fn remove_user_from_chan(username: &str, channel: String, db_chan: DBChan) {
-    let res = Response::Channel {
-        op: ChanOp::UserDel(username.to_string()),
-        chan: channel.clone(),
+// Vérifie qu'un serveur accepte les connexions à `address`, pour le HEALTHCHECK d'un conteneur:
+// le code de sortie est non nul sinon.
+async fn probe(address: &str) -> Result<()> {
+    let connect = async {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix("unix://") {
+            return tokio::net::UnixStream::connect(path).await.map(drop);
+        }
+        tokio::net::TcpStream::connect(address).await.map(drop)
     };
-    let mut db_chan = db_chan.lock().unwrap();
-    let _ = db_chan.get_mut(&channel).unwrap().send(res);
+    tokio::time::timeout(HEALTHCHECK_TIMEOUT, connect)
+        .await
+        .with_context(|| format!("{address}: no answer"))?
+        .with_context(|| format!("{address}: unreachable"))
 }
 
-async fn message_to_chan(username: &str, channel: String, content: String) -> Response {
-    Response::Channel {
-        op: ChanOp::Message {
-            from: username.to_string(),
-            content,
-        },
-        chan: channel,
+#[cfg(unix)]
+async fn shutdown_signal() -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res?,
+        _ = terminate.recv() => {},
     }
+    Ok(())
 }
 
-async fn process(socket: TcpStream, db: DB, db_chan: DBChan) {
-    let key_pair = ReceiverKeyPair::generate();
-    let mut combined: Option<ReceiverCombinedKey> = None;
-    let mut shared: SharedKey;
-    let mut public_key_other: SenderPublicKey;
-    let (reader, writer) = socket.into_split();
-    let mut typed_reader = AsyncTypedReader::<_, Request>::new(reader);
-    let mut typed_writer = AsyncTypedWriter::<_, Response>::new(writer);
-    let mut user: String = "".to_string();
-    let mut channels: Vec<String> = Vec::new();
-
-    // Channel pour gérer communication avec Broadcast
-    let (tx, mut rx) = mpsc::channel(32);
-
-    loop {
-        let res: Option<Response> = tokio::select! {
-            val = typed_reader.recv() => {
-                if val.is_err() {
-                    drop(rx);
-                    drop(tx);
-                    break;
-                }
-                let rq = val.unwrap().unwrap();
-                let db = db.clone();
-                let db_chan = db_chan.clone();
-                let response = match rq {
-                    Request::Secure(key) => {
-                        let key_bytes: [u8; 32] = key.try_into().unwrap();
-                        public_key_other = SenderPublicKey::from(PublicKey::from(key_bytes));
-                        combined = Some(ReceiverCombinedKey::new(&public_key_other, key_pair.private_key()));
-                        Response::Secure(key_pair.public_key().as_ref().as_bytes().to_vec())
-                    },
-                    Request::Shared(key) => {
-                        if combined.is_some() {
-                            let encrypted_message = EncryptedMessage::deserialize(key).unwrap();
-                            shared = SharedKey::decrypt_owned(&encrypted_message, &combined.clone().unwrap()).unwrap();
-                            typed_reader.set_shared_key(shared.clone());
-                            typed_writer.set_shared_key(shared.clone());
-                            Response::Ack
-                        } else {
-                            error("invalid".to_string())
-                        }
-                    }
-                    Request::Connect(username) => {
-                        if let Some(res) = connect_user(username.clone(), db).await {
-                            user = username.clone();
-                            res
-                        } else {
-                            error("Invalid username".to_string())
-                        }
-                    },
-                    Request::JoinChan(channel) => {
-                        if user.is_empty() {
-                            error("Please connect first".to_string())
-                        } else if let Some(mut reciever) = add_user_to_chan(&user, channel.clone(), db_chan.clone()).await {
-                            let users = reciever.into_subscribers().clone();
-                            let tx2 = tx.clone();
-                            let _ = db_chan
-                                        .lock()
-                                        .unwrap()
-                                        .get_mut(&channel)
-                                        .unwrap()
-                                        .send(Response::Channel { op: ChanOp::UserAdd(user.clone()), chan: channel.clone() });
-                            let user = user.clone();
-
-                            // Spawn un thread pour transferer messages de Broadcast
-                            tokio::spawn(async move {
-                                loop {
-                                    let mess = reciever.recv().await;
-                                    match mess {
-                                        Ok(m) => {
-                                            if let Response::Channel {op: ChanOp::UserDel(target), chan: _} = m.clone() {
-                                                if target == user {
-                                                    break;
-                                                }
-                                            }
-                                            let _ = tx2.send(m).await;
-                                        },
-                                        Err(_) => break,
-                                    }
-                                }
-                                drop(tx2);
-                                drop(reciever);
-                            });
-                            channels.push(channel.clone());
-                            Response::AckJoin { chan: channel, users }
-                        } else {
-                            error("User already in channel".to_string())
-                        }
-                    },
-                    Request::LeaveChan(channel) => {
-                        if user.is_empty() {
-                            error("Please connect first".to_string())
-                        } else {
-                            remove_user_from_chan(&user, channel.clone(), db_chan.clone()).await;
-                            Response::AckLeave(user.clone())
-                        }
-                    },
-                    Request::Message { to: MessageReceiver::Channel(channel), content } => {
-                        if user.is_empty() {
-                            error("Please connect first".to_string())
-                        } else {
-                            let mess = message_to_chan(&user, channel.clone(), content).await;
-                            let _ = db_chan.lock().unwrap().get_mut(&channel).unwrap().send(mess.clone());
-                            mess
-                        }
-                    },
-                    Request::Message { to: MessageReceiver::User(_user), content: _content } => {
-                        todo!();
-                    },
-                };
-                Some(response)
-            },
-            Some(mess) = rx.recv() => {
-                if let Response::Channel{op: ChanOp::Message{from: target, content: _},chan: _} = mess.clone() {
-                    if target != user {
-                        Some(mess)
-                    } else {
-                        None
-                    }
-                } else if let Response::Channel{op: _, chan: _} = mess.clone() {
-                    Some(mess)
-                } else {
-                    None
-                }
-            }
-            else => break,
-        };
-        if let Some(r) = res {
-            let e = typed_writer.send(&r).await;
-            if e.is_err() {
-                break;
-            }
-        }
-    }
-    println!("user {} disconnect", user);
-    let db = db.clone();
-    let db_chan = db_chan.clone();
-    disconnect_user(user.clone(), db).await;
-    for chan in channels.into_iter() {
-        let db_chan = db_chan.clone();
-        remove_user_from_chan(&user, chan, db_chan).await;
-    }
+#[cfg(not(unix))]
+async fn shutdown_signal() -> Result<()> {
+    Ok(tokio::signal::ctrl_c().await?)
 }
@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// Durée sur laquelle les messages sont comptés
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Activité de l'ensemble du serveur, partagée par toutes les connexions.
+#[derive(Debug)]
+pub(crate) struct ServerMetrics {
+    started_at: Instant,
+    // Nombre de messages par seconde écoulée depuis le démarrage, sur la dernière minute
+    recent: Mutex<VecDeque<(u64, u64)>>,
+}
+
+impl ServerMetrics {
+    pub(crate) fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Temps écoulé depuis le démarrage, à la date `now`.
+    pub(crate) fn uptime(&self, now: Instant) -> Duration {
+        now.duration_since(self.started_at)
+    }
+
+    /// Compte un message de canal ou direct, envoyé à la date `now`.
+    pub(crate) fn record_message(&self, now: Instant) {
+        let second = self.uptime(now).as_secs();
+        let mut recent = self.recent.lock().unwrap();
+        match recent.back_mut() {
+            Some((last, count)) if *last == second => *count += 1,
+            _ => recent.push_back((second, 1)),
+        }
+        prune(&mut recent, second);
+    }
+
+    /// Nombre de messages de la dernière minute, à la date `now`.
+    pub(crate) fn messages_per_minute(&self, now: Instant) -> u64 {
+        let mut recent = self.recent.lock().unwrap();
+        prune(&mut recent, self.uptime(now).as_secs());
+        recent.iter().map(|(_, count)| count).sum()
+    }
+}
+
+// Oublie les secondes sorties de la fenêtre qui se termine à la seconde `now` : celle-ci
+// compte encore la seconde qui commence une minute plus tôt
+fn prune(recent: &mut VecDeque<(u64, u64)>, now: u64) {
+    while recent
+        .front()
+        .is_some_and(|(second, _)| now.saturating_sub(*second) > RATE_WINDOW.as_secs())
+    {
+        recent.pop_front();
+    }
+}
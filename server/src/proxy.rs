@@ -0,0 +1,71 @@
+//! En-tête PROXY (version 2) qu'un répartiteur de charge comme HAProxy envoie au début de
+//! chaque connexion : le serveur y lit l'adresse réelle du client, plutôt que celle du
+//! répartiteur. Seul le format binaire de la version 2 est accepté.
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Début de tout en-tête PROXY version 2
+pub const PROXY_V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+// Commandes, dans les 4 bits de poids faible de l'octet qui suit la signature
+const LOCAL: u8 = 0x0;
+const PROXY: u8 = 0x1;
+// Familles d'adresses, dans les 4 bits de poids fort de l'octet suivant ; le protocole (TCP,
+// UDP...) est ignoré
+const INET: u8 = 0x1;
+const INET6: u8 = 0x2;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("PROXY header: {message}"),
+    )
+}
+
+/// Lit l'en-tête PROXY au début de `reader`, sans rien lire au-delà. Renvoie l'adresse du
+/// client, ou `None` pour une connexion du répartiteur lui-même (commande `LOCAL`, par
+/// exemple ses vérifications de santé) ou d'une famille d'adresses inconnue. Les extensions
+/// (TLV) qui suivent les adresses sont ignorées.
+pub async fn read_proxy_header<R>(reader: &mut R) -> io::Result<Option<SocketAddr>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut header = [0; 16];
+    reader.read_exact(&mut header).await?;
+    if header[..12] != PROXY_V2_SIGNATURE {
+        return Err(invalid("bad signature"));
+    }
+    if header[12] >> 4 != 2 {
+        return Err(invalid("unsupported version"));
+    }
+    let command = header[12] & 0xf;
+    let family = header[13] >> 4;
+    let len = u16::from_be_bytes([header[14], header[15]]) as usize;
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload).await?;
+
+    match command {
+        LOCAL => return Ok(None),
+        PROXY => {}
+        _ => return Err(invalid("unknown command")),
+    }
+    // Adresses source puis destination, ports source puis destination
+    let source = match family {
+        INET if len >= 12 => {
+            let ip: [u8; 4] = payload[..4].try_into().unwrap();
+            let port = u16::from_be_bytes([payload[8], payload[9]]);
+            SocketAddr::from((Ipv4Addr::from(ip), port))
+        }
+        INET6 if len >= 36 => {
+            let ip: [u8; 16] = payload[..16].try_into().unwrap();
+            let port = u16::from_be_bytes([payload[32], payload[33]]);
+            SocketAddr::from((Ipv6Addr::from(ip), port))
+        }
+        INET | INET6 => return Err(invalid("truncated addresses")),
+        // Famille non précisée, ou socket Unix du côté du client
+        _ => return Ok(None),
+    };
+    Ok(Some(source))
+}
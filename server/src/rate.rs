@@ -0,0 +1,43 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Fenêtre glissante limitant le nombre d'envois d'une connexion sur une durée.
+#[derive(Debug)]
+pub(crate) struct RateWindow {
+    max: usize,
+    per: Duration,
+    // Dates des derniers envois acceptés, au plus `max`
+    sent: VecDeque<Instant>,
+}
+
+impl RateWindow {
+    pub(crate) fn new(max: usize, per: Duration) -> Self {
+        Self {
+            max,
+            per,
+            sent: VecDeque::with_capacity(max),
+        }
+    }
+
+    /// Compte un envoi à la date `now` s'il reste sous la limite, sinon renvoie le délai
+    /// avant que le plus ancien sorte de la fenêtre.
+    pub(crate) fn try_send(&mut self, now: Instant) -> Result<(), Duration> {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= self.per)
+        {
+            self.sent.pop_front();
+        }
+        match self.sent.front() {
+            Some(oldest) if self.sent.len() >= self.max => {
+                Err(self.per - now.duration_since(*oldest))
+            }
+            _ => {
+                self.sent.push_back(now);
+                Ok(())
+            }
+        }
+    }
+}
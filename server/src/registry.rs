@@ -0,0 +1,252 @@
+use crate::channel::{ChannelHandle, Joined};
+use mini_irc_protocol::{BroadcastMetrics, BroadcastReceiverWithList, Response};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime};
+
+/// Nombre de fragments par défaut
+pub const DEFAULT_SHARDS: usize = 16;
+
+type Shard = HashMap<String, ChannelHandle>;
+
+/// Canaux du serveur, par nom, chacun traité par sa propre tâche (voir [`ChannelHandle`]). Les
+/// noms sont répartis en fragments selon leur hash : deux recherches dans des fragments
+/// différents ne se bloquent pas mutuellement. Aucun verrou n'est conservé au-delà d'une
+/// recherche, et aucun n'est pris pour écrire dans un canal déjà rejoint.
+///
+/// Un canal vidé est retiré par [`ChannelRegistry::release`] ou [`ChannelRegistry::sweep`],
+/// avec son archive, son propriétaire et son message d'accueil : le prochain arrivant en crée
+/// un nouveau. Les canaux système, configurés par le serveur, restent en place.
+#[derive(Debug)]
+pub struct ChannelRegistry {
+    shards: Box<[Mutex<Shard>]>,
+    hasher: RandomState,
+}
+
+impl Default for ChannelRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS)
+    }
+}
+
+impl ChannelRegistry {
+    /// Créé un registre vide de `shards` fragments ; un seul fragment revient à un unique verrou.
+    pub fn new(shards: usize) -> Self {
+        assert!(shards > 0, "a registry needs at least one shard");
+        Self {
+            shards: (0..shards).map(|_| Mutex::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    // Un verrou empoisonné laisse la table cohérente : elle est reprise telle quelle, ce qui
+    // permet de libérer des canaux pendant une panique
+    fn shard(&self, channel: &str) -> MutexGuard<'_, Shard> {
+        let index = self.hasher.hash_one(channel) as usize % self.shards.len();
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Tous les canaux, sans garder de verrou
+    fn handles(&self) -> Vec<(String, ChannelHandle)> {
+        self.shards
+            .iter()
+            .flat_map(|shard| {
+                shard
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(name, handle)| (name.clone(), handle.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Accès au canal, s'il existe.
+    pub fn get(&self, channel: &str) -> Option<ChannelHandle> {
+        self.shard(channel).get(channel).cloned()
+    }
+
+    /// Fait rejoindre le canal à `user` ([`ChannelHandle::join`]), en le créant avec `capacity`
+    /// s'il n'existe pas encore. Renvoie aussi l'accès au canal, à conserver pour y écrire.
+    /// `None` si l'utilisateur en est déjà membre.
+    pub async fn join(
+        &self,
+        channel: &str,
+        user: &str,
+        capacity: usize,
+        expiry: Option<Duration>,
+    ) -> Option<(ChannelHandle, Joined)> {
+        self.join_with(channel, user, capacity, expiry, ChannelHandle::spawn)
+            .await
+    }
+
+    /// Comme [`ChannelRegistry::join`], pour un canal système, créé au besoin par
+    /// [`ChannelHandle::spawn_system`].
+    pub async fn join_system(
+        &self,
+        channel: &str,
+        user: &str,
+        capacity: usize,
+    ) -> Option<(ChannelHandle, Joined)> {
+        self.join_with(channel, user, capacity, None, ChannelHandle::spawn_system)
+            .await
+    }
+
+    async fn join_with(
+        &self,
+        channel: &str,
+        user: &str,
+        capacity: usize,
+        expiry: Option<Duration>,
+        spawn: fn(&str, usize) -> ChannelHandle,
+    ) -> Option<(ChannelHandle, Joined)> {
+        loop {
+            let handle = self
+                .shard(channel)
+                .entry(channel.to_string())
+                .or_insert_with(|| spawn(channel, capacity))
+                .clone();
+            let joined = handle.join(user, expiry).await?;
+            // Le canal, encore vide, a pu être retiré pendant l'arrivée : il est remis en place,
+            // sauf si un nouveau canal l'a déjà remplacé, à rejoindre à sa place
+            let replaced = {
+                let mut shard = self.shard(channel);
+                match shard.get(channel) {
+                    Some(current) => !current.same_channel(&handle),
+                    None => {
+                        shard.insert(channel.to_string(), handle.clone());
+                        false
+                    }
+                }
+            };
+            if !replaced {
+                return Some((handle, joined));
+            }
+            handle.leave(user, joined.id).await;
+        }
+    }
+
+    /// Retire le canal du registre s'il est vide, par exemple après le départ de son dernier
+    /// membre, sauf un canal système. Renvoie `true` s'il a été retiré.
+    pub fn release(&self, channel: &str) -> bool {
+        let mut shard = self.shard(channel);
+        // Vérifié sous le verrou : une arrivée ne peut pas s'intercaler
+        let empty = shard
+            .get(channel)
+            .is_some_and(|handle| !handle.is_system() && handle.is_empty());
+        if empty {
+            shard.remove(channel);
+        }
+        empty
+    }
+
+    /// Comme [`ChannelRegistry::join`], en ne renvoyant que l'abonnement de `user`.
+    pub async fn subscribe(
+        &self,
+        channel: &str,
+        user: &str,
+        capacity: usize,
+    ) -> Option<BroadcastReceiverWithList<Response, String>> {
+        let (_, joined) = self.join(channel, user, capacity, None).await?;
+        Some(joined.receiver)
+    }
+
+    /// Noms et nombres de membres des canaux non vides, triés par nom.
+    pub fn list(&self) -> Vec<(String, usize)> {
+        let mut channels: Vec<_> = self
+            .handles()
+            .into_iter()
+            .map(|(name, handle)| (name, handle.len()))
+            .filter(|(_, users)| *users > 0)
+            .collect();
+        channels.sort();
+        channels
+    }
+
+    /// Canaux non vides dont le nom correspond au motif `pattern`, triés par nom : `*` y
+    /// remplace n'importe quelle suite de caractères, `?` un seul caractère.
+    pub fn matching(&self, pattern: &str) -> Vec<(String, ChannelHandle)> {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let mut channels: Vec<_> = self
+            .handles()
+            .into_iter()
+            .filter(|(name, handle)| {
+                !handle.is_empty() && glob_match(&pattern, &name.chars().collect::<Vec<_>>())
+            })
+            .collect();
+        channels.sort_by(|(a, _), (b, _)| a.cmp(b));
+        channels
+    }
+
+    /// État des diffusions de chaque canal, vide ou non, trié par nom.
+    pub fn metrics(&self) -> Vec<(String, BroadcastMetrics<String>)> {
+        let mut channels: Vec<_> = self
+            .handles()
+            .into_iter()
+            .map(|(name, handle)| (name, handle.metrics()))
+            .collect();
+        channels.sort_by(|(a, _), (b, _)| a.cmp(b));
+        channels
+    }
+
+    /// Désabonne de tous les canaux, en annonçant leur départ, les membres abonnés avant
+    /// `before` absents de `live`, les connexions existantes, puis retire les canaux vides.
+    /// Renvoie les canaux et les membres retirés.
+    pub async fn sweep(&self, before: SystemTime, live: HashSet<String>) -> Vec<(String, String)> {
+        let live = Arc::new(live);
+        let mut removed = Vec::new();
+        for (name, handle) in self.handles() {
+            for user in handle.sweep(before, live.clone()).await {
+                removed.push((name.clone(), user));
+            }
+            self.release(&name);
+        }
+        removed
+    }
+
+    /// Nombre de canaux existants.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Correspondance d'un nom avec un motif à `*` et `?`, sans retour arrière exponentiel : seule
+// la dernière étoile rencontrée est reprise
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Position de la dernière étoile, et du nom lorsqu'elle a été rencontrée
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // L'étoile absorbe un caractère de plus
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,107 @@
+use mini_irc_protocol::{ErrorCode, HandshakeRequest, Request};
+use std::fmt::Display;
+
+/// Étape d'une connexion, du point de vue du serveur. Chaque requête est vérifiée par
+/// [`ConnectionState::check`] avant d'être traitée.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Échange de clés en cours, en clair.
+    Handshake(HandshakeStep),
+    /// Canal chiffré, mais utilisateur pas encore identifié par [`Request::Connect`].
+    Authenticated,
+    /// Utilisateur identifié : toutes les requêtes sont acceptées, sauf un second `Connect`.
+    Active,
+}
+
+/// Prochaine requête attendue pendant l'échange de clés.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandshakeStep {
+    /// [`HandshakeRequest::Secure`], avec la clé publique du client.
+    Secure,
+    /// [`HandshakeRequest::Shared`], avec la clé partagée chiffrée.
+    Shared,
+}
+
+/// Requête refusée car inattendue à cette étape de la connexion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// Requête d'échange de clés dans le désordre, ou répétée.
+    UnexpectedHandshake,
+    /// Requête avant la fin de l'échange de clés.
+    HandshakeInProgress,
+    /// Requête réservée aux utilisateurs identifiés.
+    NotConnected,
+    /// Second [`Request::Connect`].
+    AlreadyConnected,
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StateError::UnexpectedHandshake => "Unexpected handshake request",
+            StateError::HandshakeInProgress => "Handshake in progress",
+            StateError::NotConnected => "Please connect first",
+            StateError::AlreadyConnected => "Already connected",
+        })
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Code envoyé au client dont la requête est refusée.
+impl From<StateError> for ErrorCode {
+    fn from(e: StateError) -> Self {
+        match e {
+            StateError::UnexpectedHandshake => ErrorCode::UnexpectedHandshake,
+            StateError::HandshakeInProgress => ErrorCode::HandshakeInProgress,
+            StateError::NotConnected => ErrorCode::NotConnected,
+            StateError::AlreadyConnected => ErrorCode::AlreadyConnected,
+        }
+    }
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        ConnectionState::Handshake(HandshakeStep::Secure)
+    }
+}
+
+impl ConnectionState {
+    /// Passe à l'étape suivante de l'échange de clés, et renvoie la clé transmise par le client.
+    /// Une requête dans le désordre est refusée sans changer d'étape.
+    pub fn on_handshake(&mut self, request: HandshakeRequest) -> Result<Vec<u8>, StateError> {
+        match (*self, request) {
+            (ConnectionState::Handshake(HandshakeStep::Secure), HandshakeRequest::Secure(key)) => {
+                *self = ConnectionState::Handshake(HandshakeStep::Shared);
+                Ok(key)
+            }
+            (ConnectionState::Handshake(HandshakeStep::Shared), HandshakeRequest::Shared(key)) => {
+                *self = ConnectionState::Authenticated;
+                Ok(key)
+            }
+            _ => Err(StateError::UnexpectedHandshake),
+        }
+    }
+
+    /// Vérifie qu'une requête peut être traitée à cette étape.
+    pub fn check(&self, request: &Request) -> Result<(), StateError> {
+        match (self, request) {
+            (ConnectionState::Handshake(_), _) => Err(StateError::HandshakeInProgress),
+            // Les limites du serveur et les sondes de latence ne concernent pas l'utilisateur
+            (_, Request::Capabilities | Request::Pong(_) | Request::Ping(_)) => Ok(()),
+            // Une session fantôme peut être fermée avant de reprendre son nom
+            (_, Request::Ghost { .. }) => Ok(()),
+            (ConnectionState::Authenticated, Request::Connect(_)) => Ok(()),
+            (ConnectionState::Authenticated, _) => Err(StateError::NotConnected),
+            (ConnectionState::Active, Request::Connect(_)) => Err(StateError::AlreadyConnected),
+            (ConnectionState::Active, _) => Ok(()),
+        }
+    }
+
+    /// L'utilisateur s'est identifié.
+    pub fn activate(&mut self) {
+        if *self == ConnectionState::Authenticated {
+            *self = ConnectionState::Active;
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use mini_irc_protocol::{BroadcastEvent, BroadcastReceiverWithList, ChanOp, Response};
+use mini_irc_server::ChannelRegistry;
+use std::time::Duration;
+
+fn op(event: BroadcastEvent<Response>) -> ChanOp {
+    match event {
+        BroadcastEvent::Message(Response::Channel { op, .. }) => op,
+        other => panic!("unexpected event: {other:?}"),
+    }
+}
+
+// Diffusions reçues jusqu'ici
+async fn received(receiver: &mut BroadcastReceiverWithList<Response, String>) -> Vec<ChanOp> {
+    let mut ops = Vec::new();
+    while let Ok(Ok(event)) = tokio::time::timeout(Duration::from_millis(20), receiver.recv()).await
+    {
+        ops.push(op(event));
+    }
+    ops
+}
+
+#[tokio::test]
+async fn leave_requires_the_current_membership() {
+    let registry = ChannelRegistry::default();
+    let (handle, mut bob) = registry.join("general", "bob", 16, None).await.unwrap();
+    let (_, alice) = registry.join("general", "alice", 16, None).await.unwrap();
+
+    // Ni un nom absent, ni une autre adhésion du même nom
+    assert!(!handle.leave("carol", bob.id).await);
+    assert!(!handle.leave("alice", bob.id).await);
+    assert!(handle.contains("alice"));
+
+    assert!(handle.leave("alice", alice.id).await);
+    assert!(!handle.leave("alice", alice.id).await);
+    assert_eq!(
+        received(&mut bob.receiver).await,
+        [
+            ChanOp::UserAdd("bob".to_string()),
+            ChanOp::UserAdd("alice".to_string()),
+            ChanOp::UserDel("alice".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn late_departure_does_not_remove_a_new_membership() {
+    let registry = ChannelRegistry::default();
+    let (handle, mut bob) = registry.join("general", "bob", 16, None).await.unwrap();
+    let (_, lost) = registry.join("general", "alice", 16, None).await.unwrap();
+    // Abonnement perdu, départ pas encore annoncé
+    drop(lost.receiver);
+
+    let (_, alice) = registry.join("general", "alice", 16, None).await.unwrap();
+    assert_ne!(alice.id, lost.id);
+    handle.depart("alice", lost.id);
+    assert!(!handle.leave("alice", lost.id).await);
+    assert_eq!(handle.subscribers(), ["bob", "alice"]);
+    assert_eq!(
+        received(&mut bob.receiver).await,
+        [
+            ChanOp::UserAdd("bob".to_string()),
+            ChanOp::UserAdd("alice".to_string()),
+            ChanOp::UserDel("alice".to_string()),
+            ChanOp::UserAdd("alice".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,505 @@
+use mini_irc_protocol::{
+    ChanInfo, ChanOp, ErrorCode, MessageReceiver, Notice, NoticeScope, Request, Response,
+};
+use mini_irc_server::{ChannelCapacity, Connection, ConnectionState, Server};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn server() -> Server {
+    Server::new(ChannelCapacity::default()).with_admins(["root".to_string()])
+}
+
+async fn connect(server: &Server, user: &str) -> (Connection, mpsc::Receiver<Response>) {
+    let (mut connection, rx) = Connection::detached(server);
+    assert_eq!(
+        connection
+            .handle_request(Request::Connect(user.to_string()))
+            .await,
+        Some(Response::AckConnect(user.to_string()))
+    );
+    (connection, rx)
+}
+
+async fn join(connection: &mut Connection, rx: &mut mpsc::Receiver<Response>, chan: &str) {
+    assert_eq!(
+        connection
+            .handle_request(Request::JoinChan(chan.to_string()))
+            .await,
+        None
+    );
+    assert!(matches!(rx.recv().await, Some(Response::AckJoin { .. })));
+    assert!(matches!(
+        rx.recv().await,
+        Some(Response::Channel {
+            op: ChanOp::UserAdd(_),
+            ..
+        })
+    ));
+}
+
+fn message(to: MessageReceiver, content: &str) -> Request {
+    Request::Message {
+        to,
+        content: content.to_string(),
+    }
+}
+
+fn channel(chan: &str) -> MessageReceiver {
+    MessageReceiver::Channel(chan.to_string())
+}
+
+#[tokio::test]
+async fn requests_before_connect_are_refused() {
+    let server = server();
+    let (mut connection, _rx) = Connection::detached(&server);
+    assert_eq!(connection.state(), ConnectionState::Authenticated);
+    assert_eq!(
+        connection.handle_request(Request::Stats).await,
+        Some(Response::Error(ErrorCode::NotConnected))
+    );
+    assert_eq!(
+        connection.handle_request(Request::Ping(4)).await,
+        Some(Response::Pong(4))
+    );
+}
+
+#[tokio::test]
+async fn connect_takes_the_nickname() {
+    let server = server();
+    let (alice, _rx) = connect(&server, "alice").await;
+    assert_eq!(alice.user(), "alice");
+    assert_eq!(alice.state(), ConnectionState::Active);
+
+    let (mut other, _rx) = Connection::detached(&server);
+    assert_eq!(
+        other
+            .handle_request(Request::Connect("alice".to_string()))
+            .await,
+        Some(Response::Error(ErrorCode::NickInUse))
+    );
+    assert_eq!(
+        other
+            .handle_request(Request::Connect("server".to_string()))
+            .await,
+        Some(Response::Error(ErrorCode::ReservedNickname(
+            "server".to_string()
+        )))
+    );
+    assert_eq!(other.user(), "");
+}
+
+#[tokio::test]
+async fn join_acknowledges_before_the_channel_broadcasts() {
+    let server = server();
+    let (mut alice, mut rx) = connect(&server, "alice").await;
+    assert_eq!(
+        alice
+            .handle_request(Request::JoinChan("general".to_string()))
+            .await,
+        None
+    );
+    assert_eq!(
+        rx.recv().await,
+        Some(Response::AckJoin {
+            chan: "general".to_string(),
+            users: vec!["alice".to_string()],
+            owner: Some("alice".to_string()),
+        })
+    );
+    assert_eq!(
+        rx.recv().await,
+        Some(Response::Channel {
+            op: ChanOp::UserAdd("alice".to_string()),
+            chan: "general".to_string(),
+            seq: 1,
+        })
+    );
+    assert_eq!(
+        alice
+            .handle_request(Request::JoinChan("general".to_string()))
+            .await,
+        Some(Response::Error(ErrorCode::AlreadyInChannel(
+            "general".to_string()
+        )))
+    );
+}
+
+#[tokio::test]
+async fn channel_messages_reach_every_member() {
+    let server = server();
+    let (mut alice, mut alice_rx) = connect(&server, "alice").await;
+    let (mut bob, mut bob_rx) = connect(&server, "bob").await;
+    join(&mut alice, &mut alice_rx, "general").await;
+    join(&mut bob, &mut bob_rx, "general").await;
+    // Arrivée de bob
+    alice_rx.recv().await.unwrap();
+
+    assert_eq!(
+        alice
+            .handle_request(message(channel("general"), "hi"))
+            .await,
+        None
+    );
+    let expected = Response::Channel {
+        op: ChanOp::Message {
+            from: "alice".to_string(),
+            content: "hi".to_string(),
+        },
+        chan: "general".to_string(),
+        seq: 3,
+    };
+    assert_eq!(alice_rx.recv().await, Some(expected.clone()));
+    assert_eq!(bob_rx.recv().await, Some(expected));
+
+    assert_eq!(
+        alice.handle_request(message(channel("rust"), "hi")).await,
+        Some(Response::Error(ErrorCode::NotInChannel))
+    );
+}
+
+#[tokio::test]
+async fn long_messages_are_refused() {
+    let server = server().with_max_message_len(4);
+    let (mut alice, _rx) = connect(&server, "alice").await;
+    assert_eq!(
+        alice
+            .handle_request(message(MessageReceiver::User("bob".to_string()), "hello"))
+            .await,
+        Some(Response::Error(ErrorCode::MessageTooLong))
+    );
+    assert_eq!(
+        alice.handle_request(Request::Capabilities).await,
+        Some(Response::Capabilities(mini_irc_protocol::Capabilities {
+            max_message_len: 4
+        }))
+    );
+}
+
+#[tokio::test]
+async fn direct_messages_reach_the_recipient() {
+    let server = server();
+    let (mut alice, _alice_rx) = connect(&server, "alice").await;
+    let (_bob, mut bob_rx) = connect(&server, "bob").await;
+    let to_bob = || message(MessageReceiver::User("bob".to_string()), "psst");
+    assert_eq!(alice.handle_request(to_bob()).await, None);
+    assert_eq!(
+        bob_rx.recv().await,
+        Some(Response::DirectMessage {
+            from: "alice".to_string(),
+            content: "psst".to_string(),
+        })
+    );
+    assert_eq!(
+        alice
+            .handle_request(message(MessageReceiver::User("carol".to_string()), "?"))
+            .await,
+        Some(Response::Error(ErrorCode::UnknownUser("carol".to_string())))
+    );
+}
+
+#[tokio::test]
+async fn leave_acknowledges_after_the_channel_broadcasts() {
+    let server = server();
+    let (mut alice, mut rx) = connect(&server, "alice").await;
+    join(&mut alice, &mut rx, "general").await;
+    assert_eq!(
+        alice
+            .handle_request(Request::LeaveChan("general".to_string()))
+            .await,
+        Some(Response::AckLeave("general".to_string()))
+    );
+    assert_eq!(
+        alice
+            .handle_request(Request::LeaveChan("general".to_string()))
+            .await,
+        Some(Response::Error(ErrorCode::NotInChannel))
+    );
+}
+
+#[tokio::test]
+async fn channel_listing_requests() {
+    let server = server();
+    let (mut alice, mut rx) = connect(&server, "alice").await;
+    join(&mut alice, &mut rx, "general").await;
+    assert_eq!(
+        alice.handle_request(Request::ListChans).await,
+        Some(Response::ChanList(vec![ChanInfo {
+            name: "general".to_string(),
+            users: 1,
+            topic: None,
+        }]))
+    );
+    assert_eq!(
+        alice
+            .handle_request(Request::Names("general".to_string()))
+            .await,
+        Some(Response::Names {
+            chan: "general".to_string(),
+            users: vec!["alice".to_string()],
+        })
+    );
+    assert_eq!(
+        alice
+            .handle_request(Request::Names("rust".to_string()))
+            .await,
+        Some(Response::Error(ErrorCode::UnknownChannel(
+            "rust".to_string()
+        )))
+    );
+}
+
+#[tokio::test]
+async fn ownership_and_welcome_requests() {
+    let server = server();
+    let (mut alice, mut alice_rx) = connect(&server, "alice").await;
+    let (mut bob, mut bob_rx) = connect(&server, "bob").await;
+    join(&mut alice, &mut alice_rx, "general").await;
+    join(&mut bob, &mut bob_rx, "general").await;
+
+    let welcome = |text: &str| Request::SetWelcome {
+        chan: "general".to_string(),
+        text: text.to_string(),
+    };
+    assert_eq!(
+        bob.handle_request(welcome("hello")).await,
+        Some(Response::Error(ErrorCode::PermissionDenied))
+    );
+    assert_eq!(
+        alice.handle_request(welcome("hello")).await,
+        Some(Response::Notice {
+            scope: NoticeScope::Channel("general".to_string()),
+            notice: Notice::WelcomeSet("hello".to_string()),
+        })
+    );
+    assert_eq!(
+        bob.handle_request(Request::ClaimOp("general".to_string()))
+            .await,
+        Some(Response::Error(ErrorCode::ChannelHasOwner))
+    );
+    assert_eq!(
+        alice
+            .handle_request(Request::TransferOp {
+                chan: "general".to_string(),
+                to: "bob".to_string(),
+            })
+            .await,
+        None
+    );
+    assert_eq!(
+        bob_rx.recv().await,
+        Some(Response::Channel {
+            op: ChanOp::Owner("bob".to_string()),
+            chan: "general".to_string(),
+            seq: 3,
+        })
+    );
+}
+
+#[tokio::test]
+async fn history_is_reserved_to_members() {
+    let server = server();
+    let (mut alice, mut rx) = connect(&server, "alice").await;
+    let (mut bob, _bob_rx) = connect(&server, "bob").await;
+    join(&mut alice, &mut rx, "general").await;
+    alice
+        .handle_request(message(channel("general"), "one"))
+        .await;
+
+    let fetch = || Request::FetchHistory {
+        chan: "general".to_string(),
+        before_id: None,
+        limit: 10,
+    };
+    match alice.handle_request(fetch()).await {
+        Some(Response::History { messages, more, .. }) => {
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].content, "one");
+            assert!(!more);
+        }
+        other => panic!("unexpected response: {other:?}"),
+    }
+    assert_eq!(
+        bob.handle_request(fetch()).await,
+        Some(Response::Error(ErrorCode::NotInChannel))
+    );
+}
+
+#[tokio::test]
+async fn invites_and_blocks() {
+    let server = server();
+    let (mut alice, mut alice_rx) = connect(&server, "alice").await;
+    let (mut bob, mut bob_rx) = connect(&server, "bob").await;
+    join(&mut alice, &mut alice_rx, "general").await;
+
+    let invite = || Request::Invite {
+        chan: "general".to_string(),
+        user: "bob".to_string(),
+    };
+    assert_eq!(
+        alice.handle_request(invite()).await,
+        Some(Response::Notice {
+            scope: NoticeScope::Channel("general".to_string()),
+            notice: Notice::InviteSent("bob".to_string()),
+        })
+    );
+    assert_eq!(
+        bob_rx.recv().await,
+        Some(Response::Invited {
+            chan: "general".to_string(),
+            by: "alice".to_string(),
+        })
+    );
+
+    assert_eq!(
+        bob.handle_request(Request::Block("bob".to_string())).await,
+        Some(Response::Error(ErrorCode::CannotBlockSelf))
+    );
+    assert_eq!(
+        bob.handle_request(Request::Block("alice".to_string()))
+            .await,
+        Some(Response::BlockList(vec!["alice".to_string()]))
+    );
+    assert_eq!(
+        alice.handle_request(invite()).await,
+        Some(Response::Error(ErrorCode::Blocked))
+    );
+}
+
+#[tokio::test]
+async fn admin_requests_are_refused_to_users() {
+    let server = server();
+    let (mut alice, _rx) = connect(&server, "alice").await;
+    let (mut admin, _admin_rx) = connect(&server, "root").await;
+    assert_eq!(
+        alice
+            .handle_request(Request::StatsOf("root".to_string()))
+            .await,
+        Some(Response::Error(ErrorCode::PermissionDenied))
+    );
+    assert!(matches!(
+        admin
+            .handle_request(Request::StatsOf("alice".to_string()))
+            .await,
+        Some(Response::Stats(stats)) if stats.user == "alice"
+    ));
+    assert_eq!(
+        alice
+            .handle_request(Request::WatchKeyword {
+                chan: "general".to_string(),
+                keyword: "rust".to_string(),
+            })
+            .await,
+        Some(Response::Error(ErrorCode::PermissionDenied))
+    );
+    assert_eq!(
+        admin
+            .handle_request(Request::WatchKeyword {
+                chan: "general".to_string(),
+                keyword: "rust".to_string(),
+            })
+            .await,
+        Some(Response::Keywords {
+            chan: "general".to_string(),
+            keywords: vec!["rust".to_string()],
+        })
+    );
+}
+
+#[tokio::test]
+async fn ghost_requires_the_registered_key() {
+    let server = server();
+    let (mut alice, _rx) = connect(&server, "alice").await;
+    alice
+        .handle_request(Request::GhostKey("secret".to_string()))
+        .await;
+    let (mut other, _other_rx) = Connection::detached(&server);
+    assert_eq!(
+        other
+            .handle_request(Request::Ghost {
+                nickname: "alice".to_string(),
+                key: "guess".to_string(),
+            })
+            .await,
+        Some(Response::Error(ErrorCode::PermissionDenied))
+    );
+    assert_eq!(
+        other
+            .handle_request(Request::Ghost {
+                nickname: "carol".to_string(),
+                key: "secret".to_string(),
+            })
+            .await,
+        Some(Response::Error(ErrorCode::UnknownUser("carol".to_string())))
+    );
+}
+
+#[tokio::test]
+async fn cleanup_frees_the_nickname_and_leaves_channels() {
+    let server = server();
+    let (mut alice, mut alice_rx) = connect(&server, "alice").await;
+    let (mut bob, mut bob_rx) = connect(&server, "bob").await;
+    join(&mut alice, &mut alice_rx, "general").await;
+    join(&mut bob, &mut bob_rx, "general").await;
+
+    alice.cleanup().await;
+    assert_eq!(
+        tokio::time::timeout(Duration::from_secs(1), bob_rx.recv())
+            .await
+            .unwrap(),
+        Some(Response::Channel {
+            op: ChanOp::UserDel("alice".to_string()),
+            chan: "general".to_string(),
+            seq: 3,
+        })
+    );
+    connect(&server, "alice").await;
+}
+
+#[tokio::test]
+async fn server_stats_subscription() {
+    let server = server();
+    let (mut alice, _rx) = connect(&server, "alice").await;
+    assert!(matches!(
+        alice.handle_request(Request::SubscribeStats).await,
+        Some(Response::ServerStats {
+            users: 1,
+            channels: 0,
+            ..
+        })
+    ));
+    assert_eq!(
+        alice.handle_request(Request::UnsubscribeStats).await,
+        Some(Response::Ack)
+    );
+}
+
+#[tokio::test]
+async fn panic_releases_the_session() {
+    let server = server();
+    let (mut bob, mut bob_rx) = connect(&server, "bob").await;
+    join(&mut bob, &mut bob_rx, "general").await;
+
+    let task = tokio::spawn({
+        let server = server.clone();
+        async move {
+            let (mut alice, mut rx) = connect(&server, "alice").await;
+            join(&mut alice, &mut rx, "general").await;
+            panic!("request handler failed");
+        }
+    });
+    assert!(task.await.unwrap_err().is_panic());
+    // Arrivée puis départ d'alice
+    bob_rx.recv().await.unwrap();
+    assert_eq!(
+        tokio::time::timeout(Duration::from_secs(1), bob_rx.recv())
+            .await
+            .unwrap(),
+        Some(Response::Channel {
+            op: ChanOp::UserDel("alice".to_string()),
+            chan: "general".to_string(),
+            seq: 3,
+        })
+    );
+    let (mut alice, mut rx) = connect(&server, "alice").await;
+    join(&mut alice, &mut rx, "general").await;
+}
@@ -0,0 +1,114 @@
+use mini_irc_server::{Dnsbl, DnsblMetrics, Resolver};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Queries = Arc<Mutex<Vec<String>>>;
+
+// Résolveur qui répond `addr` pour les noms de `records`, et note les noms demandés
+fn resolver(records: &'static [(&'static str, [u8; 4])], queries: Queries) -> Resolver {
+    Arc::new(move |name| {
+        queries.lock().unwrap().push(name.clone());
+        let answer = match records.iter().find(|(record, _)| *record == name) {
+            Some((_, addr)) => Ok(vec![IpAddr::from(*addr)]),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        };
+        Box::pin(async move { answer })
+    })
+}
+
+fn fake_dnsbl(records: &'static [(&'static str, [u8; 4])]) -> (Dnsbl, Queries) {
+    let queries = Queries::default();
+    let dnsbl = Dnsbl::new(["a.example".to_string(), "b.example".to_string()])
+        .with_resolver(resolver(records, queries.clone()));
+    (dnsbl, queries)
+}
+
+#[tokio::test]
+async fn addresses_are_queried_reversed_in_each_zone() {
+    let (dnsbl, queries) = fake_dnsbl(&[("4.3.2.1.b.example", [127, 0, 0, 2])]);
+    assert_eq!(
+        dnsbl
+            .check(Ipv4Addr::new(1, 2, 3, 4).into())
+            .await
+            .as_deref(),
+        Some("b.example")
+    );
+    // Client IPv4 d'une socket IPv6
+    let mapped = Ipv4Addr::new(5, 6, 7, 8).to_ipv6_mapped();
+    assert_eq!(dnsbl.check(mapped.into()).await, None);
+    let v6: Ipv6Addr = "2001:db8::1".parse().unwrap();
+    assert_eq!(dnsbl.check(v6.into()).await, None);
+
+    let mut queries = queries.lock().unwrap().clone();
+    queries.sort();
+    let v6_name = "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2";
+    assert_eq!(
+        queries,
+        [
+            format!("{v6_name}.a.example"),
+            format!("{v6_name}.b.example"),
+            "4.3.2.1.a.example".to_string(),
+            "4.3.2.1.b.example".to_string(),
+            "8.7.6.5.a.example".to_string(),
+            "8.7.6.5.b.example".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn zone_errors_are_not_listings() {
+    let (dnsbl, _) = fake_dnsbl(&[
+        ("4.3.2.1.a.example", [127, 255, 255, 254]),
+        ("4.3.2.1.b.example", [192, 0, 2, 1]),
+    ]);
+    assert_eq!(dnsbl.check(Ipv4Addr::new(1, 2, 3, 4).into()).await, None);
+}
+
+#[tokio::test]
+async fn results_are_cached() {
+    let (dnsbl, queries) = fake_dnsbl(&[("4.3.2.1.a.example", [127, 0, 0, 2])]);
+    let listed = IpAddr::from([1, 2, 3, 4]);
+    let clean = IpAddr::from([5, 6, 7, 8]);
+    for _ in 0..2 {
+        assert!(dnsbl.check(listed).await.is_some());
+        assert!(dnsbl.check(clean).await.is_none());
+    }
+    assert_eq!(queries.lock().unwrap().len(), 4);
+    assert_eq!(
+        dnsbl.metrics(),
+        DnsblMetrics {
+            checks: 4,
+            cache_hits: 2,
+            listed: 2,
+            timeouts: 0,
+        }
+    );
+
+    let (dnsbl, queries) = fake_dnsbl(&[]);
+    let dnsbl = dnsbl.with_cache_ttl(Duration::ZERO);
+    dnsbl.check(clean).await;
+    dnsbl.check(clean).await;
+    assert_eq!(queries.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn silent_zones_time_out_without_caching() {
+    let queries = Queries::default();
+    let silent: Resolver = Arc::new({
+        let queries = queries.clone();
+        move |name| {
+            queries.lock().unwrap().push(name);
+            Box::pin(std::future::pending())
+        }
+    });
+    let dnsbl = Dnsbl::new(["a.example".to_string()])
+        .with_timeout(Duration::from_millis(20))
+        .with_resolver(silent);
+    let ip = IpAddr::from([1, 2, 3, 4]);
+    assert_eq!(dnsbl.check(ip).await, None);
+    assert_eq!(dnsbl.check(ip).await, None);
+    assert_eq!(queries.lock().unwrap().len(), 2);
+    assert_eq!(dnsbl.metrics().timeouts, 2);
+}
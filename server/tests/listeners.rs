@@ -0,0 +1,51 @@
+use mini_irc_server::Listener;
+
+#[test]
+fn addresses_are_split_with_their_options() {
+    let listeners =
+        Listener::parse_all("127.0.0.1:6379, [::1]:6379,unix:///run/irc.sock;admin-only").unwrap();
+    assert_eq!(
+        listeners,
+        [
+            Listener {
+                address: "127.0.0.1:6379".to_string(),
+                admin_only: false,
+                proxy_protocol: false,
+            },
+            Listener {
+                address: "[::1]:6379".to_string(),
+                admin_only: false,
+                proxy_protocol: false,
+            },
+            Listener {
+                address: "unix:///run/irc.sock".to_string(),
+                admin_only: true,
+                proxy_protocol: false,
+            },
+        ]
+    );
+    assert_eq!(listeners[2].unix_path(), Some("/run/irc.sock"));
+    assert_eq!(listeners[0].unix_path(), None);
+}
+
+#[test]
+fn options_can_be_combined() {
+    let listener = Listener::parse("0.0.0.0:6379; proxy-protocol ;admin-only").unwrap();
+    assert_eq!(
+        listener,
+        Listener {
+            address: "0.0.0.0:6379".to_string(),
+            admin_only: true,
+            proxy_protocol: true,
+        }
+    );
+}
+
+#[test]
+fn invalid_listeners_are_refused() {
+    assert!(Listener::parse_all("").is_err());
+    assert!(Listener::parse_all(" , ").is_err());
+    assert!(Listener::parse(";admin-only").is_err());
+    assert!(Listener::parse("127.0.0.1:6379;plaintext").is_err());
+    assert!(Listener::parse("tls://0.0.0.0:6697").is_err());
+}
@@ -0,0 +1,90 @@
+use mini_irc_server::{read_proxy_header, PROXY_V2_SIGNATURE};
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use tokio::io::AsyncReadExt;
+
+// En-tête version 2 de commande `command`, pour la famille `family`, suivi de `payload`
+fn header(command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+    let mut header = PROXY_V2_SIGNATURE.to_vec();
+    header.push(0x20 | command);
+    header.push(family);
+    header.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    header.extend_from_slice(payload);
+    header
+}
+
+#[tokio::test]
+async fn client_address_is_read() {
+    // 192.0.2.1:4000 vers 10.0.0.1:6379, en TCP sur IPv4
+    let bytes = header(
+        0x1,
+        0x11,
+        &[192, 0, 2, 1, 10, 0, 0, 1, 0x0f, 0xa0, 0x18, 0xeb],
+    );
+    let peer = read_proxy_header(&mut bytes.as_slice()).await.unwrap();
+    assert_eq!(peer, Some("192.0.2.1:4000".parse().unwrap()));
+
+    let mut payload = vec![0x20, 0x01, 0x0d, 0xb8];
+    payload.extend([0; 11]);
+    payload.push(1);
+    payload.extend([0; 16]);
+    payload.extend([0x0f, 0xa0, 0x18, 0xeb]);
+    let bytes = header(0x1, 0x21, &payload);
+    let peer = read_proxy_header(&mut bytes.as_slice()).await.unwrap();
+    assert_eq!(peer, Some("[2001:db8::1]:4000".parse().unwrap()));
+}
+
+#[tokio::test]
+async fn nothing_is_read_past_the_header() {
+    // Extension (TLV) après les adresses, puis la première trame du client
+    let mut bytes = header(
+        0x1,
+        0x11,
+        &[192, 0, 2, 1, 10, 0, 0, 1, 0, 1, 0, 2, 0x04, 0, 1, 0xff],
+    );
+    bytes.extend(b"hello");
+    let mut stream = bytes.as_slice();
+    let peer = read_proxy_header(&mut stream).await.unwrap();
+    assert_eq!(peer, Some(SocketAddr::from(([192, 0, 2, 1], 1))));
+    let mut rest = String::new();
+    stream.read_to_string(&mut rest).await.unwrap();
+    assert_eq!(rest, "hello");
+}
+
+#[tokio::test]
+async fn local_connections_have_no_client_address() {
+    let bytes = header(0x0, 0x00, &[]);
+    assert_eq!(
+        read_proxy_header(&mut bytes.as_slice()).await.unwrap(),
+        None
+    );
+    // Client connecté au répartiteur par une socket Unix
+    let bytes = header(0x1, 0x31, &[0; 216]);
+    assert_eq!(
+        read_proxy_header(&mut bytes.as_slice()).await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn invalid_headers_are_refused() {
+    let invalid = |bytes: Vec<u8>| async move {
+        read_proxy_header(&mut bytes.as_slice())
+            .await
+            .unwrap_err()
+            .kind()
+    };
+    // Version 1, en texte
+    let v1 = b"PROXY TCP4 192.0.2.1 10.0.0.1 4000 6379\r\n".to_vec();
+    assert_eq!(invalid(v1).await, ErrorKind::InvalidData);
+    let mut v3 = header(0x1, 0x11, &[0; 12]);
+    v3[12] = 0x31;
+    assert_eq!(invalid(v3).await, ErrorKind::InvalidData);
+    assert_eq!(
+        invalid(header(0x1, 0x11, &[0; 8])).await,
+        ErrorKind::InvalidData
+    );
+    let mut truncated = header(0x1, 0x11, &[0; 12]);
+    truncated.truncate(20);
+    assert_eq!(invalid(truncated).await, ErrorKind::UnexpectedEof);
+}
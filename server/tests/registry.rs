@@ -0,0 +1,158 @@
+use mini_irc_protocol::{BroadcastEvent, ChanOp, Response};
+use mini_irc_server::ChannelRegistry;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+fn user_del(user: &str, seq: u64) -> BroadcastEvent<Response> {
+    BroadcastEvent::Message(Response::Channel {
+        op: ChanOp::UserDel(user.to_string()),
+        chan: "general".to_string(),
+        seq,
+    })
+}
+
+#[tokio::test]
+async fn sweep_removes_members_without_connection() {
+    let registry = ChannelRegistry::default();
+    // Abonnement resté en place après la perte de la connexion
+    let stale = registry.subscribe("general", "ghost", 16).await.unwrap();
+    let mut alice = registry.subscribe("general", "alice", 16).await.unwrap();
+    let later = SystemTime::now() + Duration::from_secs(1);
+
+    let live = HashSet::from(["alice".to_string()]);
+    let removed = registry.sweep(later, live).await;
+    assert_eq!(removed, [("general".to_string(), "ghost".to_string())]);
+    assert_eq!(
+        registry.get("general").map(|channel| channel.subscribers()),
+        Some(vec!["alice".to_string()])
+    );
+    // Les membres restants sont prévenus du départ
+    alice.recv().await.unwrap();
+    assert_eq!(alice.recv().await.unwrap(), user_del("ghost", 3));
+
+    // Le nom peut de nouveau rejoindre le canal
+    drop(stale);
+    assert!(registry.subscribe("general", "ghost", 16).await.is_some());
+}
+
+#[tokio::test]
+async fn sweep_spares_recent_members() {
+    let registry = ChannelRegistry::default();
+    let before = SystemTime::now() - Duration::from_secs(1);
+    let _bob = registry.subscribe("general", "bob", 16).await.unwrap();
+    assert!(registry.sweep(before, HashSet::new()).await.is_empty());
+}
+
+#[tokio::test]
+async fn empty_channels_are_removed() {
+    let registry = ChannelRegistry::default();
+    let (general, alice) = registry.join("general", "alice", 16, None).await.unwrap();
+    let (_, bob) = registry.join("general", "bob", 16, None).await.unwrap();
+    let stale = registry.subscribe("rust", "ghost", 16).await.unwrap();
+    assert_eq!(registry.len(), 2);
+
+    assert!(general.leave("alice", alice.id).await);
+    assert!(!registry.release("general"));
+    assert!(general.leave("bob", bob.id).await);
+    assert!(registry.release("general"));
+    assert_eq!(registry.len(), 1);
+
+    // Le balayage retire aussi les canaux qu'il vide
+    let later = SystemTime::now() + Duration::from_secs(1);
+    registry.sweep(later, HashSet::new()).await;
+    assert_eq!(registry.len(), 0);
+    drop(stale);
+
+    // Le nom est de nouveau libre : un nouveau canal est créé
+    let (_, alice) = registry.join("general", "alice", 16, None).await.unwrap();
+    assert_eq!(alice.users, ["alice"]);
+    assert_eq!(registry.len(), 1);
+
+    // Un canal système vidé reste en place
+    let (announcements, alice) = registry
+        .join_system("&annonces", "alice", 16)
+        .await
+        .unwrap();
+    assert!(announcements.leave("alice", alice.id).await);
+    assert!(!registry.release("&annonces"));
+    assert_eq!(registry.len(), 2);
+}
+
+/// Chaque membre connaît chacun des autres exactement une fois : soit par la liste reçue en
+/// rejoignant le canal, soit par l'annonce de son arrivée.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_joins_are_announced_once() {
+    const USERS: usize = 50;
+    let registry = Arc::new(ChannelRegistry::default());
+    let joins: Vec<_> = (0..USERS)
+        .map(|user| {
+            let registry = registry.clone();
+            tokio::spawn(async move {
+                let user = format!("user{user}");
+                let (_, joined) = registry
+                    .join("general", &user, 2 * USERS, None)
+                    .await
+                    .unwrap();
+                (user, joined)
+            })
+        })
+        .collect();
+    let mut members = Vec::new();
+    for join in joins {
+        members.push(join.await.unwrap());
+    }
+
+    for (user, mut joined) in members {
+        let mut known = joined.users;
+        while let Ok(Ok(BroadcastEvent::Message(response))) =
+            tokio::time::timeout(Duration::from_millis(10), joined.receiver.recv()).await
+        {
+            if let Response::Channel {
+                op: ChanOp::UserAdd(other),
+                ..
+            } = response
+            {
+                if other != user {
+                    known.push(other);
+                }
+            }
+        }
+        known.sort();
+        let mut expected: Vec<_> = (0..USERS).map(|user| format!("user{user}")).collect();
+        expected.sort();
+        assert_eq!(known, expected, "members known by {user}");
+    }
+}
+
+#[tokio::test]
+async fn channels_are_matched_by_pattern() {
+    let registry = ChannelRegistry::default();
+    let mut members = Vec::new();
+    for chan in ["dev-rust", "dev-go", "devops", "general", "dev"] {
+        members.push(registry.subscribe(chan, "alice", 16).await.unwrap());
+    }
+    let names = |pattern: &str| -> Vec<String> {
+        registry
+            .matching(pattern)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    };
+    assert_eq!(names("dev-*"), ["dev-go", "dev-rust"]);
+    assert_eq!(names("dev*"), ["dev", "dev-go", "dev-rust", "devops"]);
+    assert_eq!(names("*o*"), ["dev-go", "devops"]);
+    assert_eq!(names("dev-??"), ["dev-go"]);
+    assert_eq!(
+        names("*"),
+        ["dev", "dev-go", "dev-rust", "devops", "general"]
+    );
+    assert!(names("rust").is_empty());
+
+    // Les canaux vides ne reçoivent rien
+    let (old, joined) = registry.join("dev-old", "bob", 16, None).await.unwrap();
+    assert_eq!(names("dev-*"), ["dev-go", "dev-old", "dev-rust"]);
+    assert!(old.leave("bob", joined.id).await);
+    assert_eq!(names("dev-*"), ["dev-go", "dev-rust"]);
+    drop(members);
+}
@@ -0,0 +1,127 @@
+use mini_irc_protocol::{HandshakeRequest, MessageReceiver, Request};
+use mini_irc_server::{ConnectionState, HandshakeStep, StateError};
+
+fn authenticated() -> ConnectionState {
+    let mut state = ConnectionState::default();
+    state
+        .on_handshake(HandshakeRequest::Secure(vec![1]))
+        .unwrap();
+    state
+        .on_handshake(HandshakeRequest::Shared(vec![2]))
+        .unwrap();
+    state
+}
+
+#[test]
+fn handshake_steps_are_in_order() {
+    let mut state = ConnectionState::default();
+    assert_eq!(
+        state.on_handshake(HandshakeRequest::Secure(vec![1])),
+        Ok(vec![1])
+    );
+    assert_eq!(state, ConnectionState::Handshake(HandshakeStep::Shared));
+    assert_eq!(
+        state.on_handshake(HandshakeRequest::Shared(vec![2])),
+        Ok(vec![2])
+    );
+    assert_eq!(state, ConnectionState::Authenticated);
+}
+
+#[test]
+fn shared_key_before_public_key_is_refused() {
+    let mut state = ConnectionState::default();
+    assert_eq!(
+        state.on_handshake(HandshakeRequest::Shared(vec![2])),
+        Err(StateError::UnexpectedHandshake)
+    );
+    assert_eq!(state, ConnectionState::default());
+}
+
+#[test]
+fn keys_cannot_be_swapped_once_shared() {
+    let mut state = ConnectionState::default();
+    state
+        .on_handshake(HandshakeRequest::Secure(vec![1]))
+        .unwrap();
+    assert_eq!(
+        state.on_handshake(HandshakeRequest::Secure(vec![3])),
+        Err(StateError::UnexpectedHandshake)
+    );
+    let mut state = authenticated();
+    assert_eq!(
+        state.on_handshake(HandshakeRequest::Shared(vec![3])),
+        Err(StateError::UnexpectedHandshake)
+    );
+    assert_eq!(state, ConnectionState::Authenticated);
+}
+
+#[test]
+fn requests_wait_for_the_handshake() {
+    let state = ConnectionState::default();
+    assert_eq!(
+        state.check(&Request::Connect("alice".to_string())),
+        Err(StateError::HandshakeInProgress)
+    );
+}
+
+#[test]
+fn only_connect_is_accepted_before_identification() {
+    let state = authenticated();
+    assert_eq!(state.check(&Request::Connect("alice".to_string())), Ok(()));
+    assert_eq!(state.check(&Request::Capabilities), Ok(()));
+    assert_eq!(state.check(&Request::Pong(1)), Ok(()));
+    assert_eq!(state.check(&Request::Ping(1)), Ok(()));
+    assert_eq!(
+        state.check(&Request::JoinChan("general".to_string())),
+        Err(StateError::NotConnected)
+    );
+    assert_eq!(state.check(&Request::Stats), Err(StateError::NotConnected));
+}
+
+#[test]
+fn ghost_is_accepted_before_and_after_identification() {
+    let ghost = Request::Ghost {
+        nickname: "alice".to_string(),
+        key: "key".to_string(),
+    };
+    let mut state = authenticated();
+    assert_eq!(state.check(&ghost), Ok(()));
+    assert_eq!(
+        state.check(&Request::GhostKey("key".to_string())),
+        Err(StateError::NotConnected)
+    );
+    state.activate();
+    assert_eq!(state.check(&ghost), Ok(()));
+}
+
+#[test]
+fn connect_is_accepted_once() {
+    let mut state = authenticated();
+    state.activate();
+    assert_eq!(state, ConnectionState::Active);
+    assert_eq!(
+        state.check(&Request::Connect("bob".to_string())),
+        Err(StateError::AlreadyConnected)
+    );
+    let message = Request::Message {
+        to: MessageReceiver::Channel("general".to_string()),
+        content: "hello".to_string(),
+    };
+    assert_eq!(state.check(&message), Ok(()));
+}
+
+#[test]
+fn activation_needs_the_handshake() {
+    let mut state = ConnectionState::default();
+    state.activate();
+    assert_eq!(state, ConnectionState::default());
+}
+
+#[test]
+fn errors_keep_their_wire_messages() {
+    assert_eq!(StateError::NotConnected.to_string(), "Please connect first");
+    assert_eq!(
+        StateError::AlreadyConnected.to_string(),
+        "Already connected"
+    );
+}